@@ -5,8 +5,9 @@ use proc_macro::TokenStream;
 use proc_macro2::Span;
 use std::ops::Not;
 use std::sync::{LazyLock, Mutex};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Error, Ident, ItemFn, Meta, PatType};
+use syn::{parse_macro_input, Error, Ident, ItemFn, Meta, PatType, Token};
 
 static QUERIES: LazyLock<Mutex<Vec<NamedQuerySpec>>> = LazyLock::new(|| Mutex::new(vec![]));
 
@@ -23,6 +24,8 @@ struct QuerySpec {
 	pub input_type: syn::Type,
 	pub output_type: syn::Type,
 	pub default: bool,
+	/// Whether the function answers a whole batch of keys at once
+	pub batch: bool,
 }
 
 /// Parse Path to confirm that it represents a Result<T: Serialize> and return the type T
@@ -55,6 +58,32 @@ fn parse_result_generic(p: &syn::Path) -> Result<syn::Type, Error> {
 	}
 }
 
+/// Parse Type to confirm that it is a Vec<T> and return the type T, for batch query functions
+fn parse_vec_generic(ty: &syn::Type) -> Result<syn::Type, Error> {
+	use syn::GenericArgument;
+	use syn::PathArguments;
+	let error = || {
+		Error::new(
+			ty.span(),
+			"Batch query functions must take a Vec of keys and return a Result<Vec<T: Serialize>>",
+		)
+	};
+	let syn::Type::Path(p) = ty else {
+		return Err(error());
+	};
+	let last = p.path.segments.last().ok_or_else(error)?;
+	if last.ident != "Vec" {
+		return Err(error());
+	}
+	match &last.arguments {
+		PathArguments::AngleBracketed(x) => match x.args.first() {
+			Some(GenericArgument::Type(ty)) => Ok(ty.clone()),
+			_ => Err(error()),
+		},
+		_ => Err(error()),
+	}
+}
+
 /// Parse PatType to confirm that it contains a &mut PluginEngine
 fn parse_plugin_engine(engine_arg: &PatType) -> Result<(), Error> {
 	if let syn::Type::Reference(type_reference) = engine_arg.ty.as_ref() {
@@ -74,7 +103,7 @@ fn parse_plugin_engine(engine_arg: &PatType) -> Result<(), Error> {
 	))
 }
 
-fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<QuerySpec, Error> {
+fn parse_named_query_spec(metas: Vec<Meta>, item_fn: ItemFn) -> Result<QuerySpec, Error> {
 	use syn::Meta::*;
 	use syn::ReturnType;
 	let sig = &item_fn.sig;
@@ -122,8 +151,37 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 		}
 	};
 
-	let default = match opt_meta {
-		Some(NameValue(nv)) => {
+	let mut default = false;
+	let mut batch = false;
+	for meta in metas {
+		match meta {
+			Path(p) if p.is_ident("batch") => batch = true,
+			meta => default = parse_default_option(meta, &item_fn)?,
+		}
+	}
+
+	let (input_type, output_type) = match batch {
+		true => (
+			parse_vec_generic(&input_type)?,
+			parse_vec_generic(&output_type)?,
+		),
+		false => (input_type, output_type),
+	};
+
+	Ok(QuerySpec {
+		function,
+		default,
+		batch,
+		input_type,
+		output_type,
+	})
+}
+
+/// Parse the `default` option of a query function
+fn parse_default_option(meta: Meta, item_fn: &ItemFn) -> Result<bool, Error> {
+	use syn::Meta::*;
+	let default = match meta {
+		NameValue(nv) => {
 			// Panic: Safe to unwrap because there should be at least one element in the sequence
 			if nv.path.segments.first().unwrap().ident == "default" {
 				match nv.value {
@@ -150,7 +208,7 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 				));
 			}
 		}
-		Some(Path(p)) => {
+		Path(p) => {
 			let seg: &syn::PathSegment = p.segments.first().unwrap();
 			if seg.ident == "default" {
 				match seg.arguments {
@@ -164,7 +222,6 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 				));
 			}
 		}
-		None => false,
 		_ => {
 			return Err(Error::new(
 				item_fn.span(),
@@ -173,12 +230,7 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 		}
 	};
 
-	Ok(QuerySpec {
-		function,
-		default,
-		input_type,
-		output_type,
-	})
+	Ok(default)
 }
 
 /// An attribute on a function that creates an associated struct that implements
@@ -187,16 +239,23 @@ fn parse_named_query_spec(opt_meta: Option<Meta>, item_fn: ItemFn) -> Result<Que
 /// hipcheck_sdk::Result<impl serde::Serialize>`. The generated struct's name is
 /// the pascal-case version of the function name (e.g. `do_something()` ->
 /// `DoSomething`).
+///
+/// With `#[query(batch)]`, the function instead takes a `Vec` of keys and
+/// returns a `Vec` of outputs, one per key in the same order, so it can share
+/// work between the keys of a batch. Single queries are passed to it as a batch
+/// of one.
 #[proc_macro_attribute]
 pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let mut to_return = proc_macro2::TokenStream::from(item.clone());
 	let item_fn = parse_macro_input!(item as ItemFn);
-	let opt_meta: Option<Meta> = if attr.is_empty().not() {
-		Some(parse_macro_input!(attr as Meta))
+	let metas: Vec<Meta> = if attr.is_empty().not() {
+		parse_macro_input!(attr with Punctuated::<Meta, Token![,]>::parse_terminated)
+			.into_iter()
+			.collect()
 	} else {
-		None
+		vec![]
 	};
-	let spec = match parse_named_query_spec(opt_meta, item_fn) {
+	let spec = match parse_named_query_spec(metas, item_fn) {
 		Ok(span) => span,
 		Err(err) => return err.to_compile_error().into(),
 	};
@@ -212,6 +271,42 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let input_type = spec.input_type;
 	let output_type = spec.output_type;
 
+	let run = match spec.batch {
+		true => quote::quote! {
+			async fn run(&self, engine: &mut hipcheck_sdk::prelude::PluginEngine, input: hipcheck_sdk::prelude::Value) -> hipcheck_sdk::prelude::Result<hipcheck_sdk::prelude::Value> {
+				let mut outputs = self.run_batch(engine, vec![input]).await?;
+				outputs.pop().ok_or(hipcheck_sdk::prelude::Error::UnexpectedPluginQueryOutputFormat)
+			}
+
+			async fn run_batch(&self, engine: &mut hipcheck_sdk::prelude::PluginEngine, inputs: Vec<hipcheck_sdk::prelude::Value>) -> hipcheck_sdk::prelude::Result<Vec<hipcheck_sdk::prelude::Value>> {
+				let inputs = inputs
+					.into_iter()
+					.map(hipcheck_sdk::prelude::from_value::<#input_type>)
+					.collect::<std::result::Result<Vec<_>, _>>()
+					.map_err(|_| hipcheck_sdk::prelude::Error::UnexpectedPluginQueryInputFormat)?;
+				let keys = inputs.len();
+				let outputs = #ident(engine, inputs).await?;
+				if outputs.len() != keys {
+					return Err(hipcheck_sdk::prelude::Error::MismatchedBatchSize { keys, outputs: outputs.len() });
+				}
+				outputs
+					.into_iter()
+					.map(hipcheck_sdk::prelude::to_value)
+					.collect::<std::result::Result<Vec<_>, _>>()
+					.map_err(|_| hipcheck_sdk::prelude::Error::UnexpectedPluginQueryOutputFormat)
+			}
+		},
+		false => quote::quote! {
+			async fn run(&self, engine: &mut hipcheck_sdk::prelude::PluginEngine, input: hipcheck_sdk::prelude::Value) -> hipcheck_sdk::prelude::Result<hipcheck_sdk::prelude::Value> {
+				let input = hipcheck_sdk::prelude::from_value(input).map_err(|_|
+					hipcheck_sdk::prelude::Error::UnexpectedPluginQueryInputFormat)?;
+				let output = #ident(engine, input).await?;
+				hipcheck_sdk::prelude::to_value(output).map_err(|_|
+					hipcheck_sdk::prelude::Error::UnexpectedPluginQueryOutputFormat)
+			}
+		},
+	};
+
 	let to_follow = quote::quote! {
 		struct #struct_name {}
 
@@ -225,13 +320,7 @@ pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
 				hipcheck_sdk::prelude::schema_for!(#output_type).schema
 			}
 
-			async fn run(&self, engine: &mut hipcheck_sdk::prelude::PluginEngine, input: hipcheck_sdk::prelude::Value) -> hipcheck_sdk::prelude::Result<hipcheck_sdk::prelude::Value> {
-				let input = hipcheck_sdk::prelude::from_value(input).map_err(|_|
-					hipcheck_sdk::prelude::Error::UnexpectedPluginQueryInputFormat)?;
-				let output = #ident(engine, input).await?;
-				hipcheck_sdk::prelude::to_value(output).map_err(|_|
-					hipcheck_sdk::prelude::Error::UnexpectedPluginQueryOutputFormat)
-			}
+			#run
 		}
 	};
 
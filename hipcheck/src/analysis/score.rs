@@ -2,18 +2,28 @@
 
 use crate::{
 	analysis::AnalysisProvider,
+	cache::query::{HcQueryCache, QueryCacheDependency, QueryCacheKey},
 	config::{visit_leaves, Analysis, AnalysisTree, WeightTreeProvider},
-	engine::HcEngine,
+	engine::{async_query, HcEngine, HcEngineImpl},
 	error::Result,
 	hc_error,
-	plugin::QueryResult,
-	policy_exprs::Executor,
+	plugin::{get_plugin_key, HcPluginCore, PluginId, PluginVersion, QueryResult},
+	policy_exprs::{Executor, TraceEntry},
 	shell::spinner_phase::SpinnerPhase,
+	source::git::{get_head_commit, has_uncommitted_changes},
 };
+use futures::stream::{self, StreamExt};
+use indexmap::IndexMap;
 use indextree::{Arena, NodeId};
 #[cfg(test)]
 use num_traits::identities::Zero;
-use std::{collections::HashMap, default::Default};
+use serde_json::Value;
+use std::{
+	collections::{BTreeMap, HashMap},
+	default::Default,
+	num::NonZeroUsize,
+	sync::Arc,
+};
 
 #[cfg(test)]
 pub const PRACTICES_PHASE: &str = "practices";
@@ -43,11 +53,14 @@ pub struct PluginAnalysisResult {
 	pub response: Result<QueryResult>,
 	pub policy: String,
 	pub passed: bool,
+	/// How the policy expression was evaluated, if the analysis failed.
+	pub trace: Vec<TraceEntry>,
 }
 
 #[derive(Debug, Default)]
 pub struct PluginAnalysisResults {
-	pub table: HashMap<Analysis, PluginAnalysisResult>,
+	/// Results in the order the analyses appear in the policy file.
+	pub table: IndexMap<Analysis, PluginAnalysisResult>,
 }
 
 impl PluginAnalysisResults {
@@ -187,7 +200,83 @@ pub struct ScoreTreeNode {
 	pub weight: f64,
 }
 
-pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<ScoringResults> {
+/// The number of analyses to run at once if the user did not configure a limit.
+pub fn default_concurrency() -> NonZeroUsize {
+	std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Run the query for one analysis, using the persistent query cache if one is given.
+async fn query_analysis(
+	core: Arc<HcPluginCore>,
+	analysis: &Analysis,
+	target_json: &Value,
+	persist: Option<(&HcQueryCache, QueryCacheKey<'_>)>,
+) -> Result<QueryResult> {
+	if let Some((cache, key)) = &persist {
+		if let Some(result) = cache.get(key) {
+			log::debug!(
+				"Using cached result for {}/{}",
+				analysis.publisher,
+				analysis.plugin
+			);
+			return Ok(result);
+		}
+	}
+
+	let response = async_query(
+		core,
+		analysis.publisher.clone(),
+		analysis.plugin.clone(),
+		analysis.query.clone(),
+		target_json.clone(),
+	)
+	.await;
+
+	// Only successful results are saved, so errors are retried on the next run
+	if let (Some((cache, key)), Ok(result)) = (&persist, &response) {
+		if let Err(e) = cache.put(key, result) {
+			log::warn!("Failed to save query result to cache: {e}");
+		}
+	}
+
+	response
+}
+
+/// The plugins that the plugin with key `plugin_key` depends on, directly or through other
+/// plugins, in order of their keys.
+fn transitive_dependencies(core: &HcPluginCore, plugin_key: &str) -> Vec<QueryCacheDependency> {
+	let mut found = BTreeMap::new();
+	let mut to_visit = vec![plugin_key];
+	while let Some(key) = to_visit.pop() {
+		let Some(active) = core.plugins.get(key) else {
+			continue;
+		};
+		for dependency in active.dependencies() {
+			if dependency != plugin_key && !found.contains_key(dependency.as_str()) {
+				if let Some(dep) = core.plugins.get(dependency) {
+					found.insert(dependency.as_str(), dep);
+					to_visit.push(dependency);
+				}
+			}
+		}
+	}
+
+	found
+		.into_iter()
+		.map(|(key, active)| QueryCacheDependency {
+			plugin: key.to_owned(),
+			version: active.version().to_owned(),
+			config: active.config().clone(),
+		})
+		.collect()
+}
+
+pub fn score_results(
+	_phase: &SpinnerPhase,
+	db: &dyn ScoringProvider,
+	concurrency: NonZeroUsize,
+	query_cache: Option<&HcQueryCache>,
+) -> Result<ScoringResults> {
 	// Scoring should be performed by the construction of a "score tree" where scores are the
 	// nodes and weights are the edges. The leaves are the analyses themselves, which either
 	// pass (a score of 0) or fail (a score of 1). These are then combined with the other
@@ -203,16 +292,65 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 	// RFD4 analysis style - get all "leaf" analyses and call through plugin architecture
 	let plugin_score_tree = {
 		let target_json = serde_json::to_value(db.target().as_ref())?;
+		let core = db.core();
+		let analyses = analysis_tree.get_analyses();
+
+		// Persisted results are keyed on the target's HEAD commit, since the
+		// target JSON identifies the repo but not the state it is in. Changes
+		// which aren't committed aren't part of that, so results for a repo
+		// with any aren't persisted.
+		let commit = match query_cache {
+			Some(_) if has_uncommitted_changes(db.local().as_path())? => {
+				log::warn!("Not using the query cache, since the target has uncommitted changes");
+				None
+			}
+			Some(_) => Some(get_head_commit(db.local().as_path())?),
+			None => None,
+		};
+		let query_cache = query_cache.filter(|_| commit.is_some());
+		let plugin_ids = db
+			.policy()
+			.plugins
+			.0
+			.iter()
+			.filter_map(|p| {
+				// the version in use is the one resolved when the plugins were started
+				let key = get_plugin_key(&p.name.publisher.0, &p.name.name.0);
+				let active = core.plugins.get(&key)?;
+				let version = PluginVersion(active.version().to_owned());
+				let plugin_id =
+					PluginId::new(p.name.publisher.clone(), p.name.name.clone(), version);
+				let dependencies = transitive_dependencies(&core, &key);
+				Some((key, (plugin_id, active.config(), dependencies)))
+			})
+			.collect::<HashMap<String, (PluginId, &Value, Vec<QueryCacheDependency>)>>();
+
+		// Perform queries, passing target in JSON. Up to `concurrency` analyses
+		// run at once, and `buffered` yields their responses in the order the
+		// analyses were started, so results are assembled in policy order.
+		let responses = HcEngineImpl::runtime().block_on(
+			stream::iter(analyses.iter().map(|analysis| {
+				let plugin_key = get_plugin_key(&analysis.0.publisher, &analysis.0.plugin);
+				let persist = query_cache.zip(plugin_ids.get(&plugin_key)).map(
+					|(cache, (plugin, config, dependencies))| {
+						let key = QueryCacheKey {
+							plugin,
+							config,
+							query: &analysis.0.query,
+							key: &target_json,
+							commit: commit.as_deref(),
+							dependencies,
+						};
+						(cache, key)
+					},
+				);
+				query_analysis(Arc::clone(&core), &analysis.0, &target_json, persist)
+			}))
+			.buffered(concurrency.get())
+			.collect::<Vec<_>>(),
+		);
 
-		for analysis in analysis_tree.get_analyses() {
-			// Perform query, passing target in JSON
-			let response = db.query(
-				analysis.0.publisher.clone(),
-				analysis.0.plugin.clone(),
-				analysis.0.query.clone(),
-				target_json.clone(),
-			);
-
+		for (analysis, response) in analyses.into_iter().zip(responses) {
 			// Determine if analysis passed by evaluating policy expr
 			let passed = {
 				if let Ok(output) = &response {
@@ -224,6 +362,14 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 				}
 			};
 
+			// Record how a failing policy was evaluated, to explain the failure.
+			let trace = match &response {
+				Ok(output) if !passed => Executor::std()
+					.trace(analysis.1.as_str(), &output.value)
+					.map_err(|e| hc_error!("{}", e))?,
+				_ => vec![],
+			};
+
 			// Record in output map
 			plugin_results.table.insert(
 				analysis.0.clone(),
@@ -231,6 +377,7 @@ pub fn score_results(_phase: &SpinnerPhase, db: &dyn ScoringProvider) -> Result<
 					response,
 					policy: analysis.1.clone(),
 					passed,
+					trace,
 				},
 			);
 		}
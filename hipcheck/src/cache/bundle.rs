@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bundles of cached plugins and repos, for checking targets on a machine without network
+//! access. A bundle is a `.tar.gz` of directories of the cache, laid out as they are in the cache,
+//! so importing one is just unpacking it into another cache.
+
+use crate::{
+	error::Result,
+	hc_error,
+	plugin::{create_archive, extract_archive, ArchiveFormat},
+};
+use flate2::read::GzDecoder;
+use std::{
+	fs::File,
+	path::{Component, Path, PathBuf},
+};
+use tar::{Archive, EntryType};
+
+/// Write the directories `dirs` of the cache at `root` into a bundle at `bundle_path`.
+pub fn export_bundle(root: &Path, dirs: &[PathBuf], bundle_path: &Path) -> Result<()> {
+	let dirs = dirs
+		.iter()
+		.map(|dir| {
+			dir.strip_prefix(root)
+				.map(Path::to_path_buf)
+				.map_err(|_| hc_error!("'{}' is not in the cache", dir.display()))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	create_archive(bundle_path, root, &dirs)
+}
+
+/// Unpack the bundle at `bundle_path` into the cache at `root`, replacing any files it has in
+/// common with the cache. The bundle is refused without unpacking anything if it has entries other
+/// than the files and directories of cached plugins and clones.
+pub fn import_bundle(root: &Path, bundle_path: &Path) -> Result<()> {
+	check_bundle(bundle_path)?;
+	std::fs::create_dir_all(root)
+		.map_err(|e| hc_error!("Error [{}] creating cache {}", e, root.display()))?;
+	extract_archive(bundle_path, root, ArchiveFormat::TarGz)
+}
+
+/// Check that every entry of the bundle at `bundle_path` is a file or directory within a plugin's
+/// directory, `plugins/<publisher>/<name>/<version>/`, or within `clones/`.
+fn check_bundle(bundle_path: &Path) -> Result<()> {
+	let file = File::open(bundle_path)
+		.map_err(|e| hc_error!("Error [{}] opening {}", e, bundle_path.display()))?;
+	let mut archive = Archive::new(GzDecoder::new(file));
+	let entries = archive
+		.entries()
+		.map_err(|e| hc_error!("Error [{}] reading {}", e, bundle_path.display()))?;
+
+	for entry in entries {
+		let entry =
+			entry.map_err(|e| hc_error!("Error [{}] reading {}", e, bundle_path.display()))?;
+		let path = entry
+			.path()
+			.map_err(|e| hc_error!("Error [{}] reading {}", e, bundle_path.display()))?;
+
+		if !matches!(
+			entry.header().entry_type(),
+			EntryType::Regular | EntryType::Directory
+		) {
+			return Err(hc_error!(
+				"Bundle entry '{}' is not a file or directory",
+				path.display()
+			));
+		}
+		if !is_bundled_path(&path) {
+			return Err(hc_error!(
+				"Bundle entry '{}' is not a cached plugin or clone",
+				path.display()
+			));
+		}
+	}
+
+	Ok(())
+}
+
+/// Whether `path` is within a plugin's directory or `clones/`, without leaving it
+fn is_bundled_path(path: &Path) -> bool {
+	let mut components = Vec::new();
+	for component in path.components() {
+		match component {
+			Component::Normal(name) => components.push(name),
+			Component::CurDir => {}
+			Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+		}
+	}
+
+	match components.first().and_then(|dir| dir.to_str()) {
+		Some("plugins") => components.len() >= 4,
+		Some("clones") => components.len() >= 2,
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use pathbuf::pathbuf;
+	use tempfile::TempDir;
+
+	#[test]
+	fn bundle_round_trip() {
+		let from = TempDir::with_prefix("hipcheck").unwrap();
+		let plugin_dir = pathbuf![from.path(), "plugins", "mitre", "git", "0.1.0"];
+		let clone_dir = pathbuf![from.path(), "clones", "github", "mitre", "hipcheck"];
+		std::fs::create_dir_all(&plugin_dir).unwrap();
+		std::fs::create_dir_all(&clone_dir).unwrap();
+		std::fs::write(plugin_dir.join("plugin.kdl"), "publisher \"mitre\"").unwrap();
+		std::fs::write(clone_dir.join("README.md"), "# Hipcheck").unwrap();
+		// Only the directories asked for are bundled
+		std::fs::create_dir_all(pathbuf![from.path(), "clones", "local", "other"]).unwrap();
+
+		let bundle = from.path().join("bundle.tar.gz");
+		export_bundle(from.path(), &[plugin_dir, clone_dir], &bundle).unwrap();
+
+		let to = TempDir::with_prefix("hipcheck").unwrap();
+		import_bundle(to.path(), &bundle).unwrap();
+		assert_eq!(
+			std::fs::read_to_string(pathbuf![
+				to.path(),
+				"plugins",
+				"mitre",
+				"git",
+				"0.1.0",
+				"plugin.kdl"
+			])
+			.unwrap(),
+			"publisher \"mitre\""
+		);
+		assert!(pathbuf![
+			to.path(),
+			"clones",
+			"github",
+			"mitre",
+			"hipcheck",
+			"README.md"
+		]
+		.exists());
+		assert!(!pathbuf![to.path(), "clones", "local"].exists());
+	}
+
+	/// Write a bundle holding `entries`, each a path and the target of a link, or `None` for a file
+	fn bundle_of(entries: &[(&str, Option<&str>)]) -> (TempDir, PathBuf) {
+		let dir = TempDir::with_prefix("hipcheck").unwrap();
+		let bundle = dir.path().join("bundle.tar.gz");
+		let file = File::create(&bundle).unwrap();
+		let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+			file,
+			flate2::Compression::default(),
+		));
+		for (path, link) in entries {
+			let mut header = tar::Header::new_gnu();
+			header.set_mode(0o644);
+			header.set_size(0);
+			match link {
+				Some(target) => {
+					header.set_entry_type(EntryType::Symlink);
+					builder.append_link(&mut header, path, target).unwrap();
+				}
+				None => {
+					header.set_entry_type(EntryType::Regular);
+					// `append_data` would refuse paths with `..`, which is the point here
+					header.as_gnu_mut().unwrap().name[..path.len()]
+						.copy_from_slice(path.as_bytes());
+					header.set_cksum();
+					builder.append(&header, std::io::empty()).unwrap();
+				}
+			}
+		}
+		builder.into_inner().unwrap().finish().unwrap();
+		(dir, bundle)
+	}
+
+	#[test]
+	fn import_rejects_entries_outside_plugins_and_clones() {
+		let (_dir, bundle) = bundle_of(&[("clones/github/mitre/hipcheck/README.md", None)]);
+		let to = TempDir::with_prefix("hipcheck").unwrap();
+		import_bundle(to.path(), &bundle).unwrap();
+
+		for entries in [
+			&[("plugins/mitre/plugin.kdl", None)][..],
+			&[("config/Hipcheck.kdl", None)],
+			&[("clones/../../escaped", None)],
+			&[
+				("clones/github/mitre/hipcheck/README.md", None),
+				("plugins/mitre/git/0.1.0/hc-mitre-git", Some("/bin/sh")),
+			],
+		] {
+			let (_dir, bundle) = bundle_of(entries);
+			let to = TempDir::with_prefix("hipcheck").unwrap();
+			assert!(import_bundle(to.path(), &bundle).is_err());
+			// nothing is unpacked
+			assert!(std::fs::read_dir(to.path()).unwrap().next().is_none());
+		}
+	}
+
+	#[test]
+	fn export_rejects_dirs_outside_cache() {
+		let root = TempDir::with_prefix("hipcheck").unwrap();
+		let other = TempDir::with_prefix("hipcheck").unwrap();
+		let bundle = root.path().join("bundle.tar.gz");
+		assert!(export_bundle(root.path(), &[other.path().to_path_buf()], &bundle).is_err());
+	}
+}
@@ -1,10 +1,52 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use std::path::{Path, PathBuf};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
 
 use pathbuf::pathbuf;
+use tabled::{Table, Tabled};
+use walkdir::WalkDir;
 
-use crate::plugin::PluginId;
+use crate::{
+	cache::display_size,
+	error::Result,
+	hc_error,
+	plugin::{
+		check_path_component, try_get_bin_for_entrypoint, Arch, PluginId, PluginManifest,
+		PluginName, PluginPublisher, PluginVersion,
+	},
+};
+
+#[derive(Debug, Clone, Tabled)]
+struct PluginCacheEntry {
+	/// `<publisher>/<plugin_name>`
+	pub name: String,
+	pub version: String,
+	/// The arches the plugin's binary has been retrieved for
+	#[tabled(display_with("Self::display_arches", self), rename = "arch")]
+	pub arches: Vec<Arch>,
+	#[tabled(display_with("Self::display_size", self))]
+	pub size: usize,
+}
+impl PluginCacheEntry {
+	// Helper funcs for displaying PluginCacheEntry using `tabled` crate
+	fn display_arches(&self) -> String {
+		match self.arches.is_empty() {
+			true => "-".to_owned(),
+			false => self
+				.arches
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+				.join(", "),
+		}
+	}
+	fn display_size(&self) -> String {
+		display_size(self.size)
+	}
+}
 
 /// Plugins are stored with the following format `<path_to_plugin_cache>/<publisher>/<plugin_name>/<version>`
 pub struct HcPluginCache {
@@ -34,4 +76,210 @@ impl HcPluginCache {
 	pub fn plugin_kdl(&self, plugin_id: &PluginId) -> PathBuf {
 		self.plugin_download_dir(plugin_id).join("plugin.kdl")
 	}
+
+	/// The path to the record of the download a specific PluginId was retrieved from for `arch`,
+	/// which lets the download be checked again while offline
+	///
+	/// `<path_to_plugin_cache>/<publisher>/<plugin_name>/<version>/download-<arch>.kdl`
+	pub fn plugin_download_record(&self, plugin_id: &PluginId, arch: &Arch) -> PathBuf {
+		self.plugin_download_dir(plugin_id)
+			.join(format!("download-{}.kdl", arch))
+	}
+
+	/// The versions of a plugin which have already been retrieved into the cache
+	pub fn cached_versions(
+		&self,
+		publisher: &PluginPublisher,
+		name: &PluginName,
+	) -> Vec<PluginVersion> {
+		let Ok(entries) = std::fs::read_dir(self.path.join(publisher.as_ref()).join(name.as_ref()))
+		else {
+			return vec![];
+		};
+
+		entries
+			.flatten()
+			.filter(|entry| entry.path().join("plugin.kdl").exists())
+			.filter_map(|entry| entry.file_name().into_string().ok())
+			.map(PluginVersion)
+			.collect()
+	}
+
+	/// Collect one `PluginCacheEntry` per version of a plugin in the cache
+	fn entries(&self) -> Vec<PluginCacheEntry> {
+		let mut entries = WalkDir::new(&self.path)
+			.min_depth(3)
+			.max_depth(3)
+			.into_iter()
+			.filter_map(|e| e.ok())
+			.filter(|e| e.path().join("plugin.kdl").exists())
+			.filter_map(|e| path_to_cache_entry(e.path()).ok())
+			.collect::<Vec<_>>();
+		entries.sort_by(|a, b| {
+			a.name.cmp(&b.name).then_with(|| {
+				match (
+					semver::Version::parse(&a.version),
+					semver::Version::parse(&b.version),
+				) {
+					(Ok(a), Ok(b)) => a.cmp(&b),
+					_ => a.version.cmp(&b.version),
+				}
+			})
+		});
+		entries
+	}
+
+	/// List the plugins in the cache
+	pub fn list(&self) -> Result<()> {
+		println!("{}", Table::new(self.entries()));
+		Ok(())
+	}
+
+	/// Delete a plugin from the cache, either one version of it or all of them, returning the
+	/// ones deleted
+	pub fn remove(
+		&self,
+		publisher: &PluginPublisher,
+		name: &PluginName,
+		version: Option<&PluginVersion>,
+	) -> Result<Vec<PluginId>> {
+		// Never delete anything outside of the plugin's own directory
+		check_path_component("publisher", &publisher.0)?;
+		check_path_component("name", &name.0)?;
+		if let Some(version) = version {
+			check_path_component("version", &version.0)?;
+		}
+
+		let versions = match version {
+			Some(version) => vec![version.clone()],
+			None => self.cached_versions(publisher, name),
+		};
+
+		let mut removed = vec![];
+		for version in versions {
+			let plugin_id = PluginId::new(publisher.clone(), name.clone(), version);
+			let dir = self.plugin_download_dir(&plugin_id);
+			if !dir.exists() {
+				continue;
+			}
+			fs::remove_dir_all(&dir)
+				.map_err(|e| hc_error!("Error [{}] deleting {}", e, dir.display()))?;
+			removed.push(plugin_id);
+		}
+
+		if removed.is_empty() {
+			return Err(hc_error!(
+				"{}/{}{} is not in the plugin cache",
+				publisher.0,
+				name.0,
+				version.map(|v| format!(" {}", v.0)).unwrap_or_default()
+			));
+		}
+
+		// Don't leave behind the directory of a plugin with no versions left
+		let plugin_dir = self.path.join(publisher.as_ref()).join(name.as_ref());
+		if self.cached_versions(publisher, name).is_empty() {
+			let _ = fs::remove_dir_all(plugin_dir);
+		}
+
+		Ok(removed)
+	}
+}
+
+fn path_to_cache_entry(path: &Path) -> Result<PluginCacheEntry> {
+	let manifest = PluginManifest::from_file(path.join("plugin.kdl"))?;
+	let mut arches = manifest
+		.entrypoints
+		.0
+		.iter()
+		.filter(|(_, entrypoint)| {
+			try_get_bin_for_entrypoint(entrypoint)
+				.0
+				.is_some_and(|bin| path.join(bin).exists())
+		})
+		.map(|(arch, _)| arch.clone())
+		.collect::<Vec<_>>();
+	arches.sort_by_key(ToString::to_string);
+
+	Ok(PluginCacheEntry {
+		name: format!("{}/{}", manifest.publisher.0, manifest.name.0),
+		version: manifest.version.0,
+		arches,
+		size: fs_extra::dir::get_size(path)? as usize,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn cache_plugin(cache: &HcPluginCache, version: &str) -> PluginId {
+		let plugin_id = PluginId::new(
+			PluginPublisher("mitre".to_owned()),
+			PluginName("git".to_owned()),
+			PluginVersion(version.to_owned()),
+		);
+		let dir = cache.plugin_download_dir(&plugin_id);
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("git"), "").unwrap();
+		let manifest = format!(
+			r#"publisher "mitre"
+name "git"
+version "{version}"
+license "Apache-2.0"
+entrypoint {{
+  on arch="x86_64-unknown-linux-gnu" "git"
+  on arch="aarch64-apple-darwin" "git-mac"
+}}"#
+		);
+		fs::write(cache.plugin_kdl(&plugin_id), manifest).unwrap();
+		plugin_id
+	}
+
+	#[test]
+	fn plugin_cache_entries_and_remove() {
+		let tempdir = TempDir::with_prefix("hipcheck").unwrap();
+		let cache = HcPluginCache::new(tempdir.path());
+		cache_plugin(&cache, "0.10.0");
+		let old = cache_plugin(&cache, "0.2.0");
+
+		let entries = cache.entries();
+		let versions = entries
+			.iter()
+			.map(|e| e.version.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(versions, ["0.2.0", "0.10.0"]);
+		// Only the arch whose binary was retrieved is listed
+		assert_eq!(entries[0].display_arches(), "x86_64-unknown-linux-gnu");
+
+		let publisher = old.publisher().clone();
+		let name = old.name().clone();
+		let removed = cache
+			.remove(&publisher, &name, Some(old.version()))
+			.unwrap();
+		assert_eq!(removed, [old]);
+		assert_eq!(cache.cached_versions(&publisher, &name).len(), 1);
+
+		cache.remove(&publisher, &name, None).unwrap();
+		assert!(cache.entries().is_empty());
+		assert!(cache.remove(&publisher, &name, None).is_err());
+	}
+
+	#[test]
+	fn plugin_cache_remove_rejects_paths_outside_cache() {
+		let tempdir = TempDir::with_prefix("hipcheck").unwrap();
+		let cache = HcPluginCache::new(tempdir.path());
+		let plugin_id = cache_plugin(&cache, "0.1.0");
+		let publisher = plugin_id.publisher().clone();
+		let name = plugin_id.name().clone();
+
+		for version in ["..", "../..", "", "0.1.0/.."] {
+			let version = PluginVersion(version.to_owned());
+			assert!(cache.remove(&publisher, &name, Some(&version)).is_err());
+		}
+		let parent = PluginName("..".to_owned());
+		assert!(cache.remove(&publisher, &parent, None).is_err());
+		assert!(cache.plugin_kdl(&plugin_id).exists());
+	}
 }
@@ -0,0 +1,378 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+	cache::{
+		display_modified, display_size,
+		repo::{RepoCacheDeleteScope, RepoCacheListScope, RepoCacheSort},
+	},
+	error::Result,
+	hc_error,
+	plugin::{PluginId, QueryResult},
+};
+use dialoguer::Confirm;
+use pathbuf::pathbuf;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+use tabled::{Table, Tabled};
+use walkdir::WalkDir;
+
+/// Identifies a persisted query result.
+#[derive(Debug, Clone)]
+pub struct QueryCacheKey<'a> {
+	/// The plugin that answered the query, including its version.
+	pub plugin: &'a PluginId,
+	/// The configuration the plugin was given in the policy file.
+	pub config: &'a Value,
+	/// The name of the query.
+	pub query: &'a str,
+	/// The key the query was made with.
+	pub key: &'a Value,
+	/// The HEAD commit of the target repo, if the result depends on repo state.
+	pub commit: Option<&'a str>,
+	/// The plugins the plugin depends on, directly or through other plugins, since their answers
+	/// are part of its own.
+	pub dependencies: &'a [QueryCacheDependency],
+}
+
+/// A plugin depended on by the plugin that answered a query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryCacheDependency {
+	/// The plugin, as `publisher/name`.
+	pub plugin: String,
+	pub version: String,
+	/// The configuration the plugin was given in the policy file.
+	pub config: Value,
+}
+
+impl QueryCacheKey<'_> {
+	/// A digest over the plugin version and config, the versions and configs of its dependencies,
+	/// key JSON, and commit, used as the entry's file name.
+	fn digest(&self) -> String {
+		let commit = self.commit.unwrap_or_default();
+		let dependencies = self
+			.dependencies
+			.iter()
+			.map(|dep| format!("{}@{} {}\n", dep.plugin, dep.version, dep.config))
+			.collect::<String>();
+		sha256::digest(format!(
+			"{}\n{}\n{}{}\n{}",
+			self.plugin.version().0,
+			self.config,
+			dependencies,
+			commit,
+			self.key
+		))
+	}
+}
+
+// The on-disk representation of a single query result. What identifies the
+// result is stored alongside it so that a digest collision can be detected.
+#[derive(Debug, Serialize, Deserialize)]
+struct QueryCacheDiskEntry {
+	version: String,
+	config: Value,
+	key: Value,
+	commit: Option<String>,
+	#[serde(default)]
+	dependencies: Vec<QueryCacheDependency>,
+	value: Value,
+	concerns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Tabled)]
+struct QueryCacheEntry {
+	/// `<publisher>/<plugin_name>/<query_name>`
+	pub name: String,
+	pub version: String,
+	pub entries: usize,
+	#[tabled(skip)]
+	pub path: PathBuf,
+	#[tabled(display_with("Self::display_size", self))]
+	pub size: usize,
+	#[tabled(display_with("Self::display_modified", self))]
+	pub modified: SystemTime,
+}
+impl QueryCacheEntry {
+	// Helper funcs for displaying QueryCacheEntry using `tabled` crate
+	fn display_modified(&self) -> String {
+		display_modified(self.modified)
+	}
+	fn display_size(&self) -> String {
+		display_size(self.size)
+	}
+}
+
+/// Query results are stored with the format
+/// `<path_to_query_cache>/<publisher>/<plugin_name>/<version>/<query_name>/<digest>.json`
+pub struct HcQueryCache {
+	/// path to the root of the query cache
+	path: PathBuf,
+}
+
+impl HcQueryCache {
+	pub fn new(path: &Path) -> Self {
+		let queries_path = pathbuf![path, "queries"];
+		Self { path: queries_path }
+	}
+
+	/// The folder in which results for one query of a specific PluginId are stored
+	///
+	/// `<path_to_query_cache>/<publisher>/<plugin_name>/<version>/<query_name>`
+	fn query_dir(&self, plugin_id: &PluginId, query: &str) -> PathBuf {
+		// The default query has an empty name, which can't be used as a dir name
+		let query = if query.is_empty() { "default" } else { query };
+		self.path
+			.join(plugin_id.publisher().as_ref())
+			.join(plugin_id.name().as_ref())
+			.join(plugin_id.version().as_ref())
+			.join(query)
+	}
+
+	fn entry_path(&self, key: &QueryCacheKey) -> PathBuf {
+		self.query_dir(key.plugin, key.query)
+			.join(format!("{}.json", key.digest()))
+	}
+
+	/// Get a previously stored result for a query, if any.
+	///
+	/// Unreadable or mismatched entries are treated as misses.
+	pub fn get(&self, key: &QueryCacheKey) -> Option<QueryResult> {
+		let path = self.entry_path(key);
+		let data = fs::read_to_string(&path).ok()?;
+		let entry: QueryCacheDiskEntry = match serde_json::from_str(&data) {
+			Ok(entry) => entry,
+			Err(e) => {
+				log::debug!(
+					"Ignoring malformed query cache entry {}: {e}",
+					path.display()
+				);
+				return None;
+			}
+		};
+		if entry.version != key.plugin.version().0
+			|| &entry.config != key.config
+			|| &entry.key != key.key
+			|| entry.commit.as_deref() != key.commit
+			|| entry.dependencies != key.dependencies
+		{
+			return None;
+		}
+		Some(QueryResult {
+			value: entry.value,
+			concerns: entry.concerns,
+		})
+	}
+
+	/// Store the result of a query, replacing any existing entry.
+	///
+	/// The entry is written to a temporary file which then replaces it, so a
+	/// concurrent run never reads a partially-written entry.
+	pub fn put(&self, key: &QueryCacheKey, result: &QueryResult) -> Result<()> {
+		let dir = self.query_dir(key.plugin, key.query);
+		fs::create_dir_all(&dir)?;
+		let entry = QueryCacheDiskEntry {
+			version: key.plugin.version().0.clone(),
+			config: key.config.clone(),
+			key: key.key.clone(),
+			commit: key.commit.map(ToOwned::to_owned),
+			dependencies: key.dependencies.to_vec(),
+			value: result.value.clone(),
+			concerns: result.concerns.clone(),
+		};
+		let mut tmp_file = tempfile::NamedTempFile::new_in(&dir)?;
+		serde_json::to_writer(&mut tmp_file, &entry)?;
+		tmp_file
+			.persist(self.entry_path(key))
+			.map_err(|e| hc_error!("failed to save query cache entry: {}", e))?;
+		Ok(())
+	}
+
+	/// Collect one `QueryCacheEntry` per query dir in the cache
+	fn entries(&self) -> Vec<QueryCacheEntry> {
+		WalkDir::new(&self.path)
+			.min_depth(4)
+			.max_depth(4)
+			.into_iter()
+			.filter_map(|e| e.ok())
+			.filter(|e| e.path().is_dir())
+			.filter_map(|e| self.path_to_cache_entry(e.path()).ok())
+			.collect()
+	}
+
+	fn path_to_cache_entry(&self, path: &Path) -> Result<QueryCacheEntry> {
+		let rel = path.strip_prefix(&self.path)?;
+		let parts = rel
+			.iter()
+			.map(|p| p.to_str())
+			.collect::<Option<Vec<&str>>>()
+			.ok_or(hc_error!("query cache path is not valid UTF-8"))?;
+		let [publisher, plugin, version, query] = parts.as_slice() else {
+			return Err(hc_error!("unexpected query cache path {}", rel.display()));
+		};
+		let entries = fs::read_dir(path)?.count();
+		Ok(QueryCacheEntry {
+			name: format!("{publisher}/{plugin}/{query}"),
+			version: version.to_string(),
+			entries,
+			path: path.to_path_buf(),
+			size: fs_extra::dir::get_size(path)? as usize,
+			modified: fs::metadata(path)?.modified()?,
+		})
+	}
+
+	/// Internal function for sorting QueryCacheEntry Vecs
+	fn sort(entries: &mut [QueryCacheEntry], sort: RepoCacheSort, invert: bool) {
+		entries.sort_by(|a, b| {
+			let ord = match sort {
+				RepoCacheSort::Alpha => a.name.cmp(&b.name).then(a.version.cmp(&b.version)),
+				RepoCacheSort::Oldest => a.modified.cmp(&b.modified),
+				RepoCacheSort::Largest => b.size.cmp(&a.size),
+			};
+			if invert {
+				ord.reverse()
+			} else {
+				ord
+			}
+		});
+	}
+
+	fn filtered_entries(&self, filter: Option<String>) -> Result<Vec<QueryCacheEntry>> {
+		// Parse filter to a regex if provided
+		let opt_pat: Option<Regex> = match filter {
+			Some(raw_p) => Some(Regex::new(format!("^{raw_p}$").as_str())?),
+			None => None,
+		};
+		Ok(self
+			.entries()
+			.into_iter()
+			.filter(|e| match &opt_pat {
+				Some(pat) => pat.is_match(e.name.as_str()),
+				None => true,
+			})
+			.collect())
+	}
+
+	/// List cache entries
+	pub fn list(&self, scope: RepoCacheListScope, filter: Option<String>) -> Result<()> {
+		let mut entries = self.filtered_entries(filter)?;
+		HcQueryCache::sort(&mut entries, scope.sort, scope.invert);
+		if let Some(n) = scope.n {
+			entries.truncate(n);
+		}
+		println!("{}", Table::new(entries));
+		Ok(())
+	}
+
+	/// Delete cache entries
+	pub fn delete(
+		&self,
+		scope: RepoCacheDeleteScope,
+		filter: Option<String>,
+		force: bool,
+	) -> Result<()> {
+		let mut to_del = self.filtered_entries(filter)?;
+		if let RepoCacheDeleteScope::Group { sort, invert, n } = scope {
+			HcQueryCache::sort(&mut to_del, sort, invert);
+			to_del.truncate(n);
+		}
+		if to_del.is_empty() {
+			return Ok(());
+		}
+		if !force {
+			// Ask user for confirmation
+			println!("You will delete the following entries:");
+			println!("{}", Table::new(&to_del));
+			let conf = Confirm::new()
+				.with_prompt("Are you sure you want to delete?")
+				.interact()
+				.unwrap();
+			if !conf {
+				return Ok(());
+			}
+		}
+		for entry in to_del {
+			if let Err(e) = fs::remove_dir_all(&entry.path) {
+				println!("Failed to delete entry '{}': {e}", entry.name);
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::plugin::{PluginName, PluginPublisher, PluginVersion};
+	use tempfile::TempDir;
+
+	fn plugin_id() -> PluginId {
+		PluginId::new(
+			PluginPublisher("mitre".to_owned()),
+			PluginName("git".to_owned()),
+			PluginVersion("0.1.0".to_owned()),
+		)
+	}
+
+	#[test]
+	fn query_cache_round_trip() {
+		let tempdir = TempDir::with_prefix("hipcheck").unwrap();
+		let cache = HcQueryCache::new(tempdir.path());
+		let plugin = plugin_id();
+		let key_json = serde_json::json!({ "local": "/tmp/repo" });
+		let config = serde_json::json!({ "threshold": 3 });
+		let key = QueryCacheKey {
+			plugin: &plugin,
+			config: &config,
+			query: "",
+			key: &key_json,
+			commit: Some("abc123"),
+			dependencies: &[],
+		};
+		assert!(cache.get(&key).is_none());
+
+		let result = QueryResult {
+			value: serde_json::json!([1, 2, 3]),
+			concerns: vec!["concern".to_owned()],
+		};
+		cache.put(&key, &result).unwrap();
+		assert_eq!(cache.get(&key), Some(result));
+
+		// A different commit of the same target is a miss
+		let moved = QueryCacheKey {
+			commit: Some("def456"),
+			..key.clone()
+		};
+		assert!(cache.get(&moved).is_none());
+
+		// So is the same query with the plugin configured differently
+		let reconfigured_json = serde_json::json!({ "threshold": 4 });
+		let reconfigured = QueryCacheKey {
+			config: &reconfigured_json,
+			..key.clone()
+		};
+		assert!(cache.get(&reconfigured).is_none());
+
+		// Or with a dependency at another version
+		let dependencies = [QueryCacheDependency {
+			plugin: "mitre/linguist".to_owned(),
+			version: "0.2.0".to_owned(),
+			config: serde_json::json!({}),
+		}];
+		let dependency_changed = QueryCacheKey {
+			dependencies: &dependencies,
+			..key.clone()
+		};
+		assert!(cache.get(&dependency_changed).is_none());
+
+		let entries = cache.entries();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].name, "mitre/git/default");
+		assert_eq!(entries[0].version, "0.1.0");
+	}
+}
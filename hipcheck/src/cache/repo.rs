@@ -1,6 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{error::Result, hc_error};
+use crate::{
+	cache::{display_modified, display_size},
+	error::Result,
+	hc_error,
+};
 use dialoguer::Confirm;
 use git2::Repository;
 use pathbuf::pathbuf;
@@ -65,33 +69,10 @@ impl RepoCacheEntry {
 			.to_string()
 	}
 	fn display_modified(&self) -> String {
-		let Ok(dur) = self.modified.duration_since(SystemTime::UNIX_EPOCH) else {
-			return "<DISPLAY_ERROR>".to_owned();
-		};
-		let Some(dt) = chrono::DateTime::<chrono::offset::Utc>::from_timestamp(
-			dur.as_secs() as i64,
-			dur.subsec_nanos(),
-		) else {
-			return "<DISPLAY_ERROR>".to_owned();
-		};
-		let chars = dt.to_rfc2822().chars().collect::<Vec<char>>();
-		// Remove unnecessary " +0000" from end of rfc datetime str
-		chars[..chars.len() - 6].iter().collect()
+		display_modified(self.modified)
 	}
 	fn display_size(&self) -> String {
-		static ONE_KB: f64 = 1000.0;
-		static ONE_MB: f64 = ONE_KB * 1000.0;
-		static ONE_GB: f64 = ONE_MB * 1000.0;
-		let e_size = self.size as f64;
-		if e_size > ONE_GB {
-			format!("{:.2} GB", e_size / ONE_GB)
-		} else if e_size > ONE_MB {
-			format!("{:.2} MB", e_size / ONE_MB)
-		} else if e_size > ONE_KB {
-			format!("{:.2} KB", e_size / ONE_KB)
-		} else {
-			format!("{:.0} B", e_size)
-		}
+		display_size(self.size)
 	}
 }
 
@@ -12,14 +12,16 @@ use crate::{
 	shell::{color_choice::ColorChoice, verbosity::Verbosity},
 	source,
 	target::{
-		LocalGitRepo, MavenPackage, Package, PackageHost, Sbom, SbomStandard, TargetSeed,
-		TargetSeedKind, TargetType, ToTargetSeed, ToTargetSeedKind,
+		CommitRange, LocalGitRepo, Lockfile, LockfileKind, MavenPackage, Package, PackageHost,
+		Sbom, SbomStandard, TargetSeed, TargetSeedKind, TargetType, ToTargetSeed, ToTargetSeedKind,
 	},
 };
 use clap::{Parser as _, ValueEnum};
 use hipcheck_macros as hc;
 use pathbuf::pathbuf;
 use std::{
+	net::SocketAddr,
+	num::NonZeroUsize,
 	path::{Path, PathBuf},
 	str::FromStr,
 };
@@ -351,6 +353,8 @@ fn hc_env_var_value_enum<E: ValueEnum>(name: &'static str) -> Option<E> {
 /// All commands, both subcommands and flag-like commands.
 pub enum FullCommands {
 	Check(CheckArgs),
+	Diff(DiffArgs),
+	Serve(ServeArgs),
 	Schema(SchemaArgs),
 	Setup(SetupArgs),
 	Ready,
@@ -366,6 +370,8 @@ impl From<&Commands> for FullCommands {
 	fn from(command: &Commands) -> Self {
 		match command {
 			Commands::Check(args) => FullCommands::Check(args.clone()),
+			Commands::Diff(args) => FullCommands::Diff(args.clone()),
+			Commands::Serve(args) => FullCommands::Serve(args.clone()),
 			Commands::Schema(args) => FullCommands::Schema(args.clone()),
 			Commands::Setup(args) => FullCommands::Setup(args.clone()),
 			Commands::Ready => FullCommands::Ready,
@@ -381,6 +387,10 @@ impl From<&Commands> for FullCommands {
 pub enum Commands {
 	/// Analyze a package, source repository, SBOM, or pull request.
 	Check(CheckArgs),
+	/// Compare two reports saved from `hc check --format json`.
+	Diff(DiffArgs),
+	/// Run Hipcheck as an HTTP service which checks targets on request.
+	Serve(ServeArgs),
 	/// Print the JSON schema for output of a specific `check` command.
 	Schema(SchemaArgs),
 	/// Initialize Hipcheck config file and script file locations.
@@ -401,8 +411,7 @@ pub enum Commands {
 	Update(UpdateArgs),
 	/// Manage Hipcheck cache
 	Cache(CacheArgs),
-	/// Execute temporary code for exercising plugin engine
-	#[command(hide = true)]
+	/// Manage the plugin cache and the plugins used by the policy file
 	Plugin(PluginArgs),
 }
 
@@ -423,10 +432,38 @@ pub struct CheckArgs {
 	#[arg(long = "arch", value_parser = Arch::from_str)]
 	pub arch: Option<Arch>,
 
+	/// The maximum number of analyses to run at once. Defaults to the number of available CPUs
+	#[arg(long = "concurrency", value_name = "N")]
+	pub concurrency: Option<NonZeroUsize>,
+
+	/// Reuse analysis results saved by earlier runs, and save new ones, in the query cache
+	#[arg(long = "query-cache")]
+	pub query_cache: bool,
+
+	/// Connect to plugins over Unix domain sockets instead of TCP ports. Plugins must support the `--socket` flag
+	#[arg(long = "plugin-sockets")]
+	pub plugin_sockets: bool,
+
+	/// Compare the result to a report saved from an earlier run, printing what changed instead of the report
+	#[arg(long = "baseline", value_name = "REPORT")]
+	pub baseline: Option<PathBuf>,
+
+	/// Fail on any network access, taking plugins only from the plugin cache and repos only from the repo cache or local paths
+	#[arg(long = "offline")]
+	pub offline: bool,
+
+	/// Check each target listed in a file, one per line, starting the plugins only once for all of them
+	#[arg(
+		long = "targets-file",
+		value_name = "FILE",
+		conflicts_with_all = ["target", "refspec", "baseline"]
+	)]
+	pub targets_file: Option<PathBuf>,
+
 	#[arg(short = 't', long = "target")]
 	pub target_type: Option<TargetType>,
 	#[arg(
-		required = true,
+		required_unless_present = "targets_file",
 		help = "The target package, URL, commit, etc. for Hipcheck to analyze. If ambiguous, the -t flag must be set"
 	)]
 	pub target: Option<String>,
@@ -480,13 +517,7 @@ impl CheckArgs {
 			},
 		}
 
-		// We have resolved the subcommand type. Re-construct a string with all args
-		// that we can feed back into clap
-		let binding = "check".to_owned();
-		let mut reconst_args: Vec<&String> = vec![&binding, &subcmd_str, &target_str];
-		reconst_args.extend(self.trailing_args.iter());
-
-		CheckCommand::try_parse_from(reconst_args).map_err(|e| hc_error!("{}", e))
+		check_command_from_parts(&subcmd_str, &target_str, &self.trailing_args)
 	}
 
 	pub fn command(&self) -> Result<CheckCommand> {
@@ -497,6 +528,86 @@ impl CheckArgs {
 		}
 	}
 }
+/// Re-construct the args of a `check` subcommand whose type has been resolved,
+/// and feed them back into clap.
+fn check_command_from_parts(
+	subcmd_str: &String,
+	target_str: &String,
+	trailing_args: &[String],
+) -> Result<CheckCommand> {
+	let binding = "check".to_owned();
+	let mut reconst_args: Vec<&String> = vec![&binding, subcmd_str, target_str];
+	reconst_args.extend(trailing_args.iter());
+
+	CheckCommand::try_parse_from(reconst_args).map_err(|e| hc_error!("{}", e))
+}
+
+/// Resolve a target whose type can be inferred, like a pURL or Git VCS URL, to
+/// a target seed, the same as `hc check` does when no `-t` flag is given.
+pub fn infer_target_seed(target: &str) -> Result<TargetSeed> {
+	let (subcmd, target_str) = TargetType::try_resolve_from_target(target)
+		.ok_or_else(|| hc_error!("could not resolve target '{}' to a target type", target))?;
+	let kind =
+		check_command_from_parts(&subcmd.as_str(), &target_str, &[])?.to_target_seed_kind()?;
+
+	Ok(TargetSeed {
+		kind,
+		refspec: None,
+	})
+}
+
+/// Resolve a line of a targets file to a target seed. Each line holds the
+/// arguments `hc check` takes to name a single target, like `-t npm chalk@5.3.0`,
+/// split the way a shell would, so an argument with spaces can be quoted.
+pub fn target_seed_from_line(line: &str) -> Result<TargetSeed> {
+	let args = shlex::split(line).ok_or_else(|| hc_error!("unmatched quote in '{}'", line))?;
+	target_seed_from_args(args)
+}
+
+/// Resolve the arguments `hc check` takes to name a single target, like
+/// `["-t", "npm", "chalk@5.3.0"]`, to a target seed.
+pub fn target_seed_from_args<I, S>(args: I) -> Result<TargetSeed>
+where
+	I: IntoIterator<Item = S>,
+	S: Into<std::ffi::OsString> + Clone,
+{
+	use clap::{Args as _, FromArgMatches as _};
+
+	// Fit clap's errors on one line, leaving out the usage and tips.
+	let clap_error = |e: clap::Error| {
+		let msg = e.to_string();
+		let msg = msg
+			.lines()
+			.take_while(|line| !line.starts_with("Usage:"))
+			.map(str::trim)
+			.filter(|line| !line.is_empty() && !line.starts_with("tip:"))
+			.collect::<Vec<_>>()
+			.join(" ");
+		hc_error!("{}", msg.trim_start_matches("error: "))
+	};
+
+	let command = CheckArgs::augment_args(clap::Command::new("check").no_binary_name(true));
+	let matches = command.try_get_matches_from(args).map_err(clap_error)?;
+	let args = CheckArgs::from_arg_matches(&matches).map_err(clap_error)?;
+
+	// Everything besides the target applies to the whole run, and so must be
+	// given on the command line.
+	if args.arch.is_some()
+		|| args.concurrency.is_some()
+		|| args.query_cache
+		|| args.plugin_sockets
+		|| args.baseline.is_some()
+		|| args.targets_file.is_some()
+		|| args.offline
+	{
+		return Err(hc_error!(
+			"only a target, its type, and its ref may be given for each target"
+		));
+	}
+
+	args.to_target_seed()
+}
+
 impl ToTargetSeed for CheckArgs {
 	fn to_target_seed(&self) -> Result<TargetSeed> {
 		let kind = self.command()?.to_target_seed_kind()?;
@@ -506,10 +617,17 @@ impl ToTargetSeed for CheckArgs {
 		};
 		// Validate
 		if let Some(refspec) = &target.refspec {
-			if let TargetSeedKind::Package(p) = &target.kind {
-				if p.has_version() && &p.version != refspec {
+			match &target.kind {
+				TargetSeedKind::Package(p) if p.has_version() && &p.version != refspec => {
 					return Err(hc_error!("ambiguous version for package target: package target specified {}, but refspec flag specified {}. please specify only one.", p.version, refspec));
 				}
+				TargetSeedKind::PullRequest(_) | TargetSeedKind::CommitRange(_) => {
+					return Err(hc_error!(
+						"the refspec flag can't be used with {}, which already names the commits to analyze",
+						target.kind
+					));
+				}
+				_ => (),
 			}
 		};
 
@@ -520,6 +638,12 @@ impl ToTargetSeed for CheckArgs {
 
 #[derive(Debug, Clone, clap::Parser)]
 pub enum CheckCommand {
+	/// Analyze a Cargo package git repo via package URI or with format <package name>[@<optional version>]
+	#[command(hide = true)]
+	Cargo(CheckCargoArgs),
+	/// Analyze a Go module git repo via package URI or with format <module path>[@<optional version>]
+	#[command(hide = true)]
+	Go(CheckGoArgs),
 	/// Analyze a maven package git repo via package URI
 	#[command(hide = true)]
 	Maven(CheckMavenArgs),
@@ -532,23 +656,99 @@ pub enum CheckCommand {
 	/// Analyze a repository and output an overall risk assessment
 	#[command(hide = true)]
 	Repo(CheckRepoArgs),
+	/// Analyze only the commits a GitHub pull request or GitLab merge request adds
+	#[command(hide = true)]
+	Pr(CheckPrArgs),
+	/// Analyze only the commits in a range of a repository's history
+	#[command(hide = true)]
+	Range(CheckRangeArgs),
 	/// Analyze packages specified in an SBOM document
 	#[command(hide = true)]
 	Sbom(CheckSbomArgs),
+	/// Analyze every dependency listed in a lockfile
+	#[command(hide = true)]
+	Lockfile(CheckLockfileArgs),
 }
 
 impl ToTargetSeedKind for CheckCommand {
 	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
 		match self {
+			CheckCommand::Cargo(args) => args.to_target_seed_kind(),
+			CheckCommand::Go(args) => args.to_target_seed_kind(),
 			CheckCommand::Maven(args) => args.to_target_seed_kind(),
 			CheckCommand::Npm(args) => args.to_target_seed_kind(),
 			CheckCommand::Pypi(args) => args.to_target_seed_kind(),
 			CheckCommand::Repo(args) => args.to_target_seed_kind(),
+			CheckCommand::Pr(args) => args.to_target_seed_kind(),
+			CheckCommand::Range(args) => args.to_target_seed_kind(),
 			CheckCommand::Sbom(args) => args.to_target_seed_kind(),
+			CheckCommand::Lockfile(args) => args.to_target_seed_kind(),
 		}
 	}
 }
 
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckCargoArgs {
+	/// Cargo package URI or package[@<optional version>] to analyze
+	pub package: String,
+}
+
+impl ToTargetSeedKind for CheckCargoArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let raw_package = &self.package;
+
+		let (name, version) = match Url::parse(raw_package) {
+			Ok(url_parsed) => pm::extract_package_version_from_url(url_parsed)?,
+			_ => pm::extract_package_version(raw_package)?,
+		};
+
+		let purl = Url::parse(&match version.as_str() {
+			"no version" => format!("pkg:cargo/{}", name),
+			_ => format!("pkg:cargo/{}@{}", name, version),
+		})
+		.unwrap();
+
+		Ok(TargetSeedKind::Package(Package {
+			purl,
+			name,
+			version,
+			host: PackageHost::Cargo,
+		}))
+	}
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckGoArgs {
+	/// Go module URI or module[@<optional version>] to analyze
+	pub module: String,
+}
+
+impl ToTargetSeedKind for CheckGoArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let (name, version) = pm::extract_package_version(&self.module)?;
+
+		if name.is_empty() || name.contains("://") {
+			return Err(hc_error!(
+				"The provided Go module '{}' is not a module path",
+				self.module
+			));
+		}
+
+		let purl = Url::parse(&match version.as_str() {
+			"no version" => format!("pkg:golang/{}", name),
+			_ => format!("pkg:golang/{}@{}", name, version),
+		})
+		.map_err(|e| hc_error!("The provided Go module '{}' is not valid. {}", name, e))?;
+
+		Ok(TargetSeedKind::Package(Package {
+			purl,
+			name,
+			version,
+			host: PackageHost::Go,
+		}))
+	}
+}
+
 #[derive(Debug, Clone, clap::Args)]
 pub struct CheckMavenArgs {
 	/// Maven package URI to analyze
@@ -643,6 +843,7 @@ impl ToTargetSeedKind for CheckRepoArgs {
 				Ok(TargetSeedKind::LocalRepo(LocalGitRepo {
 					path,
 					git_ref: "".to_owned(),
+					base_ref: None,
 				}))
 			} else {
 				Err(hc_error!("Provided target repository could not be identified as either a remote url or path to a local file"))
@@ -651,10 +852,70 @@ impl ToTargetSeedKind for CheckRepoArgs {
 	}
 }
 
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckPrArgs {
+	/// URL of the GitHub pull request or GitLab merge request to analyze
+	pub url: String,
+}
+
+impl ToTargetSeedKind for CheckPrArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let url = Url::parse(&self.url).map_err(|e| {
+			hc_error!(
+				"The provided pull request URL {} is not a valid URL. {}",
+				self.url,
+				e
+			)
+		})?;
+		let pull_request = source::get_pull_request_from_url(url)?;
+		Ok(TargetSeedKind::PullRequest(pull_request))
+	}
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckRangeArgs {
+	/// Repository to analyze; can be a local path or a URI
+	pub source: String,
+	/// Commits to analyze, with format <base>..<head>, where <base> isn't analyzed
+	pub range: String,
+}
+
+impl ToTargetSeedKind for CheckRangeArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let Some((base, head)) = self.range.split_once("..") else {
+			return Err(hc_error!(
+				"The provided commit range '{}' is not of the form <base>..<head>",
+				self.range
+			));
+		};
+
+		if base.is_empty() || head.is_empty() || head.starts_with('.') {
+			return Err(hc_error!(
+				"The provided commit range '{}' must name both a base and a head commit",
+				self.range
+			));
+		}
+
+		let repo = CheckRepoArgs {
+			source: self.source.clone(),
+		}
+		.to_target_seed_kind()?;
+
+		Ok(TargetSeedKind::CommitRange(CommitRange {
+			repo: Box::new(repo),
+			base: base.to_owned(),
+			head: head.to_owned(),
+		}))
+	}
+}
+
 #[derive(Debug, Clone, clap::Args)]
 pub struct CheckSbomArgs {
 	/// SPDX document to analyze
 	pub path: String,
+	/// Only analyze the package the SBOM describes, not each of its components
+	#[arg(long = "root-only")]
+	pub root_only: bool,
 }
 
 impl ToTargetSeedKind for CheckSbomArgs {
@@ -686,6 +947,31 @@ impl ToTargetSeedKind for CheckSbomArgs {
 	}
 }
 
+#[derive(Debug, Clone, clap::Args)]
+pub struct CheckLockfileArgs {
+	/// Cargo.lock, package-lock.json, poetry.lock, or requirements.txt file to analyze
+	pub path: String,
+}
+
+impl ToTargetSeedKind for CheckLockfileArgs {
+	fn to_target_seed_kind(&self) -> Result<TargetSeedKind> {
+		let path = PathBuf::from(&self.path);
+		if !path.exists() {
+			return Err(hc_error!("The provided lockfile does not exist"));
+		}
+
+		let kind = path
+			.file_name()
+			.and_then(|name| name.to_str())
+			.and_then(LockfileKind::from_file_name)
+			.ok_or_else(|| {
+				hc_error!("The provided file is not a Cargo.lock, package-lock.json, poetry.lock, or requirements.txt file")
+			})?;
+
+		Ok(TargetSeedKind::Lockfile(Lockfile { path, kind }))
+	}
+}
+
 #[derive(Debug, Clone, clap::Args)]
 pub struct SchemaArgs {
 	#[clap(subcommand)]
@@ -694,6 +980,10 @@ pub struct SchemaArgs {
 
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum SchemaCommand {
+	/// Print the JSON schema for running Hipcheck against a Cargo package
+	Cargo,
+	/// Print the JSON schema for running Hipcheck against a Go module
+	Go,
 	/// Print the JSON schema for running Hipcheck against a Maven package
 	Maven,
 	/// Print the JSON schema for running Hipcheck against a NPM package
@@ -702,6 +992,51 @@ pub enum SchemaCommand {
 	Pypi,
 	/// Print the JSON schema for running Hipcheck against a source repository
 	Repo,
+	/// Print the JSON schema for running Hipcheck against every component of an SBOM
+	Sbom,
+	/// Print the JSON schema for running Hipcheck against every dependency in a lockfile
+	Lockfile,
+	/// Print the JSON schema for comparing two reports
+	Diff,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct DiffArgs {
+	/// The earlier report
+	pub old: PathBuf,
+	/// The later report
+	pub new: PathBuf,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct ServeArgs {
+	/// The address to listen on
+	#[arg(long = "address", default_value = "127.0.0.1:8080")]
+	pub address: SocketAddr,
+
+	/// A directory of policy files which requests can choose between by file name, without the `.kdl` extension
+	#[arg(long = "policies", value_name = "DIR")]
+	pub policies: Option<PathBuf>,
+
+	/// The most checks which can wait to be run at once. Requests beyond it are refused
+	#[arg(long = "queue-size", value_name = "N", default_value = "16")]
+	pub queue_size: NonZeroUsize,
+
+	/// The maximum number of analyses to run at once for a check. Defaults to the number of available CPUs
+	#[arg(long = "concurrency", value_name = "N")]
+	pub concurrency: Option<NonZeroUsize>,
+
+	/// Reuse analysis results saved by earlier checks, and save new ones, in the query cache
+	#[arg(long = "query-cache")]
+	pub query_cache: bool,
+
+	/// Connect to plugins over Unix domain sockets instead of TCP ports. Plugins must support the `--socket` flag
+	#[arg(long = "plugin-sockets")]
+	pub plugin_sockets: bool,
+
+	/// A directory local repositories, SBOMs, and lockfiles can be checked from. Local targets are refused unless they're in one. Can be given more than once
+	#[arg(long = "allow-local", value_name = "DIR")]
+	pub allow_local: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone, clap::Args)]
@@ -753,18 +1088,43 @@ impl TryFrom<CacheArgs> for CacheOp {
 	}
 }
 
+// Which of Hipcheck's caches a CacheOp applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+	Repo,
+	Query,
+}
+impl CacheKind {
+	fn from_queries_flag(queries: bool) -> Self {
+		if queries {
+			CacheKind::Query
+		} else {
+			CacheKind::Repo
+		}
+	}
+}
+
 // The target struct to which a CacheArgs instance must be translated
 #[derive(Debug, Clone)]
 pub enum CacheOp {
 	List {
+		kind: CacheKind,
 		scope: RepoCacheListScope,
 		filter: Option<String>,
 	},
 	Delete {
+		kind: CacheKind,
 		scope: RepoCacheDeleteScope,
 		filter: Option<String>,
 		force: bool,
 	},
+	Export {
+		targets_file: Option<PathBuf>,
+		output: PathBuf,
+	},
+	Import {
+		bundle: PathBuf,
+	},
 }
 
 #[derive(Debug, Clone, clap::Subcommand)]
@@ -774,6 +1134,10 @@ pub enum CacheSubcmds {
 	List(CliCacheListArgs),
 	/// Delete existing caches.
 	Delete(CliCacheDeleteArgs),
+	/// Bundle the plugins and repos needed to check targets, for importing somewhere offline.
+	Export(CliCacheExportArgs),
+	/// Import a bundle made by `hc cache export`.
+	Import(CliCacheImportArgs),
 }
 impl TryFrom<CacheSubcmds> for CacheOp {
 	type Error = crate::error::Error;
@@ -782,6 +1146,8 @@ impl TryFrom<CacheSubcmds> for CacheOp {
 		match value {
 			List(args) => Ok(args.into()),
 			Delete(args) => args.try_into(),
+			Export(args) => Ok(args.into()),
+			Import(args) => Ok(args.into()),
 		}
 	}
 }
@@ -829,6 +1195,9 @@ pub struct CliCacheListArgs {
 	/// Consider only entries matching this pattern
 	#[arg(short = 'P', long = "pattern")]
 	pub filter: Option<String>,
+	/// List cached plugin query results instead of repositories
+	#[arg(long)]
+	pub queries: bool,
 }
 impl From<CliCacheListArgs> for CacheOp {
 	fn from(value: CliCacheListArgs) -> Self {
@@ -839,6 +1208,7 @@ impl From<CliCacheListArgs> for CacheOp {
 			n: value.max,
 		};
 		CacheOp::List {
+			kind: CacheKind::from_queries_flag(value.queries),
 			scope,
 			filter: value.filter,
 		}
@@ -859,6 +1229,9 @@ pub struct CliCacheDeleteArgs {
 	/// Do not prompt user to confirm the entries to delete
 	#[arg(long, default_value_t = false)]
 	pub force: bool,
+	/// Delete cached plugin query results instead of repositories
+	#[arg(long)]
+	pub queries: bool,
 }
 // Must be fallible conversion because we are doing validation that clap can't
 // support as of writing
@@ -874,6 +1247,7 @@ impl TryFrom<CliCacheDeleteArgs> for CacheOp {
 		}
 		let scope: RepoCacheDeleteScope = value.strategy.try_into()?;
 		Ok(CacheOp::Delete {
+			kind: CacheKind::from_queries_flag(value.queries),
 			scope,
 			filter: value.filter,
 			force: value.force,
@@ -881,6 +1255,39 @@ impl TryFrom<CliCacheDeleteArgs> for CacheOp {
 	}
 }
 
+// Args for `hc cache export`
+#[derive(Debug, Clone, clap::Args)]
+pub struct CliCacheExportArgs {
+	/// A file listing the targets to clone into the bundle, one per line, as for `hc check --targets-file`
+	#[arg(long = "targets-file", value_name = "FILE")]
+	pub targets_file: Option<PathBuf>,
+	/// Where to write the bundle, a `.tar.gz` file
+	#[arg(short = 'o', long = "output", value_name = "FILE")]
+	pub output: PathBuf,
+}
+impl From<CliCacheExportArgs> for CacheOp {
+	fn from(value: CliCacheExportArgs) -> Self {
+		CacheOp::Export {
+			targets_file: value.targets_file,
+			output: value.output,
+		}
+	}
+}
+
+// Args for `hc cache import`
+#[derive(Debug, Clone, clap::Args)]
+pub struct CliCacheImportArgs {
+	/// The bundle made by `hc cache export`
+	pub bundle: PathBuf,
+}
+impl From<CliCacheImportArgs> for CacheOp {
+	fn from(value: CliCacheImportArgs) -> Self {
+		CacheOp::Import {
+			bundle: value.bundle,
+		}
+	}
+}
+
 // A valid cli string for CacheDeleteScope may be:
 //  1. "all"
 //  2. "<SORT> <N>", where <SORT> is one of the CliSortStrategy variants, <N> is
@@ -927,10 +1334,55 @@ impl TryFrom<Vec<String>> for RepoCacheDeleteScope {
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct PluginArgs {
-	#[arg(long = "async")]
+	#[clap(subcommand)]
+	pub command: Option<PluginCommand>,
+
+	/// Execute temporary code for exercising plugin engine
+	#[arg(long = "async", hide = true)]
 	pub asynch: bool,
 }
 
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum PluginCommand {
+	/// Retrieve the plugins the policy file uses again and update its `Hipcheck.lock`
+	Update,
+	/// List the plugins in the plugin cache
+	List,
+	/// Retrieve the plugins a policy file uses into the plugin cache, without checking anything
+	Fetch(PluginFetchArgs),
+	/// Print a cached plugin's manifest, query schemas, and default policy expression
+	Info(PluginInfoArgs),
+	/// Delete a plugin from the plugin cache
+	Remove(PluginRemoveArgs),
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginFetchArgs {
+	/// The policy file whose plugins to retrieve. Defaults to the one given with -p/--policy
+	pub policy: Option<PathBuf>,
+	/// Add the retrieved plugins which aren't locked yet to the policy file's `Hipcheck.lock`
+	#[arg(long = "lock")]
+	pub lock: bool,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginInfoArgs {
+	/// The plugin, as `<publisher>/<name>`
+	pub plugin: String,
+	/// The version of the plugin. Defaults to the newest one in the cache
+	#[arg(long = "version")]
+	pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PluginRemoveArgs {
+	/// The plugin, as `<publisher>/<name>`
+	pub plugin: String,
+	/// The version of the plugin to delete. Defaults to every version in the cache
+	#[arg(long = "version")]
+	pub version: Option<String>,
+}
+
 /// The format to report results in.
 #[derive(Debug, Default, Clone, Copy, clap::ValueEnum)]
 pub enum Format {
@@ -939,6 +1391,8 @@ pub enum Format {
 	/// Human-readable format.
 	#[default]
 	Human,
+	/// SARIF 2.1.0 format, for code scanning tools.
+	Sarif,
 }
 
 impl Format {
@@ -1173,11 +1627,16 @@ mod tests {
 
 	fn get_target_from_cmd(cmd: CheckCommand) -> String {
 		match cmd {
+			CheckCommand::Cargo(args) => args.package,
+			CheckCommand::Go(args) => args.module,
 			CheckCommand::Maven(args) => args.package,
 			CheckCommand::Npm(args) => args.package,
 			CheckCommand::Pypi(args) => args.package,
 			CheckCommand::Repo(args) => args.source,
+			CheckCommand::Pr(args) => args.url,
+			CheckCommand::Range(args) => args.source,
 			CheckCommand::Sbom(args) => args.path,
+			CheckCommand::Lockfile(args) => args.path,
 		}
 	}
 
@@ -1192,6 +1651,17 @@ mod tests {
 		assert!(matches!(cmd, Ok(CheckCommand::Repo(..))));
 	}
 
+	#[test]
+	fn test_infer_target_seed_from_purl() {
+		let seed = infer_target_seed("pkg:npm/left-pad@1.3.0").unwrap();
+		let TargetSeedKind::Package(package) = seed.kind else {
+			panic!("expected a package target");
+		};
+		assert_eq!(package.name, "left-pad");
+		assert_eq!(package.version, "1.3.0");
+		assert!(infer_target_seed("pkg:unsupportedtype/someurl").is_err());
+	}
+
 	#[test]
 	fn test_deductive_check_no_match() {
 		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "pkg:unsupportedtype/someurl"]);
@@ -1254,6 +1724,29 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_deductive_check_cargo_purl() {
+		let package = "serde@1.0.200".to_string();
+		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "pkg:cargo/serde@1.0.200"]);
+		assert!(matches!(cmd, Ok(CheckCommand::Cargo(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, package);
+		}
+	}
+
+	#[test]
+	fn test_deductive_check_go_purl() {
+		let module = "golang.org/x/text@v0.16.0".to_string();
+		let cmd =
+			get_check_cmd_from_cli(vec!["hc", "check", "pkg:golang/golang.org/x/text@v0.16.0"]);
+		assert!(matches!(cmd, Ok(CheckCommand::Go(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, module);
+		}
+	}
+
 	#[test]
 	fn test_deductive_check_repo_vcs_https() {
 		let url = "https://github.com/mitre/hipcheck.git".to_string();
@@ -1296,6 +1789,101 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_deductive_check_lockfile() {
+		let cmd = get_check_cmd_from_cli(vec!["hc", "check", "../app/package-lock.json"]);
+		assert!(matches!(cmd, Ok(CheckCommand::Lockfile(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, "../app/package-lock.json");
+		}
+	}
+
+	#[test]
+	fn test_deductive_check_pull_request() {
+		let url = "https://github.com/mitre/hipcheck/pull/123";
+		let cmd = get_check_cmd_from_cli(vec!["hc", "check", url]);
+		assert!(matches!(cmd, Ok(CheckCommand::Pr(..))));
+		if let Ok(chk_cmd) = cmd {
+			let target = get_target_from_cmd(chk_cmd);
+			assert_eq!(target, url);
+		}
+	}
+
+	#[test]
+	fn test_check_range() {
+		let cmd = get_check_cmd_from_cli(vec![
+			"hc",
+			"check",
+			"range",
+			"https://github.com/mitre/hipcheck.git",
+			"v3.0.0..main",
+		]);
+		let Ok(TargetSeedKind::CommitRange(range)) = cmd.unwrap().to_target_seed_kind() else {
+			panic!("expected a commit range target");
+		};
+		assert_eq!(range.base, "v3.0.0");
+		assert_eq!(range.head, "main");
+		assert!(matches!(*range.repo, TargetSeedKind::RemoteRepo(..)));
+
+		let cmd = get_check_cmd_from_cli(vec![
+			"hc",
+			"check",
+			"range",
+			"https://github.com/mitre/hipcheck.git",
+			"v3.0.0...main",
+		]);
+		assert!(cmd.unwrap().to_target_seed_kind().is_err());
+	}
+
+	#[test]
+	fn test_target_seed_from_line() {
+		let seed = target_seed_from_line("-t npm chalk@5.3.0").unwrap();
+		let TargetSeedKind::Package(package) = seed.kind else {
+			panic!("expected a package target");
+		};
+		assert_eq!(package.host, PackageHost::Npm);
+		assert_eq!(package.version, "5.3.0");
+
+		let seed =
+			target_seed_from_line("--ref v3.0.0 https://github.com/mitre/hipcheck.git").unwrap();
+		assert!(matches!(seed.kind, TargetSeedKind::RemoteRepo(..)));
+		assert_eq!(seed.refspec.as_deref(), Some("v3.0.0"));
+
+		assert!(target_seed_from_line("--concurrency 2 pkg:npm/chalk@5.3.0").is_err());
+		assert!(target_seed_from_line("--not-a-flag pkg:npm/chalk@5.3.0").is_err());
+		assert!(target_seed_from_line("-t npm 'chalk@5.3.0").is_err());
+	}
+
+	#[test]
+	fn test_target_seed_from_line_with_quoted_path() {
+		let dir = tempfile::TempDir::with_prefix("hipcheck repo").unwrap();
+		let line = format!("-t repo '{}'", dir.path().display());
+		let seed = target_seed_from_line(&line).unwrap();
+		let TargetSeedKind::LocalRepo(repo) = seed.kind else {
+			panic!("expected a local repo target");
+		};
+		assert_eq!(repo.path, dir.path().canonicalize().unwrap());
+	}
+
+	#[test]
+	fn test_check_targets_file() {
+		let parsed = CliConfig::try_parse_from(["hc", "check", "--targets-file", "list.txt"]);
+		let Some(Commands::Check(args)) = parsed.unwrap().command else {
+			panic!("expected a check command");
+		};
+		assert_eq!(args.targets_file, Some(PathBuf::from("list.txt")));
+
+		let parsed = CliConfig::try_parse_from([
+			"hc",
+			"check",
+			"--targets-file",
+			"list.txt",
+			"pkg:npm/chalk@5.3.0",
+		]);
+		assert!(parsed.is_err());
+	}
+
 	#[test]
 	fn test_check_with_target_flag() {
 		let cmd = get_check_cmd_from_cli(vec![
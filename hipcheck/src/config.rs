@@ -14,6 +14,7 @@ use crate::{
 	util::fs as file,
 	BINARY_CONFIG_FILE, F64, LANGS_FILE, ORGS_FILE, TYPO_FILE,
 };
+use indexmap::IndexMap;
 use indextree::{Arena, NodeEdge, NodeId};
 use num_traits::identities::Zero;
 use pathbuf::pathbuf;
@@ -21,7 +22,6 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smart_default::SmartDefault;
 use std::{
-	collections::HashMap,
 	default::Default,
 	path::{Path, PathBuf},
 	rc::Rc,
@@ -570,7 +570,7 @@ impl AnalysisTreeNode {
 	}
 	pub fn augment_plugin(
 		&self,
-		metrics: &HashMap<Analysis, PluginAnalysisResult>,
+		metrics: &IndexMap<Analysis, PluginAnalysisResult>,
 	) -> ScoreTreeNode {
 		match self {
 			AnalysisTreeNode::Category { label, weight } => ScoreTreeNode {
@@ -5,20 +5,26 @@ use crate::{
 	cache::plugin::HcPluginCache,
 	hc_error,
 	plugin::{
-		get_current_arch, get_plugin_key, retrieve_plugins, Plugin, PluginManifest, PluginResponse,
+		get_current_arch, get_plugin_key, retrieve_plugins, ActivePlugin, LockfileUpdate, Plugin,
+		PluginConnection, PluginFailure, PluginManifest, PluginResponse, PluginTransport, QueryKey,
 		QueryResult,
 	},
 	policy::PolicyFile,
 	Result,
 };
-use futures::future::{BoxFuture, FutureExt};
+use futures::future::{try_join_all, BoxFuture, FutureExt};
 use serde_json::Value;
-use std::sync::{Arc, LazyLock};
+use std::{
+	path::Path,
+	sync::{Arc, LazyLock},
+};
 use tokio::runtime::{Handle, Runtime};
 
-// Salsa doesn't natively support async functions, so our recursive `query()` function that
-// interacts with plugins (which use async) has to get a handle to the underlying runtime,
-// spawn and block on a task to query the plugin, then choose whether to recurse or return.
+// Salsa doesn't natively support async functions, so plugin queries are not run
+// through salsa. Instead, `async_query()` is the engine's query path, and it
+// memoizes results in the `QueryCache` owned by `HcPluginCore`. This lets many
+// plugin queries be in flight at once, which salsa's blocking model did not.
+// Synchronous callers can use `HcEngineImpl::runtime()` to block on a query.
 
 static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| Runtime::new().unwrap());
 
@@ -35,13 +41,12 @@ pub trait HcEngine: salsa::Database {
 		plugin: String,
 	) -> Result<Option<String>>;
 
-	fn query(
+	fn output_schema(
 		&self,
 		publisher: String,
 		plugin: String,
 		query: String,
-		key: Value,
-	) -> Result<QueryResult>;
+	) -> Result<Option<Value>>;
 }
 
 fn default_policy_expr(
@@ -70,107 +75,184 @@ fn default_query_explanation(
 	Ok(p_handle.get_default_query_explanation().cloned())
 }
 
-fn query(
+fn output_schema(
 	db: &dyn HcEngine,
 	publisher: String,
 	plugin: String,
 	query: String,
-	key: Value,
-) -> Result<QueryResult> {
-	let runtime = RUNTIME.handle();
+) -> Result<Option<Value>> {
 	let core = db.core();
-	let hash_key = get_plugin_key(publisher.as_str(), plugin.as_str());
-
-	// Find the plugin
-	let Some(p_handle) = core.plugins.get(&hash_key) else {
-		return Err(hc_error!("No such plugin {}", hash_key));
-	};
-	// Initiate the query. If remote closed or we got our response immediately,
-	// return
-	let mut ar = match runtime.block_on(p_handle.query(query, key))? {
-		PluginResponse::RemoteClosed => {
-			return Err(hc_error!("Plugin channel closed unexpected"));
-		}
-		PluginResponse::Completed(v) => return Ok(v),
-		PluginResponse::AwaitingResult(a) => a,
+	let key = get_plugin_key(publisher.as_str(), plugin.as_str());
+	let Some(p_handle) = core.plugins.get(&key) else {
+		return Err(hc_error!("Plugin '{}' not found", key,));
 	};
-	// Otherwise, the plugin needs more data to continue. Recursively query
-	// (with salsa memo-ization) to get the needed data, and resume our
-	// current query by providing the plugin the answer.
-	loop {
-		log::trace!("Query needs more info, recursing...");
-		let answer = db
-			.query(
-				ar.publisher.clone(),
-				ar.plugin.clone(),
-				ar.query.clone(),
-				ar.key.clone(),
-			)?
-			.value;
-		log::trace!("Got answer, resuming");
-		ar = match runtime.block_on(p_handle.resume_query(ar, answer))? {
-			PluginResponse::RemoteClosed => {
-				return Err(hc_error!("Plugin channel closed unexpected"));
-			}
-			PluginResponse::Completed(v) => return Ok(v),
-			PluginResponse::AwaitingResult(a) => a,
-		};
-	}
+	Ok(p_handle.get_output_schema(&query).cloned())
 }
 
-// Demonstration of how the above `query()` function would be implemented as async
+/// Query a plugin, returning the memoized result if the same query was made before.
+///
+/// Concurrent calls for the same (publisher, plugin, query, key) share a single
+/// request to the plugin.
 pub fn async_query(
 	core: Arc<HcPluginCore>,
 	publisher: String,
 	plugin: String,
 	query: String,
 	key: Value,
+) -> BoxFuture<'static, Result<QueryResult>> {
+	memoized_query(core, (publisher, plugin, query, key), None)
+}
+
+// `parent` is the query waiting on this one, if any. Salsa used to detect query
+// cycles for us; without checking for them a cycle would wait on its own cache
+// entry forever. Since queries run concurrently, the queries in a cycle needn't
+// have been made by each other, so waits are tracked across all of them.
+fn memoized_query(
+	core: Arc<HcPluginCore>,
+	target: QueryKey,
+	parent: Option<QueryKey>,
 ) -> BoxFuture<'static, Result<QueryResult>> {
 	async move {
+		let _waiting = match parent {
+			Some(parent) => Some(Waiting::start(&core, parent, target.clone())?),
+			None => None,
+		};
+		let cell = core.cache.entry(target.clone()).await;
+		cell.get_or_try_init(|| run_query(Arc::clone(&core), target))
+			.await
+			.cloned()
+	}
+	.boxed()
+}
+
+/// A query waiting on the result of another, recorded in the query cache for as
+/// long as it lives.
+struct Waiting {
+	core: Arc<HcPluginCore>,
+	waiter: QueryKey,
+	target: QueryKey,
+}
+
+impl Waiting {
+	fn start(core: &Arc<HcPluginCore>, waiter: QueryKey, target: QueryKey) -> Result<Waiting> {
+		if !core.cache.start_wait(&waiter, &target) {
+			let (publisher, plugin, query, _) = target;
+			return Err(hc_error!(
+				"Query '{}' on plugin '{}' depends on itself",
+				query,
+				get_plugin_key(publisher.as_str(), plugin.as_str())
+			));
+		}
+		Ok(Waiting {
+			core: Arc::clone(core),
+			waiter,
+			target,
+		})
+	}
+}
+
+impl Drop for Waiting {
+	fn drop(&mut self) {
+		self.core.cache.end_wait(&self.waiter, &self.target);
+	}
+}
+
+fn run_query(core: Arc<HcPluginCore>, target: QueryKey) -> BoxFuture<'static, Result<QueryResult>> {
+	async move {
+		let (publisher, plugin, query, key) = target.clone();
 		// Find the plugin
 		let hash_key = get_plugin_key(publisher.as_str(), plugin.as_str());
 		let Some(p_handle) = core.plugins.get(&hash_key) else {
 			return Err(hc_error!("No such plugin {}", hash_key));
 		};
-		// Initiate the query. If remote closed or we got our response immediately,
-		// return
-		log::trace!("Querying: {query}, key: {key:?}");
-		let mut ar = match p_handle.query(query, key).await? {
+		// If the plugin itself fails, restart it if it has stopped serving and run
+		// the query again from the start. Its sub-queries were memoized, so only
+		// the plugin's own work is repeated.
+		loop {
+			let transport = p_handle.transport().await;
+			let res = run_session(
+				&core,
+				p_handle,
+				&transport,
+				query.clone(),
+				key.clone(),
+				&target,
+			)
+			.await;
+			let err = match res {
+				Err(e)
+					if e.downcast_ref::<PluginFailure>()
+						.is_some_and(|f| f.plugin == hash_key) =>
+				{
+					e
+				}
+				res => return res,
+			};
+			match p_handle.recover(&transport).await {
+				Ok(true) => continue,
+				Ok(false) => return Err(err),
+				Err(restart_err) => return Err(err.context(restart_err.to_string())),
+			}
+		}
+	}
+	.boxed()
+}
+
+// Run a query over one transport to the plugin, answering any queries the
+// plugin makes of other plugins until it completes.
+async fn run_session(
+	core: &Arc<HcPluginCore>,
+	p_handle: &ActivePlugin,
+	transport: &PluginTransport,
+	query: String,
+	key: Value,
+	target: &QueryKey,
+) -> Result<QueryResult> {
+	// Initiate the query. If remote closed or we got our response immediately,
+	// return
+	log::trace!("Querying: {query}, key: {key:?}");
+	let mut ar = match p_handle.query(transport, query, key).await? {
+		PluginResponse::RemoteClosed => {
+			return Err(p_handle.failure(transport, "Plugin channel closed unexpected"));
+		}
+		PluginResponse::Completed(v) => {
+			return Ok(v);
+		}
+		PluginResponse::AwaitingResult(a) => a,
+	};
+	// Otherwise, the plugin needs more data to continue. Recursively query
+	// (with memo-ization) to get the needed data, and resume our current
+	// query by providing the plugin the answer. A batch query is split into
+	// one query per key, so each key is cached as if queried on its own.
+	loop {
+		log::trace!("Awaiting result, now recursing");
+		let answer = try_join_all(ar.keys.iter().map(|key| {
+			memoized_query(
+				Arc::clone(core),
+				(
+					ar.publisher.clone(),
+					ar.plugin.clone(),
+					ar.query.clone(),
+					key.clone(),
+				),
+				Some(target.clone()),
+			)
+		}))
+		.await?
+		.into_iter()
+		.map(|result| result.value)
+		.collect::<Vec<_>>();
+		log::trace!("Resuming query with answer {answer:?}");
+		ar = match p_handle.resume_query(transport, ar, answer).await? {
 			PluginResponse::RemoteClosed => {
-				return Err(hc_error!("Plugin channel closed unexpected"));
+				return Err(p_handle.failure(transport, "Plugin channel closed unexpected"));
 			}
 			PluginResponse::Completed(v) => {
 				return Ok(v);
 			}
 			PluginResponse::AwaitingResult(a) => a,
 		};
-		// Otherwise, the plugin needs more data to continue. Recursively query
-		// (with salsa memo-ization) to get the needed data, and resume our
-		// current query by providing the plugin the answer.
-		loop {
-			log::trace!("Awaiting result, now recursing");
-			let answer = async_query(
-				Arc::clone(&core),
-				ar.publisher.clone(),
-				ar.plugin.clone(),
-				ar.query.clone(),
-				ar.key.clone(),
-			)
-			.await?
-			.value;
-			log::trace!("Resuming query with answer {answer:?}");
-			ar = match p_handle.resume_query(ar, answer).await? {
-				PluginResponse::RemoteClosed => {
-					return Err(hc_error!("Plugin channel closed unexpected"));
-				}
-				PluginResponse::Completed(v) => {
-					return Ok(v);
-				}
-				PluginResponse::AwaitingResult(a) => a,
-			};
-		}
 	}
-	.boxed()
 }
 
 #[salsa::database(HcEngineStorage)]
@@ -213,7 +295,9 @@ impl HcEngineImpl {
 
 pub fn start_plugins(
 	policy_file: &PolicyFile,
+	policy_path: Option<&Path>,
 	plugin_cache: &HcPluginCache,
+	connection: PluginConnection,
 ) -> Result<Arc<HcPluginCore>> {
 	let executor = PluginExecutor::new(
 		/* max_spawn_attempts */ 3,
@@ -221,12 +305,15 @@ pub fn start_plugins(
 		/* port_range */ 40000..u16::MAX,
 		/* backoff_interval_micros */ 100000,
 		/* jitter_percent */ 10,
+		/* max_restarts */ 3,
+		connection,
 	)?;
 
 	let current_arch = get_current_arch();
 
 	// retrieve, verify and extract all required plugins
-	let required_plugin_names = retrieve_plugins(&policy_file.plugins.0, plugin_cache)?;
+	let required_plugin_names =
+		retrieve_plugins(policy_file, policy_path, plugin_cache, LockfileUpdate::None)?;
 
 	let mut plugins = vec![];
 	for plugin_id in required_plugin_names.iter() {
@@ -241,9 +328,25 @@ pub fn start_plugins(
 				)
 			})?;
 
+		// Only plugins listed in the policy file can have a timeout, not their dependencies
+		let timeout = policy_file
+			.plugins
+			.0
+			.iter()
+			.find(|p| p.name.to_string() == plugin_id.to_policy_file_plugin_identifier())
+			.and_then(|p| p.timeout);
+
 		let plugin = Plugin {
 			name: plugin_id.to_policy_file_plugin_identifier(),
+			version: plugin_id.version().0.clone(),
 			entrypoint,
+			timeout,
+			dependencies: plugin_manifest
+				.dependencies
+				.0
+				.iter()
+				.map(|dependency| dependency.name.to_string())
+				.collect(),
 		};
 
 		// find and serialize config for plugin
@@ -81,6 +81,15 @@ impl Error {
 	pub fn chain(&self) -> Chain {
 		Chain::new(self)
 	}
+
+	/// Get the first error in the chain with the type `E`, if there is one.
+	pub fn downcast_ref<E>(&self) -> Option<&E>
+	where
+		E: StdError + 'static,
+	{
+		self.chain()
+			.find_map(|node| node.current.downcast_ref::<E>())
+	}
 }
 
 /// Allows use of `?` operator on query system entry.
@@ -278,4 +287,13 @@ mod tests {
 		assert_eq!("second error", iter.next().unwrap().to_string());
 		assert_eq!("first error", iter.next().unwrap().to_string());
 	}
+
+	// Verify that `downcast_ref` finds a typed error under added context.
+	#[test]
+	fn hc_error_downcast() {
+		let error = crate::error::Error::new(std::fmt::Error).context("context");
+
+		assert!(error.downcast_ref::<std::fmt::Error>().is_some());
+		assert!(hc_error!("msg").downcast_ref::<std::fmt::Error>().is_none());
+	}
 }
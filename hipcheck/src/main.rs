@@ -15,6 +15,7 @@ mod plugin;
 mod policy;
 mod policy_exprs;
 mod report;
+mod serve;
 mod session;
 mod setup;
 mod shell;
@@ -23,26 +24,47 @@ mod target;
 mod util;
 mod version;
 
-pub mod hipcheck {
-	include!(concat!(env!("OUT_DIR"), "/hipcheck.v1.rs"));
-}
+pub use hipcheck_sdk::proto as hipcheck;
 
 use crate::{
-	analysis::score::score_results,
-	cache::repo::HcRepoCache,
+	analysis::score::{default_concurrency, score_results},
+	cache::{
+		bundle::{export_bundle, import_bundle},
+		plugin::HcPluginCache,
+		query::HcQueryCache,
+		repo::HcRepoCache,
+	},
 	cli::Format,
 	config::WeightTreeProvider,
+	engine::HcEngineImpl,
 	error::{Context as _, Error, Result},
-	plugin::{try_set_arch, Plugin, PluginExecutor, PluginWithConfig},
-	report::report_builder::{build_report, Report},
-	session::Session,
+	plugin::{
+		get_current_arch, retrieve_plugins, try_set_arch, LockfileUpdate, Plugin, PluginConnection,
+		PluginExecutor, PluginId, PluginLockfile, PluginManifest, PluginVersion, PluginWithConfig,
+	},
+	policy::{policy_file::PolicyPluginName, PolicyFile},
+	report::{
+		aggregate::{AggregateReport, TargetOutcome, TargetReport},
+		diff::{read_report, ReportDiff},
+		report_builder::{build_report, Report},
+	},
+	serve::ServeConfig,
+	session::{
+		cyclone_dx::extract_cyclonedx_components, load_target,
+		lockfile::extract_lockfile_dependencies, spdx::extract_spdx_components, Session,
+		SessionSetup,
+	},
 	setup::{resolve_and_transform_source, SourceType},
 	shell::Shell,
-	util::iter::{TryAny, TryFilter},
+	util::{
+		http::agent::set_offline,
+		iter::{TryAny, TryFilter},
+	},
 };
 use cli::{
-	CacheArgs, CacheOp, CheckArgs, CliConfig, FullCommands, PluginArgs, SchemaArgs, SchemaCommand,
-	SetupArgs, UpdateArgs,
+	CacheArgs, CacheKind, CacheOp, CheckArgs, CheckCommand, CliConfig, DiffArgs, FullCommands,
+	PluginArgs, PluginCommand, PluginFetchArgs, PluginInfoArgs, PluginRemoveArgs, SchemaArgs,
+	SchemaCommand, ServeArgs, SetupArgs, UpdateArgs,
 };
 use config::AnalysisTreeNode;
 use core::fmt;
@@ -52,18 +74,22 @@ use pathbuf::pathbuf;
 use schemars::schema_for;
 use shell::{color_choice::ColorChoice, spinner_phase::SpinnerPhase};
 use std::{
+	collections::HashSet,
 	env,
 	fmt::{Display, Formatter},
 	io::Write,
+	num::NonZeroUsize,
 	ops::Not as _,
 	path::{Path, PathBuf},
 	process::{Command, ExitCode},
 	result::Result as StdResult,
 	time::Duration,
 };
-use target::{RemoteGitRepo, TargetSeed, TargetSeedKind, ToTargetSeed};
+use target::{
+	Dependency, RemoteGitRepo, Sbom, SbomStandard, TargetSeed, TargetSeedKind, ToTargetSeed,
+};
 use util::command::DependentProgram;
-use util::fs::create_dir_all;
+use util::fs::{create_dir_all, read_string};
 use which::which;
 
 /// Entry point for Hipcheck.
@@ -92,12 +118,29 @@ fn main() -> ExitCode {
 
 	match config.subcommand() {
 		Some(FullCommands::Check(args)) => return cmd_check(&args, &config),
+		Some(FullCommands::Diff(args)) => return cmd_diff(&args, &config),
+		Some(FullCommands::Serve(args)) => return cmd_serve(&args, &config),
 		Some(FullCommands::Schema(args)) => cmd_schema(&args),
 		Some(FullCommands::Setup(args)) => return cmd_setup(&args, &config),
 		Some(FullCommands::Ready) => cmd_ready(&config),
 		Some(FullCommands::Update(args)) => cmd_update(&args),
 		Some(FullCommands::Cache(args)) => return cmd_cache(args, &config),
-		Some(FullCommands::Plugin(args)) => cmd_plugin(args),
+		Some(FullCommands::Plugin(args)) => match args.command {
+			Some(PluginCommand::Update) => return cmd_plugin_update(&config),
+			Some(PluginCommand::List) => {
+				return plugin_exit_code(plugin_cache(&config).and_then(|cache| cache.list()))
+			}
+			Some(PluginCommand::Fetch(args)) => {
+				return plugin_exit_code(fetch_plugins(&args, &config))
+			}
+			Some(PluginCommand::Info(args)) => {
+				return plugin_exit_code(describe_plugin(&args, &config))
+			}
+			Some(PluginCommand::Remove(args)) => {
+				return plugin_exit_code(remove_plugin(&args, &config))
+			}
+			None => cmd_plugin(args),
+		},
 		Some(FullCommands::PrintConfig) => cmd_print_config(config.config()),
 		Some(FullCommands::PrintCache) => cmd_print_home(config.cache()),
 		Some(FullCommands::Scoring) => {
@@ -125,6 +168,18 @@ fn cmd_check(args: &CheckArgs, config: &CliConfig) -> ExitCode {
 			return ExitCode::FAILURE;
 		}
 	}
+	if args.offline {
+		set_offline();
+	}
+	let plugin_connection = match args.plugin_sockets {
+		true => PluginConnection::UnixSocket,
+		false => PluginConnection::Tcp,
+	};
+
+	if let Some(targets_file) = &args.targets_file {
+		return cmd_check_targets_file(targets_file, args, config, plugin_connection);
+	}
+
 	let target = match args.to_target_seed() {
 		Ok(target) => target,
 		Err(e) => {
@@ -133,21 +188,166 @@ fn cmd_check(args: &CheckArgs, config: &CliConfig) -> ExitCode {
 		}
 	};
 
+	// An SBOM or lockfile is checked by analyzing each of its dependencies,
+	// unless only the package an SBOM describes should be analyzed.
+	let root_only =
+		matches!(args.command(), Ok(CheckCommand::Sbom(sbom_args)) if sbom_args.root_only);
+	let dependencies = match &target.kind {
+		TargetSeedKind::Sbom(sbom) if !root_only => Some(extract_sbom_dependencies(sbom)),
+		TargetSeedKind::Lockfile(lockfile) => Some(
+			extract_lockfile_dependencies(lockfile)
+				.map(|deps| (lockfile.path.display().to_string(), deps)),
+		),
+		_ => None,
+	};
+
+	if let Some(dependencies) = dependencies {
+		if args.baseline.is_some() {
+			Shell::print_error(
+				&hc_error!("a baseline can only be compared to a report on a single target"),
+				Format::Human,
+			);
+			return ExitCode::FAILURE;
+		}
+
+		let report = dependencies.and_then(|(source, dependencies)| {
+			run_many(
+				source,
+				dependency_targets(dependencies),
+				config.config().map(ToOwned::to_owned),
+				config.cache().map(ToOwned::to_owned),
+				config.policy().map(ToOwned::to_owned),
+				config.format(),
+				args.concurrency.unwrap_or_else(default_concurrency),
+				args.query_cache,
+				plugin_connection,
+				|_| Ok(()),
+			)
+		});
+
+		return match report {
+			Ok(report) => {
+				// Any dependency that couldn't be analyzed fails the run, so it isn't missed.
+				let errored = report.summary.errored > 0;
+				match Shell::print_aggregate_report(report, config.format()) {
+					Ok(()) if errored => ExitCode::FAILURE,
+					Ok(()) => ExitCode::SUCCESS,
+					Err(err) => {
+						Shell::print_error(&err, Format::Human);
+						ExitCode::FAILURE
+					}
+				}
+			}
+			Err(e) => {
+				Shell::print_error(&e, config.format());
+				ExitCode::FAILURE
+			}
+		};
+	}
+
+	// Read the baseline before the analysis, so a bad path fails quickly.
+	let baseline = match args.baseline.as_deref().map(read_report).transpose() {
+		Ok(baseline) => baseline,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			return ExitCode::FAILURE;
+		}
+	};
+
 	let report = run(
 		target,
 		config.config().map(ToOwned::to_owned),
 		config.cache().map(ToOwned::to_owned),
 		config.policy().map(ToOwned::to_owned),
 		config.format(),
+		args.concurrency.unwrap_or_else(default_concurrency),
+		args.query_cache,
+		plugin_connection,
+		&mut None,
 	);
 
-	match report {
-		Ok(report) => Shell::print_report(report, config.format())
-			.map(|()| ExitCode::SUCCESS)
-			.unwrap_or_else(|err| {
-				Shell::print_error(&err, Format::Human);
-				ExitCode::FAILURE
-			}),
+	let printed = report.map(|report| match baseline {
+		Some(baseline) => {
+			Shell::print_report_diff(ReportDiff::new(&baseline, &report), config.format())
+		}
+		None => Shell::print_report(report, config.format()),
+	});
+
+	match printed {
+		Ok(printed) => printed.map(|()| ExitCode::SUCCESS).unwrap_or_else(|err| {
+			Shell::print_error(&err, Format::Human);
+			ExitCode::FAILURE
+		}),
+		Err(e) => {
+			Shell::print_error(&e, config.format());
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Run the `check` command on each target listed in a targets file. With JSON
+/// output, each target's result is printed as a line of JSON as soon as it's
+/// checked, followed by a summary table on the standard error.
+fn cmd_check_targets_file(
+	targets_file: &Path,
+	args: &CheckArgs,
+	config: &CliConfig,
+	plugin_connection: PluginConnection,
+) -> ExitCode {
+	let format = config.format();
+
+	let report = read_targets_file(targets_file).and_then(|targets| {
+		run_many(
+			targets_file.display().to_string(),
+			targets,
+			config.config().map(ToOwned::to_owned),
+			config.cache().map(ToOwned::to_owned),
+			config.policy().map(ToOwned::to_owned),
+			format,
+			args.concurrency.unwrap_or_else(default_concurrency),
+			args.query_cache,
+			plugin_connection,
+			|target| match format {
+				Format::Json => Shell::print_json_line(target),
+				Format::Human | Format::Sarif => Ok(()),
+			},
+		)
+	});
+
+	let printed = report.map(|report| {
+		// Any target that couldn't be analyzed fails the run, so it isn't missed.
+		let errored = report.summary.errored > 0;
+		let printed = match format {
+			Format::Json => {
+				Shell::print_summary_table(&report);
+				Ok(())
+			}
+			Format::Human | Format::Sarif => Shell::print_aggregate_report(report, format),
+		};
+		(printed, errored)
+	});
+
+	match printed {
+		Ok((Ok(()), true)) => ExitCode::FAILURE,
+		Ok((Ok(()), false)) => ExitCode::SUCCESS,
+		Ok((Err(err), _)) => {
+			Shell::print_error(&err, Format::Human);
+			ExitCode::FAILURE
+		}
+		Err(e) => {
+			Shell::print_error(&e, format);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Run the `diff` command.
+fn cmd_diff(args: &DiffArgs, config: &CliConfig) -> ExitCode {
+	let printed = ReportDiff::from_files(&args.old, &args.new)
+		.and_then(|diff| Shell::print_report_diff(diff, config.format()));
+
+	match printed {
+		Ok(()) => ExitCode::SUCCESS,
 		Err(e) => {
 			Shell::print_error(&e, config.format());
 			ExitCode::FAILURE
@@ -155,13 +355,45 @@ fn cmd_check(args: &CheckArgs, config: &CliConfig) -> ExitCode {
 	}
 }
 
+/// Run the `serve` command.
+fn cmd_serve(args: &ServeArgs, config: &CliConfig) -> ExitCode {
+	let served =
+		serve::find_policies(args.policies.as_deref(), config.policy()).and_then(|policies| {
+			serve::serve(ServeConfig {
+				address: args.address,
+				queue_size: args.queue_size,
+				policies,
+				home_dir: config.cache().map(ToOwned::to_owned),
+				concurrency: args.concurrency.unwrap_or_else(default_concurrency),
+				use_query_cache: args.query_cache,
+				plugin_connection: match args.plugin_sockets {
+					true => PluginConnection::UnixSocket,
+					false => PluginConnection::Tcp,
+				},
+				allowed_local_dirs: args.allow_local.clone(),
+			})
+		});
+
+	match served {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
 /// Run the `schema` command.
 fn cmd_schema(args: &SchemaArgs) {
 	match args.command {
+		SchemaCommand::Cargo => print_cargo_schema(),
+		SchemaCommand::Go => print_go_schema(),
 		SchemaCommand::Maven => print_maven_schema(),
 		SchemaCommand::Npm => print_npm_schema(),
 		SchemaCommand::Pypi => print_pypi_schema(),
 		SchemaCommand::Repo => print_report_schema(),
+		SchemaCommand::Sbom | SchemaCommand::Lockfile => print_aggregate_report_schema(),
+		SchemaCommand::Diff => print_report_diff_schema(),
 	}
 }
 
@@ -184,6 +416,8 @@ fn cmd_print_weights(config: &CliConfig) -> Result<()> {
 		config.cache().map(ToOwned::to_owned),
 		config.policy().map(ToOwned::to_owned),
 		config.format(),
+		PluginConnection::default(),
+		None,
 	)?;
 
 	// Get the weight tree and print it.
@@ -542,7 +776,7 @@ fn check_github_token() -> StdResult<(), EnvVarCheckError> {
 }
 
 fn cmd_plugin(args: PluginArgs) {
-	use crate::engine::{async_query, HcEngine, HcEngineImpl};
+	use crate::engine::{async_query, HcEngine};
 	use std::sync::Arc;
 	use tokio::task::JoinSet;
 
@@ -552,11 +786,17 @@ fn cmd_plugin(args: PluginArgs) {
 	let entrypoint2 = pathbuf![tgt_dir, "dummy_sha256"];
 	let plugin1 = Plugin {
 		name: "dummy/rand_data".to_owned(),
+		version: "0.1.0".to_owned(),
 		entrypoint: entrypoint1.display().to_string(),
+		timeout: None,
+		dependencies: vec![],
 	};
 	let plugin2 = Plugin {
 		name: "dummy/sha256".to_owned(),
+		version: "0.1.0".to_owned(),
 		entrypoint: entrypoint2.display().to_string(),
+		timeout: None,
+		dependencies: vec![],
 	};
 	let plugin_executor = PluginExecutor::new(
 		/* max_spawn_attempts */ 3,
@@ -564,6 +804,8 @@ fn cmd_plugin(args: PluginArgs) {
 		/* port_range */ 40000..u16::MAX,
 		/* backoff_interval_micros */ 100000,
 		/* jitter_percent */ 10,
+		/* max_restarts */ 3,
+		PluginConnection::Tcp,
 	)
 	.unwrap();
 	let engine = match HcEngineImpl::new(
@@ -601,42 +843,215 @@ fn cmd_plugin(args: PluginArgs) {
 			}
 		});
 	} else {
-		let res = engine.query(
+		let res = HcEngineImpl::runtime().block_on(async_query(
+			engine.core(),
 			"dummy".to_owned(),
 			"rand_data".to_owned(),
 			"rand_data".to_owned(),
 			serde_json::json!(1),
-		);
+		));
 		println!("res: {res:?}");
-		// @Note - how to initiate multiple queries with sync calls
-		// Currently does not work, compiler complains need Sync impl
-		// use std::thread;
-		// let conc: Vec<thread::JoinHandle<()>> = vec![];
-		// for i in 0..10 {
-		// 	let snapshot = engine.snapshot();
-		// 	let fut = thread::spawn(|| {
-		// 		let res = match snapshot.query(
-		// 			"MITRE".to_owned(),
-		// 			"rand_data".to_owned(),
-		// 			"rand_data".to_owned(),
-		// 			serde_json::json!(i),
-		// 		) {
-		// 			Ok(r) => r,
-		// 			Err(e) => {
-		// 				println!("{i}: Query failed: {e}");
-		// 				return;
-		// 			}
-		// 		};
-		// 		println!("{i}: Result: {res}");
-		// 	});
-		// 	conc.push(fut);
-		// }
-		// while let Some(x) = conc.pop() {
-		// 	x.join().unwrap();
-		// }
 	}
 }
 
+/// Retrieve the plugins used by the policy file again, replacing the ones locked for the current
+/// arch in its `Hipcheck.lock`.
+fn cmd_plugin_update(config: &CliConfig) -> ExitCode {
+	match update_plugin_lockfile(config) {
+		Ok(lockfile_path) => {
+			println!("Updated {}", lockfile_path.display());
+			ExitCode::SUCCESS
+		}
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn update_plugin_lockfile(config: &CliConfig) -> Result<PathBuf> {
+	let policy_path = config
+		.policy()
+		.ok_or_else(|| hc_error!("a policy file must be given with -p/--policy"))?;
+
+	let policy = PolicyFile::load_from(policy_path)?;
+	retrieve_plugins(
+		&policy,
+		Some(policy_path),
+		&plugin_cache(config)?,
+		LockfileUpdate::Replace,
+	)?;
+
+	Ok(PluginLockfile::path_for_policy(policy_path))
+}
+
+/// Print the error a `hc plugin` command failed with, if any.
+fn plugin_exit_code(result: Result<()>) -> ExitCode {
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			Shell::print_error(&e, Format::Human);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn plugin_cache(config: &CliConfig) -> Result<HcPluginCache> {
+	let cache = config.cache().ok_or_else(|| {
+		hc_error!("cache path must be defined by cmdline arg or $HC_CACHE env var")
+	})?;
+	Ok(HcPluginCache::new(cache))
+}
+
+/// Retrieve the plugins a policy file uses into the plugin cache, checking them against its
+/// `Hipcheck.lock` as `hc check` would. With `--lock`, plugins not locked yet are added to it.
+fn fetch_plugins(args: &PluginFetchArgs, config: &CliConfig) -> Result<()> {
+	let policy_path = args
+		.policy
+		.as_deref()
+		.or(config.policy())
+		.ok_or_else(|| hc_error!("a policy file must be given, or set with -p/--policy"))?;
+
+	let policy = PolicyFile::load_from(policy_path)?;
+	let update = if args.lock {
+		LockfileUpdate::AddNew
+	} else {
+		LockfileUpdate::None
+	};
+	let mut plugin_ids =
+		retrieve_plugins(&policy, Some(policy_path), &plugin_cache(config)?, update)?
+			.into_iter()
+			.collect::<Vec<_>>();
+	plugin_ids.sort_by_key(ToString::to_string);
+
+	for plugin_id in plugin_ids {
+		println!("Fetched {}", plugin_id);
+	}
+	Ok(())
+}
+
+/// Print a cached plugin's manifest, then start it to print what it says about its queries and
+/// defaults. It's configured as the policy file given with `-p`/`--policy` configures it, if any.
+fn describe_plugin(args: &PluginInfoArgs, config: &CliConfig) -> Result<()> {
+	let name = PolicyPluginName::new(&args.plugin)?;
+	let plugin_cache = plugin_cache(config)?;
+	let not_cached = || {
+		hc_error!(
+			"{} is not in the plugin cache. Run `hc plugin fetch` to retrieve it",
+			args.plugin
+		)
+	};
+
+	let version = match &args.version {
+		Some(version) => PluginVersion::validated(version.clone())?,
+		None => plugin_cache
+			.cached_versions(&name.publisher, &name.name)
+			.into_iter()
+			.max_by_key(|version| version.to_semver().ok())
+			.ok_or_else(not_cached)?,
+	};
+	let plugin_id = PluginId::new(name.publisher.clone(), name.name.clone(), version);
+	let plugin_kdl = plugin_cache.plugin_kdl(&plugin_id);
+	if !plugin_kdl.exists() {
+		return Err(not_cached());
+	}
+
+	let manifest = PluginManifest::from_file(&plugin_kdl)?;
+	println!("{}", manifest.to_kdl_formatted_string());
+
+	let current_arch = get_current_arch();
+	let entrypoint = manifest.get_entrypoint(&current_arch).ok_or_else(|| {
+		hc_error!(
+			"Could not find {} entrypoint for {}",
+			current_arch,
+			plugin_id
+		)
+	})?;
+	let plugin_config = match config.policy() {
+		Some(policy_path) => {
+			let policy = PolicyFile::load_from(policy_path)?;
+			serde_json::to_value(policy.get_config(&name.to_string()).unwrap_or_default())?
+		}
+		None => serde_json::json!({}),
+	};
+
+	let plugin = Plugin {
+		name: name.to_string(),
+		version: plugin_id.version().0.clone(),
+		entrypoint,
+		timeout: None,
+		dependencies: manifest
+			.dependencies
+			.0
+			.iter()
+			.map(|dependency| dependency.name.to_string())
+			.collect(),
+	};
+	let executor = PluginExecutor::new(
+		/* max_spawn_attempts */ 3,
+		/* max_conn_attempts */ 5,
+		/* port_range */ 40000..u16::MAX,
+		/* backoff_interval_micros */ 100000,
+		/* jitter_percent */ 10,
+		/* max_restarts */ 0,
+		PluginConnection::Tcp,
+	)?;
+	let info = HcEngineImpl::runtime().block_on(async {
+		executor
+			.start_plugin(plugin)
+			.await?
+			.describe(plugin_config)
+			.await
+	})?;
+
+	println!("Queries:");
+	for schema in info.schemas {
+		let query_name = match schema.query_name.as_str() {
+			"" => "(default)",
+			query_name => query_name,
+		};
+		println!("  {}", query_name);
+		println!("    key:    {}", schema.key_schema);
+		println!("    output: {}", schema.output_schema);
+	}
+
+	match info.defaults {
+		Ok(defaults) => {
+			let or_none = |value: Option<String>| value.unwrap_or_else(|| "(none)".to_owned());
+			println!(
+				"Default policy expression: {}",
+				or_none(defaults.opt_default_policy_expr)
+			);
+			println!(
+				"Default query explanation: {}",
+				or_none(defaults.opt_explain_default_query)
+			);
+		}
+		Err(e) => println!(
+			"The plugin's defaults depend on its configuration, but it could not be configured: {}. Give a policy file which configures it with -p/--policy",
+			e
+		),
+	}
+
+	Ok(())
+}
+
+/// Delete a plugin from the plugin cache.
+fn remove_plugin(args: &PluginRemoveArgs, config: &CliConfig) -> Result<()> {
+	let name = PolicyPluginName::new(&args.plugin)?;
+	let version = args
+		.version
+		.clone()
+		.map(PluginVersion::validated)
+		.transpose()?;
+	let removed = plugin_cache(config)?.remove(&name.publisher, &name.name, version.as_ref())?;
+
+	for plugin_id in removed {
+		println!("Removed {}", plugin_id);
+	}
+	Ok(())
+}
+
 fn cmd_ready(config: &CliConfig) {
 	let ready = ReadyChecks {
 		hipcheck_version_check: check_hipcheck_version(),
@@ -740,16 +1155,39 @@ fn cmd_cache(args: CacheArgs, config: &CliConfig) -> ExitCode {
 			return ExitCode::FAILURE;
 		}
 	};
-	let mut cache = HcRepoCache::new(path);
 	let res = match op {
-		CacheOp::List { scope, filter } => cache.list(scope, filter),
+		CacheOp::List {
+			kind: CacheKind::Repo,
+			scope,
+			filter,
+		} => HcRepoCache::new(path).list(scope, filter),
+		CacheOp::List {
+			kind: CacheKind::Query,
+			scope,
+			filter,
+		} => HcQueryCache::new(path).list(scope, filter),
+		CacheOp::Delete {
+			kind: CacheKind::Repo,
+			scope,
+			filter,
+			force,
+		} => HcRepoCache::new(path).delete(scope, filter, force),
 		CacheOp::Delete {
+			kind: CacheKind::Query,
 			scope,
 			filter,
 			force,
-		} => cache.delete(scope, filter, force),
+		} => HcQueryCache::new(path).delete(scope, filter, force),
+		CacheOp::Export {
+			targets_file,
+			output,
+		} => export_cache_bundle(config, path, targets_file.as_deref(), &output).map(|()| {
+			println!("Exported {}", output.display());
+		}),
+		CacheOp::Import { bundle } => import_bundle(path, &bundle).map(|()| {
+			println!("Imported {} into {}", bundle.display(), path.display());
+		}),
 	};
-	drop(cache);
 	if let Err(e) = res {
 		println!("{e}");
 		ExitCode::FAILURE
@@ -758,6 +1196,49 @@ fn cmd_cache(args: CacheArgs, config: &CliConfig) -> ExitCode {
 	}
 }
 
+/// Bundle the plugins the policy file uses and the repos of the targets listed in `targets_file`,
+/// retrieving any which aren't in the cache at `cache` yet.
+fn export_cache_bundle(
+	config: &CliConfig,
+	cache: &Path,
+	targets_file: Option<&Path>,
+	output: &Path,
+) -> Result<()> {
+	let policy_path = config
+		.policy()
+		.ok_or_else(|| hc_error!("a policy file must be given with -p/--policy"))?;
+	let policy = PolicyFile::load_from(policy_path)?;
+
+	let plugin_cache = HcPluginCache::new(cache);
+	let mut dirs = retrieve_plugins(
+		&policy,
+		Some(policy_path),
+		&plugin_cache,
+		LockfileUpdate::None,
+	)?
+	.iter()
+	.map(|plugin_id| plugin_cache.plugin_download_dir(plugin_id))
+	.collect::<Vec<_>>();
+
+	let targets = match targets_file {
+		Some(targets_file) => read_targets_file(targets_file)?,
+		None => vec![],
+	};
+	for target in targets {
+		let seed = target
+			.seed
+			.map_err(|e| hc_error!("could not resolve target '{}': {}", target.name, e))?;
+		// Local repos are copied into the cache afresh every time they're checked
+		if matches!(seed.kind, TargetSeedKind::LocalRepo(_)) {
+			continue;
+		}
+		let target = load_target(&seed, cache, &policy.forges.gitlab)?;
+		dirs.push(target.local.path);
+	}
+
+	export_bundle(cache, &dirs, output)
+}
+
 /// Print the current home directory for Hipcheck.
 ///
 /// Exits `Ok` if home directory is specified, `Err` otherwise.
@@ -793,6 +1274,30 @@ fn print_report_schema() {
 	println!("{}", report_text);
 }
 
+/// Print the JSON schema of the report on every component of an SBOM
+fn print_aggregate_report_schema() {
+	let schema = schema_for!(AggregateReport);
+	let report_text = serde_json::to_string_pretty(&schema).unwrap();
+	println!("{}", report_text);
+}
+
+/// Print the JSON schema of the comparison of two reports
+fn print_report_diff_schema() {
+	let schema = schema_for!(ReportDiff);
+	let report_text = serde_json::to_string_pretty(&schema).unwrap();
+	println!("{}", report_text);
+}
+
+/// Print the JSON schema of the cargo package
+fn print_cargo_schema() {
+	print_missing()
+}
+
+/// Print the JSON schema of the go module
+fn print_go_schema() {
+	print_missing()
+}
+
 /// Print the JSON schema of the maven package
 fn print_maven_schema() {
 	print_missing()
@@ -822,14 +1327,11 @@ const BINARY_CONFIG_FILE: &str = "Binary.toml";
 const TYPO_FILE: &str = "Typos.toml";
 const ORGS_FILE: &str = "Orgs.toml";
 
-// Constants for exiting with error codes.
-/// Indicates the program failed.
-const EXIT_FAILURE: i32 = 1;
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum CheckKind {
 	Npm,
 	Pypi,
+	Cargo,
 }
 
 impl CheckKind {
@@ -838,6 +1340,7 @@ impl CheckKind {
 		match self {
 			CheckKind::Npm => "npm",
 			CheckKind::Pypi => "pypi",
+			CheckKind::Cargo => "cargo",
 		}
 	}
 }
@@ -851,20 +1354,41 @@ fn run(
 	home_dir: Option<PathBuf>,
 	policy_path: Option<PathBuf>,
 	format: Format,
+	concurrency: NonZeroUsize,
+	use_query_cache: bool,
+	plugin_connection: PluginConnection,
+	setup: &mut Option<SessionSetup>,
 ) -> Result<Report> {
-	// Initialize the session.
-	let session = match Session::new(&target, config_path, home_dir, policy_path, format) {
-		Ok(session) => session,
-		Err(err) => return Err(err),
+	let query_cache = match (use_query_cache, home_dir.as_deref()) {
+		(true, Some(home)) => Some(HcQueryCache::new(home)),
+		(true, None) => {
+			return Err(hc_error!(
+				"the query cache needs a cache directory; set one with --cache or $HC_CACHE"
+			))
+		}
+		(false, _) => None,
 	};
 
+	// Initialize the session, reusing the policy and plugins loaded for an
+	// earlier target if there are any, and keeping them for later targets otherwise.
+	let session = Session::new(
+		&target,
+		config_path,
+		home_dir,
+		policy_path,
+		format,
+		plugin_connection,
+		setup.clone(),
+	)?;
+	*setup = Some(session.setup());
+
 	// Run analyses against a repo and score the results (score calls analyses that call metrics).
 	let phase = SpinnerPhase::start("analyzing and scoring results");
 
 	// Enable steady ticking on the spinner, since we currently don't increment it manually.
 	phase.enable_steady_tick(Duration::from_millis(250));
 
-	let scoring = score_results(&phase, &session)?;
+	let scoring = score_results(&phase, &session, concurrency, query_cache.as_ref())?;
 
 	phase.finish_successful();
 
@@ -873,3 +1397,134 @@ fn run(
 
 	Ok(report)
 }
+
+/// Extract every component of an SBOM, along with the SBOM's path.
+fn extract_sbom_dependencies(sbom: &Sbom) -> Result<(String, Vec<Dependency>)> {
+	let path = sbom.path.to_str().ok_or(hc_error!(
+		"SBOM path contained one or more invalid characters"
+	))?;
+	let components = match sbom.standard {
+		SbomStandard::Spdx => extract_spdx_components(path)?,
+		SbomStandard::CycloneDX => extract_cyclonedx_components(path)?,
+	};
+
+	Ok((path.to_owned(), components))
+}
+
+/// A target listed in an SBOM, lockfile, or targets file, with what to check
+/// it by if it could be resolved to a target seed.
+struct ListedTarget {
+	name: String,
+	locator: Option<String>,
+	seed: StdResult<TargetSeed, String>,
+}
+
+/// Get the targets to check for the dependencies listed in an SBOM or lockfile.
+fn dependency_targets(dependencies: Vec<Dependency>) -> Vec<ListedTarget> {
+	// The same dependency may be listed more than once, like a dependency of
+	// many other packages, but only needs to be checked once.
+	let mut seen = HashSet::new();
+
+	dependencies
+		.into_iter()
+		.filter(|d| d.locator.is_none() || seen.insert(d.locator.clone()))
+		.map(|dependency| {
+			let name = match &dependency.version {
+				Some(version) => format!("{}@{}", dependency.name, version),
+				None => dependency.name.clone(),
+			};
+			let seed = match dependency.locator.as_deref() {
+				None => {
+					Err("the dependency has no pURL or compatible download location".to_owned())
+				}
+				Some(locator) => cli::infer_target_seed(locator).map_err(|err| err.to_string()),
+			};
+			ListedTarget {
+				name,
+				locator: dependency.locator,
+				seed,
+			}
+		})
+		.collect()
+}
+
+/// Read the targets listed in a targets file, one per line. Blank lines and
+/// lines starting with `#` are ignored.
+fn read_targets_file(path: &Path) -> Result<Vec<ListedTarget>> {
+	let contents = read_string(path)?;
+
+	let targets = contents
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| ListedTarget {
+			name: line.to_owned(),
+			locator: None,
+			seed: cli::target_seed_from_line(line).map_err(|err| err.to_string()),
+		})
+		.collect::<Vec<_>>();
+
+	if targets.is_empty() {
+		return Err(hc_error!("no targets listed in '{}'", path.display()));
+	}
+
+	Ok(targets)
+}
+
+/// Run Hipcheck's analyses on each of many targets, producing a report on all
+/// of them. The policy and one set of plugins are loaded once and shared by
+/// every analysis. Targets which can't be resolved, or whose analysis fails,
+/// are reported as such without stopping the rest. `on_checked` is called with
+/// each target's result as soon as it's checked.
+#[allow(clippy::too_many_arguments)]
+fn run_many(
+	source: String,
+	listed: Vec<ListedTarget>,
+	config_path: Option<PathBuf>,
+	home_dir: Option<PathBuf>,
+	policy_path: Option<PathBuf>,
+	format: Format,
+	concurrency: NonZeroUsize,
+	use_query_cache: bool,
+	plugin_connection: PluginConnection,
+	mut on_checked: impl FnMut(&TargetReport) -> Result<()>,
+) -> Result<AggregateReport> {
+	let mut setup = None;
+	let mut targets = vec![];
+
+	for listed in listed {
+		let outcome = match listed.seed {
+			Err(reason) => TargetOutcome::Skipped { reason },
+			Ok(target) => match run(
+				target,
+				config_path.clone(),
+				home_dir.clone(),
+				policy_path.clone(),
+				format,
+				concurrency,
+				use_query_cache,
+				plugin_connection,
+				&mut setup,
+			) {
+				Ok(report) => TargetOutcome::Analyzed {
+					report: Box::new(report),
+				},
+				Err(err) => TargetOutcome::errored(&err),
+			},
+		};
+		// The next target's queries are answered from that target alone
+		if let Some(setup) = &setup {
+			setup.clear_query_results();
+		}
+
+		let target = TargetReport {
+			name: listed.name,
+			locator: listed.locator,
+			outcome,
+		};
+		on_checked(&target)?;
+		targets.push(target);
+	}
+
+	Ok(AggregateReport::new(source, targets))
+}
@@ -2,8 +2,8 @@
 
 use crate::{
 	hc_error,
-	plugin::{arch::Arch, PluginVersion},
-	util::kdl::{extract_data, ParseKdlNode},
+	plugin::{arch::Arch, PluginDependencyList, PluginVersion},
+	util::kdl::{extract_data, ParseKdlNode, ToKdlNode},
 };
 use kdl::{KdlDocument, KdlNode, KdlValue};
 use std::{fmt::Display, str::FromStr};
@@ -87,6 +87,15 @@ impl ParseKdlNode for HashWithDigest {
 	}
 }
 
+impl ToKdlNode for HashWithDigest {
+	fn to_kdl_node(&self) -> KdlNode {
+		let mut node = KdlNode::new(Self::kdl_key());
+		node.insert("alg", self.hash_algorithm.to_string());
+		node.insert("digest", self.digest.as_str());
+		node
+	}
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ArchiveFormat {
 	/// archived with tar and compressed with the XZ algorithm
@@ -198,6 +207,63 @@ impl ParseKdlNode for Size {
 	}
 }
 
+/// The formats of detached signatures supported inside of the plugin download manifest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureFormat {
+	/// signed with minisign (ed25519), as found in a `.minisig` file
+	Minisign,
+}
+
+impl TryFrom<&str> for SignatureFormat {
+	type Error = crate::Error;
+
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		match value {
+			"minisign" => Ok(SignatureFormat::Minisign),
+			_ => Err(hc_error!("Invalid signature format specified: '{}'", value)),
+		}
+	}
+}
+
+impl Display for SignatureFormat {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SignatureFormat::Minisign => write!(f, "minisign"),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// format of the signature
+	pub format: SignatureFormat,
+	/// detached signature over the downloaded artifact
+	pub signature: String,
+}
+
+impl ParseKdlNode for Signature {
+	fn kdl_key() -> &'static str {
+		"signature"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+		let specified_format = node.get("format")?.value().as_string()?;
+		let format = SignatureFormat::try_from(specified_format).ok()?;
+		// the signature is the first argument, wherever it falls among the properties
+		let signature = node
+			.entries()
+			.iter()
+			.find(|entry| entry.name().is_none())?
+			.value()
+			.as_string()?
+			.to_string();
+		Some(Signature { format, signature })
+	}
+}
+
 /// Represents one entry in a download manifest file, as spelled out in RFD #0004
 /// Example entry:
 /// ```
@@ -208,6 +274,12 @@ impl ParseKdlNode for Size {
 ///  size bytes=2_869_896
 ///}
 ///```
+///
+/// An entry may also have a detached signature over the archive, which is checked against the
+/// keys of its publisher if the policy file trusts the publisher:
+/// ```
+///  signature format="minisign" "untrusted comment: ...\nRUQ...\ntrusted comment: ...\n..."
+///```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DownloadManifestEntry {
 	// TODO: make this a SemVer type?
@@ -227,6 +299,11 @@ pub struct DownloadManifestEntry {
 	/// Describes the size of the downloaded artifact, used to validate the download was
 	/// successful, makes it more difficult for an attacker to distribute malformed artifacts
 	pub size: Size,
+	/// An optional detached signature over the archive, made by the plugin's publisher
+	pub signature: Option<Signature>,
+	/// The dependencies listed in the plugin's manifest, if the download manifest gives them.
+	/// They let the plugin's dependencies be resolved without downloading it.
+	pub dependencies: Option<PluginDependencyList>,
 }
 
 impl ParseKdlNode for DownloadManifestEntry {
@@ -252,6 +329,21 @@ impl ParseKdlNode for DownloadManifestEntry {
 		let hash: HashWithDigest = extract_data(nodes)?;
 		let compress: Compress = extract_data(nodes)?;
 		let size: Size = extract_data(nodes)?;
+		// the signature is optional, but an entry with a signature Hipcheck can't read is invalid
+		let signature = match nodes
+			.iter()
+			.find(|node| node.name().value() == Signature::kdl_key())
+		{
+			Some(node) => Some(Signature::parse_node(node)?),
+			None => None,
+		};
+		let dependencies = match nodes
+			.iter()
+			.find(|node| node.name().value() == PluginDependencyList::kdl_key())
+		{
+			Some(node) => Some(PluginDependencyList::parse_node(node)?),
+			None => None,
+		};
 
 		Some(Self {
 			version,
@@ -260,10 +352,42 @@ impl ParseKdlNode for DownloadManifestEntry {
 			hash,
 			compress,
 			size,
+			signature,
+			dependencies,
 		})
 	}
 }
 
+impl ToKdlNode for DownloadManifestEntry {
+	fn to_kdl_node(&self) -> KdlNode {
+		let mut node = KdlNode::new(Self::kdl_key());
+		node.insert("version", self.version.0.as_str());
+		node.insert("arch", self.arch.to_string());
+
+		let mut url = KdlNode::new(url::Url::kdl_key());
+		url.insert(0, self.url.as_str());
+		let mut compress = KdlNode::new(Compress::kdl_key());
+		compress.insert("format", self.compress.format.to_string());
+		let mut size = KdlNode::new(Size::kdl_key());
+		size.insert("bytes", self.size.bytes as i64);
+
+		let mut children = KdlDocument::new();
+		let nodes = children.nodes_mut();
+		nodes.extend([url, self.hash.to_kdl_node(), compress, size]);
+		if let Some(signature) = &self.signature {
+			let mut signature_node = KdlNode::new(Signature::kdl_key());
+			signature_node.insert("format", signature.format.to_string());
+			signature_node.insert(0, signature.signature.as_str());
+			nodes.push(signature_node);
+		}
+		if let Some(dependencies) = &self.dependencies {
+			nodes.push(dependencies.to_kdl_node());
+		}
+		node.set_children(children);
+		node
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DownloadManifest {
 	pub entries: Vec<DownloadManifestEntry>,
@@ -425,6 +549,8 @@ mod test {
 			size: Size {
 				bytes: u64::from_str(size.replace("_", "").as_str()).unwrap(),
 			},
+			signature: None,
+			dependencies: None,
 		};
 
 		assert_eq!(
@@ -460,7 +586,9 @@ plugin version="0.1.0" arch="x86_64-apple-darwin" {
 				compress: Compress::new(ArchiveFormat::TarXz),
 				size: Size {
 					bytes: 2_869_896
-				}
+				},
+				signature: None,
+				dependencies: None,
 			},
 		    entries_iter.next().unwrap()
 		);
@@ -471,9 +599,83 @@ plugin version="0.1.0" arch="x86_64-apple-darwin" {
 				url: Url::parse("https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz").unwrap(),
 				hash: HashWithDigest::new(HashAlgorithm::Sha256, "ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19".to_owned()),
                 compress: Compress::new(ArchiveFormat::TarXz),
-                size: Size::new(3_183_768)
+                size: Size::new(3_183_768),
+				signature: None,
+				dependencies: None,
 			},
 		    entries_iter.next().unwrap()
         );
 	}
+
+	#[test]
+	fn test_parsing_download_manifest_entry_signature() {
+		let contents = r#"plugin version="0.1.0" arch="x86_64-apple-darwin" {
+  url "https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz"
+  hash alg="SHA256" digest="ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19"
+  compress format="tar.xz"
+  size bytes=3_183_768
+  signature format="minisign" "untrusted comment: signature from minisign secret key\nRUSkkUqdCclvNTBXyimkHQQTlxM3VAgNnIzAL/xazR4isUyuJ5w9cyrs0dSJy91bL6hgfA3j0Wju8TUMoU6dMdT5JzToGoF+wgY=\ntrusted comment: timestamp:1729000000\nKWLkgYBRXn1TJyL0NlZR0hMDS3jnPf8jRb1nZvCBuHDvchNQHN6jXxsydyOeHX7e+rTrlTeFoUcmNoK4HTfPDQ==\n"
+}"#;
+		let entries = DownloadManifest::from_str(contents).unwrap();
+		let signature = entries.iter().next().unwrap().signature.as_ref().unwrap();
+		assert_eq!(signature.format, SignatureFormat::Minisign);
+		assert_eq!(signature.signature.lines().count(), 4);
+
+		// signatures in unknown formats make the entry invalid
+		let contents = contents.replace(r#"format="minisign""#, r#"format="gpg""#);
+		assert!(DownloadManifest::from_str(&contents).is_err());
+	}
+
+	#[test]
+	fn test_parsing_download_manifest_entry_dependencies() {
+		let contents = r#"plugin version="0.1.0" arch="x86_64-apple-darwin" {
+  url "https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz"
+  hash alg="SHA256" digest="ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19"
+  compress format="tar.xz"
+  size bytes=3_183_768
+  dependencies {
+    plugin "mitre/git" version="^0.1" manifest="https://hipcheck.mitre.org/dl/plugin/mitre/git.kdl"
+  }
+}"#;
+		let entries = DownloadManifest::from_str(contents).unwrap();
+		let dependencies = entries
+			.iter()
+			.next()
+			.unwrap()
+			.dependencies
+			.as_ref()
+			.unwrap();
+		assert_eq!(dependencies.0.len(), 1);
+		assert_eq!(dependencies.0[0].name.to_string(), "mitre/git");
+
+		// entries without dependencies leave them to be read from the plugin's own manifest
+		let contents = r#"plugin version="0.1.0" arch="x86_64-apple-darwin" {
+  url "https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz"
+  hash alg="SHA256" digest="ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19"
+  compress format="tar.xz"
+  size bytes=3_183_768
+}"#;
+		let entries = DownloadManifest::from_str(contents).unwrap();
+		assert!(entries.iter().next().unwrap().dependencies.is_none());
+	}
+
+	#[test]
+	fn test_download_manifest_entry_round_trip() {
+		let contents = r#"plugin version="0.1.0" arch="x86_64-apple-darwin" {
+  url "https://github.com/mitre/hipcheck/releases/download/hipcheck-v3.4.0/hipcheck-x86_64-apple-darwin.tar.xz"
+  hash alg="SHA256" digest="ddb8c6d26dd9a91e11c99b3bd7ee2b9585aedac6e6df614190f1ba2bfe86dc19"
+  compress format="tar.xz"
+  size bytes=3_183_768
+  signature format="minisign" "untrusted comment: signature from minisign secret key\nRUS...\n"
+  dependencies {
+    plugin "mitre/git" version="^0.1" manifest="https://hipcheck.mitre.org/dl/plugin/mitre/git.kdl"
+  }
+}"#;
+		let entry = DownloadManifest::from_str(contents)
+			.unwrap()
+			.entries
+			.remove(0);
+		let node = entry.to_kdl_node();
+		assert_eq!(DownloadManifestEntry::parse_node(&node).unwrap(), entry);
+	}
 }
@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The `Hipcheck.lock` file, which records exactly which plugins were retrieved for a policy
+//! file, so later runs can check that they get the same ones.
+
+use crate::{
+	error::Result,
+	hc_error,
+	plugin::{Arch, HashWithDigest, PluginId, PluginName, PluginPublisher, PluginVersion},
+	policy::policy_file::{ManifestLocation, PolicyPluginName},
+	util::{
+		fs as file,
+		kdl::{extract_data, ParseKdlNode, ToKdlNode},
+	},
+};
+use kdl::{KdlDocument, KdlNode};
+use semver::Version;
+use std::{
+	collections::{HashMap, HashSet},
+	io::Write as _,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+use url::Url;
+
+/// The name of the lockfile, which is kept in the same directory as the policy file.
+pub const LOCKFILE_NAME: &str = "Hipcheck.lock";
+
+/// Where a plugin retrieved through a download manifest was downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedDownload {
+	/// The URL of the plugin archive for the locked arch
+	pub url: Url,
+	/// The hash the plugin archive had when it was locked
+	pub hash: HashWithDigest,
+}
+
+/// One plugin in the lockfile, as retrieved for one arch.
+///
+/// Example entry:
+/// ```
+///plugin "mitre/git" version="0.1.0" arch="x86_64-unknown-linux-gnu" manifest="https://hipcheck.mitre.org/dl/plugin/mitre/git.kdl" {
+///  url "https://github.com/mitre/hipcheck/releases/download/mitre%2Fgit-v0.1.0/git-x86_64-unknown-linux-gnu.tar.xz"
+///  hash alg="SHA256" digest="b8e111e7817c4a1eb40ed50712d04e15b369546c4748be1aa8893b553f4e756b"
+///}
+///```
+///
+/// Plugins retrieved from a local manifest have no download, so their only child is the hash of
+/// their entrypoint binary, if it's a file rather than a program found on the `PATH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPlugin {
+	pub plugin_id: PluginId,
+	pub arch: Arch,
+	pub manifest: ManifestLocation,
+	pub download: Option<LockedDownload>,
+	/// The hash a local plugin's entrypoint binary had when it was locked
+	pub entrypoint: Option<HashWithDigest>,
+}
+
+impl LockedPlugin {
+	/// Describe the first way in which `other` differs from this locked plugin.
+	fn difference(&self, other: &LockedPlugin) -> Option<String> {
+		if self.manifest != other.manifest {
+			return Some(format!(
+				"its manifest changed from '{}' to '{}'",
+				self.manifest, other.manifest
+			));
+		}
+		match (&self.download, &other.download) {
+			(Some(locked), Some(found)) if locked.url != found.url => Some(format!(
+				"its download URL changed from '{}' to '{}'",
+				locked.url, found.url
+			)),
+			(Some(locked), Some(found)) if locked.hash != found.hash => Some(format!(
+				"its hash changed from {} {} to {} {}",
+				locked.hash.hash_algorithm,
+				locked.hash.digest,
+				found.hash.hash_algorithm,
+				found.hash.digest
+			)),
+			(Some(_), None) => Some("it is no longer downloaded".to_owned()),
+			(None, Some(_)) => Some("it is now downloaded".to_owned()),
+			_ => match (&self.entrypoint, &other.entrypoint) {
+				(Some(locked), Some(found)) if locked != found => Some(format!(
+					"its entrypoint's hash changed from {} {} to {} {}",
+					locked.hash_algorithm, locked.digest, found.hash_algorithm, found.digest
+				)),
+				(Some(_), None) => Some("its entrypoint is no longer a file".to_owned()),
+				(None, Some(_)) => Some("its entrypoint is now a file".to_owned()),
+				_ => None,
+			},
+		}
+	}
+
+	/// The order of plugins in the lockfile.
+	fn sort_key(&self) -> (String, String, String) {
+		(
+			self.plugin_id.to_policy_file_plugin_identifier(),
+			self.plugin_id.version().0.clone(),
+			self.arch.to_string(),
+		)
+	}
+}
+
+impl ParseKdlNode for LockedPlugin {
+	fn kdl_key() -> &'static str {
+		"plugin"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let (publisher, name) = node
+			.entries()
+			.first()?
+			.value()
+			.as_string()?
+			.split_once('/')?;
+		let version = PluginVersion(node.get("version")?.value().as_string()?.to_string());
+		let plugin_id = PluginId::new(
+			PluginPublisher(publisher.to_string()),
+			PluginName(name.to_string()),
+			version,
+		);
+		let arch = Arch::from_str(node.get("arch")?.value().as_string()?).ok()?;
+		let manifest = node.get("manifest")?.value().as_string()?;
+		let manifest = match Url::parse(manifest) {
+			Ok(url) => ManifestLocation::Url(url),
+			Err(_) => ManifestLocation::Local(PathBuf::from(manifest)),
+		};
+
+		let (download, entrypoint) = match node.children() {
+			Some(children) => {
+				let nodes = children.nodes();
+				let hash = extract_data(nodes)?;
+				match extract_data(nodes) {
+					Some(url) => (Some(LockedDownload { url, hash }), None),
+					None => (None, Some(hash)),
+				}
+			}
+			None => (None, None),
+		};
+
+		Some(LockedPlugin {
+			plugin_id,
+			arch,
+			manifest,
+			download,
+			entrypoint,
+		})
+	}
+}
+
+impl ToKdlNode for LockedPlugin {
+	fn to_kdl_node(&self) -> KdlNode {
+		let mut node = KdlNode::new(Self::kdl_key());
+		node.insert(0, self.plugin_id.to_policy_file_plugin_identifier());
+		node.insert("version", self.plugin_id.version().0.as_str());
+		node.insert("arch", self.arch.to_string());
+		node.insert("manifest", self.manifest.to_string());
+
+		if let Some(download) = &self.download {
+			let mut url = KdlNode::new("url");
+			url.insert(0, download.url.as_str());
+			let mut children = KdlDocument::new();
+			children
+				.nodes_mut()
+				.extend([url, download.hash.to_kdl_node()]);
+			node.set_children(children);
+		} else if let Some(entrypoint) = &self.entrypoint {
+			let mut children = KdlDocument::new();
+			children.nodes_mut().push(entrypoint.to_kdl_node());
+			node.set_children(children);
+		}
+
+		node
+	}
+}
+
+/// The plugins retrieved for a policy file, as recorded in its `Hipcheck.lock`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginLockfile {
+	plugins: Vec<LockedPlugin>,
+}
+
+impl PluginLockfile {
+	/// Get the path of the lockfile for the policy file at `policy_path`.
+	pub fn path_for_policy(policy_path: &Path) -> PathBuf {
+		policy_path
+			.parent()
+			.unwrap_or_else(|| Path::new(""))
+			.join(LOCKFILE_NAME)
+	}
+
+	/// Load the lockfile at `path`, or an empty one if it doesn't exist yet.
+	pub fn load(path: &Path) -> Result<PluginLockfile> {
+		if !path.exists() {
+			return Ok(PluginLockfile::default());
+		}
+
+		PluginLockfile::from_str(&file::read_string(path)?)
+			.map_err(|e| hc_error!("{} in {}", e, path.display()))
+	}
+
+	/// Write the lockfile to `path`. It's written to a temporary file which then replaces the
+	/// lockfile, so the lockfile is never left partly written.
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let dir = match path.parent() {
+			Some(dir) if !dir.as_os_str().is_empty() => dir,
+			_ => Path::new("."),
+		};
+		let mut tmp_file = tempfile::NamedTempFile::new_in(dir)
+			.map_err(|e| hc_error!("Error [{}] writing {}", e, path.display()))?;
+		tmp_file
+			.write_all(self.to_kdl_formatted_string().as_bytes())
+			.map_err(|e| hc_error!("Error [{}] writing {}", e, path.display()))?;
+		tmp_file
+			.persist(path)
+			.map_err(|e| hc_error!("Error [{}] writing {}", e.error, path.display()))?;
+		Ok(())
+	}
+
+	/// Check a retrieved plugin against the one locked for the same arch, if there is one, or
+	/// otherwise lock it.
+	pub fn check(&mut self, plugin: LockedPlugin) -> Result<()> {
+		let locked = self
+			.plugins
+			.iter()
+			.find(|p| p.plugin_id == plugin.plugin_id && p.arch == plugin.arch);
+
+		match locked {
+			Some(locked) => match locked.difference(&plugin) {
+				Some(difference) => Err(hc_error!(
+					"{} for {} does not match {}: {}. If this change is expected, run `hc plugin update` to update {}",
+					plugin.plugin_id,
+					plugin.arch,
+					LOCKFILE_NAME,
+					difference,
+					LOCKFILE_NAME
+				)),
+				None => Ok(()),
+			},
+			None => {
+				self.plugins.push(plugin);
+				self.sort();
+				Ok(())
+			}
+		}
+	}
+
+	/// Get the version of each plugin locked for `arch`.
+	pub fn locked_versions(&self, arch: &Arch) -> HashMap<PolicyPluginName, Version> {
+		self.plugins
+			.iter()
+			.filter(|p| &p.arch == arch)
+			.filter_map(|p| {
+				let name = PolicyPluginName {
+					publisher: p.plugin_id.publisher().clone(),
+					name: p.plugin_id.name().clone(),
+				};
+				Some((name, p.plugin_id.version().to_semver().ok()?))
+			})
+			.collect()
+	}
+
+	/// Forget the plugins locked for `arch`, so they're locked afresh when next retrieved.
+	pub fn unlock_arch(&mut self, arch: &Arch) {
+		self.plugins.retain(|p| &p.arch != arch);
+	}
+
+	/// Drop any plugins which are no longer needed, for any arch.
+	pub fn retain_plugins(&mut self, plugin_ids: &HashSet<PluginId>) {
+		self.plugins.retain(|p| plugin_ids.contains(&p.plugin_id));
+	}
+
+	fn sort(&mut self) {
+		self.plugins.sort_by_key(LockedPlugin::sort_key);
+	}
+
+	/// convert `PluginLockfile` to a KDL-formatted String
+	fn to_kdl_formatted_string(&self) -> String {
+		let mut document = KdlDocument::new();
+		document
+			.nodes_mut()
+			.extend(self.plugins.iter().map(ToKdlNode::to_kdl_node));
+		document.fmt();
+		format!(
+			"// This file is generated by Hipcheck. Run `hc plugin update` to update it.\n{}",
+			document
+		)
+	}
+}
+
+impl FromStr for PluginLockfile {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let document = KdlDocument::from_str(s)
+			.map_err(|e| hc_error!("Error parsing plugin lockfile: {}", e.to_string()))?;
+		let mut plugins = vec![];
+		for node in document.nodes() {
+			match LockedPlugin::parse_node(node) {
+				Some(plugin) => plugins.push(plugin),
+				None => return Err(hc_error!("Error parsing plugin lockfile entry: {}", node)),
+			}
+		}
+		let mut lockfile = PluginLockfile { plugins };
+		lockfile.sort();
+		Ok(lockfile)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::plugin::{arch::KnownArch, HashAlgorithm};
+
+	fn locked_git_plugin(digest: &str) -> LockedPlugin {
+		LockedPlugin {
+			plugin_id: PluginId::new(
+				PluginPublisher::new("mitre".to_owned()),
+				PluginName::new("git".to_owned()),
+				PluginVersion::new("0.1.0".to_owned()),
+			),
+			arch: Arch::Known(KnownArch::X86_64UnknownLinuxGnu),
+			manifest: ManifestLocation::Url(
+				Url::parse("https://hipcheck.mitre.org/dl/plugin/mitre/git.kdl").unwrap(),
+			),
+			download: Some(LockedDownload {
+				url: Url::parse("https://example.com/git-x86_64-unknown-linux-gnu.tar.xz").unwrap(),
+				hash: HashWithDigest::new(HashAlgorithm::Sha256, digest.to_owned()),
+			}),
+			entrypoint: None,
+		}
+	}
+
+	fn locked_local_plugin(digest: &str) -> LockedPlugin {
+		LockedPlugin {
+			plugin_id: PluginId::new(
+				PluginPublisher::new("mitre".to_owned()),
+				PluginName::new("activity".to_owned()),
+				PluginVersion::new("0.1.0".to_owned()),
+			),
+			arch: Arch::Known(KnownArch::X86_64UnknownLinuxGnu),
+			manifest: ManifestLocation::Local(PathBuf::from("./plugins/activity/plugin.kdl")),
+			download: None,
+			entrypoint: Some(HashWithDigest::new(
+				HashAlgorithm::Sha256,
+				digest.to_owned(),
+			)),
+		}
+	}
+
+	#[test]
+	fn test_lockfile_round_trip() {
+		let mut lockfile = PluginLockfile::default();
+		lockfile.check(locked_git_plugin("abcd")).unwrap();
+		lockfile.check(locked_local_plugin("1234")).unwrap();
+
+		let serialized = lockfile.to_kdl_formatted_string();
+		let parsed = PluginLockfile::from_str(&serialized).unwrap();
+		assert_eq!(parsed, lockfile);
+		// Entries are kept in a stable order regardless of retrieval order
+		assert_eq!(
+			parsed.plugins[0]
+				.plugin_id
+				.to_policy_file_plugin_identifier(),
+			"mitre/activity"
+		);
+	}
+
+	#[test]
+	fn test_lockfile_check_detects_changes() {
+		let mut lockfile = PluginLockfile::default();
+		lockfile.check(locked_git_plugin("abcd")).unwrap();
+		assert!(lockfile.check(locked_git_plugin("abcd")).is_ok());
+
+		let err = lockfile.check(locked_git_plugin("ef01")).unwrap_err();
+		assert!(err.to_string().contains("its hash changed"));
+
+		// A plugin locked for another arch is locked separately
+		let mut other_arch = locked_git_plugin("ef01");
+		other_arch.arch = Arch::Known(KnownArch::Aarch64AppleDarwin);
+		assert!(lockfile.check(other_arch).is_ok());
+		assert_eq!(lockfile.plugins.len(), 2);
+
+		lockfile.unlock_arch(&Arch::Known(KnownArch::X86_64UnknownLinuxGnu));
+		assert!(lockfile.check(locked_git_plugin("ef01")).is_ok());
+	}
+
+	#[test]
+	fn test_lockfile_check_detects_changed_local_entrypoint() {
+		let mut lockfile = PluginLockfile::default();
+		lockfile.check(locked_local_plugin("1234")).unwrap();
+		assert!(lockfile.check(locked_local_plugin("1234")).is_ok());
+
+		let err = lockfile.check(locked_local_plugin("5678")).unwrap_err();
+		assert!(err.to_string().contains("its entrypoint's hash changed"));
+	}
+
+	#[test]
+	fn test_lockfile_save_replaces_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(LOCKFILE_NAME);
+		std::fs::write(&path, "not a lockfile").unwrap();
+
+		let mut lockfile = PluginLockfile::default();
+		lockfile.check(locked_git_plugin("abcd")).unwrap();
+		lockfile.save(&path).unwrap();
+
+		assert_eq!(PluginLockfile::load(&path).unwrap(), lockfile);
+		// Only the lockfile is left behind
+		assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+}
@@ -3,14 +3,54 @@
 use crate::{
 	hc_error,
 	hipcheck::plugin_service_client::PluginServiceClient,
-	plugin::{try_get_bin_for_entrypoint, HcPluginClient, Plugin, PluginContext},
+	plugin::{try_get_bin_for_entrypoint, HcPluginClient, Plugin, PluginContext, PluginStderr},
+	util::http::agent::is_offline,
 	Result,
 };
 use futures::future::join_all;
+use hipcheck_sdk::offline::OFFLINE_ENV_VAR;
 use rand::Rng;
-use std::{ops::Range, process::Command};
+use std::{
+	fmt::{self, Display},
+	io::{BufRead, BufReader},
+	ops::Range,
+	path::PathBuf,
+	process::{Command, Stdio},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+};
+use tempfile::TempDir;
 use tokio::time::{sleep_until, Duration, Instant};
 
+/// How Hipcheck connects to the plugin processes it starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PluginConnection {
+	/// Give each plugin a free TCP port on the loopback interface.
+	#[default]
+	Tcp,
+	/// Give each plugin a Unix domain socket path, in a directory only the
+	/// current user can access which is removed at the end of the run.
+	UnixSocket,
+}
+
+/// The address a plugin process listens on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PluginAddr {
+	Port(u16),
+	Socket(PathBuf),
+}
+
+impl Display for PluginAddr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PluginAddr::Port(port) => write!(f, "port {}", port),
+			PluginAddr::Socket(path) => write!(f, "socket {}", path.display()),
+		}
+	}
+}
+
 #[derive(Clone, Debug)]
 pub struct PluginExecutor {
 	max_spawn_attempts: usize,
@@ -18,14 +58,22 @@ pub struct PluginExecutor {
 	port_range: Range<u16>,
 	backoff_interval: Duration,
 	jitter_percent: u8,
+	max_restarts: usize,
+	/// The directory for plugin sockets, if plugins listen on Unix domain sockets.
+	socket_dir: Option<Arc<TempDir>>,
+	/// Used to give each spawned plugin process its own socket.
+	next_socket: Arc<AtomicUsize>,
 }
 impl PluginExecutor {
+	#[allow(clippy::too_many_arguments)]
 	pub fn new(
 		max_spawn_attempts: usize,
 		max_conn_attempts: usize,
 		port_range: Range<u16>,
 		backoff_interval_micros: u64,
 		jitter_percent: u8,
+		max_restarts: usize,
+		connection: PluginConnection,
 	) -> Result<Self> {
 		if jitter_percent > 100 {
 			return Err(hc_error!(
@@ -36,15 +84,38 @@ impl PluginExecutor {
 
 		let backoff_interval = Duration::from_micros(backoff_interval_micros);
 
+		let socket_dir = match connection {
+			PluginConnection::Tcp => None,
+			PluginConnection::UnixSocket if cfg!(unix) => Some(Arc::new(
+				tempfile::Builder::new()
+					.prefix("hipcheck-plugins-")
+					.tempdir()
+					.map_err(|e| hc_error!("failed to create plugin socket directory: {}", e))?,
+			)),
+			PluginConnection::UnixSocket => {
+				return Err(hc_error!(
+					"Unix domain sockets are not supported on this platform"
+				))
+			}
+		};
+
 		Ok(PluginExecutor {
 			max_spawn_attempts,
 			max_conn_attempts,
 			port_range,
 			backoff_interval,
 			jitter_percent,
+			max_restarts,
+			socket_dir,
+			next_socket: Arc::new(AtomicUsize::new(0)),
 		})
 	}
 
+	/// The number of times a plugin that dies mid-run may be restarted.
+	pub fn max_restarts(&self) -> usize {
+		self.max_restarts
+	}
+
 	fn get_available_port(&self) -> Result<u16> {
 		for _i in self.port_range.start..self.port_range.end {
 			// @Todo - either TcpListener::bind returns Ok even if port is bound
@@ -62,6 +133,18 @@ impl PluginExecutor {
 		Err(hc_error!("Failed to find available port"))
 	}
 
+	// Pick a new address for a plugin process to listen on
+	fn get_addr(&self) -> Result<PluginAddr> {
+		match &self.socket_dir {
+			Some(dir) => {
+				// Socket paths have a short length limit, so keep the name short
+				let n = self.next_socket.fetch_add(1, Ordering::Relaxed);
+				Ok(PluginAddr::Socket(dir.path().join(format!("{n}.sock"))))
+			}
+			None => self.get_available_port().map(PluginAddr::Port),
+		}
+	}
+
 	pub async fn start_plugins(&self, plugins: Vec<Plugin>) -> Result<Vec<PluginContext>> {
 		join_all(plugins.into_iter().map(|p| self.start_plugin(p)))
 			.await
@@ -92,23 +175,47 @@ impl PluginExecutor {
 
 		let mut spawn_attempts: usize = 0;
 		while spawn_attempts < self.max_spawn_attempts {
-			// Find free port for process. Don't retry if we fail since this means all
-			// ports in the desired range are already bound
-			let port = self.get_available_port()?;
-			let port_str = port.to_string();
+			// Find free port or socket path for process. Don't retry if we fail since
+			// this means all ports in the desired range are already bound
+			let addr = self.get_addr()?;
 
 			// Spawn plugin process
-			log::debug!("Spawning '{}' on port {}", &plugin.entrypoint, port_str);
-			let Ok(mut proc) = Command::new(&plugin.entrypoint)
-				.args(["--port", port_str.as_str()])
-				// @Temporary - directly forward stdout/stderr from plugin to shell
+			log::debug!("Spawning '{}' on {}", &plugin.entrypoint, addr);
+			let mut cmd = Command::new(&plugin.entrypoint);
+			match &addr {
+				PluginAddr::Port(port) => cmd.arg("--port").arg(port.to_string()),
+				PluginAddr::Socket(path) => cmd.arg("--socket").arg(path),
+			};
+			// Plugins are kept offline along with Hipcheck
+			if is_offline() {
+				cmd.env(OFFLINE_ENV_VAR, "1");
+			} else {
+				cmd.env_remove(OFFLINE_ENV_VAR);
+			}
+			let Ok(mut proc) = cmd
+				// @Temporary - directly forward stdout from plugin to shell
 				.stdout(std::io::stdout())
-				.stderr(std::io::stderr())
+				.stderr(Stdio::piped())
 				.spawn()
 			else {
 				spawn_attempts += 1;
 				continue;
 			};
+			// Forward stderr to the shell as well, but keep the most recent lines
+			// so we can report them if the plugin fails
+			let stderr = PluginStderr::default();
+			if let Some(pipe) = proc.stderr.take() {
+				let stderr = stderr.clone();
+				std::thread::spawn(move || {
+					for line in BufReader::new(pipe).lines() {
+						let Ok(line) = line else {
+							break;
+						};
+						eprintln!("{line}");
+						stderr.push(line);
+					}
+				});
+			}
 			// Attempt to connect to the plugin's gRPC server up to N times, using
 			// linear backoff with a percentage jitter.
 			let mut conn_attempts = 0;
@@ -128,9 +235,7 @@ impl PluginExecutor {
 					.saturating_mul(conn_attempts as u32)
 					.mul_f64(jitter_percent);
 				sleep_until(Instant::now() + sleep_duration).await;
-				if let Ok(grpc) =
-					PluginServiceClient::connect(format!("http://127.0.0.1:{port_str}")).await
-				{
+				if let Ok(grpc) = connect(&addr).await {
 					opt_grpc = Some(grpc);
 					break;
 				} else {
@@ -149,9 +254,10 @@ impl PluginExecutor {
 			// We now have an open gRPC connection to our plugin process
 			return Ok(PluginContext {
 				plugin: plugin.clone(),
-				port,
+				addr,
 				grpc,
 				proc,
+				stderr,
 			});
 		}
 		Err(hc_error!(
@@ -160,3 +266,34 @@ impl PluginExecutor {
 		))
 	}
 }
+
+// Open a gRPC client to a plugin listening on `addr`
+async fn connect(addr: &PluginAddr) -> Result<HcPluginClient> {
+	match addr {
+		PluginAddr::Port(port) => {
+			Ok(PluginServiceClient::connect(format!("http://127.0.0.1:{port}")).await?)
+		}
+		#[cfg(unix)]
+		PluginAddr::Socket(path) => {
+			use hyper_util::rt::TokioIo;
+			use tokio::net::UnixStream;
+			use tonic::transport::{Endpoint, Uri};
+
+			let path = path.clone();
+			// Tonic requires a URI, but the connector ignores it
+			let channel = Endpoint::from_static("http://[::]:50051")
+				.connect_with_connector(tower::service_fn(move |_: Uri| {
+					let path = path.clone();
+					async move {
+						Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(path).await?))
+					}
+				}))
+				.await?;
+			Ok(PluginServiceClient::new(channel))
+		}
+		#[cfg(not(unix))]
+		PluginAddr::Socket(_) => Err(hc_error!(
+			"Unix domain sockets are not supported on this platform"
+		)),
+	}
+}
@@ -2,23 +2,34 @@
 
 mod arch;
 mod download_manifest;
+mod lockfile;
 mod manager;
 mod plugin_id;
 mod plugin_manifest;
+mod resolve;
 mod retrieval;
 mod types;
 
-use crate::error::Result;
 pub use crate::plugin::{get_plugin_key, manager::*, plugin_id::PluginId, types::*};
+use crate::{
+	error::{Error, Result},
+	hc_error,
+};
 pub use arch::{get_current_arch, try_set_arch, Arch};
 pub use download_manifest::{ArchiveFormat, DownloadManifest, HashAlgorithm, HashWithDigest};
+pub use lockfile::PluginLockfile;
 pub use plugin_manifest::{
-	try_get_bin_for_entrypoint, PluginManifest, PluginName, PluginPublisher, PluginVersion,
+	check_path_component, try_get_bin_for_entrypoint, PluginDependency, PluginDependencyList,
+	PluginManifest, PluginName, PluginPublisher, PluginVersion, PluginVersionReq,
 };
-pub use retrieval::retrieve_plugins;
+pub(crate) use retrieval::{create_archive, extract_archive};
+pub use retrieval::{retrieve_plugins, LockfileUpdate};
 use serde_json::Value;
-use std::collections::HashMap;
-use tokio::sync::Mutex;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+	sync::{Mutex, OnceCell, RwLock},
+	time::timeout,
+};
 
 pub async fn initialize_plugins(
 	plugins: Vec<PluginContextWithConfig>,
@@ -40,26 +51,80 @@ pub async fn initialize_plugins(
 	Ok(out)
 }
 
+/// How long to wait on a plugin's health check before treating it as dead.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub struct ActivePlugin {
 	next_id: Mutex<usize>,
-	channel: PluginTransport,
+	/// The transport to the plugin's current process, which is replaced if the
+	/// process is restarted.
+	channel: RwLock<Arc<PluginTransport>>,
+	opt_default_policy_expr: Option<String>,
+	opt_explain_default_query: Option<String>,
+	/// The output schema of each of the plugin's queries, by query name.
+	output_schemas: HashMap<String, Value>,
+	/// What we need to start and configure the plugin again.
+	plugin: Plugin,
+	config: Value,
+	executor: PluginExecutor,
+	/// How many more times the plugin may be restarted. Holding this lock also
+	/// ensures only one restart happens at a time.
+	restarts_left: Mutex<usize>,
 }
 
 impl ActivePlugin {
-	pub fn new(channel: PluginTransport) -> Self {
+	pub fn new(channel: PluginTransport, config: Value, executor: PluginExecutor) -> Self {
 		ActivePlugin {
 			next_id: Mutex::new(1),
-			channel,
+			opt_default_policy_expr: channel.opt_default_policy_expr.clone(),
+			opt_explain_default_query: channel.opt_explain_default_query.clone(),
+			output_schemas: channel
+				.schemas
+				.iter()
+				.map(|(name, schema)| (name.clone(), schema.output_schema.clone()))
+				.collect(),
+			plugin: channel.plugin().clone(),
+			channel: RwLock::new(Arc::new(channel)),
+			config,
+			restarts_left: Mutex::new(executor.max_restarts()),
+			executor,
 		}
 	}
 
+	pub fn version(&self) -> &str {
+		&self.plugin.version
+	}
+
+	/// The configuration the plugin was given in the policy file.
+	pub fn config(&self) -> &Value {
+		&self.config
+	}
+
+	/// The plugins this one depends on directly, as `publisher/name`.
+	pub fn dependencies(&self) -> &[String] {
+		&self.plugin.dependencies
+	}
+
 	pub fn get_default_policy_expr(&self) -> Option<&String> {
-		self.channel.opt_default_policy_expr.as_ref()
+		self.opt_default_policy_expr.as_ref()
 	}
 
 	pub fn get_default_query_explanation(&self) -> Option<&String> {
-		self.channel.opt_explain_default_query.as_ref()
+		self.opt_explain_default_query.as_ref()
+	}
+
+	/// Get the JSON Schema of the output of the named query, if the plugin has one.
+	pub fn get_output_schema(&self, query: &str) -> Option<&Value> {
+		self.output_schemas.get(query)
+	}
+
+	/// Get the transport to the plugin's current process.
+	///
+	/// A query and each of its resumptions must be sent over the same
+	/// transport, since a restarted process knows nothing of earlier queries.
+	pub async fn transport(&self) -> Arc<PluginTransport> {
+		Arc::clone(&*self.channel.read().await)
 	}
 
 	async fn get_unique_id(&self) -> usize {
@@ -71,11 +136,16 @@ impl ActivePlugin {
 		res
 	}
 
-	pub async fn query(&self, name: String, key: Value) -> Result<PluginResponse> {
+	pub async fn query(
+		&self,
+		transport: &PluginTransport,
+		name: String,
+		key: Value,
+	) -> Result<PluginResponse> {
 		let id = self.get_unique_id().await;
 
 		// TODO: remove this unwrap
-		let (publisher, plugin) = self.channel.name().split_once('/').unwrap();
+		let (publisher, plugin) = self.plugin.name.split_once('/').unwrap();
 
 		// @Todo - check name+key valid for schema
 		let query = Query {
@@ -84,18 +154,21 @@ impl ActivePlugin {
 			publisher: publisher.to_owned(),
 			plugin: plugin.to_owned(),
 			query: name,
-			key,
-			output: serde_json::json!(null),
+			key: vec![key],
+			output: vec![serde_json::json!(null)],
 			concerns: vec![],
 		};
 
-		Ok(self.channel.query(query).await?.into())
+		self.send(transport, query).await
 	}
 
+	/// Resume a query that was waiting on other queries, with one output per
+	/// key in `state`.
 	pub async fn resume_query(
 		&self,
+		transport: &PluginTransport,
 		state: AwaitingResult,
-		output: Value,
+		output: Vec<Value>,
 	) -> Result<PluginResponse> {
 		let query = Query {
 			id: state.id,
@@ -103,20 +176,157 @@ impl ActivePlugin {
 			publisher: state.publisher,
 			plugin: state.plugin,
 			query: state.query,
-			key: serde_json::json!(null),
+			key: state.keys,
 			output,
 			concerns: vec![],
 		};
 
 		log::trace!("Resuming query");
 
-		Ok(self.channel.query(query).await?.into())
+		self.send(transport, query).await
+	}
+
+	// Send a query and wait for the plugin's response, up to the plugin's timeout
+	async fn send(&self, transport: &PluginTransport, query: Query) -> Result<PluginResponse> {
+		let response = match self.plugin.timeout {
+			Some(limit) => match timeout(limit, transport.query(query)).await {
+				Ok(response) => response,
+				Err(_) => {
+					return Err(self.failure(
+						transport,
+						format!("timed out after {}s waiting on plugin", limit.as_secs()),
+					))
+				}
+			},
+			None => transport.query(query).await,
+		};
+
+		response
+			.and_then(TryInto::try_into)
+			.map_err(|e| self.failure(transport, e.to_string()))
+	}
+
+	/// Make an error for a failure of the plugin itself, with what it last wrote to stderr.
+	pub fn failure(&self, transport: &PluginTransport, message: impl Into<String>) -> Error {
+		Error::new(PluginFailure {
+			plugin: self.plugin.name.clone(),
+			message: message.into(),
+			stderr: transport.stderr().tail(),
+		})
+	}
+
+	/// Restart the plugin if the process behind `transport` has stopped serving.
+	///
+	/// Returns whether a query that failed on `transport` should be retried.
+	/// Errors if the plugin needed a restart but could not be restarted.
+	pub async fn recover(&self, transport: &Arc<PluginTransport>) -> Result<bool> {
+		let mut restarts_left = self.restarts_left.lock().await;
+
+		// Another failed query may have restarted the plugin already
+		if !Arc::ptr_eq(&*self.channel.read().await, transport) {
+			return Ok(true);
+		}
+
+		if let Ok(Ok(())) = timeout(HEALTH_CHECK_TIMEOUT, transport.health_check()).await {
+			return Ok(false);
+		}
+
+		if *restarts_left == 0 {
+			return Err(hc_error!(
+				"plugin '{}' stopped serving and has no restarts left",
+				self.plugin.name
+			));
+		}
+		*restarts_left -= 1;
+
+		log::warn!(
+			"Plugin '{}' stopped serving, restarting it ({} restarts left)",
+			self.plugin.name,
+			*restarts_left
+		);
+
+		let ctx = self.executor.start_plugin(self.plugin.clone()).await?;
+		let channel = ctx.initialize(self.config.clone()).await?;
+		*self.channel.write().await = Arc::new(channel);
+
+		Ok(true)
+	}
+}
+
+/// Identifies a single plugin query as (publisher, plugin, query, key).
+pub type QueryKey = (String, String, String, Value);
+
+/// A memoization cache for the results of plugin queries.
+///
+/// Each entry is a `OnceCell`, so concurrent requests for the same query wait
+/// on the first request to complete instead of re-issuing it to the plugin.
+/// Only successful results are kept, so a failed query is tried again the
+/// next time it's made.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+	entries: Mutex<HashMap<QueryKey, Arc<OnceCell<QueryResult>>>>,
+	/// For each query being run, the queries whose results it's waiting on.
+	waits: std::sync::Mutex<HashMap<QueryKey, Vec<QueryKey>>>,
+}
+
+impl QueryCache {
+	/// Get the cell for `key`, creating an empty one if the query has not been seen.
+	pub async fn entry(&self, key: QueryKey) -> Arc<OnceCell<QueryResult>> {
+		let mut entries = self.entries.lock().await;
+		Arc::clone(entries.entry(key).or_default())
+	}
+
+	/// Forget every result, so later queries are made to the plugins again. Must
+	/// not be called from within an async runtime.
+	pub fn clear(&self) {
+		self.entries.blocking_lock().clear();
+	}
+
+	/// Record that the query `waiter` is waiting on the result of `target`, unless `target` is
+	/// already waiting on `waiter`, directly or through other queries. Those queries may have
+	/// been made concurrently, so this is checked across every query being run. Returns whether
+	/// the wait was recorded; if it wasn't, waiting would never finish.
+	pub fn start_wait(&self, waiter: &QueryKey, target: &QueryKey) -> bool {
+		let mut waits = self.waits.lock().unwrap();
+
+		let mut to_visit = vec![target];
+		let mut visited = Vec::new();
+		while let Some(key) = to_visit.pop() {
+			if key == waiter {
+				return false;
+			}
+			if visited.contains(&key) {
+				continue;
+			}
+			visited.push(key);
+			to_visit.extend(waits.get(key).into_iter().flatten());
+		}
+
+		waits
+			.entry(waiter.clone())
+			.or_default()
+			.push(target.clone());
+		true
+	}
+
+	/// Record that the query `waiter` is no longer waiting on the result of `target`.
+	pub fn end_wait(&self, waiter: &QueryKey, target: &QueryKey) {
+		let mut waits = self.waits.lock().unwrap();
+		if let Some(targets) = waits.get_mut(waiter) {
+			if let Some(i) = targets.iter().position(|key| key == target) {
+				targets.swap_remove(i);
+			}
+			if targets.is_empty() {
+				waits.remove(waiter);
+			}
+		}
 	}
 }
 
 #[derive(Debug)]
 pub struct HcPluginCore {
 	pub plugins: HashMap<String, ActivePlugin>,
+	pub cache: QueryCache,
 }
 
 impl HcPluginCore {
@@ -141,20 +351,86 @@ impl HcPluginCore {
 		let mapped_ctxs: Vec<PluginContextWithConfig> = ctxs
 			.into_iter()
 			.map(|c| {
-				let conf = conf_map.remove(&c.plugin.name).unwrap();
+				let conf = conf_map.get(&c.plugin.name).unwrap().clone();
 				PluginContextWithConfig(c, conf)
 			})
 			.collect();
 
 		// Use configs to initialize corresponding plugin
 		let plugins = HashMap::<String, ActivePlugin>::from_iter(
-			initialize_plugins(mapped_ctxs)
-				.await?
-				.into_iter()
-				.map(|p| (p.name().to_owned(), ActivePlugin::new(p))),
+			initialize_plugins(mapped_ctxs).await?.into_iter().map(|p| {
+				let conf = conf_map.remove(p.name()).unwrap();
+				let name = p.name().to_owned();
+				(name, ActivePlugin::new(p, conf, executor.clone()))
+			}),
 		);
 
 		// Now we have a set of started and initialized plugins to interact with
-		Ok(HcPluginCore { plugins })
+		Ok(HcPluginCore {
+			plugins,
+			cache: QueryCache::default(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use tokio::runtime::Runtime;
+
+	#[test]
+	fn query_cache_shares_entries_by_key() {
+		let runtime = Runtime::new().unwrap();
+		let cache = QueryCache::default();
+		let key = |k: i64| {
+			(
+				"mitre".to_owned(),
+				"git".to_owned(),
+				"commits".to_owned(),
+				serde_json::json!(k),
+			)
+		};
+
+		runtime.block_on(async {
+			let first = cache.entry(key(1)).await;
+			first
+				.get_or_init(|| async {
+					QueryResult {
+						value: serde_json::json!(42),
+						concerns: vec![],
+					}
+				})
+				.await;
+
+			let again = cache.entry(key(1)).await;
+			assert!(Arc::ptr_eq(&first, &again));
+			assert_eq!(again.get().unwrap().value, serde_json::json!(42));
+
+			let other = cache.entry(key(2)).await;
+			assert!(other.get().is_none());
+		});
+	}
+
+	#[test]
+	fn query_cache_detects_waits_across_queries() {
+		let cache = QueryCache::default();
+		let key = |query: &str| {
+			(
+				"mitre".to_owned(),
+				"git".to_owned(),
+				query.to_owned(),
+				Value::Null,
+			)
+		};
+
+		// Queries made concurrently, rather than one within another, can still wait on each other
+		assert!(cache.start_wait(&key("a"), &key("b")));
+		assert!(cache.start_wait(&key("b"), &key("c")));
+		assert!(!cache.start_wait(&key("c"), &key("a")));
+		assert!(!cache.start_wait(&key("a"), &key("a")));
+
+		// Once the wait is over, nothing is waiting on the query anymore
+		cache.end_wait(&key("a"), &key("b"));
+		assert!(cache.start_wait(&key("c"), &key("a")));
 	}
 }
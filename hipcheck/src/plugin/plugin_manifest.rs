@@ -3,8 +3,8 @@
 use crate::{
 	error::Error,
 	hc_error,
-	plugin::{Arch, PluginId},
-	policy::policy_file::ManifestLocation,
+	plugin::Arch,
+	policy::policy_file::{ManifestLocation, PolicyPluginName},
 	string_newtype_parse_kdl_node,
 	util::{
 		fs::read_string,
@@ -12,8 +12,10 @@ use crate::{
 	},
 };
 use kdl::{KdlDocument, KdlNode};
+use semver::{Op, Version, VersionReq};
 use std::{
 	collections::HashMap,
+	fmt::{self, Display},
 	ops::Not,
 	path::{Path, PathBuf},
 	str::FromStr,
@@ -37,6 +39,63 @@ string_newtype_parse_kdl_node!(PluginName, "name");
 pub struct PluginVersion(pub String);
 string_newtype_parse_kdl_node!(PluginVersion, "version");
 
+impl PluginVersion {
+	/// Make a plugin version from one given by the user, which must be usable as a directory name
+	/// in the plugin cache
+	pub fn validated(version: String) -> Result<Self, Error> {
+		check_path_component("version", &version)?;
+		Ok(PluginVersion(version))
+	}
+
+	/// Parse the version as a SemVer version
+	pub fn to_semver(&self) -> Result<Version, Error> {
+		Version::parse(&self.0).map_err(|e| hc_error!("Invalid plugin version '{}': {}", self.0, e))
+	}
+}
+
+/// Check that a plugin's publisher, name, or version can be used as a single component of a path
+/// in the plugin cache, so it can't name a directory outside of it
+pub fn check_path_component(kind: &str, value: &str) -> Result<(), Error> {
+	if value.is_empty() || value == "." || value == ".." || value.contains(['/', '\\']) {
+		return Err(hc_error!("Invalid plugin {} '{}'", kind, value));
+	}
+	Ok(())
+}
+
+/// A SemVer requirement on the version of a plugin, like `^0.1` or `>=0.2, <0.4`. Unlike Cargo, a
+/// bare version like `0.1.0` is treated as `=0.1.0`, since plugin versions were always exact
+/// before requirements were supported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginVersionReq(pub VersionReq);
+
+impl PluginVersionReq {
+	pub fn matches(&self, version: &Version) -> bool {
+		self.0.matches(version)
+	}
+}
+
+impl FromStr for PluginVersionReq {
+	type Err = crate::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut req = VersionReq::parse(s)
+			.map_err(|e| hc_error!("Invalid plugin version requirement '{}': {}", s, e))?;
+		// `semver` reads a comparator without an operator as `^`
+		for (comparator, written) in req.comparators.iter_mut().zip(s.split(',')) {
+			if comparator.op == Op::Caret && !written.trim_start().starts_with('^') {
+				comparator.op = Op::Exact;
+			}
+		}
+		Ok(PluginVersionReq(req))
+	}
+}
+
+impl Display for PluginVersionReq {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct License(pub String);
 string_newtype_parse_kdl_node!(License, "license");
@@ -105,28 +164,29 @@ impl ParseKdlNode for Entrypoints {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PluginDependency {
-	/// identifier for this PluginDependency
-	pub plugin_id: PluginId,
+	/// publisher and name of the plugin depended on
+	pub name: PolicyPluginName,
+	/// versions of the plugin which may be used
+	pub version: PluginVersionReq,
 	// NOTE: until Hipcheck supports a registry, this is effectively required
 	pub manifest: Option<ManifestLocation>,
 }
 
 impl PluginDependency {
 	#[cfg(test)]
-	pub fn new(plugin_id: PluginId, manifest: Option<ManifestLocation>) -> Self {
+	pub fn new(
+		name: PolicyPluginName,
+		version: PluginVersionReq,
+		manifest: Option<ManifestLocation>,
+	) -> Self {
 		Self {
-			plugin_id,
+			name,
+			version,
 			manifest,
 		}
 	}
 }
 
-impl AsRef<PluginId> for PluginDependency {
-	fn as_ref(&self) -> &PluginId {
-		&self.plugin_id
-	}
-}
-
 impl ParseKdlNode for PluginDependency {
 	fn kdl_key() -> &'static str {
 		"plugin"
@@ -139,15 +199,15 @@ impl ParseKdlNode for PluginDependency {
 
 		// per RFD #4, the name is the first positional entry and has type String and is of the format `<publisher>/<name>`
 		let publisher_and_name = node.entries().first()?.value().as_string()?;
-		let (publisher, name) = match publisher_and_name.split_once('/') {
-			Some((publisher, name)) => (
-				PluginPublisher(publisher.to_string()),
-				PluginName(name.to_string()),
-			),
-			None => return None,
-		};
+		let name = PolicyPluginName::new(publisher_and_name).ok()?;
 
-		let version = PluginVersion(node.get("version")?.value().as_string()?.to_string());
+		let version = match PluginVersionReq::from_str(node.get("version")?.value().as_string()?) {
+			Ok(version) => version,
+			Err(e) => {
+				log::error!("{} for dependency {}", e, name);
+				return None;
+			}
+		};
 		let manifest = match node.get("manifest") {
 			Some(manifest) => {
 				let manifest_location = manifest.value().as_string()?;
@@ -159,10 +219,10 @@ impl ParseKdlNode for PluginDependency {
 			}
 			None => None,
 		};
-		let plugin_id = PluginId::new(publisher, name, version);
 
 		Some(Self {
-			plugin_id,
+			name,
+			version,
 			manifest,
 		})
 	}
@@ -188,8 +248,8 @@ impl ToKdlNode for PluginDependencyList {
 		let dependency_children_nodes = dependency_children.nodes_mut();
 		for dep in self.0.iter() {
 			let mut entry = KdlNode::new("plugin");
-			entry.insert(0, dep.plugin_id.to_policy_file_plugin_identifier());
-			entry.insert("version", dep.plugin_id.version().0.as_str());
+			entry.insert(0, dep.name.to_string());
+			entry.insert("version", dep.version.to_string());
 			if let Some(manifest) = &dep.manifest {
 				entry.insert("manifest", manifest.to_string());
 			}
@@ -462,6 +522,21 @@ mod test {
 		assert_eq!(Entrypoints::parse_node(&node).unwrap(), expected)
 	}
 
+	#[test]
+	fn test_bare_version_req_is_exact() {
+		let version = |v| Version::parse(v).unwrap();
+		let req = PluginVersionReq::from_str("0.1.0").unwrap();
+		assert!(req.matches(&version("0.1.0")));
+		assert!(!req.matches(&version("0.1.1")));
+
+		let req = PluginVersionReq::from_str("^0.1.0").unwrap();
+		assert!(req.matches(&version("0.1.1")));
+
+		let req = PluginVersionReq::from_str(">=0.1.0, 0.1").unwrap();
+		assert!(req.matches(&version("0.1.1")));
+		assert!(!req.matches(&version("0.2.0")));
+	}
+
 	#[test]
 	fn test_parsing_plugin_dependency() {
 		let dep = r#"plugin "mitre/git" version="0.1.0" manifest="https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-git.kdl""#;
@@ -469,11 +544,8 @@ mod test {
 		assert_eq!(
 			PluginDependency::parse_node(&node).unwrap(),
 			PluginDependency::new(
-				PluginId::new(
-					PluginPublisher("mitre".to_string()),
-					PluginName("git".to_string()),
-					PluginVersion("0.1.0".to_string()),
-				),
+				PolicyPluginName::new("mitre/git").unwrap(),
+				PluginVersionReq::from_str("0.1.0").unwrap(),
 				Some(ManifestLocation::Url(
 					Url::parse(
 						"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-git.kdl"
@@ -493,11 +565,8 @@ mod test {
 		let node = KdlNode::from_str(dependencies).unwrap();
 		let mut expected = PluginDependencyList::new();
 		expected.push(PluginDependency::new(
-			PluginId::new(
-				PluginPublisher("mitre".to_string()),
-				PluginName("git".to_string()),
-				PluginVersion("0.1.0".to_string()),
-			),
+			PolicyPluginName::new("mitre/git").unwrap(),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(
 				ManifestLocation::Url(
 					url::Url::parse(
@@ -509,11 +578,8 @@ mod test {
 			),
 		));
 		expected.push(PluginDependency::new(
-			PluginId::new(
-				PluginPublisher("mitre".to_string()),
-				PluginName("plugin2".to_string()),
-				PluginVersion("0.4.0".to_string()),
-			),
+			PolicyPluginName::new("mitre/plugin2").unwrap(),
+			PluginVersionReq::from_str("0.4.0").unwrap(),
 			Some(ManifestLocation::Url(
 				url::Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-plugin2.kdl",
@@ -570,11 +636,8 @@ dependencies {
 
 		let mut dependencies = PluginDependencyList::new();
 		dependencies.push(PluginDependency::new(
-			PluginId::new(
-				PluginPublisher("mitre".to_string()),
-				PluginName("git".to_string()),
-				PluginVersion("0.1.0".to_string()),
-			),
+			PolicyPluginName::new("mitre/git").unwrap(),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(ManifestLocation::Url(
 				url::Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-git.kdl",
@@ -624,11 +687,8 @@ dependencies {
 
 		let mut dependencies = PluginDependencyList::new();
 		dependencies.push(PluginDependency::new(
-			PluginId::new(
-				PluginPublisher::new("mitre".to_owned()),
-				PluginName::new("git".to_owned()),
-				PluginVersion::new("0.1.0".to_owned()),
-			),
+			PolicyPluginName::new("mitre/git").unwrap(),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(ManifestLocation::Local("./plugins/git/plugin.kdl".into())),
 		));
 
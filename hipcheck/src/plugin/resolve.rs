@@ -0,0 +1,443 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Choosing one version of each plugin a policy file needs, which satisfies the version
+//! requirements of both the policy file and the dependencies of the other plugins.
+
+use crate::{
+	error::{Error, Result},
+	hc_error,
+	plugin::{PluginDependency, PluginId, PluginVersion, PluginVersionReq},
+	policy::policy_file::{ManifestLocation, PolicyPlugin, PolicyPluginName},
+};
+use indexmap::IndexMap;
+use semver::Version;
+use std::collections::{HashMap, VecDeque};
+
+/// How many times to revisit the chosen versions before giving up. Choosing a different version
+/// of a plugin can change what it requires of its own dependencies, so versions are chosen again
+/// until no choice changes.
+const MAX_ROUNDS: usize = 32;
+
+/// Where the available versions of plugins, and the dependencies of each version, come from.
+pub trait PluginSource {
+	/// Get the versions of a plugin available from its manifest.
+	fn versions(
+		&mut self,
+		name: &PolicyPluginName,
+		manifest: &ManifestLocation,
+	) -> Result<Vec<Version>>;
+
+	/// Get the dependencies of one version of a plugin. This is asked of every version considered
+	/// while resolving, so it should avoid retrieving the plugin where it can.
+	fn dependencies(
+		&mut self,
+		plugin_id: &PluginId,
+		manifest: &ManifestLocation,
+	) -> Result<Vec<PluginDependency>>;
+
+	/// Retrieve one version of a plugin. This is only asked of the versions finally chosen.
+	fn retrieve(&mut self, plugin_id: &PluginId, manifest: &ManifestLocation) -> Result<()>;
+}
+
+/// A requirement on the version of a plugin, and how the plugin came to be required.
+#[derive(Clone, Debug)]
+struct Requirement {
+	name: PolicyPluginName,
+	version: PluginVersionReq,
+	manifest: Option<ManifestLocation>,
+	/// The plugins whose dependencies led to this requirement, if it isn't from the policy file
+	chain: Vec<String>,
+}
+
+impl Requirement {
+	fn from_policy(plugin: &PolicyPlugin) -> Self {
+		Requirement {
+			name: plugin.name.clone(),
+			version: plugin.version.clone(),
+			manifest: plugin.manifest.clone(),
+			chain: vec![],
+		}
+	}
+
+	fn from_dependency(dependency: &PluginDependency, chain: Vec<String>) -> Self {
+		Requirement {
+			name: dependency.name.clone(),
+			version: dependency.version.clone(),
+			manifest: dependency.manifest.clone(),
+			chain,
+		}
+	}
+
+	/// Describe the requirement, like "policy file -> mitre/activity 0.1.0 requires ^0.1".
+	fn describe(&self) -> String {
+		let mut description = String::from("policy file");
+		for plugin in &self.chain {
+			description.push_str(" -> ");
+			description.push_str(plugin);
+		}
+		format!("{} requires {}", description, self.version)
+	}
+}
+
+/// Choose a version of each plugin the policy file needs, directly or as a dependency.
+///
+/// The newest available version satisfying every requirement on a plugin is chosen, unless the
+/// version in `locked` satisfies them too, in which case it is kept. Once the choices have settled,
+/// every chosen plugin is retrieved from `source` before they're returned.
+pub fn resolve_plugins(
+	policy_plugins: &[PolicyPlugin],
+	locked: &HashMap<PolicyPluginName, Version>,
+	source: &mut impl PluginSource,
+) -> Result<Vec<PluginId>> {
+	let roots: Vec<Requirement> = policy_plugins
+		.iter()
+		.map(Requirement::from_policy)
+		.collect();
+
+	// The manifest each plugin is retrieved from is the first one given for it
+	let mut manifests: HashMap<PolicyPluginName, ManifestLocation> = HashMap::new();
+	let mut available: HashMap<PolicyPluginName, Vec<Version>> = HashMap::new();
+	let mut dependencies: HashMap<PluginId, Vec<PluginDependency>> = HashMap::new();
+	let mut selected: HashMap<PolicyPluginName, Version> = HashMap::new();
+
+	for _ in 0..MAX_ROUNDS {
+		// Gather the requirements reachable from the policy file through the versions chosen
+		// so far.
+		let mut requirements: IndexMap<PolicyPluginName, Vec<Requirement>> = IndexMap::new();
+		let mut queue = VecDeque::from(roots.clone());
+
+		while let Some(requirement) = queue.pop_front() {
+			let name = requirement.name.clone();
+			let mut chain = requirement.chain.clone();
+
+			let requirements_on_plugin = requirements.entry(name.clone()).or_default();
+			requirements_on_plugin.push(requirement);
+			if requirements_on_plugin.len() > 1 {
+				continue;
+			}
+
+			let Some(version) = selected.get(&name) else {
+				continue;
+			};
+			let plugin_id = PluginId::new(
+				name.publisher.clone(),
+				name.name.clone(),
+				PluginVersion(version.to_string()),
+			);
+			if !dependencies.contains_key(&plugin_id) {
+				let found = source.dependencies(&plugin_id, &manifests[&name])?;
+				dependencies.insert(plugin_id.clone(), found);
+			}
+
+			chain.push(format!("{} {}", name, version));
+			for dependency in &dependencies[&plugin_id] {
+				queue.push_back(Requirement::from_dependency(dependency, chain.clone()));
+			}
+		}
+
+		// Choose a version of each plugin satisfying all of its requirements.
+		let mut chosen = HashMap::new();
+		let mut conflict = None;
+
+		for (name, requirements) in &requirements {
+			if !manifests.contains_key(name) {
+				let manifest = requirements
+					.iter()
+					.find_map(|r| r.manifest.clone())
+					.ok_or_else(|| {
+						hc_error!(
+							"No manifest specified for {} ({})",
+							name,
+							requirements[0].describe()
+						)
+					})?;
+				manifests.insert(name.clone(), manifest);
+			}
+			if !available.contains_key(name) {
+				let versions = source.versions(name, &manifests[name])?;
+				available.insert(name.clone(), versions);
+			}
+
+			let versions = &available[name];
+			let satisfies_all = |v: &Version| requirements.iter().all(|r| r.version.matches(v));
+			let choice = match locked.get(name) {
+				Some(version) if versions.contains(version) && satisfies_all(version) => {
+					Some(version)
+				}
+				_ => versions.iter().filter(|v| satisfies_all(v)).max(),
+			};
+
+			match choice {
+				Some(version) => {
+					chosen.insert(name.clone(), version.clone());
+				}
+				None => {
+					conflict.get_or_insert_with(|| conflict_error(name, requirements, versions));
+				}
+			}
+		}
+
+		// A conflict may only be due to the dependencies of a version which is no longer
+		// chosen, so it's only reported once the other choices have settled.
+		let changed = chosen
+			.iter()
+			.any(|(name, version)| selected.get(name) != Some(version));
+		match conflict {
+			Some(error) if !changed => return Err(error),
+			None if !changed && chosen.len() == selected.len() => {
+				let mut plugin_ids = Vec::with_capacity(requirements.len());
+				for name in requirements.keys() {
+					let plugin_id = PluginId::new(
+						name.publisher.clone(),
+						name.name.clone(),
+						PluginVersion(chosen[name].to_string()),
+					);
+					source.retrieve(&plugin_id, &manifests[name])?;
+					plugin_ids.push(plugin_id);
+				}
+				return Ok(plugin_ids);
+			}
+			_ => selected = chosen,
+		}
+	}
+
+	Err(hc_error!(
+		"Could not choose plugin versions satisfying all requirements after {} attempts",
+		MAX_ROUNDS
+	))
+}
+
+fn conflict_error(
+	name: &PolicyPluginName,
+	requirements: &[Requirement],
+	versions: &[Version],
+) -> Error {
+	let mut message = format!("No version of {} satisfies all of its requirements:", name);
+	for requirement in requirements {
+		message.push_str("\n  ");
+		message.push_str(&requirement.describe());
+	}
+
+	let mut versions = versions.to_vec();
+	versions.sort();
+	if versions.is_empty() {
+		message.push_str("\nNo versions are available for the current arch");
+	} else {
+		let versions: Vec<String> = versions.iter().map(Version::to_string).collect();
+		message.push_str(&format!("\nAvailable versions: {}", versions.join(", ")));
+	}
+
+	hc_error!("{}", message)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::str::FromStr;
+	use url::Url;
+
+	/// Plugins by name, with the dependencies of each of their versions
+	#[derive(Default)]
+	struct FakeSource {
+		plugins: HashMap<String, Vec<(&'static str, Vec<PluginDependency>)>>,
+		looked_up: Vec<String>,
+		retrieved: Vec<String>,
+	}
+
+	impl FakeSource {
+		fn add(&mut self, name: &str, version: &'static str, dependencies: &[(&str, &str)]) {
+			let dependencies = dependencies
+				.iter()
+				.map(|(name, req)| dependency(name, req))
+				.collect();
+			self.plugins
+				.entry(name.to_owned())
+				.or_default()
+				.push((version, dependencies));
+		}
+	}
+
+	impl PluginSource for FakeSource {
+		fn versions(
+			&mut self,
+			name: &PolicyPluginName,
+			_manifest: &ManifestLocation,
+		) -> Result<Vec<Version>> {
+			Ok(self.plugins[&name.to_string()]
+				.iter()
+				.map(|(v, _)| Version::parse(v).unwrap())
+				.collect())
+		}
+
+		fn dependencies(
+			&mut self,
+			plugin_id: &PluginId,
+			_manifest: &ManifestLocation,
+		) -> Result<Vec<PluginDependency>> {
+			self.looked_up.push(plugin_id.to_string());
+			let (_, dependencies) = self.plugins[&plugin_id.to_policy_file_plugin_identifier()]
+				.iter()
+				.find(|(v, _)| *v == plugin_id.version().0)
+				.unwrap();
+			Ok(dependencies.clone())
+		}
+
+		fn retrieve(&mut self, plugin_id: &PluginId, _manifest: &ManifestLocation) -> Result<()> {
+			self.retrieved.push(plugin_id.to_string());
+			Ok(())
+		}
+	}
+
+	fn manifest(name: &str) -> Option<ManifestLocation> {
+		Some(ManifestLocation::Url(
+			Url::parse(&format!("https://example.com/{}.kdl", name)).unwrap(),
+		))
+	}
+
+	fn dependency(name: &str, req: &str) -> PluginDependency {
+		PluginDependency::new(
+			PolicyPluginName::new(name).unwrap(),
+			PluginVersionReq::from_str(req).unwrap(),
+			manifest(name),
+		)
+	}
+
+	fn policy_plugin(name: &str, req: &str) -> PolicyPlugin {
+		PolicyPlugin::new(
+			PolicyPluginName::new(name).unwrap(),
+			PluginVersionReq::from_str(req).unwrap(),
+			manifest(name),
+		)
+	}
+
+	fn resolved(ids: Vec<PluginId>) -> Vec<String> {
+		let mut ids: Vec<String> = ids.iter().map(PluginId::to_string).collect();
+		ids.sort();
+		ids
+	}
+
+	#[test]
+	fn test_resolve_newest_satisfying_all_requirements() {
+		let mut source = FakeSource::default();
+		for version in ["0.1.0", "0.1.1", "0.1.2", "0.1.3", "0.2.0"] {
+			source.add("mitre/git", version, &[]);
+		}
+		source.add(
+			"mitre/activity",
+			"0.1.0",
+			&[("mitre/git", ">=0.1.1, <0.1.3")],
+		);
+
+		let policy = [
+			policy_plugin("mitre/git", "^0.1"),
+			policy_plugin("mitre/activity", "0.1.0"),
+		];
+		let ids = resolve_plugins(&policy, &HashMap::new(), &mut source).unwrap();
+
+		assert_eq!(
+			resolved(ids),
+			vec!["mitre/activity version 0.1.0", "mitre/git version 0.1.2"]
+		);
+	}
+
+	#[test]
+	fn test_resolve_prefers_locked_version() {
+		let mut source = FakeSource::default();
+		for version in ["0.1.0", "0.1.1", "0.1.2"] {
+			source.add("mitre/git", version, &[]);
+		}
+		let policy = [policy_plugin("mitre/git", "^0.1")];
+
+		let locked = HashMap::from([(
+			PolicyPluginName::new("mitre/git").unwrap(),
+			Version::parse("0.1.1").unwrap(),
+		)]);
+		let ids = resolve_plugins(&policy, &locked, &mut source).unwrap();
+		assert_eq!(resolved(ids), vec!["mitre/git version 0.1.1"]);
+
+		// A locked version which no longer satisfies the policy file is replaced
+		let policy = [policy_plugin("mitre/git", ">=0.1.2")];
+		let ids = resolve_plugins(&policy, &locked, &mut source).unwrap();
+		assert_eq!(resolved(ids), vec!["mitre/git version 0.1.2"]);
+	}
+
+	#[test]
+	fn test_resolve_conflict_names_dependency_chain() {
+		let mut source = FakeSource::default();
+		source.add("mitre/git", "0.1.0", &[]);
+		source.add("mitre/git", "0.2.0", &[]);
+		source.add("mitre/churn", "0.1.0", &[("mitre/git", "^0.2")]);
+		source.add("mitre/activity", "0.1.0", &[("mitre/churn", "0.1")]);
+
+		let policy = [
+			policy_plugin("mitre/git", "^0.1"),
+			policy_plugin("mitre/activity", "0.1.0"),
+		];
+		let error = resolve_plugins(&policy, &HashMap::new(), &mut source)
+			.unwrap_err()
+			.to_string();
+
+		assert!(error.contains("No version of mitre/git satisfies all of its requirements"));
+		assert!(error.contains("policy file requires ^0.1"));
+		assert!(error
+			.contains("policy file -> mitre/activity 0.1.0 -> mitre/churn 0.1.0 requires ^0.2"));
+		assert!(error.contains("Available versions: 0.1.0, 0.2.0"));
+	}
+
+	#[test]
+	fn test_resolve_shared_dependency_once() {
+		let mut source = FakeSource::default();
+		source.add("mitre/git", "0.1.0", &[]);
+		source.add("mitre/git", "0.1.1", &[]);
+		source.add("mitre/activity", "0.1.0", &[("mitre/git", "^0.1")]);
+		source.add("mitre/churn", "0.1.0", &[("mitre/git", "=0.1.0")]);
+
+		let policy = [
+			policy_plugin("mitre/activity", "0.1"),
+			policy_plugin("mitre/churn", "0.1"),
+		];
+		let ids = resolve_plugins(&policy, &HashMap::new(), &mut source).unwrap();
+
+		assert_eq!(
+			resolved(ids),
+			vec![
+				"mitre/activity version 0.1.0",
+				"mitre/churn version 0.1.0",
+				"mitre/git version 0.1.0"
+			]
+		);
+		// Only the chosen version of a shared dependency is looked at
+		assert!(!source
+			.looked_up
+			.contains(&"mitre/git version 0.1.1".to_owned()));
+	}
+
+	#[test]
+	fn test_resolve_retrieves_only_final_choices() {
+		let mut source = FakeSource::default();
+		source.add("mitre/git", "0.1.0", &[]);
+		source.add("mitre/git", "0.1.1", &[]);
+		source.add("mitre/churn", "0.1.0", &[("mitre/git", "=0.1.0")]);
+
+		let policy = [
+			policy_plugin("mitre/git", "^0.1"),
+			policy_plugin("mitre/churn", "0.1.0"),
+		];
+		let ids = resolve_plugins(&policy, &HashMap::new(), &mut source).unwrap();
+
+		assert_eq!(
+			resolved(ids),
+			vec!["mitre/churn version 0.1.0", "mitre/git version 0.1.0"]
+		);
+		// mitre/git 0.1.1 is chosen at first, but replaced once mitre/churn's dependencies are
+		// known, so it's never retrieved
+		assert!(source
+			.looked_up
+			.contains(&"mitre/git version 0.1.1".to_owned()));
+		source.retrieved.sort();
+		assert_eq!(
+			source.retrieved,
+			vec!["mitre/churn version 0.1.0", "mitre/git version 0.1.0"]
+		);
+	}
+}
@@ -5,102 +5,418 @@ use crate::{
 	error::Error,
 	hc_error,
 	plugin::{
-		download_manifest::DownloadManifestEntry, try_get_bin_for_entrypoint, ArchiveFormat,
-		DownloadManifest, HashAlgorithm, HashWithDigest, PluginId, PluginManifest,
+		download_manifest::{DownloadManifestEntry, Signature, SignatureFormat},
+		try_get_bin_for_entrypoint, ArchiveFormat, DownloadManifest, HashAlgorithm, HashWithDigest,
+		PluginDependency, PluginId, PluginManifest,
+	},
+	policy::{
+		policy_file::{ManifestLocation, PolicyPluginName, PolicyTrustedPublishers},
+		PolicyFile,
+	},
+	util::{
+		fs::{file_sha256, read_string},
+		http::agent::{agent, is_offline},
+		kdl::{ParseKdlNode, ToKdlNode},
 	},
-	policy::policy_file::{ManifestLocation, PolicyPlugin},
-	util::{fs::file_sha256, http::agent::agent},
 };
-use flate2::read::GzDecoder;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use fs_extra::{dir::remove, file::write_all};
+use kdl::KdlDocument;
+use minisign_verify::PublicKey;
+use semver::Version;
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
 	fs::File,
 	io::{Read, Write},
 	path::{Path, PathBuf},
 	str::FromStr,
 };
-use tar::Archive;
+use tar::{Archive, Builder};
 use url::Url;
 use xz2::read::XzDecoder;
 
-use super::get_current_arch;
+use super::{
+	get_current_arch,
+	lockfile::{LockedDownload, LockedPlugin},
+	resolve::{resolve_plugins, PluginSource},
+	PluginLockfile,
+};
+
+/// How retrieving plugins may change the `Hipcheck.lock` next to the policy file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockfileUpdate {
+	/// Only check the retrieved plugins against the lockfile, never writing it
+	None,
+	/// Add the retrieved plugins which aren't locked yet to the lockfile
+	AddNew,
+	/// Replace the plugins locked for the current arch with the ones retrieved now
+	Replace,
+}
 
 /// determine all of the plugins that need to be run and locate download them, if they do not exist
+///
+/// One version of each plugin is chosen to satisfy the version requirements of the policy file
+/// and of the plugins' dependencies, preferring the version in the lockfile if there is one.
+///
+/// Retrieved plugins are checked against the `Hipcheck.lock` next to the policy file at
+/// `policy_path`, if it has one. The lockfile is only written as `update` allows.
+///
+/// When offline, plugins with a download manifest are only taken from `plugin_cache`.
 pub fn retrieve_plugins(
-	policy_plugins: &[PolicyPlugin],
+	policy_file: &PolicyFile,
+	policy_path: Option<&Path>,
 	plugin_cache: &HcPluginCache,
+	update: LockfileUpdate,
 ) -> Result<HashSet<PluginId>, Error> {
-	let mut required_plugins = HashSet::new();
+	let lockfile_path = policy_path.map(PluginLockfile::path_for_policy);
+	let mut lockfile = match &lockfile_path {
+		Some(path) => PluginLockfile::load(path)?,
+		None => PluginLockfile::default(),
+	};
+	let original_lockfile = lockfile.clone();
+	if update != LockfileUpdate::None && is_offline() {
+		// Offline, plugins are taken from the cache without their downloads, so they can't be
+		// locked
+		return Err(hc_error!("Cannot update the plugin lockfile while offline"));
+	}
+	if update == LockfileUpdate::Replace {
+		lockfile.unlock_arch(&get_current_arch());
+	}
 
-	for policy_plugin in policy_plugins.iter() {
-		retrieve_plugin(
-			policy_plugin.get_plugin_id(),
-			&policy_plugin.manifest,
-			plugin_cache,
-			&mut required_plugins,
-		)?;
+	let locked = lockfile.locked_versions(&get_current_arch());
+	let mut retriever = Retriever {
+		plugin_cache,
+		trusted_publishers: &policy_file.trusted_publishers,
+		lockfile: &mut lockfile,
+		offline: is_offline(),
+		download_manifests: HashMap::new(),
+		retrieved: HashSet::new(),
+	};
+	let required_plugins: HashSet<PluginId> =
+		resolve_plugins(&policy_file.plugins.0, &locked, &mut retriever)?
+			.into_iter()
+			.collect();
+
+	lockfile.retain_plugins(&required_plugins);
+	if let Some(path) = lockfile_path.filter(|_| update != LockfileUpdate::None) {
+		if lockfile != original_lockfile {
+			lockfile.save(&path)?;
+		}
 	}
+
 	Ok(required_plugins)
 }
 
-fn retrieve_plugin(
-	plugin_id: PluginId,
-	manifest_location: &Option<ManifestLocation>,
-	plugin_cache: &HcPluginCache,
-	required_plugins: &mut HashSet<PluginId>,
-) -> Result<(), Error> {
-	if required_plugins.contains(&plugin_id) {
-		return Ok(());
+/// Retrieves the versions of plugins chosen while resolving the policy file's plugins, checking
+/// each against the lockfile.
+struct Retriever<'a> {
+	plugin_cache: &'a HcPluginCache,
+	trusted_publishers: &'a PolicyTrustedPublishers,
+	lockfile: &'a mut PluginLockfile,
+	/// whether plugins can only come from the plugin cache
+	offline: bool,
+	/// download manifests already fetched, by URL
+	download_manifests: HashMap<Url, DownloadManifest>,
+	/// plugins already retrieved to read their dependencies
+	retrieved: HashSet<PluginId>,
+}
+
+impl Retriever<'_> {
+	fn download_manifest(&mut self, plugin_url: &Url) -> Result<&DownloadManifest, Error> {
+		if !self.download_manifests.contains_key(plugin_url) {
+			let download_manifest = retrieve_download_manifest(plugin_url)?;
+			self.download_manifests
+				.insert(plugin_url.clone(), download_manifest);
+		}
+		Ok(&self.download_manifests[plugin_url])
 	}
-	// TODO: if the plugin.kdl file for the plugin already exists, then should we skip the retrieval process?
-	// if plugin_cache.plugin_kdl(&plugin_id).exists()
 
-	log::debug!("Retrieving Plugin ID: {:?}", plugin_id);
+	/// The entry of the download manifest at `plugin_url` for a plugin on the current arch
+	fn download_manifest_entry(
+		&mut self,
+		plugin_id: &PluginId,
+		plugin_url: &Url,
+	) -> Result<DownloadManifestEntry, Error> {
+		let current_arch = get_current_arch();
+		let version = plugin_id.version().to_semver()?;
+		self.download_manifest(plugin_url)?
+			.entries
+			.iter()
+			.find(|entry| {
+				entry.arch == current_arch
+					&& entry.version.to_semver().ok().as_ref() == Some(&version)
+			})
+			.cloned()
+			.ok_or_else(|| {
+				hc_error!(
+					"Could not find download manifest entry for {} on arch '{}'",
+					plugin_id,
+					current_arch
+				)
+			})
+	}
+}
 
-	let plugin_manifest = match manifest_location {
-		Some(ManifestLocation::Url(plugin_url)) => {
-			retrieve_plugin_from_network(plugin_id.clone(), plugin_url, plugin_cache)?
+impl PluginSource for Retriever<'_> {
+	fn versions(
+		&mut self,
+		name: &PolicyPluginName,
+		manifest: &ManifestLocation,
+	) -> Result<Vec<Version>, Error> {
+		match manifest {
+			// offline, only the versions retrieved before can be used
+			ManifestLocation::Url(_) if self.offline => Ok(self
+				.plugin_cache
+				.cached_versions(&name.publisher, &name.name)
+				.iter()
+				.filter_map(|version| version.to_semver().ok())
+				.collect()),
+			ManifestLocation::Url(plugin_url) => {
+				let current_arch = get_current_arch();
+				let mut versions = vec![];
+				for entry in &self.download_manifest(plugin_url)?.entries {
+					if entry.arch != current_arch {
+						continue;
+					}
+					match entry.version.to_semver() {
+						Ok(version) => versions.push(version),
+						Err(e) => log::warn!("{} in download manifest {}", e, plugin_url),
+					}
+				}
+				Ok(versions)
+			}
+			ManifestLocation::Local(plugin_manifest_path) => {
+				// a local plugin manifest only offers the one version it describes
+				let plugin_manifest = PluginManifest::from_file(plugin_manifest_path)?;
+				if plugin_manifest.publisher != name.publisher || plugin_manifest.name != name.name
+				{
+					return Err(hc_error!(
+						"Plugin manifest {} is for {}/{}, not {}",
+						plugin_manifest_path.display(),
+						plugin_manifest.publisher.0,
+						plugin_manifest.name.0,
+						name
+					));
+				}
+				Ok(vec![plugin_manifest.version.to_semver()?])
+			}
 		}
-		Some(ManifestLocation::Local(plugin_manifest_path)) => {
-			retrieve_local_plugin(plugin_id.clone(), plugin_manifest_path, plugin_cache)?
+	}
+
+	fn dependencies(
+		&mut self,
+		plugin_id: &PluginId,
+		manifest: &ManifestLocation,
+	) -> Result<Vec<PluginDependency>, Error> {
+		let plugin_manifest = match manifest {
+			// the cached plugin was checked against its download manifest when it was retrieved
+			ManifestLocation::Url(_) if self.offline => {
+				PluginManifest::from_file(self.plugin_cache.plugin_kdl(plugin_id))?
+			}
+			ManifestLocation::Url(plugin_url) => {
+				let entry = self.download_manifest_entry(plugin_id, plugin_url)?;
+				if let Some(dependencies) = entry.dependencies {
+					return Ok(dependencies.0);
+				}
+				// the dependencies are only in the plugin's own manifest, so it has to be
+				// retrieved to read them
+				let plugin_manifest =
+					retrieve_plugin_from_network(plugin_id.clone(), plugin_url, &entry, self)?;
+				self.retrieved.insert(plugin_id.clone());
+				plugin_manifest
+			}
+			ManifestLocation::Local(plugin_manifest_path) => {
+				PluginManifest::from_file(plugin_manifest_path)?
+			}
+		};
+		Ok(plugin_manifest.dependencies.0)
+	}
+
+	fn retrieve(&mut self, plugin_id: &PluginId, manifest: &ManifestLocation) -> Result<(), Error> {
+		if self.retrieved.contains(plugin_id) {
+			return Ok(());
 		}
-		None => {
-			// in the future, this could attempt to reach a known package registry
-			return Err(hc_error!(
-				"No manifest specified for {}",
-				plugin_id.to_policy_file_plugin_identifier()
-			));
+
+		log::debug!("Retrieving Plugin ID: {:?}", plugin_id);
+
+		match manifest {
+			ManifestLocation::Url(plugin_url) if self.offline => {
+				recheck_cached_plugin(plugin_id, plugin_url, self)?;
+			}
+			ManifestLocation::Url(plugin_url) => {
+				let entry = self.download_manifest_entry(plugin_id, plugin_url)?;
+				retrieve_plugin_from_network(plugin_id.clone(), plugin_url, &entry, self)?;
+			}
+			ManifestLocation::Local(plugin_manifest_path) => {
+				verify_local_plugin(plugin_id, self.trusted_publishers)?;
+				self.lockfile.check(LockedPlugin {
+					plugin_id: plugin_id.clone(),
+					arch: get_current_arch(),
+					manifest: ManifestLocation::Local(plugin_manifest_path.clone()),
+					download: None,
+					entrypoint: local_entrypoint_hash(plugin_manifest_path)?,
+				})?;
+				retrieve_local_plugin(plugin_id.clone(), plugin_manifest_path, self.plugin_cache)?;
+			}
 		}
-	};
-	required_plugins.insert(plugin_id);
-	for dependency in plugin_manifest.dependencies.0 {
-		retrieve_plugin(
-			dependency.as_ref().clone(),
-			&dependency.manifest,
-			plugin_cache,
-			required_plugins,
-		)?;
+		self.retrieved.insert(plugin_id.clone());
+		Ok(())
 	}
-	Ok(())
 }
 
 fn retrieve_plugin_from_network(
 	plugin_id: PluginId,
 	plugin_url: &Url,
-	plugin_cache: &HcPluginCache,
+	entry: &DownloadManifestEntry,
+	retriever: &mut Retriever,
 ) -> Result<PluginManifest, Error> {
+	// the download is checked against the locked hash before it is unpacked
+	retriever.lockfile.check(LockedPlugin {
+		plugin_id: plugin_id.clone(),
+		arch: get_current_arch(),
+		manifest: ManifestLocation::Url(plugin_url.clone()),
+		download: Some(LockedDownload {
+			url: entry.url.clone(),
+			hash: entry.hash.clone(),
+		}),
+		entrypoint: None,
+	})?;
+	download_and_unpack_plugin(
+		entry,
+		plugin_id,
+		retriever.plugin_cache,
+		retriever.trusted_publishers,
+	)
+}
+
+/// Check a plugin retrieved before against the record of the download it was retrieved from, the
+/// same way it was checked when it was downloaded, since its download manifest can't be fetched
+/// while offline
+fn recheck_cached_plugin(
+	plugin_id: &PluginId,
+	plugin_url: &Url,
+	retriever: &mut Retriever,
+) -> Result<(), Error> {
 	let current_arch = get_current_arch();
-	let download_manifest = retrieve_download_manifest(plugin_url)?;
-	for entry in &download_manifest.entries {
-		if entry.arch == current_arch {
-			return download_and_unpack_plugin(entry, plugin_id, plugin_cache);
-		}
+	let record_path = retriever
+		.plugin_cache
+		.plugin_download_record(plugin_id, &current_arch);
+	let (entry, entrypoint) = read_string(&record_path)
+		.ok()
+		.and_then(|contents| parse_download_record(&contents))
+		.ok_or_else(|| {
+			hc_error!(
+				"The plugin cache has no record of the download {} was retrieved from for arch '{}', so it can't be checked while offline",
+				plugin_id,
+				current_arch
+			)
+		})?;
+
+	retriever.lockfile.check(LockedPlugin {
+		plugin_id: plugin_id.clone(),
+		arch: current_arch,
+		manifest: ManifestLocation::Url(plugin_url.clone()),
+		download: Some(LockedDownload {
+			url: entry.url.clone(),
+			hash: entry.hash.clone(),
+		}),
+		entrypoint: None,
+	})?;
+
+	let archive_path = retriever
+		.plugin_cache
+		.plugin_download_dir(plugin_id)
+		.join(archive_file_name(&entry.url)?);
+	let contents = std::fs::read(&archive_path).map_err(|e| {
+		hc_error!(
+			"Error [{}] reading cached download {}",
+			e,
+			archive_path.display()
+		)
+	})?;
+	check_download(&contents, entry.size.bytes, &entry.hash)
+		.and_then(|_| {
+			verify_signature(
+				plugin_id,
+				&contents,
+				entry.signature.as_ref(),
+				retriever.trusted_publishers,
+			)
+		})
+		.map_err(|e| hc_error!("Error [{}] checking cached plugin {}", e, plugin_id))?;
+
+	// the archive is only checked as it was downloaded, so the binary extracted from it is
+	// checked against the hash it had once extracted
+	if cached_entrypoint_hash(retriever.plugin_cache, plugin_id)? != entrypoint {
+		return Err(hc_error!(
+			"The entrypoint of cached plugin {} has changed since it was extracted",
+			plugin_id
+		));
+	}
+	Ok(())
+}
+
+/// The record of a plugin download, kept so the download can be checked again while offline: its
+/// download manifest entry, followed by the hash its entrypoint binary had once extracted, if it's
+/// a file in the plugin's directory
+fn download_record(entry: &DownloadManifestEntry, entrypoint: Option<&HashWithDigest>) -> String {
+	let mut document = KdlDocument::new();
+	document.nodes_mut().push(entry.to_kdl_node());
+	document
+		.nodes_mut()
+		.extend(entrypoint.map(HashWithDigest::to_kdl_node));
+	document.to_string()
+}
+
+fn parse_download_record(
+	contents: &str,
+) -> Option<(DownloadManifestEntry, Option<HashWithDigest>)> {
+	let document = KdlDocument::from_str(contents).ok()?;
+	let (entry, entrypoint) = match document.nodes() {
+		[entry] => (entry, None),
+		[entry, entrypoint] => (entry, Some(HashWithDigest::parse_node(entrypoint)?)),
+		_ => return None,
+	};
+	Some((DownloadManifestEntry::parse_node(entry)?, entrypoint))
+}
+
+/// The hash of the entrypoint binary of the local plugin manifest at `plugin_manifest_path`, or
+/// `None` if the entrypoint is a program found on the `PATH`
+fn local_entrypoint_hash(plugin_manifest_path: &Path) -> Result<Option<HashWithDigest>, Error> {
+	let plugin_manifest = PluginManifest::from_file(plugin_manifest_path)?;
+	entrypoint_hash(&plugin_manifest, Path::new(""))
+}
+
+/// The hash of the entrypoint binary of a plugin in the cache, or `None` if the entrypoint isn't a
+/// file in the plugin's directory
+fn cached_entrypoint_hash(
+	plugin_cache: &HcPluginCache,
+	plugin_id: &PluginId,
+) -> Result<Option<HashWithDigest>, Error> {
+	let plugin_manifest = PluginManifest::from_file(plugin_cache.plugin_kdl(plugin_id))?;
+	entrypoint_hash(
+		&plugin_manifest,
+		&plugin_cache.plugin_download_dir(plugin_id),
+	)
+}
+
+/// The hash of the entrypoint binary of `plugin_manifest` for the current arch, with relative
+/// paths taken from `dir`, or `None` if there's no such file
+fn entrypoint_hash(
+	plugin_manifest: &PluginManifest,
+	dir: &Path,
+) -> Result<Option<HashWithDigest>, Error> {
+	let entrypoint = plugin_manifest.get_entrypoint_for(&get_current_arch())?;
+	match try_get_bin_for_entrypoint(&entrypoint)
+		.0
+		.map(|bin| dir.join(bin))
+	{
+		Some(bin) if std::fs::exists(&bin)? => Ok(Some(HashWithDigest::new(
+			HashAlgorithm::Sha256,
+			file_sha256(&bin)?,
+		))),
+		_ => Ok(None),
 	}
-	Err(hc_error!(
-		"Could not find download manifest entry for arch '{}'",
-		current_arch
-	))
 }
 
 /// retrieves a plugin from the local filesystem by copying its `plugin.kdl` and `entrypoint` binary to the plugin_cache
@@ -173,12 +489,14 @@ fn retrieve_local_plugin(
 /// This function does the following:
 /// 1. Download specified plugin for the current arch
 /// 1. Verify its size and hash
+/// 1. Verify its signature, if its publisher is trusted by the policy file
 /// 1. Extract plugin into plugin-specific folder
 /// 1. Finds `plugin.kdl` inside plugin-specific folder and parses it
 fn download_and_unpack_plugin(
 	download_manifest_entry: &DownloadManifestEntry,
 	plugin_id: PluginId,
 	plugin_cache: &HcPluginCache,
+	trusted_publishers: &PolicyTrustedPublishers,
 ) -> Result<PluginManifest, Error> {
 	let download_dir = plugin_cache.plugin_download_dir(&plugin_id);
 
@@ -187,6 +505,14 @@ fn download_and_unpack_plugin(
 		download_dir.as_path(),
 		download_manifest_entry.size.bytes,
 		&download_manifest_entry.hash,
+		|contents| {
+			verify_signature(
+				&plugin_id,
+				contents,
+				download_manifest_entry.signature.as_ref(),
+				trusted_publishers,
+			)
+		},
 	)
 	.map_err(|e| {
 		// delete any leftover remnants
@@ -198,7 +524,7 @@ fn download_and_unpack_plugin(
 		)
 	})?;
 
-	extract_plugin(
+	extract_archive(
 		output_path.as_path(),
 		download_dir.as_path(),
 		download_manifest_entry.compress.format,
@@ -213,15 +539,27 @@ fn download_and_unpack_plugin(
 		)
 	})?;
 
+	// record what was verified, so it can be checked again while offline
+	let entrypoint = cached_entrypoint_hash(plugin_cache, &plugin_id)?;
+	let record_path =
+		plugin_cache.plugin_download_record(&plugin_id, &download_manifest_entry.arch);
+	write_all(
+		&record_path,
+		&download_record(download_manifest_entry, entrypoint.as_ref()),
+	)
+	.map_err(|e| hc_error!("Error [{}] writing {}", e, record_path.to_string_lossy()))?;
+
 	PluginManifest::from_file(plugin_cache.plugin_kdl(&plugin_id))
 }
 
-/// download a plugin, verify its size and hash
+/// download a plugin, verify its size and hash, and then verify it with `verify` before it is
+/// written to disk
 fn download_plugin(
 	url: &Url,
 	download_dir: &Path,
 	expected_size: u64,
 	expected_hash_with_digest: &HashWithDigest,
+	verify: impl FnOnce(&[u8]) -> Result<(), Error>,
 ) -> Result<PathBuf, Error> {
 	// retrieve archive
 	let agent = agent();
@@ -247,29 +585,10 @@ fn download_plugin(
 		.map_err(|e| hc_error!("Error [{}] reading download into buffer", e))?;
 	contents.truncate(amount_read);
 
-	// verify size of download
-	if expected_size != amount_read as u64 {
-		return Err(hc_error!(
-			"File size mismatch, Expected {} B, Found {} B",
-			expected_size,
-			amount_read
-		));
-	}
+	check_download(&contents, expected_size, expected_hash_with_digest)?;
+	verify(&contents)?;
 
-	// verify hash
-	let actual_hash = match expected_hash_with_digest.hash_algorithm {
-		HashAlgorithm::Sha256 => sha256::digest(&contents),
-		HashAlgorithm::Blake3 => blake3::hash(&contents).to_string(),
-	};
-	if actual_hash != expected_hash_with_digest.digest {
-		return Err(hc_error!(
-			"Plugin hash mismatch. Expected [{}], Received [{}]",
-			actual_hash,
-			expected_hash_with_digest.digest
-		));
-	}
-
-	let filename = url.path_segments().unwrap().last().unwrap();
+	let filename = archive_file_name(url)?;
 	std::fs::create_dir_all(download_dir).map_err(|e| {
 		hc_error!(
 			"Error [{}] creating download directory {}",
@@ -296,8 +615,111 @@ fn download_plugin(
 	Ok(output_path)
 }
 
+/// verify the size and hash of a downloaded plugin
+fn check_download(
+	contents: &[u8],
+	expected_size: u64,
+	expected_hash_with_digest: &HashWithDigest,
+) -> Result<(), Error> {
+	// verify size of download
+	if expected_size != contents.len() as u64 {
+		return Err(hc_error!(
+			"File size mismatch, Expected {} B, Found {} B",
+			expected_size,
+			contents.len()
+		));
+	}
+
+	// verify hash
+	let actual_hash = match expected_hash_with_digest.hash_algorithm {
+		HashAlgorithm::Sha256 => sha256::digest(contents),
+		HashAlgorithm::Blake3 => blake3::hash(contents).to_string(),
+	};
+	if actual_hash != expected_hash_with_digest.digest {
+		return Err(hc_error!(
+			"Plugin hash mismatch. Expected [{}], Received [{}]",
+			actual_hash,
+			expected_hash_with_digest.digest
+		));
+	}
+
+	Ok(())
+}
+
+/// the name of the file a plugin archive downloaded from `url` is saved as
+fn archive_file_name(url: &Url) -> Result<&str, Error> {
+	url.path_segments()
+		.and_then(|mut segments| segments.next_back())
+		.filter(|name| !name.is_empty())
+		.ok_or_else(|| hc_error!("Plugin download URL {} does not name a file", url))
+}
+
+/// Verify a downloaded plugin's signature against the keys of its publisher, if the policy file
+/// trusts the publisher. If the policy file requires signatures, plugins from publishers it doesn't
+/// trust are refused.
+fn verify_signature(
+	plugin_id: &PluginId,
+	contents: &[u8],
+	signature: Option<&Signature>,
+	trusted_publishers: &PolicyTrustedPublishers,
+) -> Result<(), Error> {
+	let publisher = &plugin_id.publisher().0;
+
+	let Some(keys) = trusted_publishers.keys_for(publisher) else {
+		if trusted_publishers.require_signatures {
+			return Err(hc_error!(
+				"Plugin publisher '{}' is not trusted, and the policy file requires plugins to be signed by a trusted publisher",
+				publisher
+			));
+		}
+		return Ok(());
+	};
+
+	let Some(signature) = signature else {
+		return Err(hc_error!(
+			"Plugin is not signed, but the policy file requires plugins from '{}' to be signed",
+			publisher
+		));
+	};
+
+	match signature.format {
+		SignatureFormat::Minisign => {
+			let signature = minisign_verify::Signature::decode(&signature.signature)
+				.map_err(|e| hc_error!("Error [{}] decoding plugin signature", e))?;
+			// keys were validated when the policy file was parsed
+			let verified = keys
+				.iter()
+				.filter_map(|key| PublicKey::from_base64(key).ok())
+				.any(|key| key.verify(contents, &signature, false).is_ok());
+			if !verified {
+				return Err(hc_error!(
+					"Plugin signature does not match any key trusted for publisher '{}'",
+					publisher
+				));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Plugins with a local manifest have no download manifest, and so no signature to verify. If the
+/// policy file requires signatures, they're refused.
+fn verify_local_plugin(
+	plugin_id: &PluginId,
+	trusted_publishers: &PolicyTrustedPublishers,
+) -> Result<(), Error> {
+	if trusted_publishers.require_signatures {
+		return Err(hc_error!(
+			"Plugin '{}' has a local manifest, so it can't be signed, but the policy file requires plugins to be signed by a trusted publisher",
+			plugin_id.to_policy_file_plugin_identifier()
+		));
+	}
+	Ok(())
+}
+
 /// Extract a bundle located at `bundle_path` into `extract_dir` by applying the specified `ArchiveFormat` extractions
-fn extract_plugin(
+pub(crate) fn extract_archive(
 	bundle_path: &Path,
 	extract_dir: &Path,
 	archive_format: ArchiveFormat,
@@ -350,6 +772,35 @@ fn extract_plugin(
 	Ok(())
 }
 
+/// archive the directories at `paths`, which are relative to `root`, into a new `.tar.gz` file at
+/// `archive_path`
+pub(crate) fn create_archive(
+	archive_path: &Path,
+	root: &Path,
+	paths: &[PathBuf],
+) -> Result<(), Error> {
+	let file = File::create(archive_path).map_err(|e| {
+		hc_error!(
+			"Error [{}] creating file {}",
+			e,
+			archive_path.to_string_lossy()
+		)
+	})?;
+
+	let mut archive = Builder::new(GzEncoder::new(file, Compression::default()));
+	for path in paths {
+		archive
+			.append_dir_all(path, root.join(path))
+			.map_err(|e| hc_error!("Error [{}] archiving {}", e, path.to_string_lossy()))?;
+	}
+	archive
+		.into_inner()
+		.and_then(GzEncoder::finish)
+		.map_err(|e| hc_error!("Error [{}] writing {}", e, archive_path.to_string_lossy()))?;
+
+	Ok(())
+}
+
 /// fetch and deserialize a DownloadManifest from a URL
 fn retrieve_download_manifest(url: &Url) -> Result<DownloadManifest, Error> {
 	let agent = agent();
@@ -377,3 +828,369 @@ fn retrieve_download_manifest(url: &Url) -> Result<DownloadManifest, Error> {
 	let contents = String::from_utf8_lossy(&contents);
 	DownloadManifest::from_str(&contents)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::plugin::{
+		arch::KnownArch,
+		download_manifest::{Compress, Size},
+		Arch, PluginName, PluginPublisher, PluginVersion,
+	};
+	use std::{
+		collections::HashMap,
+		io::{BufRead, BufReader},
+		net::TcpListener,
+		thread,
+	};
+
+	const CONTENTS: &[u8] = b"plugin archive contents";
+
+	const KEY: &str = "RWSkkUqdCclvNV2YEvZbS26nMCSrJwUEwdaYMbepXyfYTRwdADjBZdAT";
+
+	const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUSkkUqdCclvNTBXyimkHQQTlxM3VAgNnIzAL/xazR4isUyuJ5w9cyrs0dSJy91bL6hgfA3j0Wju8TUMoU6dMdT5JzToGoF+wgY=
+trusted comment: timestamp:1729000000	file:plugin.tar.xz
+pFVoJ7h3Brn8sxhsy1wVHJbJVVhcQGVvbXIHfuqdUVme+wvVP0S7RMmeUJVA2FNrlav+ZxiMNYpG0BAr7VviCA==
+";
+
+	// signed by a different key
+	const OTHER_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUS2SVSigXw3ARnv61scM75NGHYiTL2piZWzPvr/z42zrO6NAHdqDK+EOEJkctVPI7ZRDM+6m6wSJpKSXhg+S7F6MIVphtCDDgU=
+trusted comment: timestamp:1729000000	file:plugin.tar.xz
+Jn+VRtcGSzCz9ndVmMZy3K7g3Vf+rb9UDmHQ09EKArvYRh2Crg9qOy6oq3nK9gzZHN64qNDNAsqwn9GOHDQzDQ==
+";
+
+	fn plugin_id(publisher: &str) -> PluginId {
+		PluginId::new(
+			PluginPublisher::new(publisher.to_owned()),
+			PluginName::new("git".to_owned()),
+			PluginVersion::new("0.1.0".to_owned()),
+		)
+	}
+
+	fn signature(signature: &str) -> Signature {
+		Signature {
+			format: SignatureFormat::Minisign,
+			signature: signature.to_owned(),
+		}
+	}
+
+	fn trusting_mitre(require_signatures: bool) -> PolicyTrustedPublishers {
+		PolicyTrustedPublishers {
+			require_signatures,
+			publishers: HashMap::from([("mitre".to_owned(), vec![KEY.to_owned()])]),
+		}
+	}
+
+	#[test]
+	fn test_verify_signature_trusted_publisher() {
+		let trusted = trusting_mitre(false);
+		let mitre = plugin_id("mitre");
+
+		assert!(verify_signature(&mitre, CONTENTS, Some(&signature(SIGNATURE)), &trusted).is_ok());
+		assert!(
+			verify_signature(&mitre, b"tampered", Some(&signature(SIGNATURE)), &trusted).is_err()
+		);
+		assert!(verify_signature(
+			&mitre,
+			CONTENTS,
+			Some(&signature(OTHER_SIGNATURE)),
+			&trusted
+		)
+		.is_err());
+		assert!(verify_signature(&mitre, CONTENTS, None, &trusted).is_err());
+	}
+
+	#[test]
+	fn test_verify_signature_untrusted_publisher() {
+		let other = plugin_id("other");
+
+		assert!(verify_signature(&other, CONTENTS, None, &trusting_mitre(false)).is_ok());
+		assert!(verify_signature(&other, CONTENTS, None, &trusting_mitre(true)).is_err());
+		assert!(verify_signature(
+			&other,
+			CONTENTS,
+			Some(&signature(SIGNATURE)),
+			&trusting_mitre(true)
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_verify_local_plugin() {
+		let mitre = plugin_id("mitre");
+
+		assert!(verify_local_plugin(&mitre, &trusting_mitre(false)).is_ok());
+		assert!(verify_local_plugin(&mitre, &trusting_mitre(true)).is_err());
+	}
+
+	/// Serve `contents` over HTTP to the first request made to the returned URL.
+	fn serve_once(contents: &'static [u8]) -> Url {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = Url::parse(&format!(
+			"http://{}/plugin.tar.xz",
+			listener.local_addr().unwrap()
+		))
+		.unwrap();
+		thread::spawn(move || {
+			let (mut stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut line = String::new();
+			while reader.read_line(&mut line).unwrap() > 2 {
+				line.clear();
+			}
+			write!(
+				stream,
+				"HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+				contents.len()
+			)
+			.unwrap();
+			stream.write_all(contents).unwrap();
+		});
+		url
+	}
+
+	#[test]
+	fn test_download_and_unpack_plugin_rejects_mismatched_signature() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let plugin_cache = HcPluginCache::new(cache_dir.path());
+		let mitre = plugin_id("mitre");
+		let entry = DownloadManifestEntry {
+			version: mitre.version().clone(),
+			arch: Arch::Known(KnownArch::X86_64UnknownLinuxGnu),
+			url: serve_once(CONTENTS),
+			hash: HashWithDigest::new(HashAlgorithm::Sha256, sha256::digest(CONTENTS)),
+			compress: Compress::new(ArchiveFormat::TarXz),
+			size: Size::new(CONTENTS.len() as u64),
+			signature: Some(signature(OTHER_SIGNATURE)),
+			dependencies: None,
+		};
+
+		let err = download_and_unpack_plugin(
+			&entry,
+			mitre.clone(),
+			&plugin_cache,
+			&trusting_mitre(false),
+		)
+		.unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("does not match any key trusted for publisher 'mitre'"));
+		// nothing is left in the cache
+		assert!(!plugin_cache.plugin_download_dir(&mitre).exists());
+	}
+
+	/// Put `contents` in `plugin_cache` as the archive of mitre/git 0.1.0, recorded as downloaded
+	/// with `signature`, as though it had been retrieved while online
+	fn cache_download(
+		plugin_cache: &HcPluginCache,
+		contents: &[u8],
+		signature: Option<Signature>,
+	) -> PluginId {
+		let mitre = plugin_id("mitre");
+		let download_dir = plugin_cache.plugin_download_dir(&mitre);
+		std::fs::create_dir_all(&download_dir).unwrap();
+		std::fs::write(
+			plugin_cache.plugin_kdl(&mitre),
+			format!(
+				r#"publisher "mitre"
+name "git"
+version "0.1.0"
+license "Apache-2.0"
+entrypoint {{
+  on arch="{}" "./hc-mitre-git"
+}}
+"#,
+				get_current_arch()
+			),
+		)
+		.unwrap();
+		std::fs::write(download_dir.join("hc-mitre-git"), "binary").unwrap();
+		std::fs::write(download_dir.join("plugin.tar.xz"), contents).unwrap();
+
+		let entry = DownloadManifestEntry {
+			version: mitre.version().clone(),
+			arch: get_current_arch(),
+			url: Url::parse("https://example.com/plugin.tar.xz").unwrap(),
+			hash: HashWithDigest::new(HashAlgorithm::Sha256, sha256::digest(CONTENTS)),
+			compress: Compress::new(ArchiveFormat::TarXz),
+			size: Size::new(CONTENTS.len() as u64),
+			signature,
+			dependencies: None,
+		};
+		std::fs::write(
+			plugin_cache.plugin_download_record(&mitre, &get_current_arch()),
+			download_record(
+				&entry,
+				cached_entrypoint_hash(plugin_cache, &mitre)
+					.unwrap()
+					.as_ref(),
+			),
+		)
+		.unwrap();
+		mitre
+	}
+
+	fn offline_retriever<'a>(
+		plugin_cache: &'a HcPluginCache,
+		trusted_publishers: &'a PolicyTrustedPublishers,
+		lockfile: &'a mut PluginLockfile,
+	) -> Retriever<'a> {
+		Retriever {
+			plugin_cache,
+			trusted_publishers,
+			lockfile,
+			offline: true,
+			download_manifests: HashMap::new(),
+			retrieved: HashSet::new(),
+		}
+	}
+
+	#[test]
+	fn test_offline_retriever_rechecks_cached_download() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let plugin_cache = HcPluginCache::new(cache_dir.path());
+		let manifest = ManifestLocation::Url(Url::parse("https://example.com/git.kdl").unwrap());
+		let trusted = trusting_mitre(true);
+		let mut lockfile = PluginLockfile::default();
+
+		let mitre = cache_download(&plugin_cache, CONTENTS, Some(signature(SIGNATURE)));
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		assert_eq!(
+			retriever
+				.versions(&PolicyPluginName::new("mitre/git").unwrap(), &manifest)
+				.unwrap(),
+			vec![Version::parse("0.1.0").unwrap()]
+		);
+		retriever.retrieve(&mitre, &manifest).unwrap();
+
+		// a cached download which no longer matches its record is refused
+		cache_download(&plugin_cache, b"tampered", Some(signature(SIGNATURE)));
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		assert!(retriever.retrieve(&mitre, &manifest).is_err());
+
+		// as is one whose signature the policy file doesn't accept
+		cache_download(&plugin_cache, CONTENTS, Some(signature(OTHER_SIGNATURE)));
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		assert!(retriever.retrieve(&mitre, &manifest).is_err());
+
+		// or one whose extracted entrypoint has changed
+		cache_download(&plugin_cache, CONTENTS, Some(signature(SIGNATURE)));
+		std::fs::write(
+			plugin_cache
+				.plugin_download_dir(&mitre)
+				.join("hc-mitre-git"),
+			"tampered",
+		)
+		.unwrap();
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		let err = retriever.retrieve(&mitre, &manifest).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("has changed since it was extracted"));
+
+		// or one retrieved without a record
+		std::fs::remove_file(plugin_cache.plugin_download_record(&mitre, &get_current_arch()))
+			.unwrap();
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		let err = retriever.retrieve(&mitre, &manifest).unwrap_err();
+		assert!(err.to_string().contains("can't be checked while offline"));
+	}
+
+	#[test]
+	fn test_offline_retriever_checks_lockfile() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let plugin_cache = HcPluginCache::new(cache_dir.path());
+		let manifest = ManifestLocation::Url(Url::parse("https://example.com/git.kdl").unwrap());
+		let trusted = trusting_mitre(false);
+		let mitre = cache_download(&plugin_cache, CONTENTS, None);
+
+		let mut lockfile = PluginLockfile::default();
+		lockfile
+			.check(LockedPlugin {
+				plugin_id: mitre.clone(),
+				arch: get_current_arch(),
+				manifest: manifest.clone(),
+				download: Some(LockedDownload {
+					url: Url::parse("https://example.com/plugin.tar.xz").unwrap(),
+					hash: HashWithDigest::new(HashAlgorithm::Sha256, "abcd".to_owned()),
+				}),
+				entrypoint: None,
+			})
+			.unwrap();
+
+		let mut retriever = offline_retriever(&plugin_cache, &trusted, &mut lockfile);
+		let err = retriever.retrieve(&mitre, &manifest).unwrap_err();
+		assert!(err.to_string().contains("its hash changed"));
+	}
+
+	#[test]
+	fn test_archive_file_name() {
+		let url = Url::parse("https://example.com/releases/plugin.tar.xz").unwrap();
+		assert_eq!(archive_file_name(&url).unwrap(), "plugin.tar.xz");
+		for url in ["https://example.com/", "data:text/plain,plugin"] {
+			assert!(archive_file_name(&Url::parse(url).unwrap()).is_err());
+		}
+	}
+
+	#[test]
+	fn test_retrieve_plugins_only_writes_lockfile_when_asked() {
+		let dir = tempfile::tempdir().unwrap();
+		let plugin_manifest_path = dir.path().join("plugin.kdl");
+		std::fs::write(
+			&plugin_manifest_path,
+			format!(
+				r#"publisher "mitre"
+name "example"
+version "0.1.0"
+license "Apache-2.0"
+entrypoint {{
+  on arch="{}" "sh"
+}}
+"#,
+				get_current_arch()
+			),
+		)
+		.unwrap();
+		let policy_path = dir.path().join("Hipcheck.kdl");
+		let policy = PolicyFile::from_str(&format!(
+			r#"plugins {{
+  plugin "mitre/example" version="0.1.0" manifest="{}"
+}}
+analyze {{
+  investigate policy="(gt 0.5 $)"
+  category "practices" {{
+    analysis "mitre/example" policy="(eq 0 $)"
+  }}
+}}
+"#,
+			plugin_manifest_path.display()
+		))
+		.unwrap();
+		let plugin_cache = HcPluginCache::new(&dir.path().join("cache"));
+		let lockfile_path = PluginLockfile::path_for_policy(&policy_path);
+
+		// as when checking a target or exporting a cache bundle
+		retrieve_plugins(
+			&policy,
+			Some(&policy_path),
+			&plugin_cache,
+			LockfileUpdate::None,
+		)
+		.unwrap();
+		assert!(!lockfile_path.exists());
+
+		retrieve_plugins(
+			&policy,
+			Some(&policy_path),
+			&plugin_cache,
+			LockfileUpdate::AddNew,
+		)
+		.unwrap();
+		assert_ne!(
+			PluginLockfile::load(&lockfile_path).unwrap(),
+			PluginLockfile::default()
+		);
+	}
+}
@@ -5,22 +5,28 @@ use crate::{
 	hipcheck::{
 		plugin_service_client::PluginServiceClient, ConfigurationStatus, Empty,
 		ExplainDefaultQueryRequest, GetDefaultPolicyExpressionRequest, GetQuerySchemasRequest,
-		GetQuerySchemasResponse as PluginSchema, InitiateQueryProtocolRequest,
-		Query as PluginQuery, QueryState, SetConfigurationRequest,
+		GetQuerySchemasResponse as PluginSchema, HealthCheckRequest, HealthStatus,
+		InitiateQueryProtocolRequest, Query as PluginQuery, QueryState, SetConfigurationRequest,
 		SetConfigurationResponse as PluginConfigResult,
 	},
+	plugin::PluginAddr,
 	Error, Result,
 };
 use futures::{Stream, StreamExt};
+use hipcheck_sdk::chunk::{chunk, combine_chunk};
 use serde_json::Value;
 use std::{
 	collections::{HashMap, VecDeque},
 	convert::TryFrom,
+	error::Error as StdError,
+	fmt,
 	future::poll_fn,
 	ops::Not as _,
 	pin::Pin,
 	process::Child,
 	result::Result as StdResult,
+	sync::Arc,
+	time::Duration,
 };
 use tokio::sync::{mpsc, Mutex};
 use tokio_stream::wrappers::ReceiverStream;
@@ -31,7 +37,13 @@ pub type HcPluginClient = PluginServiceClient<Channel>;
 #[derive(Clone, Debug)]
 pub struct Plugin {
 	pub name: String,
+	/// The version of the plugin chosen when resolving the policy file's plugins.
+	pub version: String,
 	pub entrypoint: String,
+	/// How long to wait on the plugin for each step of a query, if limited.
+	pub timeout: Option<Duration>,
+	/// The plugins this one depends on, as `publisher/name`, from its manifest.
+	pub dependencies: Vec<String>,
 }
 
 // Hipcheck-facing version of struct from crate::hipcheck
@@ -149,14 +161,17 @@ pub struct PluginContext {
 	/// The plugin being wrapped.
 	pub plugin: Plugin,
 
-	/// The port that plugin is listening on.
-	pub port: u16,
+	/// The port or socket that plugin is listening on.
+	pub addr: PluginAddr,
 
 	/// A gRPC client for interacting with the plugin.
 	pub grpc: HcPluginClient,
 
 	/// The child process in which the plugin is running.
 	pub proc: Child,
+
+	/// The most recent output of the plugin process on stderr.
+	pub stderr: PluginStderr,
 }
 
 // Redefinition of `grpc` field's functions with more useful types, additional
@@ -269,6 +284,23 @@ impl PluginContext {
 		Ok(Box::new(stream))
 	}
 
+	/// Ask the plugin what it offers, without starting the query protocol. Its default policy
+	/// expression and the explanation of its default query depend on its configuration, so
+	/// they're only available if it accepts `config`.
+	pub async fn describe(mut self, config: Value) -> Result<PluginInfo> {
+		let schemas = self.get_query_schemas().await?;
+
+		let defaults = match self.set_configuration(&config).await?.as_result() {
+			Ok(()) => Ok(PluginDefaults {
+				opt_default_policy_expr: self.get_default_policy_expression().await?,
+				opt_explain_default_query: self.explain_default_query().await?,
+			}),
+			Err(e) => Err(e),
+		};
+
+		Ok(PluginInfo { schemas, defaults })
+	}
+
 	/// Consume the builder and run the query protocol.
 	///
 	/// Consume self and produce a `PluginTransport` which will handle
@@ -313,6 +345,20 @@ impl Drop for PluginContext {
 	}
 }
 
+/// What a plugin offers, as it describes itself.
+#[derive(Debug)]
+pub struct PluginInfo {
+	pub schemas: Vec<Schema>,
+	/// The plugin's defaults, or why it couldn't be configured to get them.
+	pub defaults: Result<PluginDefaults>,
+}
+
+#[derive(Debug)]
+pub struct PluginDefaults {
+	pub opt_default_policy_expr: Option<String>,
+	pub opt_explain_default_query: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Query {
 	pub id: usize,
@@ -321,8 +367,10 @@ pub struct Query {
 	pub publisher: String,
 	pub plugin: String,
 	pub query: String,
-	pub key: Value,
-	pub output: Value,
+	// More than one key makes this a batch query. `key` and `output` always
+	// have the same length.
+	pub key: Vec<Value>,
+	pub output: Vec<Value>,
 	pub concerns: Vec<String>,
 }
 
@@ -343,8 +391,24 @@ impl TryFrom<PluginQuery> for Query {
 			Submit => true,
 		};
 
-		let key: Value = serde_json::from_str(value.key.as_str())?;
-		let output: Value = serde_json::from_str(value.output.as_str())?;
+		let key = value
+			.key
+			.iter()
+			.map(|k| serde_json::from_str(k))
+			.collect::<StdResult<Vec<Value>, _>>()?;
+		let output = value
+			.output
+			.iter()
+			.map(|o| serde_json::from_str(o))
+			.collect::<StdResult<Vec<Value>, _>>()?;
+
+		if key.len() != output.len() {
+			return Err(hc_error!(
+				"query from plugin has {} keys but {} outputs",
+				key.len(),
+				output.len()
+			));
+		}
 
 		Ok(Query {
 			id: value.id as usize,
@@ -368,8 +432,16 @@ impl TryFrom<Query> for PluginQuery {
 			false => QueryState::ReplyComplete,
 		};
 
-		let key = serde_json::to_string(&value.key)?;
-		let output = serde_json::to_string(&value.output)?;
+		let key = value
+			.key
+			.iter()
+			.map(serde_json::to_string)
+			.collect::<StdResult<Vec<String>, _>>()?;
+		let output = value
+			.output
+			.iter()
+			.map(serde_json::to_string)
+			.collect::<StdResult<Vec<String>, _>>()?;
 
 		Ok(PluginQuery {
 			id: value.id as i32,
@@ -380,114 +452,11 @@ impl TryFrom<Query> for PluginQuery {
 			key,
 			output,
 			concern: value.concerns,
+			split: false,
 		})
 	}
 }
 
-const GRPC_MAX_SIZE: usize = 1024 * 1024 * 4; // 4MB
-const GRPC_EFFECTIVE_MAX_SIZE: usize = GRPC_MAX_SIZE - 1024; // Minus one KB
-
-/// Try to drain `max` bytes from `buf`, or the full string, whichever is shortest.
-/// If `max` bytes is somewhere within `buf` but lands within a char boundary,
-/// walk backwards to the start of the previous char. Returns the substring
-/// drained from `buf`.
-fn drain_at_most_n_bytes(buf: &mut String, max: usize) -> Result<String> {
-	let mut to_drain = std::cmp::min(buf.bytes().len(), max);
-	while to_drain > 0 && buf.is_char_boundary(to_drain).not() {
-		to_drain -= 1;
-	}
-	if to_drain == 0 {
-		return Err(hc_error!("Could not drain any whole char from string"));
-	}
-	Ok(buf.drain(0..to_drain).collect::<String>())
-}
-
-fn estimate_size(msg: &PluginQuery) -> usize {
-	msg.key.bytes().len()
-		+ msg.output.bytes().len()
-		+ msg.concern.iter().map(|x| x.bytes().len()).sum::<usize>()
-}
-
-fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<PluginQuery>> {
-	// Chunking only does something on response objects, mostly because
-	// we don't have a state to represent "SubmitInProgress"
-	if msg.state == QueryState::Submit as i32 {
-		return Ok(vec![msg]);
-	}
-
-	let mut out: Vec<PluginQuery> = vec![];
-	let mut base: PluginQuery = msg;
-
-	// Track whether we did anything on each iteration to avoid infinite loop
-	let mut made_progress = true;
-	while estimate_size(&base) > max_est_size {
-		log::trace!("Estimated size is too large, chunking");
-		if !made_progress {
-			return Err(hc_error!("Message could not be chunked"));
-		}
-
-		made_progress = false;
-		// For this loop, we want to take at most MAX_SIZE bytes because that's
-		// all that can fit in a PluginQuery
-		let mut remaining = max_est_size;
-		let mut query = PluginQuery {
-			id: base.id,
-			state: QueryState::ReplyInProgress as i32,
-			publisher_name: base.publisher_name.clone(),
-			plugin_name: base.plugin_name.clone(),
-			query_name: base.query_name.clone(),
-			key: String::new(),
-			output: String::new(),
-			concern: vec![],
-		};
-
-		if remaining > 0 && base.key.bytes().len() > 0 {
-			// steal from key
-			query.key = drain_at_most_n_bytes(&mut base.key, remaining)?;
-			remaining -= query.key.bytes().len();
-			made_progress = true;
-		}
-
-		if remaining > 0 && base.output.bytes().len() > 0 {
-			// steal from output
-			query.output = drain_at_most_n_bytes(&mut base.output, remaining)?;
-			remaining -= query.output.bytes().len();
-			made_progress = true;
-		}
-
-		let mut l = base.concern.len();
-		// While we still want to steal more bytes and we have more elements of
-		// `concern` to possibly steal
-		while remaining > 0 && l > 0 {
-			let i = l - 1;
-
-			let c_bytes = base.concern.get(i).unwrap().bytes().len();
-
-			if c_bytes > max_est_size {
-				return Err(hc_error!("Query cannot be chunked, there is a concern that is larger than max chunk size"));
-			} else if c_bytes <= remaining {
-				// steal this concern
-				let concern = base.concern.swap_remove(i);
-				query.concern.push(concern);
-				remaining -= c_bytes;
-				made_progress = true;
-			}
-			// since we use `swap_remove`, whether or not we stole a concern we know the element
-			// currently at `i` is too big for `remainder` (since if we removed, the element at `i`
-			// now is one we already passed on)
-			l -= 1;
-		}
-
-		out.push(query);
-	}
-	out.push(base);
-	Ok(out)
-}
-
-fn chunk(msg: PluginQuery) -> Result<Vec<PluginQuery>> {
-	chunk_with_size(msg, GRPC_EFFECTIVE_MAX_SIZE)
-}
-
 pub struct MultiplexedQueryReceiver {
 	rx: QueryStream,
 	backlog: HashMap<i32, VecDeque<PluginQuery>>,
@@ -577,6 +546,37 @@ impl PluginTransport {
 		&self.ctx.plugin.name
 	}
 
+	pub fn plugin(&self) -> &Plugin {
+		&self.ctx.plugin
+	}
+
+	pub fn stderr(&self) -> &PluginStderr {
+		&self.ctx.stderr
+	}
+
+	/// Check that the plugin process is still able to serve queries.
+	///
+	/// This uses its own gRPC request, so it works even while the query
+	/// protocol is stuck waiting on the plugin.
+	pub async fn health_check(&self) -> Result<()> {
+		let mut grpc = self.ctx.grpc.clone();
+		let res = grpc
+			.health_check(HealthCheckRequest {
+				empty: Some(Empty {}),
+			})
+			.await?
+			.into_inner();
+
+		match res.status.try_into()? {
+			HealthStatus::Serving => Ok(()),
+			status => Err(hc_error!(
+				"plugin reported health status {}: {}",
+				status.as_str_name(),
+				res.message
+			)),
+		}
+	}
+
 	pub async fn query(&self, query: Query) -> Result<Option<Query>> {
 		use QueryState::*;
 
@@ -638,9 +638,7 @@ impl PluginTransport {
 						if state == ReplyComplete {
 							raw.state = ReplyComplete.into();
 						}
-						raw.key.push_str(next.key.as_str());
-						raw.output.push_str(next.output.as_str());
-						raw.concern.extend_from_slice(next.concern.as_slice());
+						combine_chunk(&mut raw, next);
 					}
 				};
 			}
@@ -657,6 +655,53 @@ impl PluginTransport {
 	}
 }
 
+/// The most recent lines a plugin process has written to stderr.
+///
+/// The output is still forwarded to Hipcheck's own stderr as it arrives; this
+/// keeps the tail of it so a failing plugin can be reported with its output.
+#[derive(Clone, Debug, Default)]
+pub struct PluginStderr(Arc<std::sync::Mutex<VecDeque<String>>>);
+
+impl PluginStderr {
+	/// The number of lines kept.
+	const MAX_LINES: usize = 50;
+
+	pub fn push(&self, line: String) {
+		let mut lines = self.0.lock().unwrap();
+		if lines.len() == Self::MAX_LINES {
+			lines.pop_front();
+		}
+		lines.push_back(line);
+	}
+
+	/// Get the kept lines, oldest first, or `None` if there are none.
+	pub fn tail(&self) -> Option<String> {
+		let lines = self.0.lock().unwrap();
+		if lines.is_empty() {
+			return None;
+		}
+		Some(Vec::from_iter(lines.iter().map(String::as_str)).join("\n"))
+	}
+}
+
+/// An error from a plugin's process or query protocol, as opposed to an error
+/// in a query the plugin made to another plugin.
+#[derive(Debug)]
+pub struct PluginFailure {
+	pub plugin: String,
+	pub message: String,
+	/// What the plugin last wrote to stderr before the failure.
+	pub stderr: Option<String>,
+}
+
+impl fmt::Display for PluginFailure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "plugin '{}' failed: {}", self.plugin, self.message)
+	}
+}
+
+impl StdError for PluginFailure {}
+
 pub struct PluginWithConfig(pub Plugin, pub Value);
 impl From<PluginWithConfig> for (Plugin, Value) {
 	fn from(value: PluginWithConfig) -> Self {
@@ -677,7 +722,8 @@ pub struct AwaitingResult {
 	pub publisher: String,
 	pub plugin: String,
 	pub query: String,
-	pub key: Value,
+	/// The keys the plugin is waiting on, more than one for a batch query.
+	pub keys: Vec<Value>,
 }
 
 impl From<Query> for AwaitingResult {
@@ -687,7 +733,7 @@ impl From<Query> for AwaitingResult {
 			publisher: value.publisher,
 			plugin: value.plugin,
 			query: value.query,
-			key: value.key,
+			keys: value.key,
 		}
 	}
 }
@@ -705,29 +751,101 @@ pub enum PluginResponse {
 	Completed(QueryResult),
 }
 
-impl From<Option<Query>> for PluginResponse {
-	fn from(value: Option<Query>) -> Self {
+impl TryFrom<Option<Query>> for PluginResponse {
+	type Error = Error;
+
+	fn try_from(value: Option<Query>) -> Result<Self> {
 		match value {
-			Some(q) => q.into(),
-			None => PluginResponse::RemoteClosed,
+			Some(q) => q.try_into(),
+			None => Ok(PluginResponse::RemoteClosed),
 		}
 	}
 }
 
-impl From<Query> for PluginResponse {
-	fn from(value: Query) -> Self {
-		if !value.request {
-			let result = QueryResult {
-				value: value.output,
-				concerns: value.concerns,
-			};
-			PluginResponse::Completed(result)
-		} else {
-			PluginResponse::AwaitingResult(value.into())
+impl TryFrom<Query> for PluginResponse {
+	type Error = Error;
+
+	fn try_from(value: Query) -> Result<Self> {
+		if value.request {
+			return Ok(PluginResponse::AwaitingResult(value.into()));
 		}
+		// Hipcheck only sends single-key queries to plugins, so expect a single output
+		let [output] = <[Value; 1]>::try_from(value.output).map_err(|output| {
+			hc_error!(
+				"expected one output in reply from plugin, got {}",
+				output.len()
+			)
+		})?;
+		Ok(PluginResponse::Completed(QueryResult {
+			value: output,
+			concerns: value.concerns,
+		}))
 	}
 }
 
 pub fn get_plugin_key(publisher: &str, plugin: &str) -> String {
 	format!("{publisher}/{plugin}")
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use hipcheck_sdk::chunk::{chunk_with_size, estimate_size};
+
+	fn reply(key: Vec<&str>, output: Vec<&str>, concern: Vec<&str>) -> PluginQuery {
+		let strings = |v: Vec<&str>| v.into_iter().map(ToOwned::to_owned).collect();
+		PluginQuery {
+			id: 1,
+			state: QueryState::ReplyComplete as i32,
+			publisher_name: "mitre".to_owned(),
+			plugin_name: "git".to_owned(),
+			query_name: "".to_owned(),
+			key: strings(key),
+			output: strings(output),
+			concern: strings(concern),
+			split: false,
+		}
+	}
+
+	#[test]
+	fn chunked_batch_reply_reassembles() {
+		let orig = reply(
+			vec!["\"a\"", "\"aこれは実験です\""],
+			vec!["[1,2,3,4,5,6,7,8,9]", "null"],
+			vec!["< 10", "0123456789"],
+		);
+		let chunks = chunk_with_size(orig.clone(), 10).unwrap();
+		assert!(chunks.len() > 1);
+		assert!(chunks.iter().all(|c| estimate_size(c) <= 10));
+
+		let mut chunks = chunks.into_iter();
+		let mut acc = chunks.next().unwrap();
+		for next in chunks {
+			combine_chunk(&mut acc, next);
+		}
+		assert_eq!(acc.key, orig.key);
+		assert_eq!(acc.output, orig.output);
+		let (mut concerns, mut expected) = (acc.concern, orig.concern);
+		concerns.sort();
+		expected.sort();
+		assert_eq!(concerns, expected);
+	}
+
+	#[test]
+	fn stderr_keeps_most_recent_lines() {
+		let stderr = PluginStderr::default();
+		assert_eq!(stderr.tail(), None);
+
+		for i in 0..(PluginStderr::MAX_LINES + 2) {
+			stderr.push(format!("line {i}"));
+		}
+		let tail = stderr.tail().unwrap();
+		let lines = tail.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), PluginStderr::MAX_LINES);
+		assert_eq!(lines.first(), Some(&"line 2"));
+		assert_eq!(
+			lines.last().map(|l| l.to_string()),
+			Some(format!("line {}", PluginStderr::MAX_LINES + 1))
+		);
+	}
+}
@@ -13,11 +13,11 @@ use crate::{
 	},
 	error::Result,
 	hc_error,
-	plugin::PluginVersion,
+	plugin::PluginVersionReq,
 };
 
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 use url::Url;
 
 const PLUGIN_VERSION: &str = "0.1.0";
@@ -50,6 +50,8 @@ pub fn config_to_policy(config: Config) -> Result<PolicyFile> {
 	Ok(PolicyFile {
 		plugins,
 		patch,
+		forges: PolicyForges::default(),
+		trusted_publishers: PolicyTrustedPublishers::default(),
 		analyze,
 	})
 }
@@ -141,7 +143,7 @@ fn parse_activity(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/activity").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-activity.kdl",
@@ -178,7 +180,7 @@ fn parse_binary(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/binary").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-binary.kdl",
@@ -210,7 +212,7 @@ fn parse_fuzz(plugins: &mut PolicyPluginList, practices: &mut PolicyCategory, fu
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/fuzz").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-fuzz.kdl",
@@ -247,7 +249,7 @@ fn parse_identity(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/identity").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-identity.kdl",
@@ -284,7 +286,7 @@ fn parse_review(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/review").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-review.kdl",
@@ -325,7 +327,7 @@ fn parse_typo(plugins: &mut PolicyPluginList, attacks: &mut PolicyCategory, typo
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/typo").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-typo.kdl",
@@ -370,7 +372,7 @@ fn parse_affiliation(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/affiliation").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-affiliation.kdl",
@@ -407,7 +409,7 @@ fn parse_churn(plugins: &mut PolicyPluginList, commit: &mut PolicyCategory, chur
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/churn").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-churn.kdl",
@@ -448,7 +450,7 @@ fn parse_entropy(
 		// Add the plugin
 		let plugin = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/entropy").unwrap(),
-			PluginVersion::new(PLUGIN_VERSION.to_string()),
+			PluginVersionReq::from_str(PLUGIN_VERSION).unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-entropy.kdl",
@@ -9,18 +9,23 @@ mod tests;
 use crate::{
 	error::Result,
 	hc_error,
-	policy::policy_file::{PolicyAnalyze, PolicyPatchList, PolicyPluginList, PolicyPluginName},
+	policy::policy_file::{
+		PolicyAnalyze, PolicyForges, PolicyPatchList, PolicyPluginList, PolicyPluginName,
+		PolicyTrustedPublishers,
+	},
 	util::fs as file,
-	util::kdl::extract_data,
+	util::kdl::{extract_data, ParseKdlNode},
 };
-use kdl::KdlDocument;
+use kdl::{KdlDocument, KdlEntry, KdlNode};
 use serde_json::Value;
-use std::{collections::HashMap, path::Path, str::FromStr};
+use std::{collections::HashMap, ops::Range, path::Path, str::FromStr};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PolicyFile {
 	pub plugins: PolicyPluginList,
 	pub patch: PolicyPatchList,
+	pub forges: PolicyForges,
+	pub trusted_publishers: PolicyTrustedPublishers,
 	pub analyze: PolicyAnalyze,
 }
 
@@ -36,12 +41,26 @@ impl FromStr for PolicyFile {
 			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'plugins'"))?;
 		// `patch` is an optional section
 		let patch: PolicyPatchList = extract_data(nodes).unwrap_or_default();
+		// `forges` is an optional section
+		let forges: PolicyForges = extract_data(nodes).unwrap_or_default();
+		// `trusted-publishers` is an optional section, but since it makes
+		// Hipcheck refuse plugins, a malformed one must not be ignored
+		let trusted_publishers = match nodes
+			.iter()
+			.find(|node| node.name().value() == PolicyTrustedPublishers::kdl_key())
+		{
+			Some(node) => PolicyTrustedPublishers::parse_node(node)
+				.ok_or_else(|| hc_error!("Could not parse 'trusted-publishers'"))?,
+			None => PolicyTrustedPublishers::default(),
+		};
 		let analyze: PolicyAnalyze =
 			extract_data(nodes).ok_or_else(|| hc_error!("Could not parse 'analyze'"))?;
 
 		Ok(Self {
 			plugins,
 			patch,
+			forges,
+			trusted_publishers,
 			analyze,
 		})
 	}
@@ -98,3 +117,75 @@ impl PolicyFile {
 		}
 	}
 }
+
+/// Where the policy expressions in a policy file's source text are, so errors
+/// in them can be pointed at.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyExprSpans {
+	/// The span of the `investigate` policy expression.
+	pub investigate: Option<Range<usize>>,
+	/// The span of each analysis' policy expression, by "publisher/name".
+	pub analyses: HashMap<String, Range<usize>>,
+}
+
+impl PolicyExprSpans {
+	/// Find the policy expressions in the source text of a policy file.
+	pub fn find(source: &str) -> Result<PolicyExprSpans> {
+		let document = KdlDocument::from_str(source)
+			.map_err(|e| hc_error!("Error parsing policy file: {}", e))?;
+		let mut spans = PolicyExprSpans::default();
+
+		if let Some(analyze) = document.get("analyze").and_then(KdlNode::children) {
+			spans.investigate = analyze
+				.get("investigate")
+				.and_then(|node| node.get("policy"))
+				.map(|entry| expr_span(source, entry));
+			find_analysis_spans(source, analyze, &mut spans.analyses);
+		}
+
+		Ok(spans)
+	}
+}
+
+/// Recursively find the `policy` expressions of `analysis` nodes, descending
+/// into categories.
+fn find_analysis_spans(
+	source: &str,
+	document: &KdlDocument,
+	analyses: &mut HashMap<String, Range<usize>>,
+) {
+	for node in document.nodes() {
+		match node.name().value() {
+			"analysis" => {
+				let name = node.get(0).and_then(|name| name.value().as_string());
+				if let (Some(name), Some(policy)) = (name, node.get("policy")) {
+					analyses.insert(name.to_owned(), expr_span(source, policy));
+				}
+			}
+			"category" => {
+				if let Some(children) = node.children() {
+					find_analysis_spans(source, children, analyses);
+				}
+			}
+			_ => (),
+		}
+	}
+}
+
+/// Narrow the span of a `policy="..."` entry to the expression inside the quotes.
+fn expr_span(source: &str, entry: &KdlEntry) -> Range<usize> {
+	let start = entry.span().offset();
+	let entry_span = start..start + entry.span().len();
+
+	let narrowed = entry.value_repr().and_then(|repr| {
+		let offset = source.get(entry_span.clone())?.find(repr)?;
+		// Skip the raw string prefix and the opening quote, and the closing
+		// quote and raw string suffix.
+		let open = repr.find('"')? + 1;
+		let close = repr.len() - repr.rfind('"')?;
+		let repr_start = start + offset;
+		Some(repr_start + open..repr_start + repr.len() - close)
+	});
+
+	narrowed.unwrap_or(entry_span)
+}
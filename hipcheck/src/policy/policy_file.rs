@@ -5,14 +5,14 @@
 use crate::{
 	error::Result,
 	hc_error,
-	plugin::{PluginId, PluginName, PluginPublisher, PluginVersion},
+	plugin::{check_path_component, PluginName, PluginPublisher, PluginVersionReq},
 	string_newtype_parse_kdl_node,
 	util::kdl::{extract_data, ParseKdlNode, ToKdlNode},
 };
 
-use kdl::KdlNode;
+use kdl::{KdlDocument, KdlNode};
 use serde_json::Value;
-use std::{collections::HashMap, fmt, fmt::Display, path::PathBuf};
+use std::{collections::HashMap, fmt, fmt::Display, path::PathBuf, str::FromStr, time::Duration};
 use url::Url;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -35,31 +35,27 @@ impl Display for ManifestLocation {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PolicyPlugin {
 	pub name: PolicyPluginName,
-	pub version: PluginVersion,
+	/// The versions of the plugin which may be used
+	pub version: PluginVersionReq,
 	pub manifest: Option<ManifestLocation>,
+	/// How long to wait on the plugin for each step of a query, if limited.
+	pub timeout: Option<Duration>,
 }
 
 impl PolicyPlugin {
 	#[allow(dead_code)]
 	pub fn new(
 		name: PolicyPluginName,
-		version: PluginVersion,
+		version: PluginVersionReq,
 		manifest: Option<ManifestLocation>,
 	) -> Self {
 		Self {
 			name,
 			version,
 			manifest,
+			timeout: None,
 		}
 	}
-
-	pub fn get_plugin_id(&self) -> PluginId {
-		PluginId::new(
-			self.name.publisher.clone(),
-			self.name.name.clone(),
-			self.version.clone(),
-		)
-	}
 }
 
 impl ParseKdlNode for PolicyPlugin {
@@ -82,7 +78,13 @@ impl ParseKdlNode for PolicyPlugin {
 				return None;
 			}
 		};
-		let version = PluginVersion::new(node.get("version")?.value().as_string()?.to_string());
+		let version = match PluginVersionReq::from_str(node.get("version")?.value().as_string()?) {
+			Ok(version) => version,
+			Err(e) => {
+				log::error!("{} for plugin {} in the policy file", e, name);
+				return None;
+			}
+		};
 
 		// The manifest is technically optional, as there should be a default Hipcheck plugin artifactory sometime in the future
 		// But for now it is essentially mandatory, so a plugin without a manifest will return an error downstream
@@ -106,10 +108,27 @@ impl ParseKdlNode for PolicyPlugin {
 			None => None,
 		};
 
+		// The timeout is an optional number of seconds
+		let timeout = match node.get("timeout") {
+			Some(entry) => match entry.value().as_i64() {
+				Some(secs) if secs > 0 => Some(Duration::from_secs(secs as u64)),
+				_ => {
+					log::error!(
+						"Invalid timeout {} for plugin {} in the policy file, expected a positive number of seconds",
+						entry.value(),
+						name.to_string()
+					);
+					return None;
+				}
+			},
+			None => None,
+		};
+
 		Some(Self {
 			name,
 			version,
 			manifest,
+			timeout,
 		})
 	}
 }
@@ -562,7 +581,10 @@ pub struct PolicyPluginName {
 impl PolicyPluginName {
 	pub fn new(full_name: &str) -> Result<Self> {
 		let parsed_name: Vec<&str> = full_name.split('/').collect();
-		if parsed_name.len() > 1 {
+		if parsed_name.len() == 2 {
+			// The publisher and name become directories in the plugin cache
+			check_path_component("publisher", parsed_name[0])?;
+			check_path_component("name", parsed_name[1])?;
 			let publisher = PluginPublisher::new(parsed_name[0].to_string());
 			let name = PluginName::new(parsed_name[1].to_string());
 			Ok(Self { publisher, name })
@@ -646,3 +668,101 @@ impl ParseKdlNode for PolicyPatchList {
 		Some(plugins)
 	}
 }
+
+/// Self-hosted instances of source forges whose APIs Hipcheck knows, so
+/// repositories hosted on them get the same analyses as on the public ones.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct PolicyForges {
+	/// The hosts of self-hosted GitLab instances, like "gitlab.example.com"
+	pub gitlab: Vec<String>,
+}
+
+impl ParseKdlNode for PolicyForges {
+	fn kdl_key() -> &'static str {
+		"forges"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let mut forges = PolicyForges::default();
+
+		for node in node.children()?.nodes() {
+			match node.name().to_string().as_str() {
+				"gitlab" => {
+					for entry in node.entries() {
+						forges.gitlab.push(entry.value().as_string()?.to_owned());
+					}
+				}
+				other => {
+					log::error!("unknown forge '{}' in policy file", other);
+					return None;
+				}
+			}
+		}
+
+		Some(forges)
+	}
+}
+
+/// Publishers whose plugins must be signed, with the minisign public keys
+/// their plugins may be signed with.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct PolicyTrustedPublishers {
+	/// Whether plugins from publishers not listed here are refused
+	pub require_signatures: bool,
+	/// The base64-encoded public keys trusted for each publisher
+	pub publishers: HashMap<String, Vec<String>>,
+}
+
+impl PolicyTrustedPublishers {
+	/// Get the public keys trusted for a publisher, if it is trusted.
+	pub fn keys_for(&self, publisher: &str) -> Option<&[String]> {
+		self.publishers.get(publisher).map(Vec::as_slice)
+	}
+}
+
+impl ParseKdlNode for PolicyTrustedPublishers {
+	fn kdl_key() -> &'static str {
+		"trusted-publishers"
+	}
+
+	fn parse_node(node: &KdlNode) -> Option<Self> {
+		if node.name().to_string().as_str() != Self::kdl_key() {
+			return None;
+		}
+
+		let require_signatures = match node.get("require-signatures") {
+			Some(entry) => entry.value().as_bool()?,
+			None => false,
+		};
+
+		let mut publishers: HashMap<String, Vec<String>> = HashMap::new();
+
+		// A publisher may be listed more than once to trust more than one key,
+		// such as while rotating keys.
+		for node in node.children().map(KdlDocument::nodes).unwrap_or_default() {
+			if node.name().to_string().as_str() != "publisher" {
+				log::error!("unknown node '{}' in trusted-publishers", node.name());
+				return None;
+			}
+			let publisher = node.entries().first()?.value().as_string()?;
+			let key = node.get("key")?.value().as_string()?;
+			if let Err(e) = minisign_verify::PublicKey::from_base64(key) {
+				log::error!("invalid key for trusted publisher '{}': {}", publisher, e);
+				return None;
+			}
+			publishers
+				.entry(publisher.to_owned())
+				.or_default()
+				.push(key.to_owned());
+		}
+
+		Some(PolicyTrustedPublishers {
+			require_signatures,
+			publishers,
+		})
+	}
+}
@@ -5,15 +5,18 @@
 mod test {
 	use crate::{
 		config::Config,
-		plugin::PluginVersion,
-		policy::{config_to_policy::config_to_policy, policy_file::*, PolicyFile, PolicyPatchList},
+		plugin::PluginVersionReq,
+		policy::{
+			config_to_policy::config_to_policy, policy_file::*, PolicyExprSpans, PolicyFile,
+			PolicyPatchList,
+		},
 		util::kdl::ParseKdlNode,
 	};
 
 	use kdl::KdlNode;
 	use pathbuf::pathbuf;
 	use serde_json::Value;
-	use std::{env, str::FromStr};
+	use std::{env, str::FromStr, time::Duration};
 	use url::Url;
 
 	#[test]
@@ -23,7 +26,7 @@ mod test {
 
 		let expected = PolicyPlugin::new(
 			PolicyPluginName::new("mitre/activity").unwrap(),
-			PluginVersion::new("0.1.0".to_string()),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-activity.kdl",
@@ -35,6 +38,27 @@ mod test {
 		assert_eq!(expected, PolicyPlugin::parse_node(&node).unwrap())
 	}
 
+	#[test]
+	fn test_parsing_plugin_timeout() {
+		let data = r#"plugin "mitre/activity" version="0.1.0" timeout=30"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = PolicyPlugin {
+			timeout: Some(Duration::from_secs(30)),
+			..PolicyPlugin::new(
+				PolicyPluginName::new("mitre/activity").unwrap(),
+				PluginVersionReq::from_str("0.1.0").unwrap(),
+				None,
+			)
+		};
+
+		assert_eq!(expected, PolicyPlugin::parse_node(&node).unwrap());
+
+		let data = r#"plugin "mitre/activity" version="0.1.0" timeout=0"#;
+		let node = KdlNode::from_str(data).unwrap();
+		assert!(PolicyPlugin::parse_node(&node).is_none());
+	}
+
 	#[test]
 	fn test_parsing_plugin_list() {
 		let data = r#"plugins {
@@ -46,7 +70,7 @@ mod test {
 		let mut expected = PolicyPluginList::new();
 		expected.push(PolicyPlugin::new(
 			PolicyPluginName::new("mitre/activity").unwrap(),
-			PluginVersion::new("0.1.0".to_string()),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-activity.kdl",
@@ -56,7 +80,7 @@ mod test {
 		));
 		expected.push(PolicyPlugin::new(
 			PolicyPluginName::new("mitre/binary").unwrap(),
-			PluginVersion::new("0.1.1".to_string()),
+			PluginVersionReq::from_str("0.1.1").unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-binary.kdl",
@@ -90,6 +114,44 @@ mod test {
 		assert_eq!(expected, InvestigateIfFail::parse_node(&node).unwrap())
 	}
 
+	#[test]
+	fn test_parsing_forges() {
+		let data = r#"forges {
+    gitlab "gitlab.example.com" "git.example.org"
+}"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let expected = PolicyForges {
+			gitlab: vec![
+				"gitlab.example.com".to_owned(),
+				"git.example.org".to_owned(),
+			],
+		};
+
+		assert_eq!(expected, PolicyForges::parse_node(&node).unwrap())
+	}
+
+	#[test]
+	fn test_parsing_trusted_publishers() {
+		let data = r#"trusted-publishers require-signatures=true {
+    publisher "mitre" key="RWSkkUqdCclvNV2YEvZbS26nMCSrJwUEwdaYMbepXyfYTRwdADjBZdAT"
+    publisher "mitre" key="RWS2SVSigXw3AbbS3M547Um8ySOn6oAAhQijEcs+63VALf2G+8Z904AX"
+}"#;
+		let node = KdlNode::from_str(data).unwrap();
+
+		let trusted = PolicyTrustedPublishers::parse_node(&node).unwrap();
+		assert!(trusted.require_signatures);
+		assert_eq!(trusted.keys_for("mitre").unwrap().len(), 2);
+		assert!(trusted.keys_for("other").is_none());
+
+		// Keys which aren't minisign public keys are rejected
+		let data = r#"trusted-publishers {
+    publisher "mitre" key="not a key"
+}"#;
+		let node = KdlNode::from_str(data).unwrap();
+		assert!(PolicyTrustedPublishers::parse_node(&node).is_none());
+	}
+
 	#[test]
 	fn test_parsing_analysis_weight() {
 		let data = r#"analysis "mitre/typo" policy="(eq 0 (count $))" weight=3"#;
@@ -290,7 +352,7 @@ mod test {
 		let mut plugins = PolicyPluginList::new();
 		plugins.push(PolicyPlugin::new(
 			PolicyPluginName::new("mitre/activity").unwrap(),
-			PluginVersion::new("0.1.0".to_string()),
+			PluginVersionReq::from_str("0.1.0").unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-activity.kdl",
@@ -300,7 +362,7 @@ mod test {
 		));
 		plugins.push(PolicyPlugin::new(
 			PolicyPluginName::new("mitre/binary").unwrap(),
-			PluginVersion::new("0.1.1".to_string()),
+			PluginVersionReq::from_str("0.1.1").unwrap(),
 			Some(ManifestLocation::Url(
 				Url::parse(
 					"https://github.com/mitre/hipcheck/blob/main/plugin/dist/mitre-binary.kdl",
@@ -334,6 +396,8 @@ mod test {
 		let expected = PolicyFile {
 			plugins,
 			patch: PolicyPatchList::default(),
+			forges: PolicyForges::default(),
+			trusted_publishers: PolicyTrustedPublishers::default(),
 			analyze,
 		};
 
@@ -357,4 +421,41 @@ mod test {
 
 		assert_eq!(expected, policy_file)
 	}
+
+	#[test]
+	fn test_finding_policy_expr_spans() {
+		let data = r##"analyze {
+    investigate policy="(gt 0.5 $)"
+
+    category "practices" {
+        analysis "mitre/activity" policy="(lte $ 71)" weight=1
+        analysis "mitre/binary"
+    }
+    analysis "mitre/typo" policy=r#"(eq 0 (count $))"# weight=1
+}"##;
+		let spans = PolicyExprSpans::find(data).unwrap();
+
+		let investigate = spans.investigate.unwrap();
+		assert_eq!(&data[investigate], "(gt 0.5 $)");
+		assert_eq!(spans.analyses.len(), 2);
+		let activity = spans.analyses["mitre/activity"].clone();
+		assert_eq!(&data[activity], "(lte $ 71)");
+		let typo = spans.analyses["mitre/typo"].clone();
+		assert_eq!(&data[typo], "(eq 0 (count $))");
+	}
+
+	#[test]
+	fn test_plugin_name_rejects_path_components() {
+		assert!(PolicyPluginName::new("mitre/git").is_ok());
+		for name in [
+			"mitre",
+			"../git",
+			"mitre/..",
+			"mitre/",
+			"/git",
+			"mitre/git/extra",
+		] {
+			assert!(PolicyPluginName::new(name).is_err(), "{}", name);
+		}
+	}
 }
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::policy_exprs::{
-	pass::ExprMutator, Array as StructArray, Error, Expr, ExprVisitor, Function as StructFunction,
-	Ident, Lambda as StructLambda, Primitive, Result, F64,
+	json_pointer::json_to_policy_expr, pass::ExprMutator, Array as StructArray, Error, Expr,
+	ExprVisitor, Function as StructFunction, Ident, Lambda as StructLambda, Primitive, Result, F64,
 };
 use itertools::Itertools as _;
 use jiff::{Span, Zoned};
+use regex::Regex;
+use serde_json::{Map, Value};
 use std::{cmp::Ordering, collections::HashMap, ops::Not as _};
 use Expr::*;
 use Primitive::*;
+// Keep `String` referring to the standard type, rather than `Primitive::String`.
+use std::string::String;
 
 /// Environment, containing bindings of names to functions and variables.
 pub struct Env<'parent> {
@@ -82,6 +86,19 @@ impl<'parent> Env<'parent> {
 		// Array higher-order functions.
 		env.add_fn("filter", filter);
 		env.add_fn("foreach", foreach);
+		env.add_fn("map", foreach);
+
+		// String functions.
+		env.add_fn("contains", contains);
+		env.add_fn("starts-with", starts_with);
+		env.add_fn("ends-with", ends_with);
+		env.add_fn("matches", matches);
+		env.add_fn("len", len);
+		env.add_fn("lower", lower);
+		env.add_fn("upper", upper);
+
+		// Object functions.
+		env.add_fn("get", get);
 
 		// Debugging functions.
 		env.add_fn("dbg", dbg);
@@ -250,6 +267,12 @@ enum ArrayType {
 	/// An array of time spans.
 	Span(Vec<Span>),
 
+	/// An array of strings.
+	String(Vec<String>),
+
+	/// An array of objects.
+	Object(Vec<Map<String, Value>>),
+
 	/// An empty array (no type hints).
 	Empty,
 }
@@ -316,6 +339,28 @@ fn array_type(arr: &[Primitive]) -> Result<ArrayType> {
 			}
 			Ok(ArrayType::Span(result))
 		}
+		String(_) => {
+			let mut result: Vec<String> = Vec::with_capacity(arr.len());
+			for elem in arr {
+				if let String(val) = elem {
+					result.push(val.clone());
+				} else {
+					return Err(Error::InconsistentArrayTypes);
+				}
+			}
+			Ok(ArrayType::String(result))
+		}
+		Object(_) => {
+			let mut result: Vec<Map<String, Value>> = Vec::with_capacity(arr.len());
+			for elem in arr {
+				if let Object(val) = elem {
+					result.push(val.clone());
+				} else {
+					return Err(Error::InconsistentArrayTypes);
+				}
+			}
+			Ok(ArrayType::Object(result))
+		}
 
 		Identifier(_) => unimplemented!("we don't currently support idents in arrays"),
 	}
@@ -341,13 +386,14 @@ fn gt(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 > arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 > arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 > arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 > arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_gt(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -362,13 +408,14 @@ fn lt(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 < arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 < arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 < arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 < arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_lt(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -383,13 +430,14 @@ fn gte(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 >= arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 >= arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 >= arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 >= arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_ge(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -404,13 +452,14 @@ fn lte(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 <= arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 <= arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 <= arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 <= arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_le(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -425,13 +474,14 @@ fn eq(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 == arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 == arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 == arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 == arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_eq(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -446,13 +496,14 @@ fn neq(env: &Env, args: &[Expr]) -> Result<Expr> {
 		(Float(arg_1), Float(arg_2)) => Ok(Bool(arg_1 != arg_2)),
 		(Bool(arg_1), Bool(arg_2)) => Ok(Bool(arg_1 != arg_2)),
 		(DateTime(arg_1), DateTime(arg_2)) => Ok(Bool(arg_1 != arg_2)),
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1 != arg_2)),
 		(Span(arg_1), Span(arg_2)) => Ok(Bool(
 			arg_1
 				.compare(arg_2)
 				.map_err(|err| Error::Datetime(err.to_string()))?
 				.is_ne(),
 		)),
-		_ => unreachable!(),
+		_ => Err(Error::BadType(name)),
 	};
 
 	binary_primitive_op(name, env, args, op)
@@ -584,6 +635,8 @@ fn not(env: &Env, args: &[Expr]) -> Result<Expr> {
 		Bool(arg) => Ok(Primitive::Bool(arg.not())),
 		DateTime(_) => Err(Error::BadType(name)),
 		Span(_) => Err(Error::BadType(name)),
+		String(_) => Err(Error::BadType(name)),
+		Object(_) => Err(Error::BadType(name)),
 		Identifier(_) => unreachable!("no idents should be here"),
 	};
 
@@ -611,6 +664,8 @@ fn max(env: &Env, args: &[Expr]) -> Result<Expr> {
 		ArrayType::Bool(_) => Err(Error::BadType(name)),
 		ArrayType::DateTime(_) => Err(Error::BadType(name)),
 		ArrayType::Span(_) => Err(Error::BadType(name)),
+		ArrayType::String(_) => Err(Error::BadType(name)),
+		ArrayType::Object(_) => Err(Error::BadType(name)),
 		ArrayType::Empty => Err(Error::NoMax),
 	};
 
@@ -638,6 +693,8 @@ fn min(env: &Env, args: &[Expr]) -> Result<Expr> {
 		ArrayType::Bool(_) => Err(Error::BadType(name)),
 		ArrayType::DateTime(_) => Err(Error::BadType(name)),
 		ArrayType::Span(_) => Err(Error::BadType(name)),
+		ArrayType::String(_) => Err(Error::BadType(name)),
+		ArrayType::Object(_) => Err(Error::BadType(name)),
 		ArrayType::Empty => Err(Error::NoMin),
 	};
 
@@ -663,6 +720,8 @@ fn avg(env: &Env, args: &[Expr]) -> Result<Expr> {
 		ArrayType::Bool(_) => Err(Error::BadType(name)),
 		ArrayType::DateTime(_) => Err(Error::BadType(name)),
 		ArrayType::Span(_) => Err(Error::BadType(name)),
+		ArrayType::String(_) => Err(Error::BadType(name)),
+		ArrayType::Object(_) => Err(Error::BadType(name)),
 		ArrayType::Empty => Err(Error::NoAvg),
 	};
 
@@ -686,6 +745,8 @@ fn median(env: &Env, args: &[Expr]) -> Result<Expr> {
 		ArrayType::Bool(_) => Err(Error::BadType(name)),
 		ArrayType::DateTime(_) => Err(Error::BadType(name)),
 		ArrayType::Span(_) => Err(Error::BadType(name)),
+		ArrayType::String(_) => Err(Error::BadType(name)),
+		ArrayType::Object(_) => Err(Error::BadType(name)),
 		ArrayType::Empty => Err(Error::NoMedian),
 	};
 
@@ -701,6 +762,8 @@ fn count(env: &Env, args: &[Expr]) -> Result<Expr> {
 		ArrayType::Bool(bools) => Ok(Primitive(Int(bools.len() as i64))),
 		ArrayType::DateTime(dts) => Ok(Primitive(Int(dts.len() as i64))),
 		ArrayType::Span(spans) => Ok(Primitive(Int(spans.len() as i64))),
+		ArrayType::String(strings) => Ok(Primitive(Int(strings.len() as i64))),
+		ArrayType::Object(objs) => Ok(Primitive(Int(objs.len() as i64))),
 		ArrayType::Empty => Ok(Primitive(Int(0))),
 	};
 
@@ -742,6 +805,18 @@ fn all(env: &Env, args: &[Expr]) -> Result<Expr> {
 				.process_results(|mut iter| {
 					iter.all(|expr| matches!(expr, Primitive(Bool(true))))
 				})?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| eval_lambda(env, &ident, String(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.all(|expr| matches!(expr, Primitive(Bool(true))))
+				})?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| eval_lambda(env, &ident, Object(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.all(|expr| matches!(expr, Primitive(Bool(true))))
+				})?,
 			ArrayType::Empty => true,
 		};
 
@@ -786,6 +861,18 @@ fn nall(env: &Env, args: &[Expr]) -> Result<Expr> {
 				.process_results(|mut iter| {
 					iter.all(|expr| matches!(expr, Primitive(Bool(true)))).not()
 				})?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| eval_lambda(env, &ident, String(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.all(|expr| matches!(expr, Primitive(Bool(true)))).not()
+				})?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| eval_lambda(env, &ident, Object(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.all(|expr| matches!(expr, Primitive(Bool(true)))).not()
+				})?,
 			ArrayType::Empty => false,
 		};
 
@@ -830,6 +917,18 @@ fn some(env: &Env, args: &[Expr]) -> Result<Expr> {
 				.process_results(|mut iter| {
 					iter.any(|expr| matches!(expr, Primitive(Bool(true))))
 				})?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| eval_lambda(env, &ident, String(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.any(|expr| matches!(expr, Primitive(Bool(true))))
+				})?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| eval_lambda(env, &ident, Object(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.any(|expr| matches!(expr, Primitive(Bool(true))))
+				})?,
 			ArrayType::Empty => false,
 		};
 
@@ -874,6 +973,18 @@ fn none(env: &Env, args: &[Expr]) -> Result<Expr> {
 				.process_results(|mut iter| {
 					iter.any(|expr| matches!(expr, Primitive(Bool(true)))).not()
 				})?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| eval_lambda(env, &ident, String(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.any(|expr| matches!(expr, Primitive(Bool(true)))).not()
+				})?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| eval_lambda(env, &ident, Object(val.clone()), (*body).clone()))
+				.process_results(|mut iter| {
+					iter.any(|expr| matches!(expr, Primitive(Bool(true)))).not()
+				})?,
 			ArrayType::Empty => true,
 		};
 
@@ -948,6 +1059,38 @@ fn filter(env: &Env, args: &[Expr]) -> Result<Expr> {
 					}
 				})
 				.collect::<Result<Vec<_>>>()?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| {
+					Ok((
+						val,
+						eval_lambda(env, &ident, String(val.clone()), (*body).clone()),
+					))
+				})
+				.filter_map_ok(|(val, expr)| {
+					if let Ok(Primitive(Bool(true))) = expr {
+						Some(Primitive::String(val.clone()))
+					} else {
+						None
+					}
+				})
+				.collect::<Result<Vec<_>>>()?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| {
+					Ok((
+						val,
+						eval_lambda(env, &ident, Object(val.clone()), (*body).clone()),
+					))
+				})
+				.filter_map_ok(|(val, expr)| {
+					if let Ok(Primitive(Bool(true))) = expr {
+						Some(Primitive::Object(val.clone()))
+					} else {
+						None
+					}
+				})
+				.collect::<Result<Vec<_>>>()?,
 			ArrayType::Empty => Vec::new(),
 		};
 
@@ -1007,6 +1150,24 @@ fn foreach(env: &Env, args: &[Expr]) -> Result<Expr> {
 					Err(err) => Err(err),
 				})
 				.collect::<Result<Vec<_>>>()?,
+			ArrayType::String(strings) => strings
+				.iter()
+				.map(|val| eval_lambda(env, &ident, String(val.clone()), (*body).clone()))
+				.map(|expr| match expr {
+					Ok(Primitive(inner)) => Ok(inner),
+					Ok(_) => Err(Error::BadType(name)),
+					Err(err) => Err(err),
+				})
+				.collect::<Result<Vec<_>>>()?,
+			ArrayType::Object(objs) => objs
+				.iter()
+				.map(|val| eval_lambda(env, &ident, Object(val.clone()), (*body).clone()))
+				.map(|expr| match expr {
+					Ok(Primitive(inner)) => Ok(inner),
+					Ok(_) => Err(Error::BadType(name)),
+					Err(err) => Err(err),
+				})
+				.collect::<Result<Vec<_>>>()?,
 			ArrayType::Empty => Vec::new(),
 		};
 
@@ -1016,6 +1177,125 @@ fn foreach(env: &Env, args: &[Expr]) -> Result<Expr> {
 	higher_order_array_op(name, env, args, op)
 }
 
+// Checks if the first string contains the second
+fn contains(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "contains";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1.contains(&arg_2))),
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+// Checks if the first string starts with the second
+fn starts_with(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "starts-with";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1.starts_with(&arg_2))),
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+// Checks if the first string ends with the second
+fn ends_with(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "ends-with";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(String(arg_1), String(arg_2)) => Ok(Bool(arg_1.ends_with(&arg_2))),
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+// Checks if the first string matches the regular expression given as the second.
+// The match is unanchored; use `^` and `$` to match the whole string.
+fn matches(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "matches";
+
+	let op = |arg_1, arg_2| match (arg_1, arg_2) {
+		(String(arg_1), String(arg_2)) => {
+			let re = Regex::new(&arg_2).map_err(|source| Error::InvalidRegex {
+				pattern: arg_2.clone(),
+				source,
+			})?;
+			Ok(Bool(re.is_match(&arg_1)))
+		}
+		(_, _) => Err(Error::BadType(name)),
+	};
+
+	binary_primitive_op(name, env, args, op)
+}
+
+// Counts the characters in a string
+fn len(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "len";
+
+	let op = |arg| match arg {
+		String(arg) => Ok(Int(arg.chars().count() as i64)),
+		_ => Err(Error::BadType(name)),
+	};
+
+	unary_primitive_op(name, env, args, op)
+}
+
+fn lower(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "lower";
+
+	let op = |arg| match arg {
+		String(arg) => Ok(String(arg.to_lowercase())),
+		_ => Err(Error::BadType(name)),
+	};
+
+	unary_primitive_op(name, env, args, op)
+}
+
+fn upper(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "upper";
+
+	let op = |arg| match arg {
+		String(arg) => Ok(String(arg.to_uppercase())),
+		_ => Err(Error::BadType(name)),
+	};
+
+	unary_primitive_op(name, env, args, op)
+}
+
+// Gets the value of the named field of an object
+fn get(env: &Env, args: &[Expr]) -> Result<Expr> {
+	let name = "get";
+
+	if args.len() == 1 {
+		return partially_evaluate(name, args[0].clone());
+	}
+
+	check_num_args(name, args, 2)?;
+
+	let obj = match env.visit_expr(args[0].clone())? {
+		Primitive(Object(obj)) => obj,
+		_ => return Err(Error::BadType(name)),
+	};
+
+	let field = match env.visit_expr(args[1].clone())? {
+		Primitive(String(field)) => field,
+		_ => return Err(Error::BadType(name)),
+	};
+
+	let context = Value::Object(obj);
+	match context.get(&field) {
+		Some(val) => json_to_policy_expr(val, &format!("/{}", field), &context),
+		None => Err(Error::MissingField {
+			field,
+			object: context,
+		}),
+	}
+}
+
 fn dbg(env: &Env, args: &[Expr]) -> Result<Expr> {
 	let name = "dbg";
 	check_num_args(name, args, 1)?;
@@ -123,15 +123,44 @@ pub enum Error {
 		context: serde_json::Value,
 	},
 
+	#[error(
+		"JSON Pointer wildcard applied to a value which is not an array. \
+		pointer: '{pointer}'; value: {value}"
+	)]
+	JSONPointerWildcardNotArray {
+		pointer: String,
+		value: serde_json::Value,
+	},
+
+	#[error("object has no field '{field}'; object: {object}")]
+	MissingField {
+		field: String,
+		object: serde_json::Value,
+	},
+
+	#[error("'{name}' can't be called with operands of types {found}")]
+	TypeMismatch { name: String, found: String },
+
+	#[error("expression returns {0}, not a boolean")]
+	ReturnTypeNotBool(String),
+
+	#[error("JSON Pointer '{pointer}' doesn't match the plugin's output schema: {reason}")]
+	JSONPointerSchemaMismatch { pointer: String, reason: String },
+
 	#[error("Datetime error: {0}")]
 	Datetime(String),
+
+	#[error("invalid regular expression '{pattern}'")]
+	InvalidRegex {
+		pattern: String,
+		#[source]
+		source: regex::Error,
+	},
 }
 
 #[derive(Debug, PartialEq)]
 pub enum UnrepresentableJSONType {
 	NonPrimitiveInArray,
-	JSONObject,
-	JSONString,
 	JSONNull,
 }
 
@@ -2,7 +2,7 @@
 
 use crate::policy_exprs::{
 	env::{Binding, Env},
-	token::Token,
+	token::{escape_string, Token},
 	Error, Result, Tokens,
 };
 use itertools::Itertools;
@@ -15,6 +15,7 @@ use nom::{
 	Finish as _, IResult,
 };
 use ordered_float::NotNan;
+use serde_json::{Map, Value};
 use std::{fmt::Display, ops::Deref};
 
 #[cfg(test)]
@@ -23,7 +24,7 @@ use jiff::civil::Date;
 /// A `deke` expression to evaluate.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Expr {
-	/// Primitive data (ints, floats, bool).
+	/// Primitive data (ints, floats, bool, strings).
 	Primitive(Primitive),
 
 	/// An array of primitive data.
@@ -125,6 +126,17 @@ pub enum Primitive {
 	/// For example, a span of one week, one day, one hour, one minute, and one-and-a-tenth seconds would be represented as
 	/// "P1w1dT1h1m1.1s"
 	Span(Span),
+
+	/// UTF-8 string.
+	///
+	/// String literals are enclosed in double quotes, and may contain the escape sequences `\"`, `\\`, `\n`, and `\t`.
+	String(String),
+
+	/// JSON object, produced by a JSON Pointer lookup. There is no literal syntax for objects.
+	///
+	/// Fields are only converted to policy expression values when read with `get`, so an object may
+	/// contain fields which could not otherwise be represented, as long as they aren't accessed.
+	Object(Map<String, Value>),
 }
 impl From<Primitive> for Expr {
 	fn from(value: Primitive) -> Self {
@@ -182,6 +194,11 @@ impl Display for Primitive {
 			Primitive::Bool(b) => write!(f, "{}", if *b { "#t" } else { "#f" }),
 			Primitive::DateTime(dt) => write!(f, "{}", dt),
 			Primitive::Span(span) => write!(f, "{}", span),
+			Primitive::String(s) => write!(f, "{}", escape_string(s)),
+			Primitive::Object(obj) => {
+				let json = serde_json::to_string(obj).map_err(|_| std::fmt::Error)?;
+				write!(f, "{}", json)
+			}
 		}
 	}
 }
@@ -244,6 +261,11 @@ crate::data_variant_parser! {
 	pattern = Token::Span(span) => Primitive::Span(*span);
 }
 
+crate::data_variant_parser! {
+	fn parse_string(input) -> Result<Primitive>;
+	pattern = Token::String(s) => Primitive::String(s.to_owned());
+}
+
 crate::data_variant_parser! {
 	fn parse_ident(input) -> Result<String>;
 	pattern = Token::Ident(s) => s.to_owned();
@@ -265,6 +287,7 @@ fn parse_primitive(input: Input<'_>) -> IResult<Input<'_>, Primitive> {
 		parse_bool,
 		parse_datetime,
 		parse_span,
+		parse_string,
 	))(input)
 }
 
@@ -370,6 +393,10 @@ mod tests {
 		Primitive::Span(val)
 	}
 
+	fn string(val: &str) -> Primitive {
+		Primitive::String(String::from(val))
+	}
+
 	fn array(vals: Vec<Primitive>) -> Expr {
 		Array::new(vals).into()
 	}
@@ -428,6 +455,18 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn parse_string() {
+		let input = r#"(starts-with "MIT \"or\" Apache" "MIT")"#;
+		let expected = func(
+			"starts-with",
+			vec![string(r#"MIT "or" Apache"#), string("MIT")],
+		);
+		let result = parse(input).unwrap();
+		assert_eq!(result, expected);
+		assert_eq!(result.to_string(), input);
+	}
+
 	#[test]
 	fn parse_function() {
 		let input = "(add 2 3)";
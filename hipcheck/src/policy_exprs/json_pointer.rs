@@ -25,8 +25,13 @@ impl<'ctx> ExprMutator for LookupJsonPointers<'ctx> {
 	fn visit_json_pointer(&self, mut jp: JsonPointer) -> Result<Expr> {
 		let pointer = &jp.pointer;
 		let context = self.context;
-		let val = lookup_json_pointer(pointer, context)?;
-		let expr = json_to_policy_expr(val, pointer, context)?;
+		let expr = if has_wildcard(pointer) {
+			let val = lookup_json_pointer_wildcard(pointer, context)?;
+			json_to_policy_expr(&val, pointer, context)?
+		} else {
+			let val = lookup_json_pointer(pointer, context)?;
+			json_to_policy_expr(val, pointer, context)?
+		};
 		jp.value = Some(Box::new(expr));
 		Ok(jp.into())
 	}
@@ -54,10 +59,48 @@ fn lookup_json_pointer<'val>(pointer: &str, context: &'val Value) -> Result<&'va
 	}
 }
 
+/// Check if a JSON Pointer contains a `*` wildcard segment.
+fn has_wildcard(pointer: &str) -> bool {
+	pointer.split('/').skip(1).any(|segment| segment == "*")
+}
+
+/// Look up a JSON Pointer which may contain `*` wildcard segments.
+///
+/// A wildcard matches every element of the array found at that point in the
+/// pointer, and the rest of the pointer is looked up in each element. The
+/// results are collected into an array, so `/*/reviews` on an array of objects
+/// produces the array of their `reviews` fields.
+fn lookup_json_pointer_wildcard(pointer: &str, context: &Value) -> Result<Value> {
+	let segments = pointer.split('/').collect::<Vec<_>>();
+	let Some(wildcard) = segments.iter().skip(1).position(|segment| *segment == "*") else {
+		return lookup_json_pointer(pointer, context).cloned();
+	};
+	// Account for the leading empty segment skipped above.
+	let wildcard = wildcard + 1;
+
+	let prefix = segments[..wildcard].join("/");
+	let rest = segments[wildcard + 1..]
+		.iter()
+		.map(|segment| format!("/{}", segment))
+		.collect::<String>();
+
+	match lookup_json_pointer(&prefix, context)? {
+		Value::Array(elts) => elts
+			.iter()
+			.map(|elt| lookup_json_pointer_wildcard(&rest, elt))
+			.collect::<Result<Vec<_>>>()
+			.map(Value::Array),
+		value => Err(Error::JSONPointerWildcardNotArray {
+			pointer: pointer.to_owned(),
+			value: value.clone(),
+		}),
+	}
+}
+
 /// Attempt to interpret a JSON Value as a Policy Expression.
 /// `pointer` and `context` are only passed in to provide more context in the
 /// case of errors.
-fn json_to_policy_expr(val: &Value, pointer: &str, context: &Value) -> Result<Expr> {
+pub(crate) fn json_to_policy_expr(val: &Value, pointer: &str, context: &Value) -> Result<Expr> {
 	match val {
 		Value::Number(n) => {
 			let not_nan = NotNan::new(n.as_f64().unwrap()).unwrap();
@@ -74,14 +117,10 @@ fn json_to_policy_expr(val: &Value, pointer: &str, context: &Value) -> Result<Ex
 			// That would be a type error in the Policy Expr language.
 			Ok(Array::new(primitives).into())
 		}
-		Value::Object(_) => Err(Error::JSONPointerUnrepresentableType {
-			json_type: error::UnrepresentableJSONType::JSONObject,
-			pointer: pointer.to_owned(),
-			value: val.clone(),
-			context: context.clone(),
-		}),
-		// Attempt to parse a datetime or span, and only error out if both fail.
-		// TODO: Use JSON schema data to guide which of datetime or span to parse into.
+		Value::Object(obj) => Ok(Expr::Primitive(Primitive::Object(obj.clone()))),
+		// Attempt to parse a datetime or span, and fall back to a plain string
+		// if both fail.
+		// TODO: Use JSON schema data to guide which of datetime, span or string to parse into.
 		Value::String(s) => {
 			if let Ok(date_time) = parse_datetime(s) {
 				return Ok(Expr::Primitive(Primitive::DateTime(date_time)));
@@ -91,12 +130,7 @@ fn json_to_policy_expr(val: &Value, pointer: &str, context: &Value) -> Result<Ex
 				return Ok(Expr::Primitive(Primitive::Span(span)));
 			}
 
-			Err(Error::JSONPointerUnrepresentableType {
-				json_type: error::UnrepresentableJSONType::JSONString,
-				pointer: pointer.to_owned(),
-				value: val.clone(),
-				context: context.clone(),
-			})
+			Ok(Expr::Primitive(Primitive::String(s.to_owned())))
 		}
 		Value::Null => Err(Error::JSONPointerUnrepresentableType {
 			json_type: error::UnrepresentableJSONType::JSONNull,
@@ -231,40 +265,70 @@ mod tests {
 	}
 
 	#[test]
-	fn error_unrepresentable_string() {
-		let expr = json_ptr("/str");
+	fn json_string() {
+		let pointer = "/license";
 		let context = serde_json::json!({
-			"str": "Hello World!",
+			"license": "Apache-2.0",
 		});
-		let result = LookupJsonPointers::with_context(&context).visit_expr(expr);
-		assert_eq!(
-			result,
-			Err(Error::JSONPointerUnrepresentableType {
-				json_type: error::UnrepresentableJSONType::JSONString,
-				pointer: "/str".into(),
-				value: context.get("str").unwrap().clone(),
-				context,
-			})
-		);
+		let expected = Primitive::String(String::from("Apache-2.0")).into();
+
+		let val = lookup_json_pointer(pointer, &context).unwrap();
+		let result = json_to_policy_expr(val, pointer, &context);
+		assert_eq!(result, Ok(expected));
 	}
 
 	#[test]
-	fn error_unrepresentable_object() {
-		let expr = json_ptr("/obj");
+	fn json_object() {
+		let pointer = "/obj";
 		let context = serde_json::json!({
 			"obj": {
 				"a": 4.5,
-				"b": true,
+				"b": [true, false],
 			}
 		});
+		let obj = context.get("obj").unwrap().as_object().unwrap().clone();
+		let expected = Primitive::Object(obj).into();
+
+		let val = lookup_json_pointer(pointer, &context).unwrap();
+		let result = json_to_policy_expr(val, pointer, &context);
+		assert_eq!(result, Ok(expected));
+	}
+
+	#[test]
+	fn wildcard_projects_fields() {
+		let expr = json_ptr("/prs/*/reviews");
+		let context = serde_json::json!({
+			"prs": [
+				{ "id": 1, "reviews": 2 },
+				{ "id": 2, "reviews": 0 },
+			],
+		});
+		let result = LookupJsonPointers::with_context(&context).visit_expr(expr);
+		let expected = Expr::JsonPointer(JsonPointer {
+			pointer: "/prs/*/reviews".to_owned(),
+			value: Some(Box::new(
+				Array::new(vec![
+					Primitive::Float(F64::new(2.0).unwrap()),
+					Primitive::Float(F64::new(0.0).unwrap()),
+				])
+				.into(),
+			)),
+		});
+		assert_eq!(result, Ok(expected));
+	}
+
+	#[test]
+	fn error_wildcard_not_array() {
+		let expr = json_ptr("/obj/*/a");
+		let context = serde_json::json!({
+			"obj": { "a": 4.5 },
+		});
 		let result = LookupJsonPointers::with_context(&context).visit_expr(expr);
 		assert_eq!(
 			result,
-			Err(Error::JSONPointerUnrepresentableType {
-				json_type: error::UnrepresentableJSONType::JSONObject,
-				pointer: "/obj".into(),
+			Err(Error::JSONPointerWildcardNotArray {
+				pointer: "/obj/*/a".into(),
 				value: context.get("obj").unwrap().clone(),
-				context,
 			})
 		);
 	}
@@ -9,6 +9,7 @@ pub mod expr;
 mod json_pointer;
 mod pass;
 mod token;
+mod type_checker;
 
 use crate::policy_exprs::env::Env;
 pub(crate) use crate::policy_exprs::{bridge::Tokens, expr::F64};
@@ -17,10 +18,13 @@ pub use crate::policy_exprs::{
 	expr::{Array, Expr, Function, Ident, JsonPointer, Lambda},
 	pass::{ExprMutator, ExprVisitor},
 	token::LexingError,
+	type_checker::{PrimitiveType, Type},
 };
 use env::Binding;
 pub use expr::{parse, Primitive};
 use json_pointer::LookupJsonPointers;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::ops::Deref;
 
@@ -51,7 +55,73 @@ impl Executor {
 		let expr = self.env.visit_expr(processed_program)?;
 		Ok(expr)
 	}
+
+	/// Run a `deke` program, recording the value of each function call in it.
+	///
+	/// Calls are recorded innermost first, so the trace reads in the order the
+	/// values were worked out. Partial applications and the calls made inside
+	/// lambdas as they're applied to each element of an array aren't recorded.
+	pub fn trace(&self, raw_program: &str, context: &Value) -> Result<Vec<TraceEntry>> {
+		let program = parse(raw_program)?;
+		let processed_program = LookupJsonPointers::with_context(context).run(program)?;
+		let mut trace = vec![];
+		trace_expr(&self.env, &processed_program, &mut trace)?;
+		Ok(trace)
+	}
+}
+
+/// The value of one function call in a `deke` program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct TraceEntry {
+	/// The function call, as written in the program.
+	pub expr: String,
+	/// What the function call evaluated to.
+	pub value: String,
+}
+
+/// Evaluate `expr`, recording the value of each function call in `trace`.
+fn trace_expr(env: &Env, expr: &Expr, trace: &mut Vec<TraceEntry>) -> Result<Expr> {
+	let Expr::Function(func) = expr else {
+		return env.visit_expr(expr.clone());
+	};
+
+	// Replace each call among the operands with its value, so nothing is
+	// evaluated twice. Partial applications stay as written, since the
+	// higher-order functions evaluate them into lambdas themselves.
+	let mut evaluated = func.clone();
+	for arg in evaluated.args.iter_mut() {
+		if let Expr::Function(_) = arg {
+			let value = trace_expr(env, arg, trace)?;
+			if !matches!(value, Expr::Lambda(_)) {
+				*arg = value;
+			}
+		}
+	}
+
+	let value = env.visit_expr(evaluated.into())?;
+	if !matches!(value, Expr::Lambda(_)) {
+		trace.push(TraceEntry {
+			expr: expr.to_string(),
+			value: value.to_string(),
+		});
+	}
+	Ok(value)
 }
+
+/// Check that a `deke` program is well-typed and returns a boolean, without
+/// running it.
+///
+/// `schema` is the JSON Schema of the data the program's JSON Pointers will be
+/// looked up in, if known; without it, the types of JSON Pointers are unknown.
+pub fn type_check(raw_program: &str, schema: Option<&Value>) -> Result<()> {
+	let program = parse(raw_program)?;
+	match type_checker::type_of(&program, schema)? {
+		Type::Primitive(Some(PrimitiveType::Bool) | None) | Type::Unknown => Ok(()),
+		ty => Err(Error::ReturnTypeNotBool(ty.to_string())),
+	}
+}
+
 impl ExprMutator for Env<'_> {
 	fn visit_primitive(&self, prim: Primitive) -> Result<Expr> {
 		Ok(prim.resolve(self)?.into())
@@ -267,4 +337,106 @@ mod tests {
 			.unwrap();
 		assert_eq!(expected, result2);
 	}
+
+	#[test]
+	fn eval_string_funcs() {
+		let programs = vec![
+			r#"(eq "MIT" "MIT")"#,
+			r#"(neq "MIT" "mit")"#,
+			r#"(lt "Apache-2.0" "MIT")"#,
+			r#"(eq "mit" (lower "MIT"))"#,
+			r#"(eq 10 (len "Apache-2.0"))"#,
+			r#"(contains "MIT OR Apache-2.0" "Apache")"#,
+			r#"(starts-with "GPL-3.0-only" "GPL")"#,
+			r#"(ends-with "user@example.com" "@example.com")"#,
+			r#"(matches "GPL-3.0-only" "^[AL]?GPL-\\d")"#,
+			r#"(eq 2 (count (filter (starts-with "GPL") ["GPL-2.0" "MIT" "GPL-3.0"])))"#,
+		];
+		let context = Value::Null;
+		for program in programs {
+			let is_true = Executor::std().run(program, &context).unwrap();
+			assert!(is_true, "{program}");
+		}
+	}
+
+	#[test]
+	fn run_jsonptr_string() {
+		let program = "(none (matches \"@example\\\\.com$\") $/emails)";
+		let context = serde_json::json!({
+			"emails": ["alice@example.org", "bob@example.net"],
+		});
+		let is_true = Executor::std().run(program, &context).unwrap();
+		assert!(is_true);
+	}
+
+	#[test]
+	fn eval_invalid_regex() {
+		let program = r#"(matches "MIT" "(")"#;
+		let context = Value::Null;
+		let result = Executor::std().parse_and_eval(program, &context);
+		assert!(matches!(result, Err(Error::InvalidRegex { .. })));
+	}
+
+	#[test]
+	fn eval_mismatched_types() {
+		let program = r#"(eq "1" 1)"#;
+		let context = Value::Null;
+		let result = Executor::std().parse_and_eval(program, &context);
+		assert_eq!(result, Err(Error::BadType("eq")));
+	}
+
+	#[test]
+	fn run_object_field_access() {
+		let programs = vec![
+			"(eq 1 (count (filter (eq 0) (map (get \"reviews\") $))))",
+			"(eq 1 (count (filter (eq 0) $/*/reviews)))",
+			"(eq #t (get (get $/0 \"author\") \"bot\"))",
+		];
+		let context = serde_json::json!([
+			{ "id": 1, "reviews": 2, "author": { "bot": true } },
+			{ "id": 2, "reviews": 0, "author": { "bot": false } },
+		]);
+		for program in programs {
+			let is_true = Executor::std().run(program, &context).unwrap();
+			assert!(is_true, "{program}");
+		}
+	}
+
+	#[test]
+	fn eval_missing_field() {
+		let program = "(get $ \"reviews\")";
+		let context = serde_json::json!({ "id": 1 });
+		let result = Executor::std().parse_and_eval(program, &context);
+		assert_eq!(
+			result,
+			Err(Error::MissingField {
+				field: String::from("reviews"),
+				object: context,
+			})
+		);
+	}
+
+	#[test]
+	fn trace_records_each_call() {
+		let program = "(lte (divz (count (filter (gt 3) $)) (count $)) 0.02)";
+		let context = serde_json::json!([1, 4, 5]);
+		let trace = Executor::std().trace(program, &context).unwrap();
+		let trace = trace
+			.iter()
+			.map(|entry| (entry.expr.as_str(), entry.value.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			trace,
+			vec![
+				("(filter (gt 3) $)", "[4 5]"),
+				("(count (filter (gt 3) $))", "2"),
+				("(count $)", "3"),
+				(
+					"(divz (count (filter (gt 3) $)) (count $))",
+					"0.6666666666666666"
+				),
+				(program, "#f"),
+			]
+		);
+	}
 }
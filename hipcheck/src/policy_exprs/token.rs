@@ -50,10 +50,13 @@ pub enum Token {
 	Span(Box<Span>),
 
 	// Prioritize over span regex, which starts with a 'P'
-	#[regex(r"([a-zA-Z]+)", lex_ident, priority = 10)]
+	#[regex(r"([a-zA-Z][a-zA-Z-]*)", lex_ident, priority = 10)]
 	Ident(String),
 
-	#[regex(r"\$[/~_[:alnum:]]*", lex_json_pointer)]
+	#[regex(r#""([^"\\]|\\.)*""#, lex_string)]
+	String(String),
+
+	#[regex(r"\$[/~_*[:alnum:]]*", lex_json_pointer)]
 	JSONPointer(String),
 }
 
@@ -145,6 +148,38 @@ fn lex_ident(input: &mut Lexer<'_, Token>) -> Result<String> {
 	Ok(input.slice().to_owned())
 }
 
+/// Lex a double-quoted string literal.
+/// The surrounding quotes are removed and escape sequences are resolved.
+fn lex_string(input: &mut Lexer<'_, Token>) -> Result<String> {
+	let token = input.slice();
+	let inner = token
+		.get(1..token.len() - 1)
+		.ok_or(LexingError::InternalError(format!(
+			"string token missing surrounding quotes: got '{}'",
+			token
+		)))?;
+
+	let mut result = String::with_capacity(inner.len());
+	let mut chars = inner.chars();
+	while let Some(chr) = chars.next() {
+		if chr != '\\' {
+			result.push(chr);
+			continue;
+		}
+
+		match chars.next() {
+			Some('"') => result.push('"'),
+			Some('\\') => result.push('\\'),
+			Some('n') => result.push('\n'),
+			Some('t') => result.push('\t'),
+			Some(other) => return Err(LexingError::InvalidEscape(format!("\\{}", other))),
+			None => return Err(LexingError::InvalidEscape(String::from("\\"))),
+		}
+	}
+
+	Ok(result)
+}
+
 /// Lex a JSON Pointer.
 /// The initial '$' character is removed.
 fn lex_json_pointer(input: &mut Lexer<'_, Token>) -> Result<String> {
@@ -178,11 +213,29 @@ impl Display for Token {
 			Token::DateTime(dt) => write!(f, "{dt}"),
 			Token::Span(span) => write!(f, "{span}"),
 			Token::Ident(i) => write!(f, "{i}"),
+			Token::String(s) => write!(f, "{}", escape_string(s)),
 			Token::JSONPointer(pointer) => write!(f, "${pointer}"),
 		}
 	}
 }
 
+/// Quote a string, escaping it so it lexes back to the same value.
+pub fn escape_string(s: &str) -> String {
+	let mut result = String::with_capacity(s.len() + 2);
+	result.push('"');
+	for chr in s.chars() {
+		match chr {
+			'"' => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			'\t' => result.push_str("\\t"),
+			chr => result.push(chr),
+		}
+	}
+	result.push('"');
+	result
+}
+
 /// Error arising during lexing.
 #[derive(Default, Debug, Clone, PartialEq, thiserror::Error)]
 pub enum LexingError {
@@ -216,6 +269,9 @@ pub enum LexingError {
 
 	#[error("span cannot contain units of years or months")]
 	SpanWithBadUnits,
+
+	#[error("invalid escape sequence '{0}' in string")]
+	InvalidEscape(String),
 }
 
 #[cfg(test)]
@@ -308,6 +364,14 @@ mod tests {
 		assert_eq!(tokens, expected);
 	}
 
+	#[test]
+	fn basic_lexing_with_jsonptr_wildcard() {
+		let raw_program = "$/*/reviews";
+		let expected = vec![Token::JSONPointer(String::from("/*/reviews"))];
+		let tokens = lex(raw_program).unwrap();
+		assert_eq!(tokens, expected);
+	}
+
 	#[test]
 	fn basic_lexing_with_jsonptr_in_expr() {
 		let raw_program = "(eq 1 $/data/one)";
@@ -357,6 +421,28 @@ mod tests {
 		assert_eq!(tokens, expected);
 	}
 
+	#[test]
+	fn basic_lexing_with_strings() {
+		let raw_program = r#"(starts-with "say \"hi\"\\" "say")"#;
+		let expected = vec![
+			Token::OpenParen,
+			Token::Ident(String::from("starts-with")),
+			Token::String(String::from(r#"say "hi"\"#)),
+			Token::String(String::from("say")),
+			Token::CloseParen,
+		];
+		let tokens = lex(raw_program).unwrap();
+		assert_eq!(tokens, expected);
+	}
+
+	#[test]
+	fn lexing_with_bad_escape() {
+		let raw_program = r#""\d+""#;
+		let expected = Err(Lex(LexingError::InvalidEscape(String::from("\\d"))));
+		let tokens = lex(raw_program);
+		assert_eq!(tokens, expected);
+	}
+
 	// Ensure that idents with capital P are prioritized over being treated as spans
 	#[test]
 	fn regression_lex_span_and_ident() {
@@ -0,0 +1,678 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static type checking for policy expressions.
+//!
+//! Policy expressions are only evaluated once a plugin has produced its
+//! output, which may take a long time. This pass infers the type of an
+//! expression ahead of time, using the JSON Schema of the plugin's output to
+//! type JSON Pointers, so type errors can be reported before any plugin runs.
+//!
+//! The checker is conservative: where a type can't be known ahead of time,
+//! such as a JSON string which may be read as a datetime, span, or string,
+//! it is treated as unknown and any use of it is accepted.
+
+use crate::policy_exprs::{
+	error::{Error, Result},
+	expr::{Array, Expr, Function, JsonPointer, Lambda, Primitive},
+	pass::ExprVisitor,
+};
+use itertools::Itertools as _;
+use regex::Regex;
+use serde_json::Value;
+use std::fmt::{self, Display};
+
+/// The type of a primitive value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType {
+	Int,
+	Float,
+	Bool,
+	DateTime,
+	Span,
+	String,
+	Object,
+}
+
+/// The inferred type of an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+	/// A primitive value, if its type is known.
+	Primitive(Option<PrimitiveType>),
+
+	/// An array, if the type of its elements is known.
+	Array(Option<PrimitiveType>),
+
+	/// A partially-applied function, awaiting its first operand.
+	Lambda(LambdaType),
+
+	/// Could be any type.
+	Unknown,
+}
+
+/// A partially-applied function, awaiting its first operand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LambdaType {
+	/// The name of the function.
+	func: String,
+
+	/// The types of the operands that have already been supplied.
+	args: Vec<Type>,
+}
+
+impl Display for PrimitiveType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			PrimitiveType::Int => "int",
+			PrimitiveType::Float => "float",
+			PrimitiveType::Bool => "bool",
+			PrimitiveType::DateTime => "datetime",
+			PrimitiveType::Span => "span",
+			PrimitiveType::String => "string",
+			PrimitiveType::Object => "object",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+impl Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Type::Primitive(Some(ty)) => write!(f, "{}", ty),
+			Type::Primitive(None) => write!(f, "primitive"),
+			Type::Array(Some(ty)) => write!(f, "[{}]", ty),
+			Type::Array(None) => write!(f, "array"),
+			Type::Lambda(l) => write!(f, "({} _ {})", l.func, l.args.iter().join(" ")),
+			Type::Unknown => write!(f, "unknown"),
+		}
+	}
+}
+
+/// Check the type of `expr`, given the JSON Schema of the data its JSON
+/// Pointers will be looked up in, if known.
+pub fn type_of(expr: &Expr, schema: Option<&Value>) -> Result<Type> {
+	TypeChecker { schema }.run(expr)
+}
+
+/// Policy Expression stage that infers the type of an expression.
+struct TypeChecker<'s> {
+	schema: Option<&'s Value>,
+}
+
+impl ExprVisitor<Result<Type>> for TypeChecker<'_> {
+	fn visit_primitive(&self, prim: &Primitive) -> Result<Type> {
+		Ok(match primitive_type(prim) {
+			Some(ty) => Type::Primitive(Some(ty)),
+			// Identifiers are only introduced by partial evaluation, and so never
+			// appear in a parsed program.
+			None => Type::Unknown,
+		})
+	}
+
+	fn visit_array(&self, arr: &Array) -> Result<Type> {
+		let mut elt_ty = None;
+		for elt in &arr.elts {
+			let Some(ty) = primitive_type(elt) else {
+				continue;
+			};
+			elt_ty = match elt_ty {
+				None => Some(ty),
+				Some(prev) if prev == ty => Some(ty),
+				Some(_) => return Err(Error::InconsistentArrayTypes),
+			};
+		}
+		Ok(Type::Array(elt_ty))
+	}
+
+	fn visit_function(&self, func: &Function) -> Result<Type> {
+		let args = func
+			.args
+			.iter()
+			.map(|arg| self.visit_expr(arg))
+			.collect::<Result<Vec<_>>>()?;
+
+		// Catch bad regular expressions early too, when they're given literally.
+		if &*func.ident == "matches" {
+			if let Some(Expr::Primitive(Primitive::String(pattern))) = func.args.last() {
+				Regex::new(pattern).map_err(|source| Error::InvalidRegex {
+					pattern: pattern.clone(),
+					source,
+				})?;
+			}
+		}
+
+		check_call(&func.ident, args)
+	}
+
+	fn visit_lambda(&self, _lamb: &Lambda) -> Result<Type> {
+		// Lambdas are only introduced by partial evaluation, and so never
+		// appear in a parsed program.
+		Ok(Type::Unknown)
+	}
+
+	fn visit_json_pointer(&self, jp: &JsonPointer) -> Result<Type> {
+		match self.schema {
+			Some(schema) => json_pointer_type(&jp.pointer, schema),
+			None => Ok(Type::Unknown),
+		}
+	}
+}
+
+/// Get the type of a primitive, or `None` for an identifier.
+fn primitive_type(prim: &Primitive) -> Option<PrimitiveType> {
+	match prim {
+		Primitive::Identifier(_) => None,
+		Primitive::Int(_) => Some(PrimitiveType::Int),
+		Primitive::Float(_) => Some(PrimitiveType::Float),
+		Primitive::Bool(_) => Some(PrimitiveType::Bool),
+		Primitive::DateTime(_) => Some(PrimitiveType::DateTime),
+		Primitive::Span(_) => Some(PrimitiveType::Span),
+		Primitive::String(_) => Some(PrimitiveType::String),
+		Primitive::Object(_) => Some(PrimitiveType::Object),
+	}
+}
+
+/// Functions which may be called with only their second operand, producing a
+/// lambda to be applied by a higher-order function.
+const PARTIAL_FUNCS: &[&str] = &[
+	"gt",
+	"lt",
+	"gte",
+	"lte",
+	"eq",
+	"neq",
+	"add",
+	"sub",
+	"divz",
+	"duration",
+	"and",
+	"or",
+	"contains",
+	"starts-with",
+	"ends-with",
+	"matches",
+	"get",
+];
+
+/// Infer the type returned by calling the function `name` with operands of
+/// the given types, mirroring the standard environment.
+fn check_call(name: &str, args: Vec<Type>) -> Result<Type> {
+	use PrimitiveType::*;
+
+	if args.len() == 1 && PARTIAL_FUNCS.contains(&name) {
+		return Ok(Type::Lambda(LambdaType {
+			func: name.to_owned(),
+			args,
+		}));
+	}
+
+	let mismatch = || Error::TypeMismatch {
+		name: name.to_owned(),
+		found: format!("({})", args.iter().join(", ")),
+	};
+
+	match name {
+		"gt" | "lt" | "gte" | "lte" | "eq" | "neq" => {
+			let (a, b) = binary_primitives(name, &args)?.ok_or_else(mismatch)?;
+			match (a, b) {
+				(Some(Object), _) | (_, Some(Object)) => Err(mismatch()),
+				(Some(a), Some(b)) if unify(a, b).is_none() => Err(mismatch()),
+				_ => Ok(Type::Primitive(Some(Bool))),
+			}
+		}
+		"add" | "sub" => {
+			let (a, b) = binary_primitives(name, &args)?.ok_or_else(mismatch)?;
+			let ty = match (a, b) {
+				(Some(DateTime), Some(Span)) => Some(DateTime),
+				(Some(Span), Some(DateTime)) if name == "add" => Some(DateTime),
+				(Some(Span), Some(Span)) => Some(Span),
+				(Some(a @ (Int | Float)), Some(b @ (Int | Float))) => unify(a, b),
+				(None, Some(Int | Float | Span | DateTime)) => None,
+				(Some(Int | Float | Span | DateTime), None) | (None, None) => None,
+				_ => return Err(mismatch()),
+			};
+			Ok(Type::Primitive(ty))
+		}
+		"divz" => expect_primitives(name, &args, &[&[Int, Float], &[Int, Float]], Float)?
+			.ok_or_else(mismatch),
+		"duration" => {
+			expect_primitives(name, &args, &[&[DateTime], &[DateTime]], Span)?.ok_or_else(mismatch)
+		}
+		"and" | "or" => {
+			expect_primitives(name, &args, &[&[Bool], &[Bool]], Bool)?.ok_or_else(mismatch)
+		}
+		"not" => expect_primitives(name, &args, &[&[Bool]], Bool)?.ok_or_else(mismatch),
+		"contains" | "starts-with" | "ends-with" | "matches" => {
+			expect_primitives(name, &args, &[&[String], &[String]], Bool)?.ok_or_else(mismatch)
+		}
+		"len" => expect_primitives(name, &args, &[&[String]], Int)?.ok_or_else(mismatch),
+		"lower" | "upper" => {
+			expect_primitives(name, &args, &[&[String]], String)?.ok_or_else(mismatch)
+		}
+		"get" => {
+			expect_primitives(name, &args, &[&[Object], &[String]], Object)?
+				.ok_or_else(mismatch)?;
+			// The types of object fields aren't tracked.
+			Ok(Type::Unknown)
+		}
+		"max" | "min" | "median" => match array_arg(name, &args)? {
+			Type::Array(Some(ty @ (Int | Float))) => Ok(Type::Primitive(Some(ty))),
+			Type::Array(None) => Ok(Type::Primitive(None)),
+			Type::Unknown => Ok(Type::Unknown),
+			_ => Err(mismatch()),
+		},
+		"avg" => match array_arg(name, &args)? {
+			Type::Array(Some(Int | Float) | None) | Type::Unknown => {
+				Ok(Type::Primitive(Some(Float)))
+			}
+			_ => Err(mismatch()),
+		},
+		"count" => match array_arg(name, &args)? {
+			Type::Array(_) | Type::Unknown => Ok(Type::Primitive(Some(Int))),
+			_ => Err(mismatch()),
+		},
+		"all" | "nall" | "some" | "none" | "filter" | "foreach" | "map" => {
+			check_num_args(name, &args, 2)?;
+			let elt = match &args[1] {
+				Type::Array(elt) => Type::Primitive(*elt),
+				Type::Unknown => Type::Primitive(None),
+				_ => return Err(mismatch()),
+			};
+			let ret = match &args[0] {
+				Type::Lambda(l) => {
+					let mut l_args = vec![elt.clone()];
+					l_args.extend(l.args.iter().cloned());
+					check_call(&l.func, l_args)?
+				}
+				Type::Unknown => Type::Unknown,
+				_ => return Err(mismatch()),
+			};
+			match (name, ret) {
+				("foreach" | "map", Type::Primitive(ty)) => Ok(Type::Array(ty)),
+				("foreach" | "map", Type::Unknown) => Ok(Type::Array(None)),
+				("foreach" | "map", _) => Err(mismatch()),
+				("filter", Type::Primitive(Some(Bool) | None) | Type::Unknown) => match elt {
+					Type::Primitive(ty) => Ok(Type::Array(ty)),
+					_ => Ok(Type::Array(None)),
+				},
+				(_, Type::Primitive(Some(Bool) | None) | Type::Unknown) => {
+					Ok(Type::Primitive(Some(Bool)))
+				}
+				_ => Err(mismatch()),
+			}
+		}
+		"dbg" => {
+			check_num_args(name, &args, 1)?;
+			Ok(args[0].clone())
+		}
+		_ => Err(Error::UnknownFunction(name.to_owned())),
+	}
+}
+
+/// Check the number of operands provided to the function.
+fn check_num_args(name: &str, args: &[Type], expected: usize) -> Result<()> {
+	let given = args.len();
+	if given > expected {
+		Err(Error::TooManyArgs {
+			name: name.to_owned(),
+			expected,
+			given,
+		})
+	} else if given < expected {
+		Err(Error::NotEnoughArgs {
+			name: name.to_owned(),
+			expected,
+			given,
+		})
+	} else {
+		Ok(())
+	}
+}
+
+/// Get the two primitive operands of a binary function, or `None` if either
+/// isn't a primitive.
+fn binary_primitives(
+	name: &str,
+	args: &[Type],
+) -> Result<Option<(Option<PrimitiveType>, Option<PrimitiveType>)>> {
+	check_num_args(name, args, 2)?;
+	Ok(as_primitive(&args[0]).zip(as_primitive(&args[1])))
+}
+
+/// Check each operand is a primitive with one of the allowed types, returning
+/// `ret` if so, and `None` if not.
+fn expect_primitives(
+	name: &str,
+	args: &[Type],
+	allowed: &[&[PrimitiveType]],
+	ret: PrimitiveType,
+) -> Result<Option<Type>> {
+	check_num_args(name, args, allowed.len())?;
+	let all_allowed = args
+		.iter()
+		.zip(allowed)
+		.all(|(arg, allowed)| match as_primitive(arg) {
+			Some(Some(ty)) => allowed.contains(&ty),
+			Some(None) => true,
+			None => false,
+		});
+	Ok(all_allowed.then_some(Type::Primitive(Some(ret))))
+}
+
+/// Get the single array operand of a function.
+fn array_arg(name: &str, args: &[Type]) -> Result<Type> {
+	check_num_args(name, args, 1)?;
+	Ok(args[0].clone())
+}
+
+/// Get the type of a primitive operand, with `Some(None)` meaning a primitive
+/// of unknown type, and `None` meaning the operand is not a primitive.
+fn as_primitive(ty: &Type) -> Option<Option<PrimitiveType>> {
+	match ty {
+		Type::Primitive(ty) => Some(*ty),
+		Type::Unknown => Some(None),
+		Type::Array(_) | Type::Lambda(_) => None,
+	}
+}
+
+/// Find the type both operands of a binary function are converted to, if
+/// there is one. Ints are converted to floats when mixed with them.
+fn unify(a: PrimitiveType, b: PrimitiveType) -> Option<PrimitiveType> {
+	use PrimitiveType::*;
+
+	match (a, b) {
+		(a, b) if a == b => Some(a),
+		(Int, Float) | (Float, Int) => Some(Float),
+		_ => None,
+	}
+}
+
+/// Infer the type of the value a JSON Pointer will find, from the JSON Schema
+/// of the data it will be looked up in.
+fn json_pointer_type(pointer: &str, root: &Value) -> Result<Type> {
+	let schema_mismatch = |reason: String| Error::JSONPointerSchemaMismatch {
+		pointer: pointer.to_owned(),
+		reason,
+	};
+
+	let mut schema = root;
+	let mut wildcards = 0;
+
+	for segment in pointer.split('/').skip(1) {
+		let segment = segment.replace("~1", "/").replace("~0", "~");
+		let Some(resolved) = resolve_schema(schema, root) else {
+			return Ok(Type::Unknown);
+		};
+		schema = resolved;
+		let types = schema_types(schema);
+
+		let next = if types.contains(&"array") {
+			if segment != "*" && segment.parse::<usize>().is_err() {
+				return Err(schema_mismatch(format!(
+					"'{}' is not an index into an array",
+					segment
+				)));
+			}
+			if segment == "*" {
+				wildcards += 1;
+			}
+			schema.get("items")
+		} else if segment == "*" && !types.is_empty() {
+			return Err(schema_mismatch(format!(
+				"wildcard applied to {}, not an array",
+				types.join(" or ")
+			)));
+		} else if types.contains(&"object") || schema.get("properties").is_some() {
+			match schema.get("properties").and_then(|p| p.get(&segment)) {
+				Some(field) => Some(field),
+				None => match schema.get("additionalProperties") {
+					Some(Value::Bool(true)) | None if schema.get("properties").is_none() => None,
+					Some(additional @ Value::Object(_)) => Some(additional),
+					_ => {
+						return Err(schema_mismatch(format!(
+							"the object has no field '{}'",
+							segment
+						)))
+					}
+				},
+			}
+		} else if !types.is_empty() {
+			return Err(schema_mismatch(format!(
+				"cannot look up '{}' in {}",
+				segment,
+				types.join(" or ")
+			)));
+		} else {
+			None
+		};
+
+		match next {
+			Some(next) => schema = next,
+			None => return Ok(Type::Unknown),
+		}
+	}
+
+	let ty = schema_type(schema, root);
+	Ok(match (wildcards, ty) {
+		(0, ty) => ty,
+		(1, Type::Primitive(ty)) => Type::Array(ty),
+		(_, _) => Type::Array(None),
+	})
+}
+
+/// Get the policy expression type of values matching a JSON Schema.
+fn schema_type(schema: &Value, root: &Value) -> Type {
+	use PrimitiveType::*;
+
+	let Some(schema) = resolve_schema(schema, root) else {
+		return Type::Unknown;
+	};
+
+	match schema_types(schema).as_slice() {
+		// All JSON numbers become floats.
+		["integer" | "number"] => Type::Primitive(Some(Float)),
+		["boolean"] => Type::Primitive(Some(Bool)),
+		// Strings are read as datetimes or spans if they parse as one, so
+		// their type is only known if the schema gives their format.
+		["string"] => match schema.get("format").and_then(Value::as_str) {
+			Some("date-time") => Type::Primitive(Some(DateTime)),
+			Some("duration") => Type::Primitive(Some(Span)),
+			_ => Type::Primitive(None),
+		},
+		["object"] => Type::Primitive(Some(Object)),
+		["array"] => match schema.get("items").map(|items| schema_type(items, root)) {
+			Some(Type::Primitive(ty)) => Type::Array(ty),
+			_ => Type::Array(None),
+		},
+		_ => Type::Unknown,
+	}
+}
+
+/// Get the non-null types a JSON Schema allows.
+fn schema_types(schema: &Value) -> Vec<&str> {
+	let types = match schema.get("type") {
+		Some(Value::String(ty)) => vec![ty.as_str()],
+		Some(Value::Array(tys)) => tys.iter().filter_map(Value::as_str).collect(),
+		_ => vec![],
+	};
+	types.into_iter().filter(|ty| *ty != "null").collect()
+}
+
+/// Follow local `$ref`s, and unwrap schemas which only mark another schema
+/// as nullable, returning `None` if the schema can't be resolved.
+fn resolve_schema<'v>(schema: &'v Value, root: &'v Value) -> Option<&'v Value> {
+	// Bound the number of steps, in case of a cycle of `$ref`s.
+	let mut schema = schema;
+	for _ in 0..32 {
+		if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+			schema = root.pointer(reference.strip_prefix('#')?)?;
+			continue;
+		}
+
+		let subschemas = ["anyOf", "oneOf", "allOf"]
+			.iter()
+			.find_map(|key| schema.get(*key).and_then(Value::as_array));
+		if let Some(subschemas) = subschemas {
+			let mut non_null = subschemas
+				.iter()
+				.filter(|s| s.get("type").and_then(Value::as_str) != Some("null"));
+			match (non_null.next(), non_null.next()) {
+				(Some(only), None) => {
+					schema = only;
+					continue;
+				}
+				_ => return None,
+			}
+		}
+
+		return Some(schema);
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::policy_exprs::parse;
+	use serde_json::json;
+	use test_log::test;
+
+	fn check(program: &str, schema: Option<&Value>) -> Result<Type> {
+		type_of(&parse(program).unwrap(), schema)
+	}
+
+	#[test]
+	fn literal_types() {
+		let cases = vec![
+			("(gt 2 1.0)", Type::Primitive(Some(PrimitiveType::Bool))),
+			(
+				"(add 2024-09-17 P1w)",
+				Type::Primitive(Some(PrimitiveType::DateTime)),
+			),
+			(
+				"(lower \"MIT\")",
+				Type::Primitive(Some(PrimitiveType::String)),
+			),
+			(
+				"(foreach (sub 1.0) [1.0 2.0])",
+				Type::Array(Some(PrimitiveType::Float)),
+			),
+			(
+				"(filter (starts-with \"GPL\") [\"MIT\"])",
+				Type::Array(Some(PrimitiveType::String)),
+			),
+		];
+		for (program, expected) in cases {
+			assert_eq!(check(program, None), Ok(expected), "{program}");
+		}
+	}
+
+	#[test]
+	fn literal_type_errors() {
+		assert_eq!(
+			check("(gt \"x\" 1)", None),
+			Err(Error::TypeMismatch {
+				name: "gt".to_owned(),
+				found: "(string, int)".to_owned(),
+			})
+		);
+		assert_eq!(
+			check("(add [1 2] 1)", None),
+			Err(Error::TypeMismatch {
+				name: "add".to_owned(),
+				found: "([int], int)".to_owned(),
+			})
+		);
+		assert_eq!(
+			check("(all (gt 1) [#t #f])", None),
+			Err(Error::TypeMismatch {
+				name: "gt".to_owned(),
+				found: "(bool, int)".to_owned(),
+			})
+		);
+		assert!(matches!(
+			check("(matches \"MIT\" \"(\")", None),
+			Err(Error::InvalidRegex { .. })
+		));
+		assert_eq!(
+			check("(frobnicate 1)", None),
+			Err(Error::UnknownFunction("frobnicate".to_owned()))
+		);
+	}
+
+	#[test]
+	fn json_pointers_without_schema_are_unknown() {
+		assert_eq!(
+			check("(gt $/a \"x\")", None),
+			Ok(Type::Primitive(Some(PrimitiveType::Bool)))
+		);
+		assert_eq!(check("(max $)", None), Ok(Type::Unknown));
+	}
+
+	#[test]
+	fn json_pointers_with_schema() {
+		// Shaped like the output of `schemars`, with a nullable field and a
+		// reference to a definition.
+		let schema = json!({
+			"type": "array",
+			"items": { "$ref": "#/definitions/PullRequest" },
+			"definitions": {
+				"PullRequest": {
+					"type": "object",
+					"properties": {
+						"id": { "type": "integer", "format": "uint64" },
+						"reviews": { "type": ["integer", "null"] },
+						"merged_at": { "type": "string", "format": "date-time" },
+						"title": { "type": "string" },
+					},
+				},
+			},
+		});
+		let schema = Some(&schema);
+
+		assert_eq!(
+			check("(all (gt 0) $/*/reviews)", schema),
+			Ok(Type::Primitive(Some(PrimitiveType::Bool)))
+		);
+		assert_eq!(
+			check("$/*/merged_at", schema),
+			Ok(Type::Array(Some(PrimitiveType::DateTime)))
+		);
+		assert_eq!(check("$/0/title", schema), Ok(Type::Primitive(None)));
+		assert_eq!(
+			check("(foreach (get \"reviews\") $)", schema),
+			Ok(Type::Array(None))
+		);
+		assert_eq!(
+			check("(count (filter (lt 3) $/*/reviews))", schema),
+			Ok(Type::Primitive(Some(PrimitiveType::Int)))
+		);
+
+		assert_eq!(
+			check("(gt $/0/merged_at 1)", schema),
+			Err(Error::TypeMismatch {
+				name: "gt".to_owned(),
+				found: "(datetime, int)".to_owned(),
+			})
+		);
+		assert_eq!(
+			check("$/*/reveiws", schema),
+			Err(Error::JSONPointerSchemaMismatch {
+				pointer: "/*/reveiws".to_owned(),
+				reason: "the object has no field 'reveiws'".to_owned(),
+			})
+		);
+		assert!(matches!(
+			check("$/*/id/*", schema),
+			Err(Error::JSONPointerSchemaMismatch { .. })
+		));
+	}
+
+	#[test]
+	fn unresolvable_schema_is_unknown() {
+		let schema = json!({
+			"type": "array",
+			"items": { "$ref": "#/definitions/Missing" },
+		});
+		assert_eq!(check("$/*/anything", Some(&schema)), Ok(Type::Unknown));
+	}
+}
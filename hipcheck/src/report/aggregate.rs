@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A report on many targets checked in one run, like the dependencies in an SBOM or lockfile.
+
+use crate::{
+	error::Error,
+	report::{ErrorReport, RecommendationKind, Report, Timestamp},
+};
+use chrono::prelude::*;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// The results of checking many targets, with a summary of the recommendations.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AggregateReport {
+	/// Where the targets came from, like the path to an SBOM or lockfile.
+	pub source: String,
+
+	/// The version of Hipcheck used to analyze the targets.
+	pub hipcheck_version: String,
+
+	/// When the analysis was performed.
+	pub analyzed_at: Timestamp,
+
+	/// The result for each target, in the order they were listed.
+	pub targets: Vec<TargetReport>,
+
+	/// How many targets got each recommendation.
+	pub summary: Summary,
+}
+
+impl AggregateReport {
+	/// Make a report on the given targets, summarizing their results.
+	pub fn new(source: String, targets: Vec<TargetReport>) -> AggregateReport {
+		let mut summary = Summary {
+			total: targets.len(),
+			..Summary::default()
+		};
+
+		for target in targets.iter() {
+			match &target.outcome {
+				TargetOutcome::Analyzed { report } => match report.recommendation().kind {
+					RecommendationKind::Pass => summary.pass += 1,
+					RecommendationKind::Investigate => summary.investigate += 1,
+				},
+				TargetOutcome::Errored { .. } => summary.errored += 1,
+				TargetOutcome::Skipped { .. } => summary.skipped += 1,
+			}
+		}
+
+		AggregateReport {
+			source,
+			hipcheck_version: env!("CARGO_PKG_VERSION").to_owned(),
+			analyzed_at: Timestamp::from(Local::now().fixed_offset()),
+			targets,
+			summary,
+		}
+	}
+}
+
+/// The result of checking one of many targets.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct TargetReport {
+	/// The name of the target where it was listed.
+	pub name: String,
+
+	/// How the target was located, like a pURL, if it could be.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub locator: Option<String>,
+
+	/// What happened when the target was checked.
+	#[serde(flatten)]
+	pub outcome: TargetOutcome,
+}
+
+/// What happened when a target was checked.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+#[schemars(crate = "schemars")]
+pub enum TargetOutcome {
+	/// The target was analyzed, producing a full report.
+	Analyzed { report: Box<Report> },
+
+	/// Analyzing the target failed.
+	Errored { error: ErrorReport },
+
+	/// The target couldn't be resolved to anything Hipcheck can analyze.
+	Skipped { reason: String },
+}
+
+impl TargetOutcome {
+	/// The outcome of a target that failed to be analyzed.
+	pub fn errored(error: &Error) -> TargetOutcome {
+		TargetOutcome::Errored {
+			error: ErrorReport::from(error),
+		}
+	}
+}
+
+/// How many targets got each recommendation.
+#[derive(Debug, Default, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct Summary {
+	/// How many targets were listed.
+	pub total: usize,
+
+	/// How many targets passed.
+	pub pass: usize,
+
+	/// How many targets should be investigated.
+	pub investigate: usize,
+
+	/// How many targets couldn't be analyzed because of an error.
+	pub errored: usize,
+
+	/// How many targets couldn't be resolved to anything to analyze.
+	pub skipped: usize,
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A comparison of two reports on the same target, like before and after a
+//! dependency upgrade.
+
+use crate::{
+	error::{Context as _, Result},
+	report::{RecommendationKind, Report, Timestamp},
+	util::fs::read_json,
+};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+	fmt::{self, Display, Formatter},
+	path::Path,
+};
+
+/// What changed between an earlier and a later report.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ReportDiff {
+	/// The earlier report.
+	pub old: ReportIdent,
+
+	/// The later report.
+	pub new: ReportIdent,
+
+	/// Analyses with a different outcome, including ones only run for one of the reports.
+	pub analyses: Vec<AnalysisChange>,
+
+	/// Concerns raised in the later report but not the earlier one.
+	pub new_concerns: Vec<AnalysisConcerns>,
+
+	/// Concerns raised in the earlier report but not the later one.
+	pub resolved_concerns: Vec<AnalysisConcerns>,
+
+	/// The change in risk score, if it changed.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub risk_score: Option<Change<f64>>,
+
+	/// The change in recommendation, if it changed.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub recommendation: Option<Change<RecommendationKind>>,
+}
+
+impl ReportDiff {
+	/// Compare an earlier report to a later one.
+	pub fn new(old: &Report, new: &Report) -> ReportDiff {
+		let old_outcomes = outcomes(old);
+		let new_outcomes = outcomes(new);
+
+		// Analyses are listed in the order of the later report, then any only
+		// run for the earlier one.
+		let mut names = new_outcomes.keys().copied().collect::<Vec<_>>();
+		names.extend(
+			old_outcomes
+				.keys()
+				.filter(|name| !new_outcomes.contains_key(*name)),
+		);
+
+		let analyses = names
+			.into_iter()
+			.filter_map(|name| {
+				let old = old_outcomes.get(name).copied();
+				let new = new_outcomes.get(name).copied();
+				(old != new).then(|| AnalysisChange {
+					name: name.to_owned(),
+					old,
+					new,
+				})
+			})
+			.collect();
+
+		let old_risk_score = old.recommendation.risk_score.0;
+		let new_risk_score = new.recommendation.risk_score.0;
+		let risk_score = (old_risk_score != new_risk_score).then_some(Change {
+			old: old_risk_score,
+			new: new_risk_score,
+		});
+
+		let old_kind = old.recommendation.kind;
+		let new_kind = new.recommendation.kind;
+		let recommendation = (old_kind != new_kind).then_some(Change {
+			old: old_kind,
+			new: new_kind,
+		});
+
+		ReportDiff {
+			old: ReportIdent::from(old),
+			new: ReportIdent::from(new),
+			analyses,
+			new_concerns: concerns_only_in(new, old),
+			resolved_concerns: concerns_only_in(old, new),
+			risk_score,
+			recommendation,
+		}
+	}
+
+	/// Compare two reports saved as JSON.
+	pub fn from_files(old: &Path, new: &Path) -> Result<ReportDiff> {
+		let old = read_report(old)?;
+		let new = read_report(new)?;
+		Ok(ReportDiff::new(&old, &new))
+	}
+
+	/// Check if nothing changed between the reports.
+	pub fn is_empty(&self) -> bool {
+		self.analyses.is_empty()
+			&& self.new_concerns.is_empty()
+			&& self.resolved_concerns.is_empty()
+			&& self.risk_score.is_none()
+			&& self.recommendation.is_none()
+	}
+}
+
+/// Read a report saved by `hc check --format json`.
+pub fn read_report(path: &Path) -> Result<Report> {
+	read_json(path).with_context(|| {
+		format!(
+			"failed to read '{}' as a JSON report on a single target",
+			path.display()
+		)
+	})
+}
+
+/// Which report is which.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct ReportIdent {
+	/// The name of the repository analyzed.
+	pub repo_name: String,
+
+	/// The HEAD commit hash of the repository during analysis.
+	pub repo_head: String,
+
+	/// The version of Hipcheck used to analyze the repo.
+	pub hipcheck_version: String,
+
+	/// When the analysis was performed.
+	pub analyzed_at: Timestamp,
+}
+
+impl From<&Report> for ReportIdent {
+	fn from(report: &Report) -> ReportIdent {
+		ReportIdent {
+			repo_name: report.repo_name.to_string(),
+			repo_head: report.repo_head.to_string(),
+			hipcheck_version: report.hipcheck_version.clone(),
+			analyzed_at: report.analyzed_at.clone(),
+		}
+	}
+}
+
+impl ReportIdent {
+	/// Get the repository that was analyzed.
+	pub fn analyzed(&self) -> String {
+		format!("'{}' ({})", self.repo_name, self.repo_head)
+	}
+}
+
+/// An analysis with a different outcome in each report.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalysisChange {
+	/// The name of the plugin.
+	pub name: String,
+
+	/// The outcome in the earlier report, if the analysis was run.
+	pub old: Option<AnalysisOutcome>,
+
+	/// The outcome in the later report, if the analysis was run.
+	pub new: Option<AnalysisOutcome>,
+}
+
+impl AnalysisChange {
+	pub fn statement(&self) -> String {
+		format!(
+			"'{}' {} before, {} now",
+			self.name,
+			outcome_or_not_run(self.old),
+			outcome_or_not_run(self.new)
+		)
+	}
+}
+
+fn outcome_or_not_run(outcome: Option<AnalysisOutcome>) -> String {
+	outcome.map_or_else(|| "wasn't run".to_owned(), |outcome| outcome.to_string())
+}
+
+/// The outcome of a single analysis.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[schemars(crate = "schemars")]
+pub enum AnalysisOutcome {
+	Passed,
+	Failed,
+	Errored,
+}
+
+impl Display for AnalysisOutcome {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			AnalysisOutcome::Passed => write!(f, "passed"),
+			AnalysisOutcome::Failed => write!(f, "failed"),
+			AnalysisOutcome::Errored => write!(f, "errored"),
+		}
+	}
+}
+
+/// Concerns raised by one analysis.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct AnalysisConcerns {
+	/// The name of the plugin.
+	pub analysis: String,
+
+	/// The concerns.
+	pub concerns: Vec<String>,
+}
+
+/// A value which differs between the reports.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(crate = "schemars")]
+pub struct Change<T> {
+	pub old: T,
+	pub new: T,
+}
+
+/// Get the outcome of each analysis in a report.
+fn outcomes(report: &Report) -> IndexMap<&str, AnalysisOutcome> {
+	let passing = report
+		.passing_analyses()
+		.map(|analysis| (analysis.name.as_str(), AnalysisOutcome::Passed));
+	let failing = report
+		.failing_analyses()
+		.map(|failing| (failing.analysis.name.as_str(), AnalysisOutcome::Failed));
+	let errored = report
+		.errored_analyses()
+		.map(|errored| (errored.analysis.0.as_str(), AnalysisOutcome::Errored));
+
+	passing.chain(failing).chain(errored).collect()
+}
+
+/// Get the concerns raised in one report which weren't raised by the same
+/// analysis in the other.
+fn concerns_only_in(report: &Report, other: &Report) -> Vec<AnalysisConcerns> {
+	report
+		.failing_analyses()
+		.filter_map(|failing| {
+			let name = &failing.analysis.name;
+			let other_concerns = other
+				.failing_analyses()
+				.find(|other| &other.analysis.name == name)
+				.map(|other| other.concerns.as_slice())
+				.unwrap_or_default();
+
+			let concerns = failing
+				.concerns()
+				.filter(|concern| !other_concerns.contains(concern))
+				.cloned()
+				.collect::<Vec<_>>();
+
+			(!concerns.is_empty()).then(|| AnalysisConcerns {
+				analysis: name.clone(),
+				concerns,
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		hc_error,
+		report::{
+			Analysis, AnalysisIdent, ErroredAnalysis, FailingAnalysis, PassingAnalysis,
+			Recommendation, RiskPolicy, RiskScore,
+		},
+	};
+	use chrono::DateTime;
+	use std::sync::Arc;
+
+	fn analysis(name: &str, passed: bool) -> Analysis {
+		Analysis::plugin(
+			name.to_owned(),
+			passed,
+			"(lte $ 0.02)".to_owned(),
+			format!("{name} explanation"),
+		)
+	}
+
+	fn failing(name: &str, concerns: &[&str]) -> FailingAnalysis {
+		let concerns = concerns.iter().map(|c| c.to_string()).collect();
+		FailingAnalysis::new(analysis(name, false), concerns, vec![]).unwrap()
+	}
+
+	fn report(
+		head: &str,
+		passing: &[&str],
+		failing: Vec<FailingAnalysis>,
+		errored: &[&str],
+		risk_score: f64,
+	) -> Report {
+		Report {
+			repo_name: Arc::new("hipcheck".to_owned()),
+			repo_head: Arc::new(head.to_owned()),
+			repo_uri: None,
+			hipcheck_version: "3.0.0".to_owned(),
+			analyzed_at: DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+				.unwrap()
+				.into(),
+			passing: passing
+				.iter()
+				.map(|name| PassingAnalysis::new(analysis(name, true)))
+				.collect(),
+			failing,
+			errored: errored
+				.iter()
+				.map(|name| {
+					ErroredAnalysis::new(AnalysisIdent(name.to_string()), &hc_error!("crashed"))
+				})
+				.collect(),
+			recommendation: Recommendation::is(
+				RiskScore(risk_score),
+				RiskPolicy("(gt 0.5 $)".to_owned()),
+			)
+			.unwrap(),
+		}
+	}
+
+	#[test]
+	fn diff_reports() {
+		let old = report(
+			"abc",
+			&["mitre/activity", "mitre/fuzz"],
+			vec![failing("mitre/churn", &["commit abc is large"])],
+			&[],
+			0.2,
+		);
+		let new = report(
+			"def",
+			&["mitre/fuzz"],
+			vec![
+				failing("mitre/activity", &[]),
+				failing(
+					"mitre/churn",
+					&["commit abc is large", "commit def is large"],
+				),
+			],
+			&["mitre/typo"],
+			0.6,
+		);
+
+		let diff = ReportDiff::new(&old, &new);
+
+		let statements = diff
+			.analyses
+			.iter()
+			.map(AnalysisChange::statement)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			statements,
+			[
+				"'mitre/activity' passed before, failed now",
+				"'mitre/typo' wasn't run before, errored now"
+			]
+		);
+		assert_eq!(diff.new_concerns.len(), 1);
+		assert_eq!(diff.new_concerns[0].analysis, "mitre/churn");
+		assert_eq!(diff.new_concerns[0].concerns, ["commit def is large"]);
+		assert!(diff.resolved_concerns.is_empty());
+		assert_eq!(diff.risk_score.as_ref().map(|c| c.new), Some(0.6));
+		let recommendation = diff.recommendation.unwrap();
+		assert_eq!(recommendation.old, RecommendationKind::Pass);
+		assert_eq!(recommendation.new, RecommendationKind::Investigate);
+	}
+
+	#[test]
+	fn diff_identical_reports() {
+		let old = report("abc", &["mitre/activity"], vec![], &[], 0.2);
+		let new = report("def", &["mitre/activity"], vec![], &[], 0.2);
+
+		assert!(ReportDiff::new(&old, &new).is_empty());
+	}
+
+	#[test]
+	fn report_round_trips_through_json() {
+		let old = report(
+			"abc",
+			&["mitre/activity"],
+			vec![failing("mitre/churn", &["commit abc is large"])],
+			&["mitre/typo"],
+			0.4,
+		);
+		let json = serde_json::to_string(&old).unwrap();
+		let new: Report = serde_json::from_str(&json).unwrap();
+
+		assert!(ReportDiff::new(&old, &new).is_empty());
+		assert_eq!(new.failing[0].concerns, ["commit abc is large"]);
+	}
+}
@@ -9,17 +9,21 @@
 // The report serves double-duty, because it's both the thing used to print user-friendly
 // results on the CLI, and the type that's serialized out to JSON for machine-friendly output.
 
+pub mod aggregate;
+pub mod diff;
 pub mod report_builder;
+pub mod sarif;
 
 use crate::{
 	cli::Format,
 	error::{Context, Error, Result},
-	policy_exprs::Executor,
+	plugin::PluginFailure,
+	policy_exprs::{Executor, TraceEntry},
 	version::VersionQuery,
 };
 use chrono::prelude::*;
 use schemars::JsonSchema;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
 	default::Default,
 	fmt,
@@ -31,7 +35,7 @@ use std::{
 };
 
 /// The report output to the user.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct Report {
 	/// The name of the repository being analyzed.
@@ -40,6 +44,11 @@ pub struct Report {
 	/// The HEAD commit hash of the repository during analysis.
 	pub repo_head: Arc<String>,
 
+	/// Where the repository came from: its remote URL, or the `file:` URL of
+	/// the local repository it was given as.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub repo_uri: Option<String>,
+
 	/// The version of Hipcheck used to analyze the repo.
 	pub hipcheck_version: String,
 
@@ -112,7 +121,7 @@ impl Report {
 }
 
 /// An analysis which passed.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(transparent)]
 #[schemars(crate = "schemars")]
 pub struct PassingAnalysis(
@@ -127,7 +136,7 @@ impl PassingAnalysis {
 }
 
 /// An analysis which failed, including potential specific concerns.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct FailingAnalysis {
 	/// The analysis.
@@ -135,14 +144,27 @@ pub struct FailingAnalysis {
 	analysis: Analysis,
 
 	/// Any concerns the analysis identified.
-	#[serde(skip_serializing_if = "no_concerns")]
+	#[serde(default, skip_serializing_if = "no_concerns")]
 	concerns: Vec<String>,
+
+	/// The value of each function call in the policy expression, showing why
+	/// the analysis failed.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	trace: Vec<TraceEntry>,
 }
 
 impl FailingAnalysis {
 	/// Construct a new failing analysis, verifying that concerns are appropriate.
-	pub fn new(analysis: Analysis, concerns: Vec<String>) -> Result<FailingAnalysis> {
-		Ok(FailingAnalysis { analysis, concerns })
+	pub fn new(
+		analysis: Analysis,
+		concerns: Vec<String>,
+		trace: Vec<TraceEntry>,
+	) -> Result<FailingAnalysis> {
+		Ok(FailingAnalysis {
+			analysis,
+			concerns,
+			trace,
+		})
 	}
 
 	pub fn analysis(&self) -> &Analysis {
@@ -152,6 +174,10 @@ impl FailingAnalysis {
 	pub fn concerns(&self) -> impl Iterator<Item = &String> {
 		self.concerns.iter()
 	}
+
+	pub fn trace(&self) -> impl Iterator<Item = &TraceEntry> {
+		self.trace.iter()
+	}
 }
 
 /// Is the concern list empty?
@@ -162,11 +188,14 @@ fn no_concerns(concerns: &[String]) -> bool {
 }
 
 /// An analysis that did _not_ succeed.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct ErroredAnalysis {
 	analysis: AnalysisIdent,
 	error: ErrorReport,
+	/// What the failing plugin last wrote to stderr, if a plugin failed.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	plugin_stderr: Option<String>,
 }
 
 impl ErroredAnalysis {
@@ -175,6 +204,9 @@ impl ErroredAnalysis {
 		ErroredAnalysis {
 			analysis,
 			error: ErrorReport::from(error),
+			plugin_stderr: error
+				.downcast_ref::<PluginFailure>()
+				.and_then(|failure| failure.stderr.clone()),
 		}
 	}
 
@@ -189,6 +221,10 @@ impl ErroredAnalysis {
 
 		msgs
 	}
+
+	pub fn plugin_stderr(&self) -> Option<&str> {
+		self.plugin_stderr.as_deref()
+	}
 }
 
 fn try_add_msg(msgs: &mut Vec<String>, error_report: &Option<Box<ErrorReport>>) {
@@ -199,7 +235,7 @@ fn try_add_msg(msgs: &mut Vec<String>, error_report: &Option<Box<ErrorReport>>)
 }
 
 /// The name of the analyses.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct AnalysisIdent(String);
 
@@ -210,11 +246,11 @@ impl Display for AnalysisIdent {
 }
 
 /// A simple, serializable version of `Error`.
-#[derive(Debug, Serialize, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct ErrorReport {
 	msg: String,
-	#[serde(skip_serializing_if = "source_is_none")]
+	#[serde(default, skip_serializing_if = "source_is_none")]
 	source: Option<Box<ErrorReport>>,
 }
 
@@ -222,6 +258,15 @@ fn source_is_none(source: &Option<Box<ErrorReport>>) -> bool {
 	source.is_none()
 }
 
+impl ErrorReport {
+	/// Get the message of the error and of each of its sources, outermost first.
+	pub fn messages(&self) -> Vec<String> {
+		let mut msgs = vec![self.msg.clone()];
+		try_add_msg(&mut msgs, &self.source);
+		msgs
+	}
+}
+
 impl From<&Error> for ErrorReport {
 	fn from(error: &Error) -> ErrorReport {
 		log::trace!("detailed error for report [error: {:#?}]", error);
@@ -264,7 +309,7 @@ impl From<&(dyn std::error::Error + 'static)> for ErrorReport {
 }
 
 /// An analysis, with score and threshold.
-#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(tag = "analysis")]
 #[schemars(crate = "schemars")]
 pub struct Analysis {
@@ -340,7 +385,7 @@ pub struct Percent {
 
 /// A final recommendation of whether to use or investigate a piece of software,
 /// including the risk threshold associated with that decision.
-#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[schemars(crate = "schemars")]
 pub struct Recommendation {
 	pub kind: RecommendationKind,
@@ -360,6 +405,11 @@ impl Recommendation {
 		})
 	}
 
+	/// Get the risk score the recommendation was made from.
+	pub fn risk_score(&self) -> f64 {
+		self.risk_score.0
+	}
+
 	pub fn statement(&self) -> String {
 		format!(
 			"risk rated as {:.2}, policy was {}",
@@ -369,7 +419,7 @@ impl Recommendation {
 }
 
 /// The kind of recommendation being made.
-#[derive(Debug, Serialize, JsonSchema, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
 #[schemars(crate = "schemars")]
 pub enum RecommendationKind {
 	Pass,
@@ -393,19 +443,19 @@ impl RecommendationKind {
 }
 
 /// The overall final risk score for a repo.
-#[derive(Debug, Serialize, JsonSchema, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy)]
 #[serde(transparent)]
 #[schemars(crate = "schemars")]
 pub struct RiskScore(pub f64);
 
 /// The risk threshold configured for the Hipcheck session.
-#[derive(Debug, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(transparent)]
 #[schemars(crate = "schemars")]
 pub struct RiskPolicy(pub String);
 
 /// A serializable and printable wrapper around a datetime with the local timezone.
-#[derive(Debug, JsonSchema)]
+#[derive(Debug, Clone, JsonSchema)]
 #[schemars(crate = "schemars")]
 pub struct Timestamp(DateTime<Local>);
 
@@ -437,6 +487,18 @@ impl Serialize for Timestamp {
 	}
 }
 
+impl<'de> Deserialize<'de> for Timestamp {
+	fn deserialize<D>(deserializer: D) -> StdResult<Timestamp, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let date_time = String::deserialize(deserializer)?;
+		DateTime::parse_from_rfc3339(&date_time)
+			.map(Timestamp::from)
+			.map_err(serde::de::Error::custom)
+	}
+}
+
 /// Queries for how Hipcheck reports session results
 #[salsa::query_group(ReportParamsStorage)]
 pub trait ReportParams: VersionQuery {
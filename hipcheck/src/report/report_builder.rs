@@ -9,11 +9,13 @@ use crate::{
 	hc_error,
 	plugin::{PluginName, PluginPublisher},
 	policy::policy_file::PolicyPluginName,
+	policy_exprs::TraceEntry,
 	session::Session,
 	source::SourceQuery,
 	version::VersionQuery,
 };
 use std::{collections::HashSet, default::Default};
+use url::Url;
 
 /// Print the final report of a Hipcheck run.
 pub fn build_report(session: &Session, scoring: &ScoringResults) -> Result<Report> {
@@ -43,6 +45,7 @@ pub fn build_report(session: &Session, scoring: &ScoringResults) -> Result<Repor
 				builder.add_analysis(
 					Analysis::plugin(name, stored.passed, stored.policy.clone(), message),
 					res.concerns.clone(),
+					stored.trace.clone(),
 				)?;
 			}
 			Err(error) => {
@@ -110,12 +113,17 @@ impl<'sess> ReportBuilder<'sess> {
 		}
 	}
 
-	/// Add an analysis.
-	pub fn add_analysis(&mut self, analysis: Analysis, concerns: Vec<String>) -> Result<&mut Self> {
+	/// Add an analysis, with the trace of its policy's evaluation if it failed.
+	pub fn add_analysis(
+		&mut self,
+		analysis: Analysis,
+		concerns: Vec<String>,
+		trace: Vec<TraceEntry>,
+	) -> Result<&mut Self> {
 		if analysis.is_passing() {
 			Ok(self.add_passing_analysis(analysis))
 		} else {
-			self.add_failing_analysis(analysis, concerns)
+			self.add_failing_analysis(analysis, concerns, trace)
 		}
 	}
 
@@ -136,8 +144,10 @@ impl<'sess> ReportBuilder<'sess> {
 		&mut self,
 		analysis: Analysis,
 		concerns: Vec<String>,
+		trace: Vec<TraceEntry>,
 	) -> Result<&mut Self> {
-		self.failing.push(FailingAnalysis::new(analysis, concerns)?);
+		self.failing
+			.push(FailingAnalysis::new(analysis, concerns, trace)?);
 		Ok(self)
 	}
 
@@ -160,6 +170,13 @@ impl<'sess> ReportBuilder<'sess> {
 	pub fn build(self) -> Result<Report> {
 		let repo_name = self.session.name();
 		let repo_head = self.session.head();
+		let repo_uri = match self.session.url() {
+			Some(url) => Some(url.to_string()),
+			None => std::fs::canonicalize(&self.session.target().specifier)
+				.ok()
+				.and_then(|path| Url::from_directory_path(path).ok())
+				.map(String::from),
+		};
 		let hipcheck_version = self.session.hc_version().to_string();
 		let analyzed_at = Timestamp::from(self.session.started_at());
 		let passing = self.passing;
@@ -198,6 +215,7 @@ impl<'sess> ReportBuilder<'sess> {
 		let report = Report {
 			repo_name,
 			repo_head,
+			repo_uri,
 			hipcheck_version,
 			analyzed_at,
 			passing,
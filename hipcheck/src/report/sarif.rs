@@ -0,0 +1,434 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conversion of a `Report` to a SARIF 2.1.0 log.
+//!
+//! Each plugin is a rule, and each failing or errored analysis is a result of
+//! that rule. Only the parts of SARIF needed to describe a report are modeled
+//! here; see the [specification] for the rest.
+//!
+//! [specification]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use crate::{
+	error::Error,
+	report::{
+		aggregate::{AggregateReport, TargetOutcome},
+		Analysis, ErroredAnalysis, FailingAnalysis, Report,
+	},
+};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+const TOOL_NAME: &str = "Hipcheck";
+const TOOL_URI: &str = "https://mitre.github.io/hipcheck";
+
+/// A SARIF log, the top-level object of a SARIF file.
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+	#[serde(rename = "$schema")]
+	schema: &'static str,
+	version: &'static str,
+	runs: Vec<Run>,
+}
+
+/// A single run of Hipcheck.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+	tool: Tool,
+	invocations: Vec<Invocation>,
+	results: Vec<SarifResult>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	properties: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+	driver: ToolComponent,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolComponent {
+	name: &'static str,
+	version: String,
+	information_uri: &'static str,
+	rules: Vec<Rule>,
+}
+
+/// A plugin, which results are reported against.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+	id: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	short_description: Option<Message>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+	execution_successful: bool,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	tool_execution_notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Serialize)]
+struct Notification {
+	level: Level,
+	message: Message,
+}
+
+/// A failing or errored analysis.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+	rule_id: String,
+	rule_index: usize,
+	level: Level,
+	message: Message,
+	locations: Vec<Location>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	properties: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+	text: String,
+}
+
+impl Message {
+	fn new(text: impl Into<String>) -> Message {
+		Message { text: text.into() }
+	}
+}
+
+/// The repository analyzed. It isn't a file, so it's given as a logical location, along with
+/// where it came from as the artifact, if that's known.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	physical_location: Option<PhysicalLocation>,
+	logical_locations: Vec<LogicalLocation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+	artifact_location: ArtifactLocation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ArtifactLocation {
+	uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogicalLocation {
+	name: String,
+	fully_qualified_name: String,
+	kind: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Level {
+	Warning,
+	Error,
+}
+
+impl SarifLog {
+	/// Convert a report to a SARIF log.
+	pub fn from_report(report: &Report) -> SarifLog {
+		SarifLog::new(vec![Run::from_report(report)])
+	}
+
+	/// Convert a report on many targets to a SARIF log, with a run for each.
+	pub fn from_aggregate_report(report: &AggregateReport) -> SarifLog {
+		let runs = report.targets.iter().map(|target| match &target.outcome {
+			TargetOutcome::Analyzed { report } => Run::from_report(report),
+			TargetOutcome::Errored { error } => Run::failed(
+				format!("{}: {}", target.name, error.messages().join(": ")),
+				Level::Error,
+			),
+			TargetOutcome::Skipped { reason } => {
+				let mut run = Run::failed(format!("{}: {}", target.name, reason), Level::Warning);
+				// Skipping a target isn't a failure of Hipcheck.
+				run.invocations[0].execution_successful = true;
+				run
+			}
+		});
+
+		SarifLog::new(runs.collect())
+	}
+
+	/// Make a SARIF log for a run of Hipcheck that failed with an error.
+	pub fn from_error(err: &Error) -> SarifLog {
+		let text = err
+			.chain()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(": ");
+
+		SarifLog::new(vec![Run::failed(text, Level::Error)])
+	}
+
+	fn new(runs: Vec<Run>) -> SarifLog {
+		SarifLog {
+			schema: SARIF_SCHEMA,
+			version: SARIF_VERSION,
+			runs,
+		}
+	}
+}
+
+impl Run {
+	/// Make the run for a report on a single target.
+	fn from_report(report: &Report) -> Run {
+		let mut rules = Rules::default();
+		for analysis in report.passing_analyses() {
+			rules.insert(analysis);
+		}
+
+		let location = Location {
+			physical_location: report.repo_uri.clone().map(|uri| PhysicalLocation {
+				artifact_location: ArtifactLocation { uri },
+			}),
+			logical_locations: vec![LogicalLocation {
+				name: report.repo_name.to_string(),
+				fully_qualified_name: report.analyzed(),
+				kind: "module",
+			}],
+		};
+
+		let failing = report.failing_analyses().map(|failing| {
+			let rule_index = rules.insert(failing.analysis());
+			failing_result(failing, rule_index, &location)
+		});
+		let failing = failing.collect::<Vec<_>>();
+
+		let errored = report.errored_analyses().map(|errored| {
+			let rule_index = rules.insert_name(&errored.analysis.0, None);
+			errored_result(errored, rule_index, &location)
+		});
+		let errored = errored.collect::<Vec<_>>();
+
+		let recommendation = report.recommendation();
+		let properties = serde_json::json!({
+			"analyzedAt": report.analyzed_at,
+			"recommendation": recommendation.kind,
+			"riskScore": recommendation.risk_score,
+			"riskPolicy": recommendation.risk_policy,
+		});
+
+		Run {
+			tool: Tool {
+				driver: ToolComponent {
+					name: TOOL_NAME,
+					version: report.hipcheck_version.clone(),
+					information_uri: TOOL_URI,
+					rules: rules.into_rules(),
+				},
+			},
+			invocations: vec![Invocation {
+				execution_successful: true,
+				tool_execution_notifications: vec![],
+			}],
+			results: failing.into_iter().chain(errored).collect(),
+			properties: Some(properties),
+		}
+	}
+
+	/// Make a run with no results, which stopped with the given notification.
+	fn failed(text: String, level: Level) -> Run {
+		Run {
+			tool: Tool {
+				driver: ToolComponent {
+					name: TOOL_NAME,
+					version: env!("CARGO_PKG_VERSION").to_owned(),
+					information_uri: TOOL_URI,
+					rules: vec![],
+				},
+			},
+			invocations: vec![Invocation {
+				execution_successful: false,
+				tool_execution_notifications: vec![Notification {
+					level,
+					message: Message::new(text),
+				}],
+			}],
+			results: vec![],
+			properties: None,
+		}
+	}
+}
+
+/// The rules of a run, in the order they were first seen.
+#[derive(Default)]
+struct Rules(IndexMap<String, Option<Message>>);
+
+impl Rules {
+	/// Add the rule for an analysis if it's new, returning its index.
+	fn insert(&mut self, analysis: &Analysis) -> usize {
+		self.insert_name(&analysis.name, Some(analysis.explanation()))
+	}
+
+	fn insert_name(&mut self, name: &str, description: Option<String>) -> usize {
+		let entry = self.0.entry(name.to_owned());
+		let index = entry.index();
+		entry.or_insert_with(|| description.map(Message::new));
+		index
+	}
+
+	fn into_rules(self) -> Vec<Rule> {
+		self.0
+			.into_iter()
+			.map(|(id, short_description)| Rule {
+				id,
+				short_description,
+			})
+			.collect()
+	}
+}
+
+fn failing_result(
+	failing: &FailingAnalysis,
+	rule_index: usize,
+	location: &Location,
+) -> SarifResult {
+	let analysis = failing.analysis();
+
+	let mut text = analysis.statement();
+	for concern in failing.concerns() {
+		text.push_str("\n- ");
+		text.push_str(concern);
+	}
+
+	let properties = serde_json::json!({
+		"policy": analysis.policy_expr,
+		"concerns": failing.concerns,
+		"trace": failing.trace,
+	});
+
+	SarifResult {
+		rule_id: analysis.name.clone(),
+		rule_index,
+		level: Level::Warning,
+		message: Message::new(text),
+		locations: vec![location.clone()],
+		properties: Some(properties),
+	}
+}
+
+fn errored_result(
+	errored: &ErroredAnalysis,
+	rule_index: usize,
+	location: &Location,
+) -> SarifResult {
+	let mut text = errored.top_msg();
+	for msg in errored.source_msgs() {
+		text.push_str(": ");
+		text.push_str(&msg);
+	}
+
+	let properties = errored
+		.plugin_stderr()
+		.map(|stderr| serde_json::json!({ "pluginStderr": stderr }));
+
+	SarifResult {
+		rule_id: errored.analysis.0.clone(),
+		rule_index,
+		level: Level::Error,
+		message: Message::new(text),
+		locations: vec![location.clone()],
+		properties,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		hc_error,
+		policy_exprs::TraceEntry,
+		report::{AnalysisIdent, PassingAnalysis, Recommendation, RiskPolicy, RiskScore},
+	};
+	use chrono::DateTime;
+	use std::sync::Arc;
+
+	fn analysis(name: &str, passed: bool) -> Analysis {
+		Analysis::plugin(
+			name.to_owned(),
+			passed,
+			"(lte $ 0.02)".to_owned(),
+			format!("{name} explanation"),
+		)
+	}
+
+	#[test]
+	fn report_to_sarif() {
+		let failing = FailingAnalysis::new(
+			analysis("mitre/churn", false),
+			vec!["commit abc is large".to_owned()],
+			vec![TraceEntry {
+				expr: "(lte $ 0.02)".to_owned(),
+				value: "#f".to_owned(),
+			}],
+		)
+		.unwrap();
+		let errored = ErroredAnalysis::new(
+			AnalysisIdent("mitre/typo".to_owned()),
+			&hc_error!("plugin crashed"),
+		);
+		let report = Report {
+			repo_name: Arc::new("hipcheck".to_owned()),
+			repo_head: Arc::new("abc123".to_owned()),
+			repo_uri: Some("https://github.com/mitre/hipcheck".to_owned()),
+			hipcheck_version: "3.0.0".to_owned(),
+			analyzed_at: DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+				.unwrap()
+				.into(),
+			passing: vec![PassingAnalysis::new(analysis("mitre/activity", true))],
+			failing: vec![failing],
+			errored: vec![errored],
+			recommendation: Recommendation::is(RiskScore(0.5), RiskPolicy("(gt 0.5 $)".to_owned()))
+				.unwrap(),
+		};
+
+		let sarif = serde_json::to_value(SarifLog::from_report(&report)).unwrap();
+		let run = &sarif["runs"][0];
+
+		assert_eq!(sarif["version"], "2.1.0");
+		let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+		let rule_ids = rules.iter().map(|r| &r["id"]).collect::<Vec<_>>();
+		assert_eq!(rule_ids, ["mitre/activity", "mitre/churn", "mitre/typo"]);
+
+		let results = run["results"].as_array().unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0]["ruleId"], "mitre/churn");
+		assert_eq!(results[0]["ruleIndex"], 1);
+		assert_eq!(results[0]["level"], "warning");
+		assert_eq!(
+			results[0]["message"]["text"],
+			"'mitre/churn' failed, (lte $ 0.02)\n- commit abc is large"
+		);
+		assert_eq!(results[0]["properties"]["trace"][0]["value"], "#f");
+		assert_eq!(results[1]["ruleId"], "mitre/typo");
+		assert_eq!(results[1]["ruleIndex"], 2);
+		assert_eq!(results[1]["level"], "error");
+		assert_eq!(
+			results[1]["locations"][0]["logicalLocations"][0]["name"],
+			"hipcheck"
+		);
+		for result in results {
+			assert_eq!(
+				result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+				"https://github.com/mitre/hipcheck"
+			);
+		}
+	}
+}
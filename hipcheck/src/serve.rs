@@ -0,0 +1,730 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! `hc serve`, which runs Hipcheck as an HTTP service.
+//!
+//! Checks are queued and run one at a time on a worker thread, which keeps the
+//! plugins for the most recently used policy files running between checks,
+//! until the policy file changes. Plugin query results aren't kept between checks, since a branch
+//! may have moved since it was last checked; with the query cache, results
+//! for the same commit are still reused. Clones of remote repositories stay in
+//! the repo cache, so later checks of the same target only fetch new commits.
+//! Targets on the local filesystem are refused unless they're in a directory
+//! allowed with `--allow-local`.
+//!
+//! The service responds to:
+//!
+//! - `POST /check`: check a target, responding with its report once done.
+//! - `POST /jobs`: queue a check of a target, responding with the job's ID.
+//! - `GET /jobs/{id}`: get the status of a job, with its report once done.
+//! - `GET /jobs/{id}/events`: stream each change to a job's status as a line
+//!   of JSON, until it's done.
+//! - `GET /policies`: list the policies a check can choose between.
+
+use crate::{
+	cli::{self, Format},
+	error::{Context as _, Result},
+	hc_error,
+	plugin::PluginConnection,
+	report::ErrorReport,
+	session::SessionSetup,
+	shell::spinner_phase::set_phase_listener,
+	target::{TargetSeed, TargetSeedKind},
+};
+use axum::{
+	body::Body,
+	extract::{Path as UrlPath, State},
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+	routing::{get, post},
+	Router,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+	any::Any,
+	fs,
+	net::SocketAddr,
+	num::NonZeroUsize,
+	panic::{self, AssertUnwindSafe},
+	path::{Path, PathBuf},
+	result::Result as StdResult,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		mpsc::{self, Receiver, SyncSender, TrySendError},
+		Arc, Mutex,
+	},
+	thread,
+	time::SystemTime,
+};
+use tokio::sync::watch;
+use url::Url;
+
+/// How many finished jobs are remembered, so their status can still be requested.
+const FINISHED_JOBS_KEPT: usize = 256;
+
+/// How many policies' plugins are kept running between checks. The plugins
+/// for the policy used least recently are stopped to make room for another.
+const SETUPS_KEPT: usize = 4;
+
+/// The name of the policy given with `--policy`, used when a check doesn't choose one.
+const DEFAULT_POLICY: &str = "default";
+
+/// How the service is run, and how it runs checks.
+pub struct ServeConfig {
+	/// The address to listen on.
+	pub address: SocketAddr,
+
+	/// The most checks which can wait to be run at once.
+	pub queue_size: NonZeroUsize,
+
+	/// The policy files checks can choose between, by name.
+	pub policies: IndexMap<String, PathBuf>,
+
+	/// The Hipcheck home, holding the caches.
+	pub home_dir: Option<PathBuf>,
+
+	/// The maximum number of analyses to run at once for a check.
+	pub concurrency: NonZeroUsize,
+
+	/// Whether to use the query cache.
+	pub use_query_cache: bool,
+
+	/// How to connect to plugins.
+	pub plugin_connection: PluginConnection,
+
+	/// The directories local targets can be checked from. Local targets are
+	/// refused unless they're in one of them.
+	pub allowed_local_dirs: Vec<PathBuf>,
+}
+
+/// Find the policy files checks can choose between: each `.kdl` file in a
+/// directory, named by its file name without the extension, and the policy
+/// file given with `--policy`, named "default".
+pub fn find_policies(
+	dir: Option<&Path>,
+	default: Option<&Path>,
+) -> Result<IndexMap<String, PathBuf>> {
+	let mut policies = IndexMap::new();
+
+	if let Some(default) = default {
+		policies.insert(DEFAULT_POLICY.to_owned(), default.to_owned());
+	}
+
+	if let Some(dir) = dir {
+		let entries = fs::read_dir(dir)
+			.with_context(|| format!("failed to read policy directory '{}'", dir.display()))?;
+
+		let mut paths = entries
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<StdResult<Vec<_>, _>>()?;
+		paths.sort();
+
+		for path in paths {
+			if path.extension().and_then(|ext| ext.to_str()) != Some("kdl") {
+				continue;
+			}
+			let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+				continue;
+			};
+			if policies.contains_key(name) {
+				return Err(hc_error!(
+					"policy '{}' in '{}' has the same name as another policy",
+					name,
+					dir.display()
+				));
+			}
+			policies.insert(name.to_owned(), path.clone());
+		}
+	}
+
+	if policies.is_empty() {
+		return Err(hc_error!(
+			"no policy files to check targets with. Please provide a policy file or a directory of them."
+		));
+	}
+
+	Ok(policies)
+}
+
+/// Run the service until it's stopped.
+pub fn serve(config: ServeConfig) -> Result<()> {
+	if config.use_query_cache && config.home_dir.is_none() {
+		return Err(hc_error!(
+			"the query cache needs a cache directory; set one with --cache or $HC_CACHE"
+		));
+	}
+
+	let (queue, queued) = mpsc::sync_channel(config.queue_size.get());
+
+	// The phases of each check are reported as the status of its job.
+	let current = Arc::new(Mutex::new(None::<Arc<watch::Sender<JobStatus>>>));
+	let listener_current = Arc::clone(&current);
+	set_phase_listener(Box::new(move |phase, status| {
+		if let Some(job) = listener_current.lock().unwrap().as_ref() {
+			job.send_replace(JobStatus::Running {
+				phase: phase.to_owned(),
+				status: status.to_owned(),
+			});
+		}
+	}));
+
+	let worker = Worker {
+		home_dir: config.home_dir,
+		concurrency: config.concurrency,
+		use_query_cache: config.use_query_cache,
+		plugin_connection: config.plugin_connection,
+		current,
+	};
+	thread::Builder::new()
+		.name("hc-serve-worker".to_owned())
+		.spawn(move || worker.run(queued))
+		.context("failed to start worker thread")?;
+
+	let allowed_local_dirs = config
+		.allowed_local_dirs
+		.iter()
+		.map(|dir| {
+			fs::canonicalize(dir).with_context(|| {
+				format!("failed to find allowed local directory '{}'", dir.display())
+			})
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	let service = Arc::new(Service {
+		queue,
+		jobs: Mutex::new(IndexMap::new()),
+		next_id: AtomicU64::new(1),
+		policies: config.policies,
+		allowed_local_dirs,
+	});
+
+	let app = Router::new()
+		.route("/check", post(check))
+		.route("/jobs", post(submit_job))
+		.route("/jobs/:id", get(job_status))
+		.route("/jobs/:id/events", get(job_events))
+		.route("/policies", get(list_policies))
+		.with_state(service);
+
+	let runtime = tokio::runtime::Builder::new_multi_thread()
+		.enable_all()
+		.build()
+		.context("failed to start the service's runtime")?;
+
+	runtime.block_on(async {
+		let listener = tokio::net::TcpListener::bind(config.address)
+			.await
+			.with_context(|| format!("failed to listen on {}", config.address))?;
+		log::info!("listening on http://{}", config.address);
+		axum::serve(listener, app)
+			.await
+			.context("the service stopped unexpectedly")
+	})
+}
+
+/// A request to check a target.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckRequest {
+	/// The target, as given to `hc check`.
+	target: String,
+
+	/// The type of the target, if it can't be inferred.
+	#[serde(default, rename = "type")]
+	target_type: Option<String>,
+
+	/// The ref of the target to analyze.
+	#[serde(default, rename = "ref")]
+	refspec: Option<String>,
+
+	/// The name of the policy to check the target with.
+	#[serde(default)]
+	policy: Option<String>,
+}
+
+impl CheckRequest {
+	/// Resolve the requested target the same way `hc check` would.
+	fn target_seed(&self) -> Result<TargetSeed> {
+		let mut args = vec![];
+		if let Some(target_type) = &self.target_type {
+			args.extend(["-t".to_owned(), target_type.clone()]);
+		}
+		if let Some(refspec) = &self.refspec {
+			args.extend(["--ref".to_owned(), refspec.clone()]);
+		}
+		args.push(self.target.clone());
+		cli::target_seed_from_args(args)
+	}
+}
+
+/// The status of a requested check.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum JobStatus {
+	/// Waiting for earlier checks to finish.
+	Queued,
+
+	/// Being checked, and in the given phase.
+	Running { phase: String, status: String },
+
+	/// Checked, producing a report.
+	Finished { report: Arc<Value> },
+
+	/// Checking the target failed.
+	Errored { error: Arc<ErrorReport> },
+}
+
+impl JobStatus {
+	fn is_done(&self) -> bool {
+		matches!(self, JobStatus::Finished { .. } | JobStatus::Errored { .. })
+	}
+}
+
+/// A queued check.
+struct Job {
+	target: TargetSeed,
+	policy: PathBuf,
+	status: Arc<watch::Sender<JobStatus>>,
+}
+
+/// The state shared by the service's request handlers.
+struct Service {
+	queue: SyncSender<Job>,
+	jobs: Mutex<IndexMap<u64, Arc<watch::Sender<JobStatus>>>>,
+	next_id: AtomicU64,
+	policies: IndexMap<String, PathBuf>,
+	allowed_local_dirs: Vec<PathBuf>,
+}
+
+impl Service {
+	/// Queue a check, getting the ID of its job and a way to watch its status.
+	fn submit(&self, body: &str) -> StdResult<(u64, watch::Receiver<JobStatus>), ApiError> {
+		let request: CheckRequest = serde_json::from_str(body)
+			.map_err(|e| ApiError::bad_request(format!("invalid check request: {e}")))?;
+
+		let policy_name = request.policy.as_deref().unwrap_or(DEFAULT_POLICY);
+		let policy = self.policies.get(policy_name).ok_or_else(|| {
+			ApiError::bad_request(match &request.policy {
+				Some(name) => format!("unknown policy '{name}'"),
+				None => "no default policy, so a policy must be chosen".to_owned(),
+			})
+		})?;
+
+		let target = request
+			.target_seed()
+			.map_err(|e| ApiError::bad_request(format!("invalid target: {e}")))?;
+		self.check_local_path(&target.kind)?;
+
+		let (status, watcher) = watch::channel(JobStatus::Queued);
+		let status = Arc::new(status);
+
+		let job = Job {
+			target,
+			policy: policy.clone(),
+			status: Arc::clone(&status),
+		};
+		self.queue.try_send(job).map_err(|e| match e {
+			TrySendError::Full(_) => ApiError::new(
+				StatusCode::SERVICE_UNAVAILABLE,
+				"too many checks are queued, try again later",
+			),
+			TrySendError::Disconnected(_) => ApiError::new(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				"the service can no longer run checks",
+			),
+		})?;
+
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let mut jobs = self.jobs.lock().unwrap();
+		jobs.insert(id, status);
+
+		// Forget the oldest finished jobs once too many are remembered.
+		let finished = jobs
+			.iter()
+			.filter(|(_, status)| status.borrow().is_done())
+			.map(|(id, _)| *id)
+			.collect::<Vec<_>>();
+		for id in finished
+			.iter()
+			.take(finished.len().saturating_sub(FINISHED_JOBS_KEPT))
+		{
+			jobs.shift_remove(id);
+		}
+
+		Ok((id, watcher))
+	}
+
+	/// Refuse a target read from the local filesystem unless it's in one of
+	/// the directories local targets are allowed from.
+	fn check_local_path(&self, kind: &TargetSeedKind) -> StdResult<(), ApiError> {
+		let Some(path) = local_path(kind) else {
+			return Ok(());
+		};
+		let allowed = fs::canonicalize(&path).is_ok_and(|path| {
+			self.allowed_local_dirs
+				.iter()
+				.any(|dir| path.starts_with(dir))
+		});
+		match allowed {
+			true => Ok(()),
+			false => Err(ApiError::new(
+				StatusCode::FORBIDDEN,
+				format!(
+					"local target '{}' isn't in a directory allowed with --allow-local",
+					path.display()
+				),
+			)),
+		}
+	}
+
+	/// Watch the status of a job.
+	fn watch(&self, id: u64) -> StdResult<watch::Receiver<JobStatus>, ApiError> {
+		self.jobs
+			.lock()
+			.unwrap()
+			.get(&id)
+			.map(|status| status.subscribe())
+			.ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no job {id}")))
+	}
+}
+
+/// Get the path a target is read from, if it's on the local filesystem.
+fn local_path(kind: &TargetSeedKind) -> Option<PathBuf> {
+	match kind {
+		TargetSeedKind::LocalRepo(repo) => Some(repo.path.clone()),
+		TargetSeedKind::Sbom(sbom) => Some(sbom.path.clone()),
+		TargetSeedKind::Lockfile(lockfile) => Some(lockfile.path.clone()),
+		TargetSeedKind::RemoteRepo(remote) => local_url_path(&remote.url),
+		TargetSeedKind::MavenPackage(package) => local_url_path(&package.url),
+		TargetSeedKind::CommitRange(range) => local_path(&range.repo),
+		TargetSeedKind::Package(_) | TargetSeedKind::PullRequest(_) => None,
+	}
+}
+
+/// Get the path a `file:` URL points to. Other URLs aren't local.
+fn local_url_path(url: &Url) -> Option<PathBuf> {
+	match url.scheme() {
+		"file" => Some(
+			url.to_file_path()
+				.unwrap_or_else(|_| PathBuf::from(url.path())),
+		),
+		_ => None,
+	}
+}
+
+/// Runs queued checks one at a time, keeping the plugins started for the
+/// most recently used policies running for later checks with the same policy.
+struct Worker {
+	home_dir: Option<PathBuf>,
+	concurrency: NonZeroUsize,
+	use_query_cache: bool,
+	plugin_connection: PluginConnection,
+	current: Arc<Mutex<Option<Arc<watch::Sender<JobStatus>>>>>,
+}
+
+impl Worker {
+	fn run(self, queued: Receiver<Job>) {
+		let mut setups: IndexMap<PathBuf, (SessionSetup, PolicyStamp)> = IndexMap::new();
+
+		for job in queued {
+			*self.current.lock().unwrap() = Some(Arc::clone(&job.status));
+			let report = self.run_job(&mut setups, &job);
+			*self.current.lock().unwrap() = None;
+
+			job.status.send_replace(match report {
+				Ok(report) => JobStatus::Finished {
+					report: Arc::new(report),
+				},
+				Err(error) => JobStatus::Errored {
+					error: Arc::new(ErrorReport::from(&error)),
+				},
+			});
+		}
+	}
+
+	/// Run a check, reusing the setup loaded for its policy by an earlier
+	/// check if the policy file hasn't changed since.
+	fn run_job(
+		&self,
+		setups: &mut IndexMap<PathBuf, (SessionSetup, PolicyStamp)>,
+		job: &Job,
+	) -> Result<Value> {
+		let stamp = PolicyStamp::read(&job.policy)?;
+		let mut setup = setups
+			.shift_remove(&job.policy)
+			.filter(|(_, loaded)| *loaded == stamp)
+			.map(|(setup, _)| setup);
+		if let Some(setup) = &setup {
+			setup.clear_query_results();
+		}
+
+		// A panic is reported as the check's error rather than stopping the
+		// worker. The setup is dropped with it, since its plugins may be left
+		// in any state.
+		let report = panic::catch_unwind(AssertUnwindSafe(|| {
+			crate::run(
+				job.target.clone(),
+				None,
+				self.home_dir.clone(),
+				Some(job.policy.clone()),
+				Format::Json,
+				self.concurrency,
+				self.use_query_cache,
+				self.plugin_connection,
+				&mut setup,
+			)
+		}))
+		.map_err(|panic| hc_error!("the check panicked: {}", panic_message(panic.as_ref())))?;
+
+		// The setup is put back as the most recently used, and the least
+		// recently used are dropped, stopping their plugins, once too many
+		// are kept.
+		if let Some(setup) = setup {
+			setups.insert(job.policy.clone(), (setup, stamp));
+		}
+		while setups.len() > SETUPS_KEPT {
+			setups.shift_remove_index(0);
+		}
+
+		Ok(serde_json::to_value(report?)?)
+	}
+}
+
+/// When a policy file was last modified and a digest of its contents, to tell
+/// whether what was loaded from it is out of date.
+#[derive(Debug, PartialEq, Eq)]
+struct PolicyStamp {
+	modified: Option<SystemTime>,
+	digest: String,
+}
+
+impl PolicyStamp {
+	fn read(path: &Path) -> Result<PolicyStamp> {
+		let contents = fs::read(path)
+			.with_context(|| format!("failed to read policy file '{}'", path.display()))?;
+		let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+		Ok(PolicyStamp {
+			modified,
+			digest: sha256::digest(contents.as_slice()),
+		})
+	}
+}
+
+/// Get the message a panic was started with, if it has one.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+	panic
+		.downcast_ref::<&str>()
+		.copied()
+		.or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+		.unwrap_or("no message")
+}
+
+/// An error responding to a request, sent as a JSON object with an `error` message.
+struct ApiError {
+	status: StatusCode,
+	message: String,
+}
+
+impl ApiError {
+	fn new(status: StatusCode, message: impl Into<String>) -> ApiError {
+		ApiError {
+			status,
+			message: message.into(),
+		}
+	}
+
+	fn bad_request(message: impl Into<String>) -> ApiError {
+		ApiError::new(StatusCode::BAD_REQUEST, message)
+	}
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		json_response(self.status, &json!({ "error": self.message }))
+	}
+}
+
+fn json_response(status: StatusCode, value: &impl Serialize) -> Response {
+	match serde_json::to_string(value) {
+		Ok(body) => (status, [(header::CONTENT_TYPE, "application/json")], body).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+	}
+}
+
+/// Check a target, responding with its report once done.
+async fn check(State(service): State<Arc<Service>>, body: String) -> StdResult<Response, ApiError> {
+	let (_, mut status) = service.submit(&body)?;
+
+	let done = status
+		.wait_for(JobStatus::is_done)
+		.await
+		.map_err(|_| {
+			ApiError::new(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				"the check stopped without finishing",
+			)
+		})?
+		.clone();
+
+	Ok(match done {
+		JobStatus::Finished { report } => json_response(StatusCode::OK, &report),
+		JobStatus::Errored { error } => json_response(
+			StatusCode::UNPROCESSABLE_ENTITY,
+			&json!({ "error": error.messages().join(": ") }),
+		),
+		JobStatus::Queued | JobStatus::Running { .. } => unreachable!("the job is done"),
+	})
+}
+
+/// Queue a check of a target, responding with the ID of its job.
+async fn submit_job(
+	State(service): State<Arc<Service>>,
+	body: String,
+) -> StdResult<Response, ApiError> {
+	let (id, _) = service.submit(&body)?;
+	Ok(json_response(StatusCode::ACCEPTED, &json!({ "id": id })))
+}
+
+/// Get the status of a job.
+async fn job_status(
+	State(service): State<Arc<Service>>,
+	UrlPath(id): UrlPath<u64>,
+) -> StdResult<Response, ApiError> {
+	let status = service.watch(id)?.borrow().clone();
+	Ok(json_response(StatusCode::OK, &status))
+}
+
+/// Stream each change to the status of a job as a line of JSON, until it's done.
+async fn job_events(
+	State(service): State<Arc<Service>>,
+	UrlPath(id): UrlPath<u64>,
+) -> StdResult<Response, ApiError> {
+	let mut status = service.watch(id)?;
+
+	let events = async_stream::stream! {
+		loop {
+			let current = status.borrow_and_update().clone();
+			yield serde_json::to_string(&current).map(|line| line + "\n");
+			if current.is_done() || status.changed().await.is_err() {
+				break;
+			}
+		}
+	};
+
+	Ok((
+		[(header::CONTENT_TYPE, "application/x-ndjson")],
+		Body::from_stream(events),
+	)
+		.into_response())
+}
+
+/// List the policies a check can choose between.
+async fn list_policies(State(service): State<Arc<Service>>) -> Response {
+	let names = service.policies.keys().collect::<Vec<_>>();
+	json_response(StatusCode::OK, &json!({ "policies": names }))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_find_policies() {
+		let dir = tempfile::tempdir().unwrap();
+		fs::write(dir.path().join("strict.kdl"), "").unwrap();
+		fs::write(dir.path().join("lenient.kdl"), "").unwrap();
+		fs::write(dir.path().join("README.md"), "").unwrap();
+		let default = PathBuf::from("Hipcheck.kdl");
+
+		let policies = find_policies(Some(dir.path()), Some(&default)).unwrap();
+		assert_eq!(
+			policies.keys().collect::<Vec<_>>(),
+			["default", "lenient", "strict"]
+		);
+		assert_eq!(policies["strict"], dir.path().join("strict.kdl"));
+
+		assert!(find_policies(None, None).is_err());
+	}
+
+	#[test]
+	fn test_check_request_target_seed() {
+		let request: CheckRequest = serde_json::from_str(
+			r#"{ "target": "chalk@5.3.0", "type": "npm", "policy": "strict" }"#,
+		)
+		.unwrap();
+		let seed = request.target_seed().unwrap();
+		assert!(matches!(seed.kind, TargetSeedKind::Package(..)));
+
+		let request: CheckRequest = serde_json::from_str(
+			r#"{ "target": "https://github.com/mitre/hipcheck", "ref": "v3.0.0" }"#,
+		)
+		.unwrap();
+		let seed = request.target_seed().unwrap();
+		assert_eq!(seed.refspec.as_deref(), Some("v3.0.0"));
+
+		assert!(serde_json::from_str::<CheckRequest>(r#"{ "targets": [] }"#).is_err());
+	}
+
+	#[test]
+	fn test_local_targets_need_allowed_dir() {
+		let allowed = tempfile::tempdir().unwrap();
+		let other = tempfile::tempdir().unwrap();
+		let (queue, _queued) = mpsc::sync_channel(1);
+		let service = Service {
+			queue,
+			jobs: Mutex::new(IndexMap::new()),
+			next_id: AtomicU64::new(1),
+			policies: IndexMap::new(),
+			allowed_local_dirs: vec![fs::canonicalize(allowed.path()).unwrap()],
+		};
+		let local_repo = |path: &Path| {
+			TargetSeedKind::LocalRepo(crate::target::LocalGitRepo {
+				path: path.to_owned(),
+				git_ref: "HEAD".to_owned(),
+				base_ref: None,
+			})
+		};
+
+		assert!(service
+			.check_local_path(&local_repo(allowed.path()))
+			.is_ok());
+		let refused = service
+			.check_local_path(&local_repo(other.path()))
+			.unwrap_err();
+		assert_eq!(refused.status, StatusCode::FORBIDDEN);
+		let escaped = allowed
+			.path()
+			.join("..")
+			.join(other.path().file_name().unwrap());
+		assert!(service.check_local_path(&local_repo(&escaped)).is_err());
+
+		let file_remote = TargetSeedKind::RemoteRepo(crate::target::RemoteGitRepo {
+			url: Url::from_directory_path(other.path()).unwrap(),
+			known_remote: None,
+		});
+		assert!(service.check_local_path(&file_remote).is_err());
+		let request: CheckRequest =
+			serde_json::from_str(r#"{ "target": "chalk@5.3.0", "type": "npm" }"#).unwrap();
+		let seed = request.target_seed().unwrap();
+		assert!(service.check_local_path(&seed.kind).is_ok());
+	}
+
+	#[test]
+	fn test_policy_stamp_changes_with_policy() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("Hipcheck.kdl");
+		fs::write(&path, "plugins {}").unwrap();
+		let stamp = PolicyStamp::read(&path).unwrap();
+		assert_eq!(PolicyStamp::read(&path).unwrap(), stamp);
+
+		fs::write(&path, "plugins {}\nanalyze {}").unwrap();
+		assert_ne!(PolicyStamp::read(&path).unwrap(), stamp);
+	}
+
+	#[test]
+	fn test_panic_message() {
+		let panic = panic::catch_unwind(|| panic!("plugin {} went away", "mitre/git")).unwrap_err();
+		assert_eq!(panic_message(panic.as_ref()), "plugin mitre/git went away");
+	}
+}
@@ -6,6 +6,7 @@ use crate::{
 	error::{Context as _, Result},
 	hc_error,
 	session::pm::{extract_repo_for_maven, extract_repo_for_npm, extract_repo_for_pypi},
+	target::Dependency,
 };
 use cyclonedx_bom::prelude::*;
 use packageurl::PackageUrl;
@@ -15,26 +16,54 @@ use url::Url;
 /// Extract the first compatible package download location from a
 /// CycloneDX document
 pub fn extract_cyclonedx_download_url(filepath: &str) -> Result<Url> {
+	extract_download_url(parse_bom(filepath)?)
+}
+
+/// Extract every component of a CycloneDX document, starting with the package
+/// the document describes, and including components nested in other components.
+pub fn extract_cyclonedx_components(filepath: &str) -> Result<Vec<Dependency>> {
+	let bom = parse_bom(filepath)?;
+	let mut components = vec![];
+
+	if let Some(root) = bom.metadata.and_then(|metadata| metadata.component) {
+		collect_components(root, &mut components);
+	}
+	for component in bom.components.into_iter().flat_map(|c| c.0) {
+		collect_components(component, &mut components);
+	}
+
+	Ok(components)
+}
+
+fn collect_components(component: Component, components: &mut Vec<Dependency>) {
+	components.push(Dependency {
+		name: component.name.to_string(),
+		version: component.version.map(|version| version.to_string()),
+		locator: component.purl.map(|purl| purl.to_string()),
+	});
+	for child in component.components.into_iter().flat_map(|c| c.0) {
+		collect_components(child, components);
+	}
+}
+
+/// Parse and validate a CycloneDX document in either JSON or XML format.
+fn parse_bom(filepath: &str) -> Result<Bom> {
 	let contents = std::fs::read_to_string(filepath)?;
 
-	if filepath.contains(".json") {
-		let bom = Bom::parse_from_json(contents.as_bytes()).map_err(|_| {
+	let bom = if filepath.contains(".json") {
+		Bom::parse_from_json(contents.as_bytes()).map_err(|_| {
 			hc_error!("CycloneDX JSON file is corrupt or otherwise cannot be parsed. It may be in an incompatble CycloneDX format (only v. 1.3 - 1.5 supported)")
-		})?;
-		if bom.validate().passed() {
-			extract_download_url(bom)
-		} else {
-			Err(hc_error!("CycloneDX file is not a valid SBOM"))
-		}
+		})?
 	} else if filepath.contains(".xml") {
-		let bom = parse_from_xml(contents)?;
-		if bom.validate().passed() {
-			extract_download_url(bom)
-		} else {
-			Err(hc_error!("CycloneDX file is not a valid SBOM"))
-		}
+		parse_from_xml(contents)?
 	} else {
-		Err(hc_error!("CycloneDX file is not in a comatible format"))
+		return Err(hc_error!("CycloneDX file is not in a comatible format"));
+	};
+
+	if bom.validate().passed() {
+		Ok(bom)
+	} else {
+		Err(hc_error!("CycloneDX file is not a valid SBOM"))
 	}
 }
 
@@ -182,4 +211,25 @@ mod tests {
 			"https://github.com/juice-shop/juice-shop.git".to_string()
 		);
 	}
+
+	#[test]
+	fn test_extract_components_from_cyclonedx_json() {
+		let manifest = env!("CARGO_MANIFEST_DIR");
+		let path: PathBuf = [manifest, "src", "session", "tests", "juiceshop_bom.json"]
+			.iter()
+			.collect();
+		let json = path.to_str().unwrap();
+		let components = extract_cyclonedx_components(json).unwrap();
+		let locators = components
+			.iter()
+			.map(|c| c.locator.as_deref())
+			.collect::<Vec<_>>();
+		assert_eq!(
+			locators,
+			vec![
+				Some("pkg:github/juice-shop/juice-shop"),
+				Some("pkg:npm/body-parser@1.19.0")
+			]
+		);
+	}
 }
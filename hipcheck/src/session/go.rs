@@ -0,0 +1,562 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for finding the source repository of a Go module.
+//!
+//! Modules hosted on GitHub are found from the module path alone. Otherwise,
+//! the module proxy is asked for the version's origin, and failing that, the
+//! `go-import` meta tag served for the module path is used, the same as
+//! `go get` would. `GOPROXY`, `GOPRIVATE`, and `GONOPROXY` decide which of
+//! those lookups are allowed, as they do for `go`.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	util::http::agent,
+};
+use regex::Regex;
+use serde_json::Value;
+use std::{env, fs, io};
+use url::Url;
+
+/// The proxies used when `GOPROXY` isn't set.
+const DEFAULT_GO_PROXY: &str = "https://proxy.golang.org,direct";
+
+/// Function to extract repo URL for a Go module given a module path and version
+pub fn extract_repo_for_go(module: &str, version: &str) -> Result<Url> {
+	extract_repo_for_go_with_proxy(&GoProxy::from_env()?, module, version)
+}
+
+fn extract_repo_for_go_with_proxy(proxy: &GoProxy, module: &str, version: &str) -> Result<Url> {
+	if let Some(url) = repo_from_module_path(module) {
+		return Ok(url);
+	}
+
+	// Private modules are always fetched directly.
+	let direct = [GoProxyEntry::Direct];
+	let entries = match proxy.is_private(module) {
+		true => &direct[..],
+		false => &proxy.entries[..],
+	};
+
+	let mut last_error = None;
+	for entry in entries {
+		let (url, fall_through) = match entry {
+			GoProxyEntry::Proxy { url, fall_through } => (url, *fall_through),
+			GoProxyEntry::Direct => return go_get_repo(proxy, module),
+		};
+
+		let info = match proxy.info(url, module, version) {
+			Ok(Some(info)) => info,
+			// The proxy doesn't have the module, so the next entry is tried.
+			Ok(None) => continue,
+			Err(e) if fall_through => {
+				last_error = Some(e);
+				continue;
+			}
+			Err(e) => {
+				return Err(e)
+					.with_context(|| format!("request to Go module proxy for '{}' failed", module))
+			}
+		};
+
+		if let Some(url) = info.pointer("/Origin/URL").and_then(Value::as_str) {
+			return Url::parse(url).context("invalid repository URL from Go module proxy");
+		}
+
+		// Older module versions were cached by the proxy without their origin.
+		if !entries.contains(&GoProxyEntry::Direct) {
+			return Err(hc_error!(
+				"can't find the repository of Go module '{}' without fetching it directly, which GOPROXY doesn't allow",
+				module
+			));
+		}
+		return go_get_repo(proxy, module);
+	}
+
+	match last_error {
+		Some(e) => {
+			Err(e).with_context(|| format!("request to Go module proxy for '{}' failed", module))
+		}
+		None => Err(hc_error!(
+			"Go module '{}' wasn't found by any proxy GOPROXY allows",
+			module
+		)),
+	}
+}
+
+/// Find a module's repository from the `go-import` meta tag served for its path.
+fn go_get_repo(proxy: &GoProxy, module: &str) -> Result<Url> {
+	let page = proxy
+		.go_get_page(module)
+		.context("request for Go module's go-import meta tag failed")?
+		.ok_or_else(|| hc_error!("no go-get page found for Go module '{}'", module))?;
+
+	find_go_import_repo(&page, module)?
+		.ok_or_else(|| hc_error!("no git repository found for Go module '{}'", module))
+}
+
+/// Get the repository of a module on GitHub, whose path starts with the owner
+/// and name of its repository.
+///
+/// Other hosts, like GitLab, allow repos nested in groups, so the module path
+/// alone doesn't say where its repository ends.
+fn repo_from_module_path(module: &str) -> Option<Url> {
+	let mut segments = module.split('/');
+	let host @ "github.com" = segments.next()? else {
+		return None;
+	};
+	let owner = segments.next()?;
+	let repo = segments.next()?;
+	Url::parse(&format!("https://{}/{}/{}", host, owner, repo)).ok()
+}
+
+/// Find the git repository named by the `go-import` meta tag of a go-get page
+/// whose prefix matches the module path.
+fn find_go_import_repo(page: &str, module: &str) -> Result<Option<Url>> {
+	let re = Regex::new(r#"<meta\s+name="go-import"\s+content="([^"]+)""#)
+		.context("failed to build go-import regex")?;
+
+	for captures in re.captures_iter(page) {
+		let mut fields = captures[1].split_whitespace();
+		let (Some(prefix), Some("git"), Some(repo)) = (fields.next(), fields.next(), fields.next())
+		else {
+			continue;
+		};
+
+		if module == prefix || module.starts_with(&format!("{}/", prefix)) {
+			return Url::parse(repo)
+				.map(Some)
+				.context("invalid repository URL in go-import meta tag");
+		}
+	}
+
+	Ok(None)
+}
+
+/// Where module info may be looked up, as configured for `go`.
+struct GoProxy {
+	/// The entries of `GOPROXY` before any `off`, in the order they're tried.
+	entries: Vec<GoProxyEntry>,
+
+	/// Patterns of module path prefixes from `GONOPROXY`, or `GOPRIVATE` if
+	/// it isn't set, which are always fetched directly.
+	private: Vec<String>,
+
+	/// Gets the contents of a URL, or `None` if there's nothing there.
+	fetch: fn(&Url) -> Result<Option<String>>,
+}
+
+/// A place `GOPROXY` lists for looking up modules.
+#[derive(Debug, PartialEq)]
+enum GoProxyEntry {
+	/// A proxy, which may be a local directory laid out like one. The next
+	/// entry is tried if the proxy doesn't have the module, or after any error
+	/// if the proxy is followed by `|` rather than `,`.
+	Proxy { url: Url, fall_through: bool },
+
+	/// Fetching the module directly from its host. Entries after this one are
+	/// never tried.
+	Direct,
+}
+
+impl GoProxy {
+	fn from_env() -> Result<GoProxy> {
+		let goproxy = env::var("GOPROXY").ok();
+		let private = env::var("GONOPROXY")
+			.ok()
+			.filter(|v| !v.is_empty())
+			.or_else(|| env::var("GOPRIVATE").ok());
+
+		GoProxy::new(goproxy.as_deref(), private.as_deref().unwrap_or_default())
+	}
+
+	/// Use the proxies listed in `goproxy` in order, like `go` does, up to
+	/// `direct` or `off`.
+	fn new(goproxy: Option<&str>, private: &str) -> Result<GoProxy> {
+		let goproxy = goproxy
+			.filter(|v| !v.trim().is_empty())
+			.unwrap_or(DEFAULT_GO_PROXY);
+
+		let mut entries = vec![];
+		let mut rest = goproxy;
+		loop {
+			let (entry, separator) = match rest.find([',', '|']) {
+				Some(i) => (&rest[..i], Some(&rest[i..=i])),
+				None => (rest, None),
+			};
+			match entry.trim() {
+				"" => (),
+				"off" => break,
+				"direct" => {
+					entries.push(GoProxyEntry::Direct);
+					break;
+				}
+				entry => {
+					let url = Url::parse(&format!("{}/", entry.trim_end_matches('/')))
+						.with_context(|| format!("invalid Go module proxy URL '{}'", entry))?;
+					entries.push(GoProxyEntry::Proxy {
+						url,
+						fall_through: separator == Some("|"),
+					});
+				}
+			}
+			let Some(separator) = separator else {
+				break;
+			};
+			rest = &rest[entry.len() + separator.len()..];
+		}
+
+		let private = private
+			.split(',')
+			.map(str::trim)
+			.filter(|pattern| !pattern.is_empty())
+			.map(str::to_owned)
+			.collect();
+
+		Ok(GoProxy {
+			entries,
+			private,
+			fetch,
+		})
+	}
+
+	/// Whether a module matches a `GONOPROXY` or `GOPRIVATE` pattern.
+	fn is_private(&self, module: &str) -> bool {
+		self.private
+			.iter()
+			.any(|pattern| matches_path_prefix(pattern, module))
+	}
+
+	/// Get a proxy's info for a version of a module, or its latest version,
+	/// or `None` if the proxy doesn't have it.
+	fn info(&self, proxy: &Url, module: &str, version: &str) -> Result<Option<Value>> {
+		let path = match version {
+			"no version" => format!("{}/@latest", escape_module_path(module)),
+			_ => format!("{}/@v/{}.info", escape_module_path(module), version),
+		};
+		let url = proxy
+			.join(&path)
+			.with_context(|| format!("invalid Go module proxy path '{}'", path))?;
+
+		let Some(contents) = (self.fetch)(&url)? else {
+			return Ok(None);
+		};
+		serde_json::from_str(&contents)
+			.map(Some)
+			.context("Go module proxy response isn't valid JSON")
+	}
+
+	/// Get the page `go get` reads a module's `go-import` meta tag from, or
+	/// `None` if there isn't one.
+	fn go_get_page(&self, module: &str) -> Result<Option<String>> {
+		let url = Url::parse(&format!("https://{}?go-get=1", module))
+			.with_context(|| format!("invalid Go module path '{}'", module))?;
+		(self.fetch)(&url)
+	}
+}
+
+/// Get the contents of a local file or a remote URL, or `None` if the file
+/// doesn't exist or the server responds that it's not found or gone, which
+/// proxies use to say they don't have a module.
+fn fetch(url: &Url) -> Result<Option<String>> {
+	match url.scheme() {
+		"file" => {
+			let file = url
+				.to_file_path()
+				.map_err(|_| hc_error!("invalid Go module proxy path '{}'", url))?;
+			match fs::read_to_string(&file) {
+				Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+				contents => contents
+					.map(Some)
+					.with_context(|| format!("failed to read '{}'", file.display())),
+			}
+		}
+		_ => match agent::agent().get(url.as_str()).call() {
+			Err(ureq::Error::Status(404 | 410, _)) => Ok(None),
+			response => response?
+				.into_string()
+				.map(Some)
+				.with_context(|| format!("can't read response from '{}'", url)),
+		},
+	}
+}
+
+/// Whether a `GOPRIVATE`-style glob pattern matches a prefix of a module path,
+/// element by element.
+fn matches_path_prefix(pattern: &str, module: &str) -> bool {
+	let mut module = module.split('/');
+	pattern
+		.split('/')
+		.all(|p| module.next().is_some_and(|m| matches_glob(p, m)))
+}
+
+/// Whether a path element matches a glob of `*` and `?` wildcards.
+fn matches_glob(pattern: &str, text: &str) -> bool {
+	match pattern.chars().next() {
+		None => text.is_empty(),
+		Some('*') => {
+			let rest = &pattern[1..];
+			text.char_indices()
+				.map(|(i, _)| i)
+				.chain([text.len()])
+				.any(|i| matches_glob(rest, &text[i..]))
+		}
+		Some(p) => {
+			let mut chars = text.chars();
+			match chars.next() {
+				Some(t) if p == '?' || p == t => {
+					matches_glob(&pattern[p.len_utf8()..], chars.as_str())
+				}
+				_ => false,
+			}
+		}
+	}
+}
+
+/// Escape a module path for a proxy, which replaces each uppercase letter with
+/// an exclamation mark followed by the letter's lowercase.
+fn escape_module_path(module: &str) -> String {
+	let mut escaped = String::with_capacity(module.len());
+	for c in module.chars() {
+		if c.is_ascii_uppercase() {
+			escaped.push('!');
+			escaped.push(c.to_ascii_lowercase());
+		} else {
+			escaped.push(c);
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+
+	fn local_proxy_url() -> Url {
+		let manifest = env!("CARGO_MANIFEST_DIR");
+		let path: PathBuf = [manifest, "src", "session", "tests", "goproxy"]
+			.iter()
+			.collect();
+		Url::from_directory_path(path).unwrap()
+	}
+
+	fn local_proxy() -> GoProxy {
+		GoProxy::new(Some(local_proxy_url().as_str()), "").unwrap()
+	}
+
+	/// Serves the go-get page of `gitlab.com/group/subgroup/project`, and
+	/// nothing else.
+	fn fetch_go_get(url: &Url) -> Result<Option<String>> {
+		match url.as_str() {
+			"https://gitlab.com/group/subgroup/project/v2?go-get=1" => Ok(Some(r#"<html><head>
+<meta name="go-import" content="gitlab.com/group/subgroup/project git https://gitlab.com/group/subgroup/project.git">
+</head></html>"#
+				.to_owned())),
+			_ => Err(hc_error!("unexpected request for '{}'", url)),
+		}
+	}
+
+	/// Reads from local proxies, fails for `https://broken.example.com`, and
+	/// otherwise serves go-get pages as `fetch_go_get` does.
+	fn fetch_local_or_go_get(url: &Url) -> Result<Option<String>> {
+		match url.scheme() {
+			"file" => fetch(url),
+			_ if url.host_str() == Some("broken.example.com") => Err(hc_error!("proxy is down")),
+			_ => fetch_go_get(url),
+		}
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_github() {
+		let url = extract_repo_for_go_with_proxy(
+			&local_proxy(),
+			"github.com/spf13/cobra/v2/doc",
+			"v2.0.0",
+		)
+		.unwrap();
+		assert_eq!(url.as_str(), "https://github.com/spf13/cobra");
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_proxy() {
+		let url =
+			extract_repo_for_go_with_proxy(&local_proxy(), "golang.org/x/text", "v0.16.0").unwrap();
+		assert_eq!(url.as_str(), "https://go.googlesource.com/text");
+
+		let url = extract_repo_for_go_with_proxy(&local_proxy(), "golang.org/x/text", "no version")
+			.unwrap();
+		assert_eq!(url.as_str(), "https://go.googlesource.com/text");
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_direct() {
+		let proxy = GoProxy {
+			fetch: fetch_go_get,
+			..GoProxy::new(Some("direct"), "").unwrap()
+		};
+		let url = extract_repo_for_go_with_proxy(
+			&proxy,
+			"gitlab.com/group/subgroup/project/v2",
+			"v2.1.0",
+		)
+		.unwrap();
+		assert_eq!(
+			url.as_str(),
+			"https://gitlab.com/group/subgroup/project.git"
+		);
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_private() {
+		// The proxy isn't asked about private modules, even though it's listed
+		let proxy = GoProxy {
+			fetch: fetch_go_get,
+			..GoProxy::new(Some("https://proxy.example.com,off"), "gitlab.com/group/*").unwrap()
+		};
+		let url = extract_repo_for_go_with_proxy(
+			&proxy,
+			"gitlab.com/group/subgroup/project/v2",
+			"v2.1.0",
+		)
+		.unwrap();
+		assert_eq!(
+			url.as_str(),
+			"https://gitlab.com/group/subgroup/project.git"
+		);
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_proxy_off() {
+		let proxy = GoProxy {
+			fetch: fetch_go_get,
+			..GoProxy::new(Some("off"), "").unwrap()
+		};
+		assert!(extract_repo_for_go_with_proxy(
+			&proxy,
+			"gitlab.com/group/subgroup/project/v2",
+			"v2.1.0"
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_extract_repo_for_go_falls_through_proxies() {
+		// A module a proxy doesn't have is looked up in the next one, and then
+		// directly if `direct` is listed.
+		let module = "gitlab.com/group/subgroup/project/v2";
+		let local = local_proxy_url();
+		let proxy = GoProxy {
+			fetch: fetch_local_or_go_get,
+			..GoProxy::new(Some(&format!("{},direct", local)), "").unwrap()
+		};
+		let url = extract_repo_for_go_with_proxy(&proxy, module, "v2.1.0").unwrap();
+		assert_eq!(
+			url.as_str(),
+			"https://gitlab.com/group/subgroup/project.git"
+		);
+
+		let missing = local.join("missing/").unwrap();
+		let proxy = GoProxy {
+			fetch: fetch_local_or_go_get,
+			..GoProxy::new(Some(&format!("{},{}", missing, local)), "").unwrap()
+		};
+		let url = extract_repo_for_go_with_proxy(&proxy, "golang.org/x/text", "v0.16.0").unwrap();
+		assert_eq!(url.as_str(), "https://go.googlesource.com/text");
+
+		// Without `direct`, a module no proxy has isn't found.
+		let proxy = GoProxy {
+			fetch: fetch_local_or_go_get,
+			..GoProxy::new(Some(&format!("{},off,direct", local)), "").unwrap()
+		};
+		assert!(extract_repo_for_go_with_proxy(&proxy, module, "v2.1.0").is_err());
+
+		// Other failures stop the lookup, unless the proxy is followed by `|`.
+		let proxy = GoProxy {
+			fetch: fetch_local_or_go_get,
+			..GoProxy::new(Some("https://broken.example.com,direct"), "").unwrap()
+		};
+		assert!(extract_repo_for_go_with_proxy(&proxy, module, "v2.1.0").is_err());
+
+		let proxy = GoProxy {
+			fetch: fetch_local_or_go_get,
+			..GoProxy::new(Some("https://broken.example.com|direct"), "").unwrap()
+		};
+		assert!(extract_repo_for_go_with_proxy(&proxy, module, "v2.1.0").is_ok());
+	}
+
+	#[test]
+	fn test_go_proxy_from_goproxy() {
+		let proxy = GoProxy::new(None, "").unwrap();
+		assert_eq!(
+			proxy.entries,
+			[
+				GoProxyEntry::Proxy {
+					url: Url::parse("https://proxy.golang.org/").unwrap(),
+					fall_through: false,
+				},
+				GoProxyEntry::Direct,
+			]
+		);
+
+		let proxy = GoProxy::new(Some("https://a.example.com|https://b.example.com"), "").unwrap();
+		assert_eq!(
+			proxy.entries,
+			[
+				GoProxyEntry::Proxy {
+					url: Url::parse("https://a.example.com/").unwrap(),
+					fall_through: true,
+				},
+				GoProxyEntry::Proxy {
+					url: Url::parse("https://b.example.com/").unwrap(),
+					fall_through: false,
+				},
+			]
+		);
+
+		let proxy = GoProxy::new(Some("off,direct"), "").unwrap();
+		assert!(proxy.entries.is_empty());
+	}
+
+	#[test]
+	fn test_matches_path_prefix() {
+		assert!(matches_path_prefix(
+			"*.corp.example.com",
+			"git.corp.example.com/a/b"
+		));
+		assert!(matches_path_prefix(
+			"rsc.io/private",
+			"rsc.io/private/quote"
+		));
+		assert!(matches_path_prefix("rsc.io/p?ivate", "rsc.io/private"));
+		assert!(!matches_path_prefix("rsc.io/private", "rsc.io/privateer"));
+		assert!(!matches_path_prefix(
+			"rsc.io/private/quote",
+			"rsc.io/private"
+		));
+	}
+
+	#[test]
+	fn test_escape_module_path() {
+		assert_eq!(
+			escape_module_path("github.com/BurntSushi/toml"),
+			"github.com/!burnt!sushi/toml"
+		);
+	}
+
+	#[test]
+	fn test_find_go_import_repo() {
+		let page = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta name="go-import" content="go.uber.org/zap git https://github.com/uber-go/zap">
+<meta name="go-source" content="go.uber.org/zap https://github.com/uber-go/zap https://github.com/uber-go/zap/tree/master{/dir} https://github.com/uber-go/zap/tree/master{/dir}/{file}#L{line}">
+</head>
+</html>"#;
+		let url = find_go_import_repo(page, "go.uber.org/zap/zapcore").unwrap();
+		assert_eq!(url.unwrap().as_str(), "https://github.com/uber-go/zap");
+		assert!(find_go_import_repo(page, "go.uber.org/zapper")
+			.unwrap()
+			.is_none());
+	}
+}
@@ -0,0 +1,480 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for extracting the dependencies listed in package manager lockfiles.
+
+use crate::{
+	error::{Context as _, Result},
+	hc_error,
+	target::{Dependency, Lockfile, LockfileKind},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+/// Extract every dependency listed in a lockfile, located by a pURL for its
+/// registry, or by a `git+` VCS URL if it comes from a Git repository.
+///
+/// Dependencies with neither, like path dependencies, have no locator.
+pub fn extract_lockfile_dependencies(lockfile: &Lockfile) -> Result<Vec<Dependency>> {
+	let contents = std::fs::read_to_string(&lockfile.path)
+		.with_context(|| format!("failed to read lockfile '{}'", lockfile.path.display()))?;
+
+	match lockfile.kind {
+		LockfileKind::Cargo => extract_cargo_lock(&contents),
+		LockfileKind::Npm => extract_package_lock(&contents),
+		LockfileKind::Poetry => extract_poetry_lock(&contents),
+		LockfileKind::Requirements => Ok(extract_requirements(&contents)),
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlLockfile {
+	#[serde(default)]
+	package: Vec<TomlPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlPackage {
+	name: String,
+	version: String,
+	source: Option<TomlSource>,
+}
+
+/// Cargo gives a package's source as a string, and Poetry as a table.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TomlSource {
+	Cargo(String),
+	Poetry {
+		#[serde(rename = "type")]
+		ty: String,
+		url: String,
+	},
+}
+
+fn parse_toml_lockfile(contents: &str) -> Result<TomlLockfile> {
+	toml::from_str(contents).map_err(|e| hc_error!("lockfile is corrupt or incompatible: {}", e))
+}
+
+/// The sources Cargo gives for packages from crates.io, through its Git and
+/// sparse indexes.
+const CRATES_IO_SOURCES: [&str; 2] = [
+	"registry+https://github.com/rust-lang/crates.io-index",
+	"sparse+https://index.crates.io/",
+];
+
+// Extract the packages of a `Cargo.lock`. Packages without a source are in the
+// workspace or are path dependencies. Packages from other registries have no
+// locator, since looking them up on crates.io could find a different package
+// which only shares the name.
+fn extract_cargo_lock(contents: &str) -> Result<Vec<Dependency>> {
+	let lockfile = parse_toml_lockfile(contents)?;
+
+	let dependencies = lockfile
+		.package
+		.into_iter()
+		.map(|package| {
+			let locator = match package.source {
+				Some(TomlSource::Cargo(source)) if source.starts_with("git+") => {
+					git_locator(&source)
+				}
+				Some(TomlSource::Cargo(source)) if CRATES_IO_SOURCES.contains(&source.as_str()) => {
+					Some(format!("pkg:cargo/{}@{}", package.name, package.version))
+				}
+				_ => None,
+			};
+
+			Dependency {
+				name: package.name,
+				version: Some(package.version),
+				locator,
+			}
+		})
+		.collect();
+
+	Ok(dependencies)
+}
+
+// Extract the packages of a `poetry.lock`. Packages from a "directory" or
+// "file" source are local. Packages from a "legacy" source come from a private
+// index, so they have no locator: looking them up on PyPI could find a
+// different package which only shares the name.
+fn extract_poetry_lock(contents: &str) -> Result<Vec<Dependency>> {
+	let lockfile = parse_toml_lockfile(contents)?;
+
+	let dependencies = lockfile
+		.package
+		.into_iter()
+		.map(|package| {
+			let locator = match &package.source {
+				None => Some(format!("pkg:pypi/{}@{}", package.name, package.version)),
+				Some(TomlSource::Poetry { ty, url }) if ty == "git" => {
+					git_locator(&format!("git+{}", url))
+				}
+				Some(_) => None,
+			};
+
+			Dependency {
+				name: package.name,
+				version: Some(package.version),
+				locator,
+			}
+		})
+		.collect();
+
+	Ok(dependencies)
+}
+
+// Extract the packages of a `package-lock.json`. Version 2 and 3 lockfiles list
+// every package by its path under "packages", while version 1 lockfiles nest
+// them under "dependencies".
+fn extract_package_lock(contents: &str) -> Result<Vec<Dependency>> {
+	let lockfile: Value = serde_json::from_str(contents)
+		.map_err(|e| hc_error!("package-lock.json is corrupt or incompatible: {}", e))?;
+
+	let mut dependencies = vec![];
+
+	if let Some(packages) = lockfile.get("packages").and_then(Value::as_object) {
+		for (path, package) in packages {
+			// The empty path is the root package, and links are local packages.
+			if path.is_empty() || package.get("link").and_then(Value::as_bool) == Some(true) {
+				continue;
+			}
+
+			// Paths outside of any `node_modules` are workspace packages,
+			// which are local rather than from the registry.
+			if !path.starts_with("node_modules/") && !path.contains("/node_modules/") {
+				continue;
+			}
+
+			// Aliased packages give their real name as a field.
+			let name = match package.get("name").and_then(Value::as_str) {
+				Some(name) => name,
+				None => path.rsplit("node_modules/").next().unwrap_or(path),
+			};
+			dependencies.push(npm_dependency(name, package));
+		}
+	} else if let Some(deps) = lockfile.get("dependencies").and_then(Value::as_object) {
+		collect_package_lock_v1(deps, &mut dependencies);
+	} else {
+		return Err(hc_error!("package-lock.json lists no packages"));
+	}
+
+	Ok(dependencies)
+}
+
+fn collect_package_lock_v1(
+	deps: &serde_json::Map<String, Value>,
+	dependencies: &mut Vec<Dependency>,
+) {
+	for (name, package) in deps {
+		dependencies.push(npm_dependency(name, package));
+
+		if let Some(nested) = package.get("dependencies").and_then(Value::as_object) {
+			collect_package_lock_v1(nested, dependencies);
+		}
+	}
+}
+
+fn npm_dependency(name: &str, package: &Value) -> Dependency {
+	let version = package.get("version").and_then(Value::as_str);
+	let resolved = package.get("resolved").and_then(Value::as_str);
+
+	// Git dependencies give their repository as the version in version 1
+	// lockfiles, and as the resolved location in later versions.
+	let git_source = [version, resolved]
+		.into_iter()
+		.flatten()
+		.find(|source| source.starts_with("git+"));
+
+	// Packages resolved from another registry have no locator, as for Cargo.
+	let from_npm = resolved.is_none_or(|resolved| {
+		Url::parse(resolved).is_ok_and(|url| url.host_str() == Some("registry.npmjs.org"))
+	});
+
+	let locator = match (git_source, version) {
+		(Some(source), _) => git_locator(source),
+		(None, Some(version)) if from_npm && !version.contains(':') => {
+			Some(format!("pkg:npm/{}@{}", name.replace('@', "%40"), version))
+		}
+		_ => None,
+	};
+
+	Dependency {
+		name: name.to_owned(),
+		version: version.map(ToOwned::to_owned),
+		locator,
+	}
+}
+
+// Extract the requirements of a pip requirements file. Only pinned (`==`)
+// requirements have a version; others are checked at their latest version.
+fn extract_requirements(contents: &str) -> Vec<Dependency> {
+	// Lines ending in a backslash continue on the next line.
+	let contents = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+
+	contents.lines().filter_map(requirement).collect()
+}
+
+fn requirement(line: &str) -> Option<Dependency> {
+	// Comments must be preceded by whitespace, since '#' appears in URLs.
+	let line = match line.find(" #") {
+		Some(idx) => &line[..idx],
+		None => line,
+	};
+	let line = line.trim();
+
+	// Options, like `-r other.txt` or `--hash`, aren't requirements. Editable
+	// installs are local.
+	if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+		return None;
+	}
+
+	// A VCS requirement, with the name given as `#egg=<name>`
+	if line.starts_with("git+") {
+		let name = line
+			.split_once("#egg=")
+			.map(|(_, name)| name.split('&').next().unwrap_or(name))
+			.unwrap_or(line);
+
+		return Some(Dependency {
+			name: name.to_owned(),
+			version: None,
+			locator: git_locator(line),
+		});
+	}
+
+	// Drop any per-requirement options and environment markers.
+	let spec = line.split(" --").next().unwrap_or(line);
+	let spec = spec.split(';').next().unwrap_or(spec).trim();
+
+	let name_end = spec
+		.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+		.unwrap_or(spec.len());
+	let (name, rest) = spec.split_at(name_end);
+	if name.is_empty() {
+		return None;
+	}
+
+	// A direct reference, like `name @ git+https://...`
+	if let Some((_, reference)) = rest.split_once('@') {
+		let reference = reference.trim();
+		return Some(Dependency {
+			name: name.to_owned(),
+			version: None,
+			locator: reference
+				.starts_with("git+")
+				.then(|| git_locator(reference))
+				.flatten(),
+		});
+	}
+
+	let version = rest
+		.split(',')
+		.find_map(|clause| clause.trim().strip_prefix("=="))
+		.map(|version| version.trim().to_owned())
+		.filter(|version| !version.contains('*'));
+
+	let locator = match &version {
+		Some(version) => format!("pkg:pypi/{}@{}", name, version),
+		None => format!("pkg:pypi/{}", name),
+	};
+
+	Some(Dependency {
+		name: name.to_owned(),
+		version,
+		locator: Some(locator),
+	})
+}
+
+// Turn a `git+` source, which may name a branch, tag, or commit in its query or
+// fragment, into a `git+` VCS URL for the repository alone.
+fn git_locator(source: &str) -> Option<String> {
+	let mut url = Url::parse(source.strip_prefix("git+")?).ok()?;
+	url.set_query(None);
+	url.set_fragment(None);
+	// Refs may also follow an '@' in the path, as in pip's `repo.git@v1.0`.
+	let path = url.path().to_owned();
+	if let Some((repo, _)) = path.split_once(".git@") {
+		url.set_path(&format!("{}.git", repo));
+	}
+	Some(format!("git+{}", url))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn locators(dependencies: &[Dependency]) -> Vec<Option<&str>> {
+		dependencies.iter().map(|d| d.locator.as_deref()).collect()
+	}
+
+	#[test]
+	fn test_extract_cargo_lock() {
+		let contents = r#"
+version = 4
+
+[[package]]
+name = "hipcheck"
+version = "3.10.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.215"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "6513c1ad0b11a9376da888e3e0baa0077f1aed55c17f50e7b2397136129fb88f"
+
+[[package]]
+name = "kdl"
+version = "4.6.0"
+source = "git+https://github.com/kdl-org/kdl-rs?branch=main#0123abc"
+
+[[package]]
+name = "log"
+version = "0.4.22"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "internal"
+version = "0.1.0"
+source = "sparse+https://cargo.example.com/index/"
+"#;
+		let dependencies = extract_cargo_lock(contents).unwrap();
+		assert_eq!(
+			locators(&dependencies),
+			[
+				None,
+				Some("pkg:cargo/serde@1.0.215"),
+				Some("git+https://github.com/kdl-org/kdl-rs"),
+				Some("pkg:cargo/log@0.4.22"),
+				None,
+			]
+		);
+	}
+
+	#[test]
+	fn test_extract_package_lock() {
+		let v3 = r#"{
+			"name": "app",
+			"lockfileVersion": 3,
+			"packages": {
+				"": { "name": "app", "version": "1.0.0" },
+				"node_modules/@babel/core": { "version": "7.26.0", "resolved": "https://registry.npmjs.org/@babel/core/-/core-7.26.0.tgz" },
+				"node_modules/a/node_modules/left-pad": { "version": "1.3.0" },
+				"node_modules/local": { "resolved": "packages/local", "link": true },
+				"node_modules/forked": { "version": "2.0.0", "resolved": "git+ssh://git@github.com/someone/forked.git#0123abc" },
+				"node_modules/internal": { "version": "1.0.0", "resolved": "https://npm.example.com/internal/-/internal-1.0.0.tgz" }
+			}
+		}"#;
+		let dependencies = extract_package_lock(v3).unwrap();
+		assert_eq!(
+			locators(&dependencies),
+			[
+				Some("pkg:npm/%40babel/core@7.26.0"),
+				Some("pkg:npm/left-pad@1.3.0"),
+				Some("git+ssh://git@github.com/someone/forked.git"),
+				None,
+			]
+		);
+
+		let manifest = env!("CARGO_MANIFEST_DIR");
+		let path: std::path::PathBuf = [
+			manifest,
+			"src",
+			"session",
+			"tests",
+			"package_lock_workspaces.json",
+		]
+		.iter()
+		.collect();
+		let workspaces = std::fs::read_to_string(path).unwrap();
+		let dependencies = extract_package_lock(&workspaces).unwrap();
+		assert_eq!(
+			locators(&dependencies),
+			[Some("pkg:npm/chalk@5.3.0"), Some("pkg:npm/react@18.3.1")]
+		);
+
+		let v1 = r#"{
+			"lockfileVersion": 1,
+			"dependencies": {
+				"express": {
+					"version": "4.21.1",
+					"dependencies": { "debug": { "version": "2.6.9" } }
+				}
+			}
+		}"#;
+		let dependencies = extract_package_lock(v1).unwrap();
+		assert_eq!(
+			locators(&dependencies),
+			[Some("pkg:npm/express@4.21.1"), Some("pkg:npm/debug@2.6.9")]
+		);
+	}
+
+	#[test]
+	fn test_extract_poetry_lock() {
+		let contents = r#"
+[[package]]
+name = "requests"
+version = "2.32.3"
+description = "Python HTTP for Humans."
+
+[[package]]
+name = "internal"
+version = "0.1.0"
+
+[package.source]
+type = "git"
+url = "https://github.com/someone/internal.git"
+reference = "main"
+resolved_reference = "0123abc"
+
+[[package]]
+name = "private-utils"
+version = "1.2.0"
+
+[package.source]
+type = "legacy"
+url = "https://pypi.example.com/simple"
+reference = "private"
+"#;
+		let dependencies = extract_poetry_lock(contents).unwrap();
+		assert_eq!(
+			locators(&dependencies),
+			[
+				Some("pkg:pypi/requests@2.32.3"),
+				Some("git+https://github.com/someone/internal.git"),
+				None,
+			]
+		);
+	}
+
+	#[test]
+	fn test_extract_requirements() {
+		let contents = "\
+# pinned for reproducibility
+-r base.txt
+requests==2.32.3 \\
+    --hash=sha256:70761cfe03c773ceb22aa2f671b4757976145175cdfca038c02654d061d6dcc6
+Django[argon2]>=4.2,<5.0 ; python_version >= \"3.8\"
+flask == 3.1.0  # web
+git+https://github.com/someone/internal.git@v1.0#egg=internal
+-e ./local
+";
+		let dependencies = extract_requirements(contents);
+		let names = dependencies
+			.iter()
+			.map(|d| d.name.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["requests", "Django", "flask", "internal"]);
+		assert_eq!(
+			locators(&dependencies),
+			[
+				Some("pkg:pypi/requests@2.32.3"),
+				Some("pkg:pypi/Django"),
+				Some("pkg:pypi/flask@3.1.0"),
+				Some("git+https://github.com/someone/internal.git"),
+			]
+		);
+	}
+}
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod cyclone_dx;
+pub mod go;
+pub mod lockfile;
 pub mod pm;
 pub mod spdx;
 
@@ -11,7 +13,7 @@ use crate::{
 	config::{
 		AttacksConfigQueryStorage, CommitConfigQueryStorage, Config, ConfigSource,
 		ConfigSourceStorage, LanguagesConfigQueryStorage, PracticesConfigQueryStorage,
-		RiskConfigQueryStorage, WeightTreeQueryStorage,
+		RiskConfigQueryStorage, WeightTreeProvider, WeightTreeQueryStorage, DEFAULT_QUERY,
 	},
 	data::{
 		git::{get_git_version, GitProviderStorage},
@@ -19,13 +21,15 @@ use crate::{
 		CodeQualityProviderStorage, DependenciesProviderStorage, FuzzProviderStorage,
 		GitHubProviderStorage, PullRequestReviewProviderStorage,
 	},
-	engine::{start_plugins, HcEngine, HcEngineStorage},
+	engine::{start_plugins, HcEngine, HcEngineStorage, HcPluginCore},
 	error::{Context as _, Error, Result},
 	hc_error,
 	metric::{
 		binary_detector::BinaryFileStorage, linguist::LinguistStorage, MetricProviderStorage,
 	},
-	policy::{config_to_policy::config_to_policy, PolicyFile},
+	plugin::PluginConnection,
+	policy::{config_to_policy::config_to_policy, PolicyExprSpans, PolicyFile},
+	policy_exprs,
 	report::{ReportParams, ReportParamsStorage},
 	session::{
 		cyclone_dx::extract_cyclonedx_download_url,
@@ -43,6 +47,7 @@ use chrono::prelude::*;
 use dotenv::var;
 use std::{
 	fmt,
+	ops::Range,
 	path::{Path, PathBuf},
 	rc::Rc,
 	result::Result as StdResult,
@@ -91,6 +96,29 @@ impl fmt::Debug for Session {
 	}
 }
 
+/// What a session loads which doesn't depend on its target, like the policy
+/// and the running plugins. Runs checking many targets load it for the first
+/// target and share it with the sessions for the rest.
+#[derive(Clone)]
+pub struct SessionSetup {
+	git_version: Rc<String>,
+	npm_version: Rc<String>,
+	config_dir: Option<Rc<PathBuf>>,
+	policy: Rc<PolicyFile>,
+	policy_path: Option<Rc<PathBuf>>,
+	github_api_token: Option<Rc<String>>,
+	core: Arc<HcPluginCore>,
+}
+
+impl SessionSetup {
+	/// Forget the results of plugin queries made for earlier targets, so
+	/// queries for the next target are answered from the target as it is now,
+	/// even if it has the same specifier and ref as an earlier one.
+	pub fn clear_query_results(&self) {
+		self.core.cache.clear();
+	}
+}
+
 impl Session {
 	// Note that error handling in the constructor for `Session` is a little awkward.
 	// This is because we want to be able to hand back the `Shell` passed in if setup
@@ -112,6 +140,8 @@ impl Session {
 		home_dir: Option<PathBuf>,
 		policy_path: Option<PathBuf>,
 		format: Format,
+		plugin_connection: PluginConnection,
+		setup: Option<SessionSetup>,
 	) -> StdResult<Session, Error> {
 		/*===================================================================
 		 *  Setting up the session.
@@ -130,58 +160,28 @@ impl Session {
 		Shell::print_prelude(target.to_string());
 
 		/*===================================================================
-		 *  Loading current versions of needed software git, npm, and eslint into salsa.
+		 *  Loading software versions and configuration.
 		 *-----------------------------------------------------------------*/
 
-		let (git_version, npm_version) = match load_software_versions() {
-			Ok(results) => results,
-			Err(err) => return Err(err),
+		// Everything which doesn't depend on the target is only loaded if an
+		// earlier session didn't already load it.
+		let is_first_session = setup.is_none();
+		let core = match setup {
+			Some(setup) => {
+				session.set_git_version(setup.git_version);
+				session.set_npm_version(setup.npm_version);
+				session.set_config_dir(setup.config_dir);
+				session.set_policy(setup.policy);
+				session.set_policy_path(setup.policy_path);
+				session.set_github_api_token(setup.github_api_token);
+				Some(setup.core)
+			}
+			None => {
+				load_setup_inputs(&mut session, config_path.as_deref(), policy_path.as_deref())?;
+				None
+			}
 		};
 
-		session.set_git_version(Rc::new(git_version));
-		session.set_npm_version(Rc::new(npm_version));
-
-		/*===================================================================
-		 *  Loading configuration.
-		 *-----------------------------------------------------------------*/
-
-		// Check if a policy file was provided, otherwise convert a deprecated config file to a policy file. If neither was provided, error out.
-		if policy_path.is_some() {
-			let (policy, policy_path, hc_github_token) =
-				match load_policy_and_data(policy_path.as_deref()) {
-					Ok(results) => results,
-					Err(err) => return Err(err),
-				};
-
-			// No config or dir
-			session.set_config_dir(None);
-
-			// Set policy file and its location
-			session.set_policy(Rc::new(policy));
-			session.set_policy_path(Some(Rc::new(policy_path)));
-
-			// Set github token in salsa
-			session.set_github_api_token(Some(Rc::new(hc_github_token)));
-		} else if config_path.is_some() {
-			let (policy, config_dir, hc_github_token) =
-				match load_config_and_data(config_path.as_deref()) {
-					Ok(results) => results,
-					Err(err) => return Err(err),
-				};
-
-			// Set config dir
-			session.set_config_dir(Some(Rc::new(config_dir)));
-
-			// Set policy file, with no location to represent that none was given
-			session.set_policy(Rc::new(policy));
-			session.set_policy_path(None);
-
-			// Set github token in salsa
-			session.set_github_api_token(Some(Rc::new(hc_github_token)));
-		} else {
-			return Err(hc_error!("No policy file or (deprecated) config file found. Please provide a policy file before running Hipcheck."));
-		}
-
 		/*===================================================================
 		 *  Resolving the Hipcheck home.
 		 *-----------------------------------------------------------------*/
@@ -203,10 +203,10 @@ impl Session {
 		 *  Resolving the source.
 		 *-----------------------------------------------------------------*/
 
-		let target = match load_target(target, &home) {
-			Ok(results) => results,
-			Err(err) => return Err(err),
-		};
+		// Repos on self-hosted forges named in the policy file are known too.
+		let gitlab_hosts = session.policy().forges.gitlab.clone();
+
+		let target = load_target(target, &home, &gitlab_hosts)?;
 
 		session.set_target(Arc::new(target));
 
@@ -235,12 +235,193 @@ impl Session {
 		// has all the plugins up and running. However, HcPluginCore does not impl
 		// equal, and the idea of memoizing/invalidating it does not make sense.
 		// Thus, we will do the plugin startup here.
-		let policy = session.policy();
-		let core = start_plugins(policy.as_ref(), &plugin_cache)?;
+		//
+		// Plugins already started for another target in the same run are reused,
+		// since how they're configured depends on the policy, not the target.
+		let core = match core {
+			Some(core) => core,
+			None => {
+				let policy = session.policy();
+				let policy_path = session.policy_path();
+				start_plugins(
+					policy.as_ref(),
+					policy_path.as_deref().map(PathBuf::as_path),
+					&plugin_cache,
+					plugin_connection,
+				)?
+			}
+		};
 		session.set_core(core);
 
+		/*===================================================================
+		 *  Type-checking policy expressions.
+		 *-----------------------------------------------------------------*/
+
+		// Now that the plugins are running we know the schemas of their
+		// outputs, so mistakes in policy expressions can be reported before
+		// any analysis is run. They only need checking once per policy.
+		if is_first_session {
+			check_policy_exprs(&session)?;
+		}
+
 		Ok(session)
 	}
+
+	/// Get what this session loaded which doesn't depend on its target, to
+	/// share with the session for another target.
+	pub fn setup(&self) -> SessionSetup {
+		SessionSetup {
+			git_version: self.git_version(),
+			npm_version: self.npm_version(),
+			config_dir: self.config_dir(),
+			policy: self.policy(),
+			policy_path: self.policy_path(),
+			github_api_token: self.github_api_token(),
+			core: self.core(),
+		}
+	}
+}
+
+/// Load the versions of needed software and the policy into a session's inputs.
+fn load_setup_inputs(
+	session: &mut Session,
+	config_path: Option<&Path>,
+	policy_path: Option<&Path>,
+) -> Result<()> {
+	/*===================================================================
+	 *  Loading current versions of needed software git, npm, and eslint into salsa.
+	 *-----------------------------------------------------------------*/
+
+	let (git_version, npm_version) = load_software_versions()?;
+
+	session.set_git_version(Rc::new(git_version));
+	session.set_npm_version(Rc::new(npm_version));
+
+	/*===================================================================
+	 *  Loading configuration.
+	 *-----------------------------------------------------------------*/
+
+	// Check if a policy file was provided, otherwise convert a deprecated config file to a policy file. If neither was provided, error out.
+	if policy_path.is_some() {
+		let (policy, policy_path, hc_github_token) = load_policy_and_data(policy_path)?;
+
+		// No config or dir
+		session.set_config_dir(None);
+
+		// Set policy file and its location
+		session.set_policy(Rc::new(policy));
+		session.set_policy_path(Some(Rc::new(policy_path)));
+
+		// Set github token in salsa
+		session.set_github_api_token(Some(Rc::new(hc_github_token)));
+	} else if config_path.is_some() {
+		let (policy, config_dir, hc_github_token) = load_config_and_data(config_path)?;
+
+		// Set config dir
+		session.set_config_dir(Some(Rc::new(config_dir)));
+
+		// Set policy file, with no location to represent that none was given
+		session.set_policy(Rc::new(policy));
+		session.set_policy_path(None);
+
+		// Set github token in salsa
+		session.set_github_api_token(Some(Rc::new(hc_github_token)));
+	} else {
+		return Err(hc_error!("No policy file or (deprecated) config file found. Please provide a policy file before running Hipcheck."));
+	}
+
+	Ok(())
+}
+
+/// Type-check the investigate policy and the policy of every analysis, reporting
+/// all of the ill-typed ones at once.
+fn check_policy_exprs(session: &Session) -> Result<()> {
+	let policy = session.policy();
+	let policy_path = session.policy_path();
+
+	// Find where the expressions are in the policy file, so errors in them can
+	// be pointed at. Policies converted from a legacy config file have no source.
+	let source = match policy_path.as_deref() {
+		Some(path) => Some(
+			std::fs::read_to_string(path)
+				.with_context(|| format!("failed to read policy file '{}'", path.display()))?,
+		),
+		None => None,
+	};
+	let spans = match source.as_deref() {
+		Some(source) => PolicyExprSpans::find(source)?,
+		None => PolicyExprSpans::default(),
+	};
+	let location = |span: Option<&Range<usize>>| -> Option<String> {
+		let (path, source, span) = (policy_path.as_deref()?, source.as_deref()?, span?);
+		Some(render_span(path, source, span))
+	};
+
+	let mut errors = vec![];
+
+	// The investigate policy is run on the overall risk score.
+	let investigate_policy = &policy.analyze.investigate_policy.0;
+	let risk_score_schema = serde_json::json!({ "type": "number" });
+	if let Err(err) = policy_exprs::type_check(investigate_policy, Some(&risk_score_schema)) {
+		errors.push(match location(spans.investigate.as_ref()) {
+			Some(location) => format!("investigate policy: {err}\n{location}"),
+			None => format!("investigate policy '{investigate_policy}': {err}"),
+		});
+	}
+
+	for analysis in session.analysis_tree()?.get_analyses() {
+		let name = format!("{}/{}", analysis.0.publisher, analysis.0.plugin);
+		let schema = session.output_schema(
+			analysis.0.publisher.clone(),
+			analysis.0.plugin.clone(),
+			DEFAULT_QUERY.to_owned(),
+		)?;
+		if let Err(err) = policy_exprs::type_check(&analysis.1, schema.as_ref()) {
+			// Analyses without a policy in the policy file use the plugin's default.
+			errors.push(match location(spans.analyses.get(&name)) {
+				Some(location) => format!("policy for analysis '{name}': {err}\n{location}"),
+				None => format!("default policy '{}' of plugin '{name}': {err}", analysis.1),
+			});
+		}
+	}
+
+	if errors.is_empty() {
+		Ok(())
+	} else {
+		Err(hc_error!(
+			"{} ill-typed policy expression(s):\n\n{}",
+			errors.len(),
+			errors.join("\n\n")
+		))
+	}
+}
+
+/// Render the location of `span` in `source` with the line it's on and a
+/// marker under the span, in the style of a compiler error.
+fn render_span(path: &Path, source: &str, span: &Range<usize>) -> String {
+	let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+	let line_end = source[span.start..]
+		.find('\n')
+		.map_or(source.len(), |i| span.start + i);
+	let line_num = source[..span.start].matches('\n').count() + 1;
+	let line = &source[line_start..line_end];
+	let prefix = &source[line_start..span.start];
+	let col = prefix.chars().count() + 1;
+	let width = source[span.start..span.end.min(line_end)]
+		.chars()
+		.count()
+		.max(1);
+	// Keep tabs in the indent so the marker lines up with the line above it.
+	let indent: String = prefix
+		.chars()
+		.map(|c| if c == '\t' { '\t' } else { ' ' })
+		.collect();
+
+	format!(
+		"  --> {}:{line_num}:{col}\n   |\n   | {line}\n   | {indent}{}",
+		path.display(),
+		"^".repeat(width)
+	)
 }
 
 fn load_software_versions() -> Result<(String, String)> {
@@ -307,7 +488,8 @@ fn load_policy_and_data(policy_path: Option<&Path>) -> Result<(PolicyFile, PathB
 	Ok((policy, valid_policy_path.to_path_buf(), hc_github_token))
 }
 
-fn load_target(seed: &TargetSeed, home: &Path) -> Result<Target> {
+/// Resolve a target seed into a target, cloning or copying its repo into the cache at `home`.
+pub fn load_target(seed: &TargetSeed, home: &Path, gitlab_hosts: &[String]) -> Result<Target> {
 	// Resolve the source specifier into an actual source.
 	let phase_desc = match seed.kind {
 		TargetSeedKind::LocalRepo(_) | TargetSeedKind::RemoteRepo(_) => {
@@ -315,13 +497,16 @@ fn load_target(seed: &TargetSeed, home: &Path) -> Result<Target> {
 		}
 		TargetSeedKind::Package(_) => "resolving package target",
 		TargetSeedKind::Sbom(_) => "parsing SBOM document",
+		TargetSeedKind::Lockfile(_) => "parsing lockfile",
 		TargetSeedKind::MavenPackage(_) => "resolving maven package target",
+		TargetSeedKind::PullRequest(_) => "resolving pull request target",
+		TargetSeedKind::CommitRange(_) => "resolving commit range target",
 	};
 
 	let phase = SpinnerPhase::start(phase_desc);
 	// Set the phase to tick steadily 10 times a second.
 	phase.enable_steady_tick(Duration::from_millis(100));
-	let target = resolve_target(seed, &phase, home)?;
+	let target = resolve_target(seed, &phase, home, gitlab_hosts)?;
 	phase.finish_successful();
 
 	Ok(target)
@@ -335,22 +520,32 @@ fn resolve_token() -> Result<String> {
 	}
 }
 
-/// Resolves the target specifier into an actual target.
-fn resolve_target(seed: &TargetSeed, phase: &SpinnerPhase, home: &Path) -> Result<Target> {
+/// Resolves the target specifier into an actual target. Repos on the given
+/// self-hosted GitLab instances are treated like those on gitlab.com.
+fn resolve_target(
+	seed: &TargetSeed,
+	phase: &SpinnerPhase,
+	home: &Path,
+	gitlab_hosts: &[String],
+) -> Result<Target> {
 	use TargetSeedKind::*;
 	#[cfg(feature = "print-timings")]
 	let _0 = crate::benchmarking::print_scope_time!("resolve_source");
 
 	match &seed.kind {
-		RemoteRepo(remote) => {
-			source::resolve_remote_repo(phase, home, remote.to_owned(), seed.refspec.clone())
-		}
+		RemoteRepo(remote) => source::resolve_remote_repo(
+			phase,
+			home,
+			remote.to_owned(),
+			seed.refspec.clone(),
+			gitlab_hosts,
+		),
 		LocalRepo(source) => {
 			// Because other TargetSeedKind variants need to transfer refspec info from the CLI,
 			// there's overlap with LocalGitRepo.git_ref. Copy CLI refspec here.
 			let mut source = source.to_owned();
 			source.git_ref = seed.refspec.clone().unwrap_or("HEAD".to_owned());
-			source::resolve_local_repo(phase, home, source)
+			source::resolve_local_repo(phase, home, source, gitlab_hosts)
 		}
 		Package(package) => {
 			// Attempt to get the git repo URL for the package
@@ -374,6 +569,7 @@ fn resolve_target(seed: &TargetSeed, phase: &SpinnerPhase, home: &Path) -> Resul
 				package_git_repo,
 				format!("{}@{}", package.name, package.version),
 				refspec,
+				gitlab_hosts,
 			)
 		}
 		MavenPackage(package) => {
@@ -390,6 +586,7 @@ fn resolve_target(seed: &TargetSeed, phase: &SpinnerPhase, home: &Path) -> Resul
 				package_git_repo,
 				package.url.to_string(),
 				seed.refspec.clone(),
+				gitlab_hosts,
 			)
 		}
 		Sbom(sbom) => {
@@ -411,8 +608,31 @@ fn resolve_target(seed: &TargetSeed, phase: &SpinnerPhase, home: &Path) -> Resul
 				sbom_git_repo,
 				source.to_string(),
 				seed.refspec.clone(),
+				gitlab_hosts,
 			)
 		}
+		Lockfile(lockfile) => Err(hc_error!(
+			"{} lists many targets, which must each be checked",
+			lockfile.kind
+		)),
+		PullRequest(pull_request) => {
+			source::resolve_pull_request(phase, home, pull_request.to_owned(), gitlab_hosts)
+		}
+		CommitRange(range) => {
+			// Resolve the repo at the end of the range, then note where the range starts
+			let repo_seed = TargetSeed {
+				kind: range.repo.as_ref().to_owned(),
+				refspec: Some(range.head.clone()),
+			};
+			let mut target = resolve_target(&repo_seed, phase, home, gitlab_hosts)?;
+			let base_ref = source::git::resolve_commit(&target.local.path, &range.base)
+				.with_context(|| {
+					format!("failed to resolve start of commit range '{}'", range.base)
+				})?;
+			target.specifier = format!("{}@{}..{}", target.specifier, range.base, range.head);
+			target.local.base_ref = Some(base_ref);
+			Ok(target)
+		}
 	}
 }
 
@@ -426,4 +646,15 @@ mod tests {
 		let vars = vec![("HC_GITHUB_TOKEN", Some("test"))];
 		with_env_vars(vars, || assert_eq!(resolve_token().unwrap(), "test"));
 	}
+
+	#[test]
+	fn render_span_test() {
+		let source = "analyze {\n\tinvestigate policy=\"(gt 0.5 $)\"\n}\n";
+		let start = source.find("(gt").unwrap();
+		let rendered = render_span(Path::new("Hipcheck.kdl"), source, &(start..start + 10));
+		assert_eq!(
+			rendered,
+			"  --> Hipcheck.kdl:2:22\n   |\n   | \tinvestigate policy=\"(gt 0.5 $)\"\n   | \t                    ^^^^^^^^^^"
+		);
+	}
 }
@@ -3,20 +3,19 @@
 use crate::{
 	error::{Context as _, Error, Result},
 	hc_error,
+	session::go,
 	target::{Package, PackageHost},
 	util::http::agent,
-	CheckKind, EXIT_FAILURE,
+	CheckKind,
 };
 use serde_json::Value;
-use std::{
-	cmp::{max, Ordering},
-	process::exit,
-};
+use std::cmp::Ordering;
 use url::{Host, Url};
 use xml::reader::{EventReader, XmlEvent};
 
 const NPM: &str = CheckKind::Npm.name();
 const PYPI: &str = CheckKind::Pypi.name();
+const CARGO: &str = CheckKind::Cargo.name();
 
 /// Detect a package's host and extract its correspinding git repo URL
 /// Note: This function is not used for Maven packages, as they are specified differently
@@ -25,6 +24,8 @@ pub fn detect_and_extract(package: &Package) -> Result<Url> {
 	match package.host {
 		PackageHost::Npm => extract_repo_for_npm_package(package),
 		PackageHost::PyPI => extract_repo_for_pypi_package(package),
+		PackageHost::Cargo => extract_repo_for_cargo_package(package),
+		PackageHost::Go => extract_repo_for_go_package(package),
 	}
 }
 
@@ -35,6 +36,8 @@ enum PackageManager {
 	Pypi,
 
 	Maven,
+
+	Cargo,
 }
 
 /*=============================================================================
@@ -280,6 +283,7 @@ impl PackageManager {
 				"pypi.io" | "pypi.org" | "pypi.python.org" | "files.pythonhosted.org",
 			)) => Ok(PackageManager::Pypi),
 			Some(Host::Domain("repo.maven.apache.org")) => Ok(PackageManager::Maven),
+			Some(Host::Domain("crates.io")) => Ok(PackageManager::Cargo),
 			_ => Err(Error::msg("not a known package manager URL")),
 		}
 	}
@@ -313,11 +317,12 @@ pub fn extract_package_version(raw_package: &str) -> Result<(String, String)> {
 }
 
 pub fn extract_package_version_from_url(url: Url) -> Result<(String, String)> {
-	//Get package and version from the URL, npm and pypi only
+	//Get package and version from the URL, npm, pypi, and cargo only
 	//Note maven urls are too complex to work with the npm and pypi url parsing model below
 	let package_type = match PackageManager::detect(&url) {
 		Ok(PackageManager::Npm) => NPM,
 		Ok(PackageManager::Pypi) => PYPI,
+		Ok(PackageManager::Cargo) => CARGO,
 		_ => "no package found for package url",
 	};
 
@@ -351,8 +356,8 @@ pub fn extract_package_version_from_url(url: Url) -> Result<(String, String)> {
 			package_value.unwrap(), //this will graceful error if empty because of panic checking above
 			version.to_string(),    //we check for this in match so we can format url correctly
 		))
-	} else if package_type.contains(PYPI) {
-		//pypi gets the second and third segments
+	} else if package_type.contains(PYPI) || package_type.contains(CARGO) {
+		//pypi and cargo get the second and third segments
 		let mut path_segments = url
 			.path_segments()
 			.ok_or_else(|| hc_error!("Unable to get path"))?;
@@ -376,7 +381,7 @@ fn extract_repo_for_npm_package(full_package: &Package) -> Result<Url> {
 	// Get the package and version
 	let (package, version) = (full_package.name.as_ref(), full_package.version.as_ref());
 
-	let package = error_if_empty(Some(package), "no repository given for npm package");
+	let package = error_if_empty(Some(package), "no repository given for npm package")?;
 	let version = warn_if_empty(
 		Some(version),
 		"no version given for npm package; getting URL for latest version",
@@ -433,7 +438,7 @@ fn extract_repo_for_pypi_package(full_package: &Package) -> Result<Url> {
 	// Get the package and version
 	let (package, version) = (full_package.name.as_ref(), full_package.version.as_ref());
 
-	let package = error_if_empty(Some(package), "no repository given for python package");
+	let package = error_if_empty(Some(package), "no repository given for python package")?;
 
 	extract_repo_for_pypi(package, version)
 }
@@ -477,6 +482,89 @@ pub fn extract_repo_for_pypi(package: &str, version: &str) -> Result<Url> {
 	}
 }
 
+/// Function to extract repo URL for Go module given a Package struct
+fn extract_repo_for_go_package(full_package: &Package) -> Result<Url> {
+	// Get the module path and version
+	let (module, version) = (full_package.name.as_ref(), full_package.version.as_ref());
+
+	let module = error_if_empty(Some(module), "no module path given for go module")?;
+
+	go::extract_repo_for_go(module, version)
+}
+
+/// Function to extract repo URL for Cargo package given a Package struct
+fn extract_repo_for_cargo_package(full_package: &Package) -> Result<Url> {
+	// Get the package and version
+	let (package, version) = (full_package.name.as_ref(), full_package.version.as_ref());
+
+	let package = error_if_empty(Some(package), "no repository given for cargo package")?;
+
+	extract_repo_for_cargo(package, version)
+}
+
+/// Function to extract repo URL for Cargo package given a package name and version
+pub fn extract_repo_for_cargo(package: &str, version: &str) -> Result<Url> {
+	// The repository is part of the crate's metadata, not each version's, so
+	// the crate is looked up even when a version is given.
+	let registry = format!("https://crates.io/api/v1/crates/{}", package);
+
+	// Make an HTTP request to that URL. crates.io rejects requests which don't
+	// identify who is making them.
+	let response = agent::agent()
+		.get(&registry)
+		.set("User-Agent", CRATES_IO_USER_AGENT)
+		.call()
+		.context("request to crates.io API failed, make sure the crate name is correct as well as the crate version")?;
+
+	// Parse the response as JSON.
+	let json: Value = {
+		let intermediate = response
+			.into_string()
+			.context("can't parse crates.io API response")?;
+		serde_json::from_str(&intermediate).context("crates.io API response isn't valid JSON")?
+	};
+
+	find_cargo_repo_url(&json, version)
+}
+
+const CRATES_IO_USER_AGENT: &str = concat!(
+	"hipcheck/",
+	env!("CARGO_PKG_VERSION"),
+	" (https://github.com/mitre/hipcheck)"
+);
+
+/// Find the repository URL in a crates.io API response for a crate, checking
+/// that the crate has the given version.
+fn find_cargo_repo_url(json: &Value, version: &str) -> Result<Url> {
+	if version != "no version" {
+		let published = json
+			.get("versions")
+			.and_then(Value::as_array)
+			.ok_or_else(|| hc_error!("no versions field for crate"))?
+			.iter()
+			.any(|v| v.get("num").and_then(Value::as_str) == Some(version));
+
+		if !published {
+			return Err(hc_error!("crate has no version '{}'", version));
+		}
+	}
+
+	let raw_repository = json
+		.pointer("/crate/repository")
+		.and_then(Value::as_str)
+		.ok_or_else(|| hc_error!("no repository field for crate"))?;
+
+	let repo = sanitize_url(raw_repository.to_owned())
+		.context("invalid repository URL from crates.io API")?;
+
+	// Crates in a workspace often link to their own directory in the repository.
+	if let Some("github.com" | "gitlab.com") = repo.host_str() {
+		pop_url_segments(repo)
+	} else {
+		Ok(repo)
+	}
+}
+
 pub fn extract_repo_for_maven(url: &str) -> Result<Url> {
 	// Make an HTTP request to that URL to get the POM file.
 
@@ -500,12 +588,11 @@ pub fn extract_repo_for_maven(url: &str) -> Result<Url> {
 
 /// Remove unnecessary path segments from tail end of repository URL.
 fn pop_url_segments(mut repo: Url) -> Result<Url> {
-	let times_to_pop = max(
-		repo.path_segments()
-			.ok_or_else(|| hc_error!("Unable to get path"))?
-			.count() - 2,
-		0,
-	);
+	let times_to_pop = repo
+		.path_segments()
+		.ok_or_else(|| hc_error!("Unable to get path"))?
+		.count()
+		.saturating_sub(2);
 	for _ in 0..times_to_pop {
 		repo.path_segments_mut()
 			.map_err(|_| hc_error!("No path found in URL"))?
@@ -514,13 +601,11 @@ fn pop_url_segments(mut repo: Url) -> Result<Url> {
 	Ok(repo)
 }
 
-/// Print an error message if the string is empty or `None`.
-fn error_if_empty<'out, 'inp: 'out>(s: Option<&'inp str>, msg: &'static str) -> &'out str {
-	if is_none_or_empty(s) {
-		eprintln!("error: {}", msg);
-		exit(EXIT_FAILURE);
-	} else {
-		s.unwrap()
+/// Get the string, or an error with the given message if it's empty or `None`.
+fn error_if_empty<'out, 'inp: 'out>(s: Option<&'inp str>, msg: &'static str) -> Result<&'out str> {
+	match s {
+		Some(s) if !s.is_empty() => Ok(s),
+		_ => Err(hc_error!("{}", msg)),
 	}
 }
 
@@ -544,15 +629,26 @@ fn is_none_or_empty(s: Option<&str>) -> bool {
 #[cfg(test)]
 mod tests {
 	use crate::{
-		cli::{CheckNpmArgs, CheckPypiArgs},
+		cli::{CheckCargoArgs, CheckNpmArgs, CheckPypiArgs},
 		target::{TargetSeedKind, ToTargetSeedKind},
 	};
 
 	// Note this useful idiom: importing names from outer (for mod tests) scope.
 	use super::*;
 	use serde_json::json;
+	use std::path::PathBuf;
 	use url::Url;
 
+	/// Load the crates.io API response for `serde` saved in the test fixtures.
+	fn crates_io_serde() -> Value {
+		let manifest = env!("CARGO_MANIFEST_DIR");
+		let path: PathBuf = [manifest, "src", "session", "tests", "crates_io_serde.json"]
+			.iter()
+			.collect();
+		let contents = std::fs::read_to_string(path).unwrap();
+		serde_json::from_str(&contents).unwrap()
+	}
+
 	#[test]
 	fn test_extract_repo_for_pypi() {
 		let link = "https://pypi.org/project/certifi/2021.5.30";
@@ -950,6 +1046,120 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_extract_repo_for_cargo() {
+		let cargo_package = "serde@1.0.200";
+		let link2 = "https://github.com/serde-rs/serde";
+
+		let target_seed = CheckCargoArgs {
+			package: cargo_package.to_string(),
+		}
+		.to_target_seed_kind()
+		.unwrap();
+		if let TargetSeedKind::Package(package) = target_seed {
+			assert_eq!(
+				package,
+				Package {
+					purl: Url::parse("pkg:cargo/serde@1.0.200").unwrap(),
+					name: "serde".to_string(),
+					version: "1.0.200".to_string(),
+					host: PackageHost::Cargo
+				}
+			);
+
+			let cargo_git = Url::parse(link2).unwrap();
+			assert_eq!(
+				find_cargo_repo_url(&crates_io_serde(), &package.version).unwrap(),
+				cargo_git
+			);
+		} else {
+			panic!()
+		}
+	}
+
+	#[test]
+	fn test_extract_repo_for_cargo_2() {
+		let link = "https://crates.io/crates/serde/1.0.199";
+		let link2 = "https://github.com/serde-rs/serde";
+
+		let target_seed = CheckCargoArgs {
+			package: link.to_string(),
+		}
+		.to_target_seed_kind()
+		.unwrap();
+		if let TargetSeedKind::Package(package) = target_seed {
+			assert_eq!(
+				package,
+				Package {
+					purl: Url::parse("pkg:cargo/serde@1.0.199").unwrap(),
+					name: "serde".to_string(),
+					version: "1.0.199".to_string(),
+					host: PackageHost::Cargo
+				}
+			);
+
+			let cargo_git = Url::parse(link2).unwrap();
+			assert_eq!(
+				find_cargo_repo_url(&crates_io_serde(), &package.version).unwrap(),
+				cargo_git
+			);
+		} else {
+			panic!()
+		}
+	}
+
+	#[test]
+	fn test_extract_repo_for_cargo_3() {
+		let cargo_package = "serde";
+		let link2 = "https://github.com/serde-rs/serde";
+
+		let target_seed = CheckCargoArgs {
+			package: cargo_package.to_string(),
+		}
+		.to_target_seed_kind()
+		.unwrap();
+		if let TargetSeedKind::Package(package) = target_seed {
+			assert_eq!(
+				package,
+				Package {
+					purl: Url::parse("pkg:cargo/serde").unwrap(),
+					name: "serde".to_string(),
+					version: "no version".to_string(),
+					host: PackageHost::Cargo
+				}
+			);
+
+			let cargo_git = Url::parse(link2).unwrap();
+			assert_eq!(
+				find_cargo_repo_url(&crates_io_serde(), &package.version).unwrap(),
+				cargo_git
+			);
+		} else {
+			panic!()
+		}
+	}
+
+	#[test]
+	fn test_extract_repo_for_cargo_4() {
+		//should fail, since the crate was never published at this version
+		let result = find_cargo_repo_url(&crates_io_serde(), "0.0.1");
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn repo_for_cargo_workspace_member() {
+		let json = json!({
+			"crate": {
+				"name": "tokio-util",
+				"repository": "https://github.com/tokio-rs/tokio/tree/master/tokio-util"
+			},
+			"versions": [{ "num": "0.7.11" }]
+		});
+		let actual = find_cargo_repo_url(&json, "0.7.11").unwrap();
+		let expected = Url::parse("https://github.com/tokio-rs/tokio").unwrap();
+		assert_eq!(actual, expected);
+	}
+
 	#[test]
 	/// Tests scm:git: prefix removal case.
 	fn test_extract_repo_for_maven_2() {
@@ -1088,4 +1298,15 @@ mod tests {
 		];
 		assert_eq!(actual, expected);
 	}
+
+	#[test]
+	fn test_pop_url_segments() {
+		let url = Url::parse("https://github.com/rust-lang/cargo/tree/master").unwrap();
+		let expected = Url::parse("https://github.com/rust-lang/cargo").unwrap();
+		assert_eq!(pop_url_segments(url).unwrap(), expected);
+
+		// URLs with fewer than two path segments are left alone
+		let url = Url::parse("https://github.com/rust-lang").unwrap();
+		assert_eq!(pop_url_segments(url.clone()).unwrap(), url);
+	}
 }
@@ -5,6 +5,7 @@
 use crate::{
 	error::{Context as _, Result},
 	hc_error,
+	target::Dependency,
 };
 use spdx_rs::models::SPDX;
 use url::Url;
@@ -15,6 +16,14 @@ const DLOAD_LOCN_TAG: &str = "PackageDownloadLocation";
 // Tag-value delimiter
 const DELIMITER: char = ':';
 
+// Tags of the package fields read for each component
+const NAME_TAG: &str = "PackageName";
+const VERSION_TAG: &str = "PackageVersion";
+const EXT_REF_TAG: &str = "ExternalRef";
+
+// The external reference type for pURLs
+const PURL_REF_TYPE: &str = "purl";
+
 // Indicates that a download location does not exist
 const DLOAD_NONE: &str = "NONE";
 
@@ -41,6 +50,107 @@ pub fn extract_spdx_download_url(filepath: &str) -> Result<String> {
 	}
 }
 
+/// Extract every package of an SPDX document, located by its pURL if it has
+/// one, and otherwise by its download location if that's compatible
+pub fn extract_spdx_components(filepath: &str) -> Result<Vec<Dependency>> {
+	let contents = std::fs::read_to_string(filepath)?;
+
+	if contents.contains(DLOAD_LOCN_TAG) {
+		Ok(extract_components_text(&contents))
+	} else if let Ok(spdx) = serde_json::from_str(&contents) {
+		Ok(extract_components_json(spdx))
+	} else {
+		Err(hc_error!("SPDX file is corrupt or incompatible"))
+	}
+}
+
+// Extract every package from an SPDX object obtained from a JSON file
+fn extract_components_json(spdx: SPDX) -> Vec<Dependency> {
+	spdx.package_information
+		.into_iter()
+		.map(|package| {
+			let purl = package
+				.external_reference
+				.iter()
+				.find(|r| r.reference_type == PURL_REF_TYPE)
+				.map(|r| r.reference_locator.clone());
+
+			Dependency {
+				locator: purl.or_else(|| git_locator(&package.package_download_location)),
+				name: package.package_name,
+				version: package.package_version,
+			}
+		})
+		.collect()
+}
+
+// Extract every package from an SPDX text document. Each package's fields
+// follow its `PackageName` tag.
+fn extract_components_text(contents: &str) -> Vec<Dependency> {
+	let mut components = vec![];
+	let mut download_locn = None;
+
+	for line in contents.lines() {
+		let Some((tag, value)) = line.split_once(DELIMITER) else {
+			continue;
+		};
+		let value = value.trim();
+
+		match tag {
+			NAME_TAG => {
+				finish_text_component(&mut components, download_locn.take());
+				components.push(Dependency {
+					name: value.to_owned(),
+					version: None,
+					locator: None,
+				});
+			}
+			VERSION_TAG => {
+				if let Some(component) = components.last_mut() {
+					component.version = Some(value.to_owned());
+				}
+			}
+			DLOAD_LOCN_TAG => download_locn = Some(value.to_owned()),
+			EXT_REF_TAG => {
+				// The value is "<category> <type> <locator>"
+				let mut parts = value.split_whitespace();
+				if let (Some(_), Some(PURL_REF_TYPE), Some(locator)) =
+					(parts.next(), parts.next(), parts.next())
+				{
+					if let Some(component) = components.last_mut() {
+						component.locator = Some(locator.to_owned());
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+	finish_text_component(&mut components, download_locn);
+
+	components
+}
+
+// Fall back to the download location of the last package read from a text
+// document if it had no pURL
+fn finish_text_component(components: &mut [Dependency], download_locn: Option<String>) {
+	if let (Some(component), Some(locn)) = (components.last_mut(), download_locn) {
+		if component.locator.is_none() {
+			component.locator = git_locator(&locn);
+		}
+	}
+}
+
+// Turn a download location into a Git VCS URL that can be checked as a target,
+// if it's compatible
+fn git_locator(locn: &str) -> Option<String> {
+	match locn {
+		DLOAD_NONE | DLOAD_NOASSERT => None,
+		locn => parse_download_url(locn)
+			.ok()
+			.map(|url| format!("{SCM_GIT_PLUS}{url}")),
+	}
+}
+
 // Extract the first compatible package download location from an SPDX
 // object obtained from a JSON file
 fn extract_download_url_json(spdx: SPDX) -> Result<String> {
@@ -94,3 +204,45 @@ fn parse_download_url(locn: &str) -> Result<String> {
 
 	Ok(url.as_str().to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_extract_components_from_spdx_text() {
+		let contents = "SPDXVersion: SPDX-2.3
+PackageName: left-pad
+PackageVersion: 1.3.0
+PackageDownloadLocation: NOASSERTION
+ExternalRef: PACKAGE-MANAGER purl pkg:npm/left-pad@1.3.0
+
+PackageName: hipcheck
+PackageDownloadLocation: git+https://github.com/mitre/hipcheck.git
+
+PackageName: unknown
+PackageDownloadLocation: NONE
+";
+		let components = extract_components_text(contents);
+		assert_eq!(
+			components,
+			vec![
+				Dependency {
+					name: "left-pad".to_owned(),
+					version: Some("1.3.0".to_owned()),
+					locator: Some("pkg:npm/left-pad@1.3.0".to_owned()),
+				},
+				Dependency {
+					name: "hipcheck".to_owned(),
+					version: None,
+					locator: Some("git+https://github.com/mitre/hipcheck.git".to_owned()),
+				},
+				Dependency {
+					name: "unknown".to_owned(),
+					version: None,
+					locator: None,
+				},
+			]
+		);
+	}
+}
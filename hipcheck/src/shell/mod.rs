@@ -5,10 +5,16 @@
 use crate::{
 	cli::Format,
 	error::{Error, Result},
-	report::{RecommendationKind, Report},
+	report::{
+		aggregate::{AggregateReport, TargetOutcome},
+		diff::{AnalysisOutcome, ReportDiff},
+		sarif::SarifLog,
+		RecommendationKind, Report,
+	},
 };
 use console::{Emoji, Style, Term};
 use indicatif::{MultiProgress, ProgressDrawTarget};
+use serde::Serialize;
 use std::{
 	fmt,
 	fmt::{Alignment, Debug, Display, Formatter},
@@ -43,6 +49,9 @@ pub const LEFT_COL_WIDTH: usize = 20;
 /// Empty static string used for drawing padding.
 const EMPTY: &str = "";
 
+/// The most characters of a value to show in a policy evaluation trace.
+const MAX_TRACE_VALUE_WIDTH: usize = 60;
+
 /// Type interface to the global shell used to produce output in the user's terminal.
 #[derive(Debug)]
 pub struct Shell {
@@ -221,7 +230,7 @@ impl Shell {
 		}
 	}
 
-	/// Print a hipcheck [Error]. Human readable errors will go to the standard error, JSON will go to the standard output.
+	/// Print a hipcheck [Error]. Human readable errors will go to the standard error, JSON and SARIF will go to the standard output.
 	pub fn print_error(err: &Error, format: Format) {
 		match format {
 			Format::Human => {
@@ -267,6 +276,22 @@ impl Shell {
 					stdout.flush().expect("flushed standard out");
 				});
 			}
+
+			Format::Sarif => {
+				// Report the error as a failed run, so tools reading the output
+				// still get a valid SARIF log.
+				let sarif = SarifLog::from_error(err);
+
+				Shell::in_suspend(|| {
+					let mut stdout = Term::buffered_stdout();
+
+					serde_json::to_writer_pretty(&mut stdout, &sarif)
+						.expect("Wrote SARIF to standard output.");
+
+					writeln!(&mut stdout).expect("wrote newline to standard out");
+					stdout.flush().expect("flushed standard out");
+				});
+			}
 		}
 	}
 
@@ -275,11 +300,49 @@ impl Shell {
 		match format {
 			Format::Json => print_json(report),
 			Format::Human => print_human(report),
+			Format::Sarif => print_json(SarifLog::from_report(&report)),
+		}
+	}
+
+	/// Print a report on many targets in the requested format to the standard output.
+	pub fn print_aggregate_report(report: AggregateReport, format: Format) -> Result<()> {
+		match format {
+			Format::Json => print_json(report),
+			Format::Human => print_aggregate_human(report),
+			Format::Sarif => print_json(SarifLog::from_aggregate_report(&report)),
+		}
+	}
+
+	/// Print a value as a single line of JSON to the standard output, like one
+	/// entry in a stream of results.
+	pub fn print_json_line(value: impl Serialize) -> Result<()> {
+		Shell::in_suspend(|| {
+			let mut stdout = Term::stdout();
+			serde_json::to_writer(&mut stdout, &value)?;
+			writeln!(&mut stdout)?;
+			stdout.flush()?;
+			Ok(())
+		})
+	}
+
+	/// Print a table of the outcome of each of many targets to the standard error.
+	pub fn print_summary_table(report: &AggregateReport) {
+		print_summary_table(report)
+	}
+
+	/// Print what changed between two reports in the requested format to the standard output.
+	pub fn print_report_diff(diff: ReportDiff, format: Format) -> Result<()> {
+		match format {
+			Format::Json => print_json(diff),
+			Format::Human => print_diff_human(diff),
+			Format::Sarif => Err(crate::hc_error!(
+				"SARIF output isn't supported when comparing reports"
+			)),
 		}
 	}
 }
 
-fn print_json(report: Report) -> Result<()> {
+fn print_json(report: impl Serialize) -> Result<()> {
 	// Suspend the shell to print the JSON report.
 	Shell::in_suspend(|| {
 		let mut stdout = Term::stdout();
@@ -387,6 +450,19 @@ fn print_human(report: Report) -> Result<()> {
 				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", concern);
 			}
 
+			// Show how the policy was evaluated, so it's clear what tripped it.
+			let mut trace = failing_analysis.trace().peekable();
+			if trace.peek().is_some() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} policy evaluation:");
+				for entry in trace {
+					macros::println!(
+						"{EMPTY:LEFT_COL_WIDTH$}   {} \u{2192} {}",
+						entry.expr,
+						elide(&entry.value, MAX_TRACE_VALUE_WIDTH)
+					);
+				}
+			}
+
 			// Newline at the end for spacing.
 			macros::println!();
 		}
@@ -412,6 +488,13 @@ fn print_human(report: Report) -> Result<()> {
 				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {msg}");
 			}
 
+			if let Some(stderr) = errored_analysis.plugin_stderr() {
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} plugin stderr:");
+				for line in stderr.lines() {
+					macros::println!("{EMPTY:LEFT_COL_WIDTH$}   {line}");
+				}
+			}
+
 			// Newline for spacing.
 			macros::println!();
 		}
@@ -437,6 +520,246 @@ fn print_human(report: Report) -> Result<()> {
 	Ok(())
 }
 
+fn print_aggregate_human(report: AggregateReport) -> Result<()> {
+	//      Analyzed 'bom.json'
+	//               using Hipcheck <hipcheck_version>
+	//               on <analyzed_at:pretty_print>
+	//
+	//       Targets
+	//          PASS body-parser@1.19.0 (pkg:npm/body-parser@1.19.0)
+	//               risk rated as 0.20, policy was (gt 0.5 $)
+	//   INVESTIGATE juice-shop (pkg:github/juice-shop/juice-shop)
+	//               risk rated as 0.60, policy was (gt 0.5 $)
+	//               - 'mitre/activity' failed, (lte $ P71w)
+	//         Error left-pad@1.3.0 (pkg:npm/left-pad@1.3.0)
+	//               failed to clone remote repository
+	//       Skipped internal-lib
+	//               the dependency has no pURL or compatible download location
+	//
+	//       Summary
+	//               4 targets: 1 pass, 1 investigate, 1 errored, 1 skipped
+
+	macros::println!();
+	macros::println!("{:>LEFT_COL_WIDTH$} '{}'", Title::Analyzed, report.source);
+	macros::println!(
+		"{EMPTY:LEFT_COL_WIDTH$} using Hipcheck {}",
+		report.hipcheck_version
+	);
+	macros::println!("{EMPTY:LEFT_COL_WIDTH$} on {}", report.analyzed_at);
+	macros::println!();
+
+	macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Targets"));
+
+	for target in report.targets.iter() {
+		let name = match &target.locator {
+			Some(locator) => format!("{} ({})", target.name, locator),
+			None => target.name.clone(),
+		};
+
+		match &target.outcome {
+			TargetOutcome::Analyzed { report } => {
+				let recommendation = report.recommendation();
+				macros::println!(
+					"{:>LEFT_COL_WIDTH$} {}",
+					Title::from(recommendation.kind),
+					name
+				);
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {}", recommendation.statement());
+				for failing in report.failing_analyses() {
+					macros::println!(
+						"{EMPTY:LEFT_COL_WIDTH$} - {}",
+						failing.analysis().statement()
+					);
+				}
+				for errored in report.errored_analyses() {
+					macros::println!("{EMPTY:LEFT_COL_WIDTH$} ? {}", errored.top_msg());
+				}
+			}
+			TargetOutcome::Errored { error } => {
+				macros::println!("{:>LEFT_COL_WIDTH$} {}", Title::Error, name);
+				for msg in error.messages() {
+					macros::println!("{EMPTY:LEFT_COL_WIDTH$} {msg}");
+				}
+			}
+			TargetOutcome::Skipped { reason } => {
+				macros::println!("{:>LEFT_COL_WIDTH$} {}", Title::Skipped, name);
+				macros::println!("{EMPTY:LEFT_COL_WIDTH$} {reason}");
+			}
+		}
+	}
+
+	macros::println!();
+
+	let summary = &report.summary;
+	macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Summary"));
+	macros::println!(
+		"{EMPTY:LEFT_COL_WIDTH$} {} targets: {} pass, {} investigate, {} errored, {} skipped",
+		summary.total,
+		summary.pass,
+		summary.investigate,
+		summary.errored,
+		summary.skipped
+	);
+	macros::println!();
+
+	Ok(())
+}
+
+fn print_summary_table(report: &AggregateReport) {
+	//       Summary
+	//          PASS 0.20 left-pad@1.3.0
+	//   INVESTIGATE 0.60 pkg:npm/chalk@5.3.0
+	//         Error      https://github.com/mitre/hipcheck/pull/123
+	//       Skipped      -t npm
+	//               4 targets: 1 pass, 1 investigate, 1 errored, 1 skipped
+
+	macros::eprintln!();
+	macros::eprintln!("{:>LEFT_COL_WIDTH$}", Title::Section("Summary"));
+
+	for target in report.targets.iter() {
+		match &target.outcome {
+			TargetOutcome::Analyzed { report } => {
+				let recommendation = report.recommendation();
+				macros::eprintln!(
+					"{:>LEFT_COL_WIDTH$} {:.2} {}",
+					Title::from(recommendation.kind),
+					recommendation.risk_score(),
+					target.name
+				);
+			}
+			TargetOutcome::Errored { .. } => {
+				macros::eprintln!("{:>LEFT_COL_WIDTH$}      {}", Title::Error, target.name);
+			}
+			TargetOutcome::Skipped { .. } => {
+				macros::eprintln!("{:>LEFT_COL_WIDTH$}      {}", Title::Skipped, target.name);
+			}
+		}
+	}
+
+	let summary = &report.summary;
+	macros::eprintln!(
+		"{EMPTY:LEFT_COL_WIDTH$} {} targets: {} pass, {} investigate, {} errored, {} skipped",
+		summary.total,
+		summary.pass,
+		summary.investigate,
+		summary.errored,
+		summary.skipped
+	);
+	macros::eprintln!();
+}
+
+fn print_diff_human(diff: ReportDiff) -> Result<()> {
+	//      Compared 'hipcheck' (abc123) on <analyzed_at:pretty_print>
+	//               with 'hipcheck' (def456) on <analyzed_at:pretty_print>
+	//
+	//      Analyses
+	//             - 'mitre/activity' passed before, failed now
+	//             + 'mitre/churn' failed before, passed now
+	//               'mitre/typo' errored before, wasn't run now
+	//
+	//  New Concerns
+	//             - mitre/churn: commit def is large
+	//
+	// Resolved Concerns
+	//             + mitre/churn: commit abc is large
+	//
+	//    Risk Score
+	//               rated as 0.60, was 0.20
+	//
+	// Recommendation
+	//   INVESTIGATE was PASS
+
+	macros::println!();
+	macros::println!(
+		"{:>LEFT_COL_WIDTH$} {} on {}",
+		Title::Compared,
+		diff.old.analyzed(),
+		diff.old.analyzed_at
+	);
+	macros::println!(
+		"{EMPTY:LEFT_COL_WIDTH$} with {} on {}",
+		diff.new.analyzed(),
+		diff.new.analyzed_at
+	);
+	macros::println!();
+
+	if diff.is_empty() {
+		macros::println!("{EMPTY:LEFT_COL_WIDTH$} no changes");
+		macros::println!();
+		return Ok(());
+	}
+
+	if !diff.analyses.is_empty() {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Analyses"));
+		for change in diff.analyses.iter() {
+			let title = match change.new {
+				Some(AnalysisOutcome::Passed) => Title::Passed,
+				Some(AnalysisOutcome::Failed) => Title::Failed,
+				Some(AnalysisOutcome::Errored) => Title::Errored,
+				None => Title::Section(""),
+			};
+			macros::println!("{:>LEFT_COL_WIDTH$} {}", title, change.statement());
+		}
+		macros::println!();
+	}
+
+	let concern_sections = [
+		("New Concerns", Title::Failed, &diff.new_concerns),
+		("Resolved Concerns", Title::Passed, &diff.resolved_concerns),
+	];
+	for (section, title, concerns) in concern_sections {
+		if concerns.is_empty() {
+			continue;
+		}
+
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section(section));
+		for analysis in concerns.iter() {
+			for concern in analysis.concerns.iter() {
+				macros::println!(
+					"{:>LEFT_COL_WIDTH$} {}: {}",
+					title,
+					analysis.analysis,
+					concern
+				);
+			}
+		}
+		macros::println!();
+	}
+
+	if let Some(risk_score) = &diff.risk_score {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Risk Score"));
+		macros::println!(
+			"{EMPTY:LEFT_COL_WIDTH$} rated as {:.2}, was {:.2}",
+			risk_score.new,
+			risk_score.old
+		);
+		macros::println!();
+	}
+
+	if let Some(recommendation) = &diff.recommendation {
+		macros::println!("{:>LEFT_COL_WIDTH$}", Title::Section("Recommendation"));
+		macros::println!(
+			"{:>LEFT_COL_WIDTH$} was {}",
+			Title::from(recommendation.new),
+			Title::from(recommendation.old)
+		);
+		macros::println!();
+	}
+
+	Ok(())
+}
+
+/// Shorten a value to at most `max` characters, marking where it was cut.
+fn elide(value: &str, max: usize) -> String {
+	if value.chars().count() <= max {
+		return value.to_owned();
+	}
+
+	let mut elided: String = value.chars().take(max.saturating_sub(1)).collect();
+	elided.push('\u{2026}');
+	elided
+}
+
 /// The "title" of a message; may be accompanied by a timestamp or outcome.
 #[derive(Debug)]
 #[allow(unused)]
@@ -445,6 +768,8 @@ enum Title {
 	Analyzing,
 	/// "Analyzed"
 	Analyzed,
+	/// "Compared"
+	Compared,
 	/// The name of the section.
 	Section(&'static str),
 	/// An analysis passed.
@@ -463,6 +788,8 @@ enum Title {
 	Investigate,
 	/// "Error"
 	Error,
+	/// "Skipped"
+	Skipped,
 }
 
 impl Title {
@@ -472,6 +799,7 @@ impl Title {
 		match self {
 			Analyzing => "Analyzing",
 			Analyzed => "Analyzed",
+			Compared => "Compared",
 			Section(s) => s,
 			Passed => "+",
 			Failed => "-",
@@ -481,6 +809,7 @@ impl Title {
 			Pass => "PASS",
 			Investigate => "INVESTIGATE",
 			Error => "Error",
+			Skipped => "Skipped",
 		}
 	}
 
@@ -489,12 +818,12 @@ impl Title {
 		use Title::*;
 
 		let color = match self {
-			Analyzed | Section(..) => Some(Blue),
+			Analyzed | Compared | Section(..) => Some(Blue),
 			Analyzing | Done => Some(Cyan),
 			InProgress => Some(Magenta),
 			Passed | Pass => Some(Green),
 			Failed | Investigate => Some(Red),
-			Errored => Some(Yellow),
+			Errored | Skipped => Some(Yellow),
 			Error => Some(Red),
 		};
 
@@ -25,6 +25,26 @@ pub fn spinner_style() -> &'static ProgressStyle {
 	})
 }
 
+/// A function told the name and status of each phase whenever either changes.
+pub type PhaseListener = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+/// Global static storing the function phase changes are reported to, if any.
+static PHASE_LISTENER: OnceLock<PhaseListener> = OnceLock::new();
+
+/// Report each change to a phase to the given function, besides drawing it in
+/// the terminal, like to show progress somewhere else. Only one listener can
+/// be set; returns `false` if one already was.
+pub fn set_phase_listener(listener: PhaseListener) -> bool {
+	PHASE_LISTENER.set(listener).is_ok()
+}
+
+/// Tell the phase listener, if there is one, about a change to a phase.
+fn notify_listener(name: &str, status: &str) {
+	if let Some(listener) = PHASE_LISTENER.get() {
+		listener(name, status);
+	}
+}
+
 /// A spinner phase (unknown duration/completion length) in the processing of hipcheck.
 ///
 /// This phase will contain and manage a spinner [ProgressBar] that will print its status and will print a
@@ -60,6 +80,7 @@ impl SpinnerPhase {
 		// Set the initial message of the bar.
 		bar.set_prefix(ROCKET_SHIP.to_string());
 		bar.set_message(format!("{name} (starting...)"));
+		notify_listener(&name, "starting");
 
 		// Return phase object.
 		Self { name, bar }
@@ -75,6 +96,7 @@ impl SpinnerPhase {
 		if self.bar.position() == 0 {
 			self.bar.set_message(format!("{} (running...)", self.name));
 			self.bar.set_prefix(HOUR_GLASS.to_string());
+			notify_listener(&self.name, "running");
 		}
 
 		self.bar.inc(1)
@@ -83,8 +105,10 @@ impl SpinnerPhase {
 	/// Update the status and redraw this bar with the new status.
 	/// This status may be over-written if the bar changes states into "done" or the status is updated otherwise.
 	pub fn update_status(&self, status: impl Display) {
+		let status = status.to_string();
 		self.bar.set_message(format!("{} ({status})", self.name));
 		self.bar.set_prefix(HOUR_GLASS.to_string());
+		notify_listener(&self.name, &status);
 	}
 
 	/// Set this spinner phase to tick steadily.
@@ -99,6 +123,7 @@ impl SpinnerPhase {
 
 	/// Finishes this spinner, leaving it in the terminal with an updated "done" message.
 	pub fn finish_successful(&self) {
+		notify_listener(&self.name, "done");
 		match Shell::get_verbosity() {
 			Verbosity::Normal => {
 				super::macros::println!(
@@ -116,6 +141,7 @@ impl SpinnerPhase {
 	#[allow(unused)]
 	/// Finish this spinner, leaving it in the terminal with an updated "error" message and a red exclamation.
 	pub fn finish_error(&self) {
+		notify_listener(&self.name, "errored");
 		super::macros::println!(
 			"{:>LEFT_COL_WIDTH$} {} ({})",
 			Title::Errored,
@@ -11,6 +11,7 @@ use console::Term;
 use git2::{
 	build::{CheckoutBuilder, RepoBuilder},
 	AnnotatedCommit, Branch, FetchOptions, Progress, Reference, RemoteCallbacks, Repository,
+	StatusOptions,
 };
 use std::{cell::OnceCell, io::Write, path::Path};
 use url::Url;
@@ -156,8 +157,12 @@ pub fn checkout(repo_path: &Path, refspec: Option<String>) -> HcResult<String> {
 		// Try refspec as given
 		let tgt_ref: AnnotatedCommit = match repo.revparse_single(&refspec_str) {
 			Ok(object) => repo.find_annotated_commit(object.peel_to_commit()?.id())?,
-			// If that refspec is not found, try it again with a leading "v"
-			Err(e) => match repo.revparse_single(&format!("v{refspec_str}")) {
+			// If that refspec is not found, try it again with a leading "v", then
+			// as a branch of the remote the repo was cloned from
+			Err(e) => match repo
+				.revparse_single(&format!("v{refspec_str}"))
+				.or_else(|_| repo.revparse_single(&format!("origin/{refspec_str}")))
+			{
 				Ok(new_object) => repo.find_annotated_commit(new_object.peel_to_commit()?.id())?,
 				Err(_) => {
 					return Err(hc_error!(
@@ -245,6 +250,55 @@ pub fn checkout(repo_path: &Path, refspec: Option<String>) -> HcResult<String> {
 	Ok(ret_str)
 }
 
+/// Get the full ID of the commit currently checked out in the repo.
+pub fn get_head_commit(repo_path: &Path) -> HcResult<String> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let commit = repo.head()?.peel_to_commit()?;
+	Ok(commit.id().to_string())
+}
+
+/// Whether the repo has changes which aren't committed, including files which
+/// aren't tracked or ignored.
+pub fn has_uncommitted_changes(repo_path: &Path) -> HcResult<bool> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let mut options = StatusOptions::new();
+	options.include_untracked(true).include_ignored(false);
+	let statuses = repo.statuses(Some(&mut options))?;
+	Ok(!statuses.is_empty())
+}
+
+/// Get the full ID of the commit a ref points to. Like with `checkout`, a
+/// branch of the remote the repo was cloned from may be given by its name alone.
+pub fn resolve_commit(repo_path: &Path, refspec: &str) -> HcResult<String> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let object = repo
+		.revparse_single(refspec)
+		.or_else(|_| repo.revparse_single(&format!("origin/{refspec}")))
+		.map_err(|e| hc_error!("could not find '{}' in repo: {}", refspec, e))?;
+	let id = object.peel_to_commit()?.id().to_string();
+	Ok(id)
+}
+
+/// Get the full ID of the best common ancestor of two refs, where a branch
+/// from one was merged with the other.
+pub fn merge_base(repo_path: &Path, one: &str, two: &str) -> HcResult<String> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let one = repo.revparse_single(one)?.peel_to_commit()?.id();
+	let two = repo.revparse_single(two)?.peel_to_commit()?.id();
+	let id = repo.merge_base(one, two)?.to_string();
+	Ok(id)
+}
+
+/// Fetch a single ref from the remote the repo was cloned from, like the head
+/// of a pull request, which isn't fetched with the remote's branches.
+pub fn fetch_ref(repo_path: &Path, remote_ref: &str, local_ref: &str) -> HcResult<()> {
+	let repo: Repository = Repository::open(repo_path)?;
+	let mut remote = repo.find_remote("origin")?;
+	let refspec = format!("+{remote_ref}:{local_ref}");
+	remote.fetch(&[refspec.as_str()], Some(&mut make_fetch_opts()), None)?;
+	Ok(())
+}
+
 /// Do a `git fetch` for all remotes in the repo.
 pub fn fetch(repo_path: &Path) -> HcResult<()> {
 	// Open the repo with git2.
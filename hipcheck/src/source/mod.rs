@@ -9,11 +9,19 @@ use crate::{
 	error::{Context, Error, Result},
 	hc_error,
 	shell::spinner_phase::SpinnerPhase,
-	target::{KnownRemote, LocalGitRepo, RemoteGitRepo, Target},
+	target::{KnownRemote, LocalGitRepo, PullRequest, RemoteGitRepo, Target},
+	util::http::{
+		agent::{self, is_offline},
+		authenticated_agent::AuthenticatedAgent,
+	},
 };
 use pathbuf::pathbuf;
-use std::path::{Path, PathBuf};
-use url::{Host, Url};
+use serde_json::Value;
+use std::{
+	env::var,
+	path::{Path, PathBuf},
+};
+use url::{form_urlencoded, Host, Url};
 
 /// Resolving is how we ensure we have a valid, ready-to-go source of Git data
 /// for the rest of Hipcheck's analysis. The below functions handle the resolution
@@ -38,6 +46,7 @@ pub fn resolve_local_repo(
 	phase: &SpinnerPhase,
 	root: &Path,
 	local_repo: LocalGitRepo,
+	gitlab_hosts: &[String],
 ) -> Result<Target> {
 	let src = local_repo.path.clone();
 
@@ -53,14 +62,18 @@ pub fn resolve_local_repo(
 	let git_ref = git::checkout(&path, Some(local_repo.git_ref.clone()))?;
 	phase.update_status("trying to get remote");
 	let remote = match try_resolve_remote_for_local(&path) {
-		Ok(remote) => Some(remote),
+		Ok(remote) => Some(identify_self_hosted_remote(remote, gitlab_hosts)?),
 		Err(err) => {
 			log::debug!("failed to get remote [err='{}']", err);
 			None
 		}
 	};
 
-	let local = LocalGitRepo { path, git_ref };
+	let local = LocalGitRepo {
+		path,
+		git_ref,
+		base_ref: None,
+	};
 
 	Ok(Target {
 		specifier,
@@ -70,21 +83,53 @@ pub fn resolve_local_repo(
 	})
 }
 
-/// Creates a RemoteGitRepo struct from a given git URL by idenfitying if it is from a known host (currently only GitHub) or not
+/// Creates a RemoteGitRepo struct from a given git URL by idenfitying if it is from a known host (currently GitHub or gitlab.com) or not
 pub fn get_remote_repo_from_url(url: Url) -> Result<RemoteGitRepo> {
+	if url.host().is_none() {
+		return Err(hc_error!("Target repo URL is missing a host"));
+	}
+
+	let known_remote = get_known_remote(&url, &[])?;
+	Ok(RemoteGitRepo { url, known_remote })
+}
+
+/// Identify the known host of a remote repo which wasn't recognized when it
+/// was first resolved, because it's on one of the given self-hosted GitLab
+/// instances.
+pub fn identify_self_hosted_remote(
+	remote_repo: RemoteGitRepo,
+	gitlab_hosts: &[String],
+) -> Result<RemoteGitRepo> {
+	if remote_repo.known_remote.is_some() {
+		return Ok(remote_repo);
+	}
+
+	let known_remote = get_known_remote(&remote_repo.url, gitlab_hosts)?;
+	Ok(RemoteGitRepo {
+		known_remote,
+		..remote_repo
+	})
+}
+
+/// Get the known host a git URL is from, if any. Besides GitHub and
+/// gitlab.com, the given self-hosted GitLab instances are known.
+fn get_known_remote(url: &Url, gitlab_hosts: &[String]) -> Result<Option<KnownRemote>> {
 	match url.host() {
 		Some(Host::Domain("github.com")) => {
-			let (owner, repo) = get_github_owner_and_repo(&url)?;
-			Ok(RemoteGitRepo {
-				url,
-				known_remote: Some(KnownRemote::GitHub { owner, repo }),
-			})
+			let (owner, repo) = get_github_owner_and_repo(url)?;
+			Ok(Some(KnownRemote::GitHub { owner, repo }))
+		}
+		Some(Host::Domain(host))
+			if host == "gitlab.com" || gitlab_hosts.iter().any(|h| h == host) =>
+		{
+			let (namespace, project) = get_gitlab_namespace_and_project(url)?;
+			Ok(Some(KnownRemote::GitLab {
+				host: host.to_owned(),
+				namespace,
+				project,
+			}))
 		}
-		Some(_) => Ok(RemoteGitRepo {
-			url,
-			known_remote: None,
-		}),
-		None => Err(hc_error!("Target repo URL is missing a host")),
+		_ => Ok(None),
 	}
 }
 
@@ -94,15 +139,167 @@ pub fn resolve_remote_repo(
 	root: &Path,
 	remote_repo: RemoteGitRepo,
 	refspec: Option<String>,
+	gitlab_hosts: &[String],
 ) -> Result<Target> {
 	// For remote repos originally specified by their URL, the specifier is just that URL
 	let specifier = remote_repo.url.to_string();
 
+	// Self-hosted GitLab repos are cloned where other GitLab repos are
+	let remote_repo = identify_self_hosted_remote(remote_repo, gitlab_hosts)?;
+
+	let path = remote_clone_dir(root, &remote_repo)?;
+	let git_ref = clone_or_update_remote(phase, &remote_repo.url, &path, refspec)?;
+
+	let local = LocalGitRepo {
+		path,
+		git_ref,
+		base_ref: None,
+	};
+
+	Ok(Target {
+		specifier,
+		local,
+		remote: Some(remote_repo),
+		package: None,
+	})
+}
+
+/// Resolves a pull request into a Target for analysis by Hipcheck, covering
+/// the commits it adds to the branch it's to be merged into
+pub fn resolve_pull_request(
+	phase: &SpinnerPhase,
+	root: &Path,
+	mut pull_request: PullRequest,
+	gitlab_hosts: &[String],
+) -> Result<Target> {
+	// fetching the pull request and finding its base both need the network
+	if is_offline() {
+		return Err(hc_error!(
+			"pull request #{} can't be checked while offline",
+			pull_request.number
+		));
+	}
+
+	let specifier = pull_request.url.to_string();
+
+	pull_request.remote = identify_self_hosted_remote(pull_request.remote, gitlab_hosts)?;
+
+	let path = remote_clone_dir(root, &pull_request.remote)?;
+	update_remote(phase, &pull_request.remote.url, &path)?;
+
+	phase.update_status("fetching pull request");
+	let local_ref = format!("refs/remotes/origin/pull/{}", pull_request.number);
+	git::fetch_ref(&path, &pull_request.head_ref, &local_ref)
+		.with_context(|| format!("failed to fetch pull request #{}", pull_request.number))?;
+
+	phase.update_status("finding pull request base");
+	let base_branch = get_pull_request_base_branch(&pull_request).with_context(|| {
+		format!(
+			"failed to get the base branch of pull request #{}",
+			pull_request.number
+		)
+	})?;
+	let base_ref = git::merge_base(&path, &local_ref, &format!("origin/{base_branch}"))
+		.with_context(|| {
+			format!(
+				"failed to find where pull request #{} branched from '{}'",
+				pull_request.number, base_branch
+			)
+		})?;
+	let git_ref = git::checkout(&path, Some(local_ref))?;
+
+	let local = LocalGitRepo {
+		path,
+		git_ref,
+		base_ref: Some(base_ref),
+	};
+
+	Ok(Target {
+		specifier,
+		local,
+		remote: Some(pull_request.remote),
+		package: None,
+	})
+}
+
+/// Get the branch a pull request is to be merged into, from the API of the
+/// GitHub or GitLab instance it's on.
+fn get_pull_request_base_branch(pull_request: &PullRequest) -> Result<String> {
+	let (api_url, pointer) = pull_request_api_url(pull_request)?;
+
+	let request = match pull_request.remote.known_remote {
+		Some(KnownRemote::GitHub { .. }) => match var("HC_GITHUB_TOKEN") {
+			Ok(token) if !token.is_empty() => AuthenticatedAgent::new(&token).get(&api_url),
+			_ => agent::agent().get(&api_url),
+		},
+		_ => agent::agent().get(&api_url),
+	};
+	let response: Value = request
+		.call()?
+		.into_json()
+		.context("pull request API response isn't valid JSON")?;
+
+	response
+		.pointer(pointer)
+		.and_then(Value::as_str)
+		.map(str::to_owned)
+		.ok_or_else(|| hc_error!("pull request API response has no base branch"))
+}
+
+/// Get the API URL for a pull request, and the pointer to the branch it's to
+/// be merged into in the response.
+fn pull_request_api_url(pull_request: &PullRequest) -> Result<(String, &'static str)> {
+	match &pull_request.remote.known_remote {
+		Some(KnownRemote::GitHub { owner, repo }) => Ok((
+			format!(
+				"https://api.github.com/repos/{}/{}/pulls/{}",
+				owner, repo, pull_request.number
+			),
+			"/base/ref",
+		)),
+		Some(KnownRemote::GitLab {
+			host,
+			namespace,
+			project,
+		}) => {
+			let id: String =
+				form_urlencoded::byte_serialize(format!("{namespace}/{project}").as_bytes())
+					.collect();
+			Ok((
+				format!(
+					"https://{}/api/v4/projects/{}/merge_requests/{}",
+					host, id, pull_request.number
+				),
+				"/target_branch",
+			))
+		}
+		None => Err(hc_error!(
+			"{} isn't on GitHub or a known GitLab instance, so its base branch can't be found",
+			pull_request.url
+		)),
+	}
+}
+
+/// Get the directory in '<root>/clones' a remote repo is cloned into.
+fn remote_clone_dir(root: &Path, remote_repo: &RemoteGitRepo) -> Result<PathBuf> {
 	let path = match remote_repo.known_remote {
 		Some(KnownRemote::GitHub {
 			ref owner,
 			ref repo,
 		}) => pathbuf![root, "clones", "github", owner, repo],
+		// Subgroups are flattened into one directory, like unknown remotes are.
+		Some(KnownRemote::GitLab {
+			ref host,
+			ref namespace,
+			ref project,
+		}) => pathbuf![
+			root,
+			"clones",
+			"gitlab",
+			host,
+			&namespace.replace('/', "__"),
+			project
+		],
 		_ => {
 			let clone_dir = build_unknown_remote_clone_dir(&remote_repo.url)
 				.context("failed to prepare local clone directory")?;
@@ -110,16 +307,7 @@ pub fn resolve_remote_repo(
 		}
 	};
 
-	let git_ref = clone_or_update_remote(phase, &remote_repo.url, &path, refspec)?;
-
-	let local = LocalGitRepo { path, git_ref };
-
-	Ok(Target {
-		specifier,
-		local,
-		remote: Some(remote_repo),
-		package: None,
-	})
+	Ok(path)
 }
 
 /// Resolves a remote git repo derived from a source other than its remote location (e.g. a package or SPDX file) into a Target for analysis by Hipcheck
@@ -129,8 +317,9 @@ pub fn resolve_remote_package_repo(
 	remote_repo: RemoteGitRepo,
 	specifier: String,
 	refspec: Option<String>,
+	gitlab_hosts: &[String],
 ) -> Result<Target> {
-	let mut target = resolve_remote_repo(phase, root, remote_repo, refspec)?;
+	let mut target = resolve_remote_repo(phase, root, remote_repo, refspec, gitlab_hosts)?;
 	target.specifier = specifier;
 	Ok(target)
 }
@@ -173,23 +362,12 @@ fn try_resolve_remote_for_local(local: &Path) -> Result<RemoteGitRepo> {
 		Url::parse(&raw)?
 	};
 
-	let host = url
-		.host_str()
-		.ok_or_else(|| hc_error!("no host name in '{}'", url))?;
-
-	match host {
-		"github.com" => {
-			let (owner, repo) = get_github_owner_and_repo(&url)?;
-			Ok(RemoteGitRepo {
-				url,
-				known_remote: Some(KnownRemote::GitHub { owner, repo }),
-			})
-		}
-		_ => Ok(RemoteGitRepo {
-			url,
-			known_remote: None,
-		}),
+	if url.host().is_none() {
+		return Err(hc_error!("no host name in '{}'", url));
 	}
+
+	let known_remote = get_known_remote(&url, &[])?;
+	Ok(RemoteGitRepo { url, known_remote })
 }
 
 fn get_remote_from_upstream(upstream: &str) -> Option<&str> {
@@ -215,6 +393,82 @@ pub fn get_github_owner_and_repo(url: &Url) -> Result<(String, String)> {
 	Ok((owner, repo))
 }
 
+/// Get the pull request a GitHub pull request URL, like
+/// `https://github.com/<owner>/<repo>/pull/<number>`, or GitLab merge request
+/// URL, like `https://<host>/<namespace>/<project>/-/merge_requests/<number>`,
+/// is for.
+pub fn get_pull_request_from_url(url: Url) -> Result<PullRequest> {
+	let segments = url
+		.path_segments()
+		.ok_or_else(|| Error::msg("pull request URL missing path"))?
+		.filter(|segment| !segment.is_empty())
+		.collect::<Vec<_>>();
+
+	let (repo_path, number, head_ref) = match segments.as_slice() {
+		[owner, repo, "pull", number, ..] if url.host_str() == Some("github.com") => {
+			(format!("{owner}/{repo}"), *number, "refs/pull/{}/head")
+		}
+		_ => match segments.iter().position(|segment| *segment == "-") {
+			Some(i) if i >= 2 && segments.get(i + 1) == Some(&"merge_requests") => {
+				let number = segments
+					.get(i + 2)
+					.ok_or_else(|| Error::msg("merge request URL missing number"))?;
+				(
+					segments[..i].join("/"),
+					*number,
+					"refs/merge-requests/{}/head",
+				)
+			}
+			_ => {
+				return Err(hc_error!(
+					"'{}' is not a GitHub pull request or GitLab merge request URL",
+					url
+				))
+			}
+		},
+	};
+
+	let number: u64 = number
+		.parse()
+		.map_err(|_| hc_error!("invalid pull request number '{}'", number))?;
+
+	let mut repo_url = url.clone();
+	repo_url.set_path(&repo_path);
+	repo_url.set_query(None);
+	repo_url.set_fragment(None);
+
+	Ok(PullRequest {
+		remote: get_remote_repo_from_url(repo_url)?,
+		url,
+		number,
+		head_ref: head_ref.replace("{}", &number.to_string()),
+	})
+}
+
+/// Get the namespace and project of a GitLab project URL. Projects may be in
+/// nested subgroups, so the namespace is every path segment but the last.
+pub fn get_gitlab_namespace_and_project(url: &Url) -> Result<(String, String)> {
+	let segments = url
+		.path_segments()
+		.ok_or_else(|| Error::msg("GitLab URL missing path for namespace and project"))?
+		// Paths to pages within a project, like its files, follow a '-' segment.
+		.take_while(|segment| *segment != "-")
+		.filter(|segment| !segment.is_empty())
+		.collect::<Vec<_>>();
+
+	let Some((project, namespace)) = segments.split_last() else {
+		return Err(Error::msg("GitLab URL missing project"));
+	};
+	if namespace.is_empty() {
+		return Err(Error::msg("GitLab URL missing namespace"));
+	}
+
+	Ok((
+		namespace.join("/"),
+		project.trim_end_matches(".git").to_owned(),
+	))
+}
+
 fn build_unknown_remote_clone_dir(url: &Url) -> Result<String> {
 	let mut dir = String::new();
 
@@ -265,14 +519,42 @@ pub fn clone_or_update_remote(
 	dest: &Path,
 	refspec: Option<String>,
 ) -> Result<String> {
+	update_remote(phase, url, dest)?;
+	git::checkout(dest, refspec)
+}
+
+/// Clone a remote repo, or fetch it if it was cloned before.
+///
+/// When offline, a repo cloned before is used as it is, and one which wasn't can't be used at all.
+fn update_remote(phase: &SpinnerPhase, url: &Url, dest: &Path) -> Result<()> {
+	if is_offline() {
+		return use_cached_clone(url, dest);
+	}
+
 	if dest.exists() {
 		phase.update_status("pulling");
-		git::fetch(dest).context("failed to update remote repository")?;
+		git::fetch(dest).context("failed to update remote repository")
 	} else {
 		phase.update_status("cloning");
-		git::clone(url, dest).context("failed to clone remote repository")?;
+		git::clone(url, dest).context("failed to clone remote repository")
+	}
+}
+
+/// Use the clone of a remote repo at `dest` as it is, since it can't be fetched while offline.
+fn use_cached_clone(url: &Url, dest: &Path) -> Result<()> {
+	match dest.exists() {
+		true => {
+			log::info!(
+				"offline, so using the cached clone of {} without fetching",
+				url
+			);
+			Ok(())
+		}
+		false => Err(hc_error!(
+			"{} is not in the repo cache, and can't be cloned while offline",
+			url
+		)),
 	}
-	git::checkout(dest, refspec)
 }
 
 fn get_symbolic_ref(dest: &Path) -> Result<String> {
@@ -299,3 +581,122 @@ fn get_url_for_remote(dest: &Path, remote: &str) -> Result<String> {
 
 	Ok(output.trim().to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_get_known_remote_gitlab() {
+		let url = Url::parse("https://gitlab.com/gitlab-org/security/gitlab.git").unwrap();
+		let known_remote = get_known_remote(&url, &[]).unwrap();
+		assert_eq!(
+			known_remote,
+			Some(KnownRemote::GitLab {
+				host: "gitlab.com".to_owned(),
+				namespace: "gitlab-org/security".to_owned(),
+				project: "gitlab".to_owned(),
+			})
+		);
+
+		let url = Url::parse("https://git.example.org/team/tool/-/tree/main").unwrap();
+		assert_eq!(get_known_remote(&url, &[]).unwrap(), None);
+		let hosts = ["git.example.org".to_owned()];
+		let known_remote = get_known_remote(&url, &hosts).unwrap();
+		assert_eq!(
+			known_remote,
+			Some(KnownRemote::GitLab {
+				host: "git.example.org".to_owned(),
+				namespace: "team".to_owned(),
+				project: "tool".to_owned(),
+			})
+		);
+	}
+
+	#[test]
+	fn test_pull_request_api_url() {
+		let url = Url::parse("https://github.com/mitre/hipcheck/pull/123").unwrap();
+		let pull_request = get_pull_request_from_url(url).unwrap();
+		assert_eq!(
+			pull_request_api_url(&pull_request).unwrap(),
+			(
+				"https://api.github.com/repos/mitre/hipcheck/pulls/123".to_owned(),
+				"/base/ref"
+			)
+		);
+
+		let url =
+			Url::parse("https://gitlab.com/gitlab-org/security/gitlab/-/merge_requests/7").unwrap();
+		let pull_request = get_pull_request_from_url(url).unwrap();
+		assert_eq!(
+			pull_request_api_url(&pull_request).unwrap(),
+			(
+				"https://gitlab.com/api/v4/projects/gitlab-org%2Fsecurity%2Fgitlab/merge_requests/7"
+					.to_owned(),
+				"/target_branch"
+			)
+		);
+	}
+
+	#[test]
+	fn test_remote_clone_dir_self_hosted_gitlab() {
+		let root = Path::new("/cache");
+		let url = Url::parse("https://git.example.org/team/tools/lint.git").unwrap();
+		let remote = get_remote_repo_from_url(url).unwrap();
+		let hosts = ["git.example.org".to_owned()];
+		let remote = identify_self_hosted_remote(remote, &hosts).unwrap();
+		assert_eq!(
+			remote_clone_dir(root, &remote).unwrap(),
+			pathbuf![
+				root,
+				"clones",
+				"gitlab",
+				"git.example.org",
+				"team__tools",
+				"lint"
+			]
+		);
+	}
+
+	#[test]
+	fn test_get_pull_request_from_url() {
+		let url = Url::parse("https://github.com/mitre/hipcheck/pull/123/files").unwrap();
+		let pull_request = get_pull_request_from_url(url).unwrap();
+		assert_eq!(pull_request.number, 123);
+		assert_eq!(pull_request.head_ref, "refs/pull/123/head");
+		assert_eq!(
+			pull_request.remote.url.as_str(),
+			"https://github.com/mitre/hipcheck"
+		);
+
+		let url =
+			Url::parse("https://gitlab.com/gitlab-org/security/gitlab/-/merge_requests/7").unwrap();
+		let pull_request = get_pull_request_from_url(url).unwrap();
+		assert_eq!(pull_request.number, 7);
+		assert_eq!(pull_request.head_ref, "refs/merge-requests/7/head");
+		assert_eq!(
+			pull_request.remote.known_remote,
+			Some(KnownRemote::GitLab {
+				host: "gitlab.com".to_owned(),
+				namespace: "gitlab-org/security".to_owned(),
+				project: "gitlab".to_owned(),
+			})
+		);
+
+		let url = Url::parse("https://github.com/mitre/hipcheck").unwrap();
+		assert!(get_pull_request_from_url(url).is_err());
+	}
+
+	#[test]
+	fn test_use_cached_clone_offline() {
+		let cache = tempfile::tempdir().unwrap();
+		let url = Url::parse("https://github.com/mitre/hipcheck.git").unwrap();
+
+		let dest = cache.path().join("hipcheck");
+		let err = use_cached_clone(&url, &dest).unwrap_err();
+		assert!(err.to_string().contains("can't be cloned while offline"));
+
+		std::fs::create_dir(&dest).unwrap();
+		assert!(use_cached_clone(&url, &dest).is_ok());
+	}
+}
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::{
+	fmt,
+	fmt::{Display, Formatter},
+	path::PathBuf,
+};
+use url::Url;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Target {
+	/// The original specifier provided by the user.
+	pub specifier: String,
+
+	/// The path to the local repository.
+	pub local: LocalGitRepo,
+
+	/// The url of the remote repository, if any.
+	pub remote: Option<RemoteGitRepo>,
+
+	/// The package associated with the target, if any.
+	pub package: Option<Package>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct RemoteGitRepo {
+	pub url: Url,
+	pub known_remote: Option<KnownRemote>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum KnownRemote {
+	GitHub {
+		owner: String,
+		repo: String,
+	},
+	/// A project on gitlab.com or a self-hosted GitLab instance
+	GitLab {
+		/// The host of the GitLab instance, like "gitlab.com"
+		host: String,
+		/// The group, and any subgroups, the project belongs to, separated by '/'
+		namespace: String,
+		project: String,
+	},
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct LocalGitRepo {
+	/// The path to the repo.
+	pub path: PathBuf,
+
+	/// The Git ref we're referring to.
+	pub git_ref: String,
+
+	/// The commit a range of commits to analyze starts after, if only the
+	/// commits after it, up to `git_ref`, should be analyzed.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub base_ref: Option<String>,
+}
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Package {
+	/// A package url for the package.
+	pub purl: Url,
+
+	/// The package name
+	pub name: String,
+
+	/// The package version
+	pub version: String,
+
+	/// What host the package is from.
+	pub host: PackageHost,
+}
+impl Package {
+	pub fn has_version(&self) -> bool {
+		self.version != Package::no_version()
+	}
+	pub fn no_version() -> &'static str {
+		"no version"
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct MavenPackage {
+	/// The Maven url
+	pub url: Url,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+// Maven as a possible host is ommitted because a MavenPackage is currently its own struct without a host field
+pub enum PackageHost {
+	Npm,
+	PyPI,
+	Cargo,
+	Go,
+}
+
+impl Display for PackageHost {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			PackageHost::Npm => write!(f, "Npm"),
+			PackageHost::PyPI => write!(f, "PyPI"),
+			PackageHost::Cargo => write!(f, "Cargo"),
+			PackageHost::Go => write!(f, "Go"),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Sbom {
+	/// The path to the SBOM file
+	pub path: PathBuf,
+
+	/// What standard the SBOM uses
+	pub standard: SbomStandard,
+}
+
+/// A dependency listed in an SBOM or lockfile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Dependency {
+	/// The name of the dependency.
+	pub name: String,
+
+	/// The version of the dependency, if given.
+	pub version: Option<String>,
+
+	/// Where to find the dependency, as a pURL or a `git+` VCS URL, if known.
+	pub locator: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Lockfile {
+	/// The path to the lockfile
+	pub path: PathBuf,
+
+	/// What kind of lockfile it is
+	pub kind: LockfileKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum LockfileKind {
+	/// A Cargo `Cargo.lock`
+	Cargo,
+	/// An npm `package-lock.json`
+	Npm,
+	/// A Poetry `poetry.lock`
+	Poetry,
+	/// A pip `requirements.txt`
+	Requirements,
+}
+
+impl LockfileKind {
+	/// Identify the kind of a lockfile from its file name.
+	pub fn from_file_name(name: &str) -> Option<LockfileKind> {
+		match name {
+			"Cargo.lock" => Some(LockfileKind::Cargo),
+			"package-lock.json" => Some(LockfileKind::Npm),
+			"poetry.lock" => Some(LockfileKind::Poetry),
+			name if name.starts_with("requirements") && name.ends_with(".txt") => {
+				Some(LockfileKind::Requirements)
+			}
+			_ => None,
+		}
+	}
+}
+
+impl Display for LockfileKind {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			LockfileKind::Cargo => write!(f, "Cargo.lock"),
+			LockfileKind::Npm => write!(f, "package-lock.json"),
+			LockfileKind::Poetry => write!(f, "poetry.lock"),
+			LockfileKind::Requirements => write!(f, "requirements.txt"),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum SbomStandard {
+	Spdx,
+	CycloneDX,
+}
+
+impl Display for SbomStandard {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			SbomStandard::Spdx => write!(f, "SPDX"),
+			SbomStandard::CycloneDX => write!(f, "CycloneDX"),
+		}
+	}
+}
+
+/// A pull request on GitHub, or merge request on GitLab.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PullRequest {
+	/// The URL of the pull request.
+	pub url: Url,
+
+	/// The repository the pull request is to.
+	pub remote: RemoteGitRepo,
+
+	/// The number of the pull request.
+	pub number: u64,
+
+	/// The ref the remote keeps the head of the pull request at.
+	pub head_ref: String,
+}
+
+/// The commits in a repository after one commit, up to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitRange {
+	/// The local or remote repository the commits are in.
+	pub repo: Box<TargetSeedKind>,
+
+	/// The commit the range starts after.
+	pub base: String,
+
+	/// The last commit in the range.
+	pub head: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TargetSeedKind {
+	LocalRepo(LocalGitRepo),
+	RemoteRepo(RemoteGitRepo),
+	Package(Package),
+	MavenPackage(MavenPackage),
+	Sbom(Sbom),
+	Lockfile(Lockfile),
+	PullRequest(PullRequest),
+	CommitRange(CommitRange),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetSeed {
+	pub kind: TargetSeedKind,
+	pub refspec: Option<String>,
+}
+
+impl Display for TargetSeedKind {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		use TargetSeedKind::*;
+		match self {
+			LocalRepo(repo) => write!(f, "local repo at {}", repo.path.display()),
+			RemoteRepo(remote) => match &remote.known_remote {
+				Some(KnownRemote::GitHub { owner, repo }) => {
+					write!(f, "GitHub repo {}/{} from {}", owner, repo, remote.url)
+				}
+				Some(KnownRemote::GitLab {
+					namespace, project, ..
+				}) => {
+					write!(
+						f,
+						"GitLab repo {}/{} from {}",
+						namespace, project, remote.url
+					)
+				}
+				_ => write!(f, "remote repo at {}", remote.url.as_str()),
+			},
+			Package(package) => write!(
+				f,
+				"{} package {}@{}",
+				package.host, package.name, package.version
+			),
+			MavenPackage(package) => {
+				write!(f, "Maven package {}", package.url.as_str())
+			}
+			Sbom(sbom) => {
+				write!(f, "{} SBOM file at {}", sbom.standard, sbom.path.display())
+			}
+			Lockfile(lockfile) => {
+				write!(
+					f,
+					"{} lockfile at {}",
+					lockfile.kind,
+					lockfile.path.display()
+				)
+			}
+			PullRequest(pr) => write!(f, "pull request #{} to {}", pr.number, pr.remote.url),
+			CommitRange(range) => {
+				write!(
+					f,
+					"commits {}..{} of {}",
+					range.base, range.head, range.repo
+				)
+			}
+		}
+	}
+}
+impl Display for TargetSeed {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		self.kind.fmt(f)
+	}
+}
@@ -3,12 +3,44 @@
 //! Globally defined agent containing system TLS Certs.
 
 use rustls::{ClientConfig, RootCertStore};
-use std::sync::{Arc, OnceLock};
-use ureq::{Agent, AgentBuilder};
+use std::{
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, OnceLock,
+	},
+};
+use ureq::{Agent, AgentBuilder, MiddlewareNext, Request, Response};
 
 /// Global static holding the agent with the appropriate TLS certs.
 static AGENT: OnceLock<Agent> = OnceLock::new();
 
+/// Global static recording whether network access has been turned off with `--offline`.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turn off network access for the rest of the run. Every request made through the agent after
+/// this fails immediately.
+pub fn set_offline() {
+	OFFLINE.store(true, Ordering::Relaxed);
+}
+
+/// Whether network access has been turned off with `--offline`.
+pub fn is_offline() -> bool {
+	OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Refuse any request made while offline.
+fn refuse_if_offline(request: Request, next: MiddlewareNext) -> Result<Response, ureq::Error> {
+	if is_offline() {
+		return Err(io::Error::other(format!(
+			"network access is turned off by --offline, but a request was made to {}",
+			request.url()
+		))
+		.into());
+	}
+	next.handle(request)
+}
+
 /// Get or initialize the global static agent used in making http(s) requests for hipcheck.
 ///
 /// # Panics
@@ -27,6 +59,9 @@ pub fn agent() -> &'static Agent {
 			.with_no_client_auth();
 
 		// Construct agent
-		AgentBuilder::new().tls_config(Arc::new(tls_config)).build()
+		AgentBuilder::new()
+			.tls_config(Arc::new(tls_config))
+			.middleware(refuse_if_offline)
+			.build()
 	})
 }
@@ -76,7 +76,7 @@ impl Plugin for ActivityPlugin {
 			return Err(Error::UnspecifiedQueryState);
 		};
 
-		Ok(format!("lte $ P{}w", conf.weeks.unwrap_or(71)))
+		Ok(format!("(lte $ P{}w)", conf.weeks.unwrap_or(71)))
 	}
 
 	fn explain_default_query(&self) -> Result<Option<String>> {
@@ -90,15 +90,18 @@ impl Plugin for ActivityPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(ActivityPlugin {})
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
 
@@ -113,6 +116,7 @@ mod test {
 	fn repo() -> LocalGitRepo {
 		LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/expressjs/express/".to_string(),
+			base_ref: None,
 			git_ref: "main".to_string(),
 		}
 	}
@@ -87,20 +87,6 @@ pub struct DetailedGitRepo {
 	pub details: Option<String>,
 }
 
-/// A locally stored git repo, with a list of additional details
-/// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
-///
-/// This struct exists for using the temproary "batch" queries until proper batching is implemented
-/// TODO: Remove this struct once batching works
-#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
-pub struct BatchGitRepo {
-	/// The local repo
-	local: LocalGitRepo,
-
-	/// Optional additional information for the query
-	pub details: Vec<String>,
-}
-
 /// Commits as understood in Hipcheck's data model.
 /// The `written_on` and `committed_on` datetime fields contain Strings that are created from `jiff:Timestamps`.
 /// Because `Timestamp` does not `impl JsonSchema`, we display the datetimes as Strings for passing out of this plugin.
@@ -220,67 +206,6 @@ impl AffiliatedType {
 	}
 }
 
-// Can be hopefully removed once Submit has chunking
-mod chunk {
-	use super::*;
-
-	pub const GRPC_MAX_SIZE: usize = 1024 * 1024 * 4; // 4MB
-	pub const GRPC_EFFECTIVE_MAX_SIZE: usize = 3 * (GRPC_MAX_SIZE / 4); // 1024; // Minus one KB
-
-	pub fn chunk_hashes(
-		mut hashes: Vec<String>,
-		max_chunk_size: usize,
-	) -> Result<Vec<Vec<String>>> {
-		let mut out = vec![];
-
-		let mut made_progress = true;
-		while !hashes.is_empty() && made_progress {
-			made_progress = false;
-			let mut curr = vec![];
-			let mut remaining = max_chunk_size;
-
-			// While we still want to steal more bytes and we have more elements of
-			// `concern` to possibly steal
-			while remaining > 0 && !hashes.is_empty() {
-				let c_bytes = hashes.last().unwrap().bytes().len();
-
-				if c_bytes > max_chunk_size {
-					log::error!("Query cannot be chunked, there is a concern that is larger than max chunk size");
-					return Err(Error::UnspecifiedQueryState);
-				} else if c_bytes <= remaining {
-					// steal this concern
-					let concern = hashes.pop().unwrap();
-					curr.push(concern);
-					remaining -= c_bytes;
-					made_progress = true;
-				} else {
-					// Unlike concern chunking, hashes are likely to all be same size, no need to
-					// keep checking if we fail on one
-					break;
-				}
-			}
-			out.push(curr);
-		}
-
-		Ok(out)
-	}
-
-	#[cfg(test)]
-	mod test {
-		use super::*;
-
-		#[test]
-		fn test_hash_chunk() {
-			let hashes: Vec<String> = vec!["1234", "1234", "1234", "1234", "1234"]
-				.into_iter()
-				.map(String::from)
-				.collect();
-			let res = chunk_hashes(hashes, 10).unwrap();
-			assert_eq!(res.len(), 3);
-		}
-	}
-}
-
 /// Returns a boolean list with one entry per contributor to the repo
 /// A `true` entry corresponds to an affiliated contributor
 #[query(default)]
@@ -311,67 +236,31 @@ async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>
 		Error::UnspecifiedQueryState
 	})?;
 
-	// Temporary solution to retrieve afilliated commits until batching is implemented
-	// TODO: Once batching works, revert to using looped calls of contributors_to_commit() in the commented out code
-	// @Note - this lacks a way to chunk the commits into `contributors_for_commit`. Until this
-	// code is updated ot do so or outbound PluginQuerys get a SubmitInProgress state, this will be
-	// broken when analyzing large repos.
-
-	// for commit in commits.iter() {
-	// 	// Check if a commit matches the affiliation rules.
-	// 	let hash = commit.hash.clone();
-	// 	let detailed_repo = DetailedGitRepo {
-	// 		local: repo.clone(),
-	// 		details: Some(hash.clone()),
-	// 	};
-	// 	let view_value = engine
-	// 		.query("mitre/git/contributors_for_commit", detailed_repo)
-	// 		.await
-	// 		.map_err(|e| {
-	// 			log::error!("failed to get contributors for commit {}: {}", hash, e);
-	// 			Error::UnspecifiedQueryState
-	// 		})?;
-	// 	let commit_view: CommitContributorView = serde_json::from_value(view_value)
-	// 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
-	// for commit_view in commit_views {
-	// Get the affiliation type for the commit
-	// affiliations.push(AffiliationDetails {
-	// 	commit: commit.clone(),
-	// 	affiliated_type,
-	// });
-	// }
-	// let affiliated_iter = affiliations
-	// 	.into_iter()
-	// 	.filter(|a| a.affiliated_type.is_affiliated())
-
 	let mut contributors = HashSet::new();
 	let mut contributor_freq_map = HashMap::new();
 
-	// Get the hashes for each commit
-	let hashes = commits.iter().map(|c| c.hash.clone()).collect();
-
-	// Chunk hashes because for large repos the request message would be too large
-	let chunked_hashes = chunk::chunk_hashes(hashes, chunk::GRPC_EFFECTIVE_MAX_SIZE)?;
-
-	let mut commit_views: Vec<CommitContributorView> = vec![];
-	for hashes in chunked_hashes {
-		// Repo with the hash of every commit
-		let commit_batch_repo = BatchGitRepo {
+	// Repo with the hash of each commit
+	let commit_repos: Vec<DetailedGitRepo> = commits
+		.iter()
+		.map(|c| DetailedGitRepo {
 			local: repo.clone(),
-			details: hashes,
-		};
-		// Get a list of lookup structs for linking contributors to each commit
-		let commit_values = engine
-			.query("mitre/git/batch_contributors_for_commit", commit_batch_repo)
-			.await
-			.map_err(|e| {
-				log::error!("failed to get contributors for commits: {}", e);
-				Error::UnspecifiedQueryState
-			})?;
-		let views: Vec<CommitContributorView> = serde_json::from_value(commit_values)
-			.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
-		commit_views.extend(views.into_iter());
-	}
+			details: Some(c.hash.clone()),
+		})
+		.collect();
+
+	// Get a list of lookup structs for linking contributors to each commit
+	let commit_values = engine
+		.batch_query("mitre/git/contributors_for_commit", commit_repos)
+		.await
+		.map_err(|e| {
+			log::error!("failed to get contributors for commits: {}", e);
+			Error::UnspecifiedQueryState
+		})?;
+	let commit_views: Vec<CommitContributorView> = commit_values
+		.into_iter()
+		.map(serde_json::from_value)
+		.collect::<StdResult<_, _>>()
+		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
 
 	// For each commit, collect contributors that fail the affiliation rules
 	for commit_view in commit_views {
@@ -397,49 +286,27 @@ async fn affiliation(engine: &mut PluginEngine, key: Target) -> Result<Vec<bool>
 		}
 	}
 
-	// Temporary solution to retrieve afilliated commits until batching is implemented
-	// TODO: Once batching works, revert to using looped calls of commits_for_contributor() in the commented out code
-
-	// let mut contributors = HashSet::new();
-	// let mut contributor_freq_map = HashMap::new();
-
-	// // Get the affiliated contributors from the commits
-	// for affiliation in affiliated_iter {
-	// 	let hash = affiliation.commit.hash;
-	// 	let commit_repo = DetailedGitRepo {
-	// 		local: repo.clone(),
-	// 		details: Some(hash.clone()),
-	// 	};
-	// 	let view_value = engine
-	// 		.query("mitre/git/contributors_for_commit", commit_repo)
-	// 		.await
-	// 		.map_err(|e| {
-	// 			log::error!("failed to get contributors for commit {}: {}", hash, e);
-	// 			Error::UnspecifiedQueryState
-	// 		})?;
-	// 	let commit_view: CommitContributorView = serde_json::from_value(view_value)
-	// 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
-
-	// Get the emails for each affiliated contributor
-	let emails = contributors.iter().map(|c| c.1.clone()).collect();
-	// Repo with the email of every affiliated contributor
-	let contributor_batch_repo = BatchGitRepo {
-		local: repo.clone(),
-		details: emails,
-	};
+	// Repo with the email of each affiliated contributor
+	let contributor_repos: Vec<DetailedGitRepo> = contributors
+		.iter()
+		.map(|c| DetailedGitRepo {
+			local: repo.clone(),
+			details: Some(c.1.clone()),
+		})
+		.collect();
 
 	// Get a list of lookup structs for linking commits to each affiliated contributor
 	let contributor_values = engine
-		.query(
-			"mitre/git/batch_commits_for_contributor",
-			contributor_batch_repo,
-		)
+		.batch_query("mitre/git/commits_for_contributor", contributor_repos)
 		.await
 		.map_err(|e| {
 			log::error!("failed to get commits for contributors: {}", e);
 			Error::UnspecifiedQueryState
 		})?;
-	let contributor_views: Vec<ContributorView> = serde_json::from_value(contributor_values)
+	let contributor_views: Vec<ContributorView> = contributor_values
+		.into_iter()
+		.map(serde_json::from_value)
+		.collect::<StdResult<_, _>>()
 		.map_err(|_| Error::UnexpectedPluginQueryInputFormat)?;
 
 	// For each affiliated contributor, count how many commits they contributed to,
@@ -538,15 +405,18 @@ impl Plugin for AffiliationPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(AffiliationPlugin::default())
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
 
@@ -559,6 +429,7 @@ mod test {
 	fn repo() -> LocalGitRepo {
 		LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/foo/bar/".to_string(),
+			base_ref: None,
 			git_ref: "main".to_string(),
 		}
 	}
@@ -622,28 +493,9 @@ mod test {
 			commits: vec![commit_2.clone(), commit_3.clone()],
 		};
 
-		let commits_repo = BatchGitRepo {
+		let detailed_repo = |details: &str| DetailedGitRepo {
 			local: repo.clone(),
-			details: vec![
-				"ghi-789".to_string(),
-				"def-456".to_string(),
-				"abc-123".to_string(),
-			],
-		};
-
-		let contributors_repo = BatchGitRepo {
-			local: repo.clone(),
-			details: vec!["jsmith@mitre.org".to_string(), "jdoe@gmail.com".to_string()],
-		};
-
-		let contributor_1_repo = BatchGitRepo {
-			local: repo.clone(),
-			details: vec!["jsmith@mitre.org".to_string()],
-		};
-
-		let contributor_2_repo = BatchGitRepo {
-			local: repo.clone(),
-			details: vec!["jdoe@gmail.com".to_string()],
+			details: Some(details.to_string()),
 		};
 
 		let mut mock_responses = MockResponses::new();
@@ -658,38 +510,28 @@ mod test {
 		mock_responses
 			.insert(
 				"mitre/git/contributors",
-				repo,
+				repo.clone(),
 				Ok(vec![contributor_1, contributor_2]),
 			)
 			.unwrap();
-		mock_responses
-			.insert(
-				"mitre/git/batch_contributors_for_commit",
-				commits_repo,
-				Ok(vec![commit_3_view, commit_2_view, commit_1_view]),
-			)
-			.unwrap();
-		mock_responses
-			.insert(
-				"mitre/git/batch_commits_for_contributor",
-				contributors_repo,
-				Ok(vec![contributor_1_view.clone(), contributor_2_view.clone()]),
-			)
-			.unwrap();
-		mock_responses
-			.insert(
-				"mitre/git/batch_commits_for_contributor",
-				contributor_1_repo,
-				Ok(vec![contributor_1_view]),
-			)
-			.unwrap();
-		mock_responses
-			.insert(
-				"mitre/git/batch_commits_for_contributor",
-				contributor_2_repo,
-				Ok(vec![contributor_2_view]),
-			)
-			.unwrap();
+		for commit_view in [commit_1_view, commit_2_view, commit_3_view] {
+			mock_responses
+				.insert(
+					"mitre/git/contributors_for_commit",
+					detailed_repo(&commit_view.commit.hash),
+					Ok(commit_view),
+				)
+				.unwrap();
+		}
+		for contributor_view in [contributor_1_view, contributor_2_view] {
+			mock_responses
+				.insert(
+					"mitre/git/commits_for_contributor",
+					detailed_repo(&contributor_view.contributor.email),
+					Ok(contributor_view),
+				)
+				.unwrap();
+		}
 
 		Ok(mock_responses)
 	}
@@ -122,14 +122,17 @@ impl Plugin for BinaryPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(BinaryPlugin::default())
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
@@ -8,11 +8,15 @@ mod types;
 use crate::{linguist::*, metric::*, types::*};
 
 use clap::Parser;
-use hipcheck_sdk::{prelude::*, types::Target};
+use hipcheck_sdk::{
+	prelude::*,
+	types::{LocalGitRepo, Target},
+};
 use serde::Deserialize;
 use tokio::sync::Mutex;
 
 use std::{
+	collections::HashSet,
 	path::PathBuf,
 	result::Result as StdResult,
 	sync::{Arc, OnceLock},
@@ -136,14 +140,27 @@ async fn commit_entropies(
 #[query(default)]
 async fn entropy(engine: &mut PluginEngine, value: Target) -> Result<Vec<f64>> {
 	let local = value.local;
-	let val_commits = engine.query("mitre/git/commit_diffs", local).await?;
+	// Scores are relative to the whole history, even when only the commits
+	// after `base_ref` are being checked
+	let history = LocalGitRepo {
+		base_ref: None,
+		..local.clone()
+	};
+	let val_commits = engine.query("mitre/git/commit_diffs", history).await?;
 	let commits: Vec<CommitDiff> =
 		serde_json::from_value(val_commits).map_err(Error::InvalidJsonInQueryOutput)?;
-	Ok(commit_entropies(engine, commits)
-		.await?
-		.iter()
-		.map(|o| o.entropy)
-		.collect())
+	let mut scores = commit_entropies(engine, commits).await?;
+
+	let val_range = engine
+		.query("mitre/git/commit_hashes_in_range", local)
+		.await?;
+	let range: Option<HashSet<String>> =
+		serde_json::from_value(val_range).map_err(Error::InvalidJsonInQueryOutput)?;
+	if let Some(range) = range {
+		scores.retain(|o| range.contains(&o.commit.hash));
+	}
+
+	Ok(scores.iter().map(|o| o.entropy).collect())
 }
 
 #[derive(Clone, Debug, Default)]
@@ -219,14 +236,17 @@ impl Plugin for EntropyPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(EntropyPlugin::default())
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use clap::Parser;
-use hipcheck_sdk::{prelude::*, types::Target};
+use hipcheck_sdk::{
+	prelude::*,
+	types::{KnownRemote, Target},
+};
 use serde_json::Value;
 use std::result::Result as StdResult;
 
@@ -9,7 +12,13 @@ use std::result::Result as StdResult;
 #[query(default)]
 async fn fuzz(engine: &mut PluginEngine, key: Target) -> Result<Value> {
 	if let Some(remote) = &key.remote {
-		engine.query("mitre/github", remote.clone()).await
+		// GitLab projects are checked for GitLab's own fuzz testing, and everything
+		// else for membership in OSS-Fuzz
+		let plugin = match remote.known_remote {
+			Some(KnownRemote::GitLab { .. }) => "mitre/gitlab",
+			_ => "mitre/github",
+		};
+		engine.query(plugin, remote.clone()).await
 	} else {
 		Err(Error::UnexpectedPluginQueryInputFormat)
 	}
@@ -17,8 +26,11 @@ async fn fuzz(engine: &mut PluginEngine, key: Target) -> Result<Value> {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[derive(Clone, Debug)]
@@ -47,18 +59,19 @@ impl Plugin for FuzzAnalysisPlugin {
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(FuzzAnalysisPlugin {})
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
 
 #[cfg(test)]
 mod test {
 	use super::*;
-	use hipcheck_sdk::types::{KnownRemote, LocalGitRepo, RemoteGitRepo};
+	use hipcheck_sdk::types::{LocalGitRepo, RemoteGitRepo};
 
 	fn target() -> Target {
 		let local = LocalGitRepo {
 			path: "/home/users/me/.cache/hipcheck/clones/github/mitre/hipcheck/".to_string(),
+			base_ref: None,
 			git_ref: "main".to_string(),
 		};
 		let known_remote = Some(KnownRemote::GitHub {
@@ -96,4 +109,32 @@ mod test {
 
 		assert_eq!(result, expected);
 	}
+
+	#[tokio::test]
+	async fn test_fuzz_gitlab() {
+		let remote = RemoteGitRepo {
+			url: "https://gitlab.com/gitlab-org/gitlab-runner"
+				.parse()
+				.unwrap(),
+			known_remote: Some(KnownRemote::GitLab {
+				host: "gitlab.com".to_owned(),
+				namespace: "gitlab-org".to_owned(),
+				project: "gitlab-runner".to_owned(),
+			}),
+		};
+		let mut mock_responses = MockResponses::new();
+		mock_responses
+			.insert("mitre/gitlab", remote.clone(), Ok(false))
+			.unwrap();
+
+		let target = Target {
+			remote: Some(remote),
+			..target()
+		};
+		let mut engine = PluginEngine::mock(mock_responses);
+		let output = fuzz(&mut engine, target).await.unwrap();
+		let result: bool = serde_json::from_value(output).unwrap();
+
+		assert!(!result);
+	}
 }
@@ -66,16 +66,6 @@ impl Display for Contributor {
 	}
 }
 
-/// "Join struct" for commits and contributors.
-#[derive(Debug, PartialEq, Eq, Serialize, Clone)]
-pub struct CommitContributor {
-	// Index of commit cache
-	pub commit_id: usize,
-	// Indices of contributor cache
-	pub author_id: usize,
-	pub committer_id: usize,
-}
-
 /// Temporary data structure for looking up the contributors of a commit
 #[derive(Debug, Serialize, Clone, PartialEq, Eq, JsonSchema)]
 pub struct CommitContributorView {
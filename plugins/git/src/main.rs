@@ -8,36 +8,193 @@ mod util;
 
 use crate::{
 	data::{
-		Commit, CommitContributor, CommitContributorView, CommitDiff, Contributor, ContributorView,
-		DetailedGitRepo, Diff, RawCommit,
+		Commit, CommitContributorView, CommitDiff, Contributor, ContributorView, DetailedGitRepo,
+		Diff, RawCommit,
+	},
+	util::git_command::{
+		get_commit_hashes_after, get_commits, get_commits_from_date, get_diffs, get_head_commit,
 	},
-	util::git_command::{get_commits, get_commits_from_date, get_diffs},
 };
 use clap::Parser;
 use hipcheck_sdk::{prelude::*, types::LocalGitRepo};
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-
-/// A locally stored git repo, with a list of additional details
-/// The details will vary based on the query (e.g. a date, a committer e-mail address, a commit hash)
-///
-/// This struct exists for using the temproary "batch" queries until proper batching is implemented
-/// TODO: Remove this struct once batching works
-#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
-pub struct BatchGitRepo {
-	/// The local repo
-	local: LocalGitRepo,
-
-	/// Optional additional information for the query
-	pub details: Vec<String>,
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	sync::{Arc, LazyLock, Mutex},
+};
+
+/// Raw commits for recently analyzed repos, keyed by path and checked-out
+/// commit. Queries that take a single commit or contributor are usually made in
+/// large batches, so this keeps each batch from re-running `git log`. Keying
+/// on the commit id rather than the ref means a repo that has moved since it
+/// was cached is read again.
+type RawCommitsKey = (String, String);
+
+/// Cached raw commits, most recently used last.
+type RawCommitsCache = VecDeque<(RawCommitsKey, Arc<RawCommits>)>;
+
+/// The most repos whose raw commits are kept at once.
+const RAW_COMMITS_CAPACITY: usize = 8;
+
+static RAW_COMMITS: LazyLock<Mutex<RawCommitsCache>> =
+	LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+/// All raw commits extracted from a repository's history, indexed by hash and
+/// by the e-mail addresses of their contributors
+struct RawCommits {
+	commits: Vec<RawCommit>,
+	by_hash: HashMap<String, usize>,
+	by_email: HashMap<String, Vec<usize>>,
+}
+
+impl RawCommits {
+	fn new(commits: Vec<RawCommit>) -> Self {
+		let mut by_hash = HashMap::with_capacity(commits.len());
+		let mut by_email: HashMap<String, Vec<usize>> = HashMap::new();
+		for (i, raw) in commits.iter().enumerate() {
+			by_hash.insert(raw.hash.clone(), i);
+			by_email
+				.entry(raw.author.email.clone())
+				.or_default()
+				.push(i);
+			if raw.committer.email != raw.author.email {
+				by_email
+					.entry(raw.committer.email.clone())
+					.or_default()
+					.push(i);
+			}
+		}
+		RawCommits {
+			commits,
+			by_hash,
+			by_email,
+		}
+	}
 }
 
-/// Returns all raw commits extracted from the repository
-fn local_raw_commits(repo: LocalGitRepo) -> Result<Vec<RawCommit>> {
-	get_commits(&repo.path).map_err(|e| {
+/// Returns all raw commits extracted from the repository's history, reusing
+/// earlier results
+fn local_raw_commits(repo: &LocalGitRepo) -> Result<Arc<RawCommits>> {
+	let head = get_head_commit(&repo.path).map_err(|e| {
+		log::error!("failed to get checked-out commit: {}", e);
+		Error::UnspecifiedQueryState
+	})?;
+	let key = (repo.path.clone(), head);
+
+	{
+		let mut cache = RAW_COMMITS.lock().unwrap();
+		if let Some(idx) = cache.iter().position(|(k, _)| *k == key) {
+			let entry = cache.remove(idx).unwrap();
+			let raw_commits = Arc::clone(&entry.1);
+			cache.push_back(entry);
+			return Ok(raw_commits);
+		}
+	}
+
+	let raw_commits = Arc::new(RawCommits::new(get_commits(&repo.path).map_err(|e| {
 		log::error!("failed to get raw commits: {}", e);
 		Error::UnspecifiedQueryState
-	})
+	})?));
+
+	let mut cache = RAW_COMMITS.lock().unwrap();
+	cache.retain(|(k, _)| *k != key);
+	if cache.len() >= RAW_COMMITS_CAPACITY {
+		cache.pop_front();
+	}
+	cache.push_back((key, Arc::clone(&raw_commits)));
+	Ok(raw_commits)
+}
+
+/// Returns the hashes of the commits after the repository's `base_ref`, or
+/// `None` if the whole history is being analyzed
+fn range_hashes(repo: &LocalGitRepo) -> Result<Option<HashSet<String>>> {
+	repo.base_ref
+		.as_deref()
+		.map(|base_ref| {
+			get_commit_hashes_after(&repo.path, base_ref).map_err(|e| {
+				log::error!("failed to get commits after '{}': {}", base_ref, e);
+				Error::UnspecifiedQueryState
+			})
+		})
+		.transpose()
+}
+
+/// The raw commits of a repository, along with the range of them being
+/// analyzed. The range is taken from the whole history, so it's the same
+/// commits however they're asked for.
+struct RepoCommits {
+	raw: Arc<RawCommits>,
+	/// The hashes of the commits after `base_ref`, or `None` for the whole history
+	range: Option<HashSet<String>>,
+}
+
+impl RepoCommits {
+	fn load(repo: &LocalGitRepo) -> Result<Self> {
+		Ok(RepoCommits {
+			raw: local_raw_commits(repo)?,
+			range: range_hashes(repo)?,
+		})
+	}
+
+	fn in_range(&self, raw: &RawCommit) -> bool {
+		self.range
+			.as_ref()
+			.is_none_or(|range| range.contains(&raw.hash))
+	}
+
+	/// The commits in the range, in `git log` order
+	fn iter(&self) -> impl Iterator<Item = &RawCommit> {
+		self.raw.commits.iter().filter(|raw| self.in_range(raw))
+	}
+
+	/// The commit in the range with the given hash
+	fn find(&self, hash: &str) -> Option<&RawCommit> {
+		let raw = &self.raw.commits[*self.raw.by_hash.get(hash)?];
+		self.in_range(raw).then_some(raw)
+	}
+
+	/// The commits in the range authored or committed with the given e-mail address
+	fn for_email<'a>(&'a self, email: &str) -> impl Iterator<Item = &'a RawCommit> {
+		self.raw
+			.by_email
+			.get(email)
+			.into_iter()
+			.flatten()
+			.map(|i| &self.raw.commits[*i])
+			.filter(|raw| self.in_range(raw))
+	}
+}
+
+/// Loads the commits of each distinct repository among a batch of keys once,
+/// returning them alongside the keys' details
+fn load_batch(repos: Vec<DetailedGitRepo>) -> Result<Vec<(Arc<RepoCommits>, Option<String>)>> {
+	let mut loaded: Vec<(LocalGitRepo, Arc<RepoCommits>)> = vec![];
+	repos
+		.into_iter()
+		.map(|repo| {
+			let same_repo = |local: &LocalGitRepo| {
+				local.path == repo.local.path
+					&& local.git_ref == repo.local.git_ref
+					&& local.base_ref == repo.local.base_ref
+			};
+			let commits = match loaded.iter().find(|(local, _)| same_repo(local)) {
+				Some((_, commits)) => Arc::clone(commits),
+				None => {
+					let commits = Arc::new(RepoCommits::load(&repo.local)?);
+					loaded.push((repo.local, Arc::clone(&commits)));
+					commits
+				}
+			};
+			Ok((commits, repo.details))
+		})
+		.collect()
+}
+
+fn to_commit(raw: &RawCommit) -> Commit {
+	Commit {
+		hash: raw.hash.to_owned(),
+		written_on: raw.written_on.to_owned(),
+		committed_on: raw.committed_on.to_owned(),
+	}
 }
 
 /// Returns the date of the most recent commit to a Git repo as `jiff:Timestamp` displayed as a String
@@ -61,7 +218,7 @@ async fn last_commit_date(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Res
 	})
 }
 
-/// Returns all diffs extracted from the repository
+/// Returns all diffs extracted from the repository, or the range of it after `base_ref`
 #[query]
 async fn diffs(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Diff>> {
 	let path = &repo.path;
@@ -69,27 +226,44 @@ async fn diffs(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Dif
 		log::error!("{}", e);
 		Error::UnspecifiedQueryState
 	})?;
-	Ok(diffs)
+	let Some(range) = range_hashes(&repo)? else {
+		return Ok(diffs);
+	};
+
+	// Diffs don't carry their commit's hash, so pair them with the commits,
+	// which are listed in the same order
+	let raw_commits = local_raw_commits(&repo)?;
+	if raw_commits.commits.len() != diffs.len() {
+		log::error!(
+			"parsed {} diffs but there are {} commits",
+			diffs.len(),
+			raw_commits.commits.len()
+		);
+		return Err(Error::UnspecifiedQueryState);
+	}
+
+	Ok(Iterator::zip(raw_commits.commits.iter(), diffs)
+		.filter(|(raw, _)| range.contains(&raw.hash))
+		.map(|(_, diff)| diff)
+		.collect())
 }
 
-/// Returns all commits extracted from the repository
+/// Returns all commits extracted from the repository, or the range of it after `base_ref`
 #[query]
 async fn commits(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Commit>> {
-	let path = &repo.path;
-	let raw_commits = get_commits(path).map_err(|e| {
-		log::error!("failed to get raw commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let commits = raw_commits
-		.iter()
-		.map(|raw| Commit {
-			hash: raw.hash.to_owned(),
-			written_on: raw.written_on.to_owned(),
-			committed_on: raw.committed_on.to_owned(),
-		})
-		.collect();
+	let commits = RepoCommits::load(&repo)?;
+	Ok(commits.iter().map(to_commit).collect())
+}
 
-	Ok(commits)
+/// Returns the hashes of the commits after `base_ref`, or `None` if the whole history is being
+/// analyzed. Queries that score commits against the whole history use this to keep only the
+/// scores of those in the range.
+#[query]
+async fn commit_hashes_in_range(
+	_engine: &mut PluginEngine,
+	repo: LocalGitRepo,
+) -> Result<Option<Vec<String>>> {
+	Ok(range_hashes(&repo)?.map(|range| range.into_iter().collect()))
 }
 
 /// Returns all commits extracted from the repository for a date given in the `details` field
@@ -112,28 +286,15 @@ async fn commits_from_date(
 		log::error!("failed to get raw commits from date: {}", e);
 		Error::UnspecifiedQueryState
 	})?;
-	let commits = raw_commits_from_date
-		.iter()
-		.map(|raw| Commit {
-			hash: raw.hash.to_owned(),
-			written_on: raw.written_on.to_owned(),
-			committed_on: raw.committed_on.to_owned(),
-		})
-		.collect();
-
-	Ok(commits)
+	Ok(raw_commits_from_date.iter().map(to_commit).collect())
 }
 
-/// Returns all contributors to the repository
+/// Returns all contributors to the repository, or the range of it after `base_ref`
 #[query]
 async fn contributors(_engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<Vec<Contributor>> {
-	let path = &repo.path;
-	let raw_commits = get_commits(path).map_err(|e| {
-		log::error!("failed to get raw commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
+	let commits = RepoCommits::load(&repo)?;
 
-	let mut contributors: Vec<_> = raw_commits
+	let mut contributors: Vec<_> = commits
 		.iter()
 		.flat_map(|raw| [raw.author.to_owned(), raw.committer.to_owned()])
 		.collect();
@@ -176,296 +337,74 @@ async fn commit_diffs(engine: &mut PluginEngine, repo: LocalGitRepo) -> Result<V
 }
 
 /// Returns the commits associated with a given contributor (identified by e-mail address in the `details` value)
-#[query]
+#[query(batch)]
 async fn commits_for_contributor(
-	engine: &mut PluginEngine,
-	repo: DetailedGitRepo,
-) -> Result<ContributorView> {
-	let local = repo.local;
-	let email = match repo.details {
-		Some(ref email) => email.clone(),
-		None => {
-			log::error!("No contributor e-maill address provided");
-			return Err(Error::UnspecifiedQueryState);
-		}
-	};
-
-	let all_commits = commits(engine, local.clone()).await.map_err(|e| {
-		log::error!("failed to get commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let contributors = contributors(engine, local.clone()).await.map_err(|e| {
-		log::error!("failed to get contributors: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let commit_contributors = commit_contributors(engine, local.clone())
-		.await
-		.map_err(|e| {
-			log::error!("failed to get join table: {}", e);
-			Error::UnspecifiedQueryState
-		})?;
-
-	// Get the index of the contributor
-	let contributor_id = contributors
-		.iter()
-		.position(|c| c.email == email)
-		.ok_or_else(|| {
-			log::error!("failed to find contributor");
-			Error::UnspecifiedQueryState
-		})?;
-
-	// Get the contributor
-	let contributor = contributors[contributor_id].clone();
-
-	// Find commits that have that contributor
-	let commits = commit_contributors
-		.iter()
-		.filter_map(|com_con| {
-			if com_con.author_id == contributor_id || com_con.committer_id == contributor_id {
-				// SAFETY: This index is guaranteed to be valid in
-				// `all_commits` because of how it and `commit_contributors`
-				// are constructed from `db.raw_commits()`
-				Some(all_commits[com_con.commit_id].clone())
-			} else {
-				None
-			}
-		})
-		.collect();
-
-	Ok(ContributorView {
-		contributor,
-		commits,
-	})
-}
-
-use std::collections::{HashMap, HashSet};
-
-// Temporary query to call multiple commits_for_contributors() queries until we implement batching
-// TODO: Remove this query once batching works
-#[query]
-async fn batch_commits_for_contributor(
 	_engine: &mut PluginEngine,
-	repo: BatchGitRepo,
+	repos: Vec<DetailedGitRepo>,
 ) -> Result<Vec<ContributorView>> {
-	let local = repo.local;
-	let emails = repo.details;
-
-	let mut views = Vec::new();
+	load_batch(repos)?
+		.into_iter()
+		.map(|(commits, email)| {
+			let Some(email) = email else {
+				log::error!("No contributor e-maill address provided");
+				return Err(Error::UnspecifiedQueryState);
+			};
 
-	let raw_commits = local_raw_commits(local.clone()).map_err(|e| {
-		log::error!("failed to get commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let commits: Vec<Commit> = raw_commits
-		.iter()
-		.map(|raw| Commit {
-			hash: raw.hash.to_owned(),
-			written_on: raw.written_on.to_owned(),
-			committed_on: raw.committed_on.to_owned(),
+			// Get the contributor. Where one address is used under several
+			// names, use the first by sort order, as in `contributors`.
+			let contributor = commits
+				.for_email(&email)
+				.flat_map(|raw| [&raw.author, &raw.committer])
+				.filter(|c| c.email == email)
+				.min()
+				.cloned()
+				.ok_or_else(|| {
+					log::error!("failed to find contributor");
+					Error::UnspecifiedQueryState
+				})?;
+
+			// Find commits that have that contributor
+			let commits = commits
+				.for_email(&email)
+				.filter(|raw| raw.author == contributor || raw.committer == contributor)
+				.map(to_commit)
+				.collect();
+
+			Ok(ContributorView {
+				contributor,
+				commits,
+			})
 		})
-		.collect();
-	// @Assert - raw_commit and commits idxes correspond
-
-	// Map contributors to the set of commits (by idx) they have contributed to
-	let mut contrib_to_commits: HashMap<Contributor, HashSet<usize>> = HashMap::default();
-	// Map an email to a contributor
-	let mut email_to_contrib: HashMap<String, Contributor> = HashMap::default();
-
-	fn add_contributor(
-		map: &mut HashMap<Contributor, HashSet<usize>>,
-		c: &Contributor,
-		commit_id: usize,
-	) {
-		let cv = match map.get_mut(c) {
-			Some(v) => v,
-			None => {
-				map.insert(c.clone(), HashSet::new());
-				map.get_mut(c).unwrap()
-			}
-		};
-		cv.insert(commit_id);
-	}
-
-	// For each commit, update the contributors' entries in the above maps
-	for (i, commit) in raw_commits.iter().enumerate() {
-		add_contributor(&mut contrib_to_commits, &commit.author, i);
-		email_to_contrib.insert(commit.author.email.clone(), commit.author.clone());
-		add_contributor(&mut contrib_to_commits, &commit.committer, i);
-		email_to_contrib.insert(commit.committer.email.clone(), commit.committer.clone());
-	}
-
-	for email in emails {
-		// Get a contributor from their email
-		let contributor = email_to_contrib
-			.get(&email)
-			.ok_or_else(|| {
-				log::error!("failed to find contributor");
-				Error::UnspecifiedQueryState
-			})?
-			.clone();
-		// Resolve all commits that contributor touched by idx
-		let commits = contrib_to_commits
-			.get(&contributor)
-			.unwrap()
-			.iter()
-			.map(|i| commits.get(*i).unwrap().clone())
-			.collect::<Vec<Commit>>();
-		views.push(ContributorView {
-			contributor,
-			commits,
-		});
-	}
-
-	Ok(views)
+		.collect()
 }
 
 /// Returns the contributor view for a given commit (idenftied by hash in the `details` field)
-#[query]
+#[query(batch)]
 async fn contributors_for_commit(
-	engine: &mut PluginEngine,
-	repo: DetailedGitRepo,
-) -> Result<CommitContributorView> {
-	let local = repo.local;
-	let hash = match repo.details {
-		Some(ref hash) => hash.clone(),
-		None => {
-			log::error!("No commit hash provided");
-			return Err(Error::UnspecifiedQueryState);
-		}
-	};
-
-	let commits = commits(engine, local.clone()).await.map_err(|e| {
-		log::error!("failed to get commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let contributors = contributors(engine, local.clone()).await.map_err(|e| {
-		log::error!("failed to get contributors: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let commit_contributors = commit_contributors(engine, local.clone())
-		.await
-		.map_err(|e| {
-			log::error!("failed to get join table: {}", e);
-			Error::UnspecifiedQueryState
-		})?;
-
-	// Get the index of the commit
-	let commit_id = commits.iter().position(|c| c.hash == hash).ok_or_else(|| {
-		log::error!("failed to find contributor");
-		Error::UnspecifiedQueryState
-	})?;
-
-	// Get the commit
-	let commit = commits[commit_id].clone();
-
-	// Find the author and committer for that commit
-	commit_contributors
-		.iter()
-		.find(|com_con| com_con.commit_id == commit_id)
-		.map(|com_con| {
-			// SAFETY: These indices are guaranteed to be valid in
-			// `contributors` because of how `commit_contributors` is
-			// constructed from it.
-			let author = contributors[com_con.author_id].clone();
-			let committer = contributors[com_con.committer_id].clone();
-
-			CommitContributorView {
-				commit,
-				author,
-				committer,
-			}
-		})
-		.ok_or_else(|| {
-			log::error!("failed to find contributor info");
-			Error::UnspecifiedQueryState
-		})
-}
-
-// Temporary query to call multiple contributors_for_commit() queries until we implement batching
-// TODO: Remove this query once batching works
-#[query]
-async fn batch_contributors_for_commit(
 	_engine: &mut PluginEngine,
-	repo: BatchGitRepo,
+	repos: Vec<DetailedGitRepo>,
 ) -> Result<Vec<CommitContributorView>> {
-	let local = repo.local;
-	let hashes = repo.details;
-
-	let raw_commits = local_raw_commits(local.clone()).map_err(|e| {
-		log::error!("failed to get commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-
-	let mut hash_to_idx: HashMap<String, usize> = HashMap::default();
-	let commit_views: Vec<CommitContributorView> = raw_commits
+	load_batch(repos)?
 		.into_iter()
-		.enumerate()
-		.map(|(i, raw)| {
-			let commit = Commit {
-				hash: raw.hash.to_owned(),
-				written_on: raw.written_on.to_owned(),
-				committed_on: raw.committed_on.to_owned(),
+		.map(|(commits, hash)| {
+			let Some(hash) = hash else {
+				log::error!("No commit hash provided");
+				return Err(Error::UnspecifiedQueryState);
 			};
-			let author = raw.author;
-			let committer = raw.committer;
-			hash_to_idx.insert(raw.hash.clone(), i);
-			CommitContributorView {
-				commit,
-				author,
-				committer,
-			}
-		})
-		.collect();
-
-	let mut views: Vec<CommitContributorView> = vec![];
-
-	for hash in hashes {
-		let idx = hash_to_idx.get(&hash).ok_or_else(|| {
-			log::error!("hash could not be found in repo");
-			Error::UnspecifiedQueryState
-		})?;
-		views.push(commit_views.get(*idx).unwrap().clone());
-	}
-
-	Ok(views)
-}
-
-/// Internal use function that returns a join table of contributors by commit
-async fn commit_contributors(
-	engine: &mut PluginEngine,
-	repo: LocalGitRepo,
-) -> Result<Vec<CommitContributor>> {
-	let path = &repo.path;
-	let contributors = contributors(engine, repo.clone()).await.map_err(|e| {
-		log::error!("failed to get contributors: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
-	let raw_commits = get_commits(path).map_err(|e| {
-		log::error!("failed to get raw commits: {}", e);
-		Error::UnspecifiedQueryState
-	})?;
 
-	let commit_contributors = raw_commits
-		.iter()
-		.enumerate()
-		.map(|(commit_id, raw)| {
-			// SAFETY: These `position` calls are guaranteed to return `Some`
-			// given how `contributors` is constructed from `db.raw_commits()`
-			let author_id = contributors.iter().position(|c| c == &raw.author).unwrap();
-			let committer_id = contributors
-				.iter()
-				.position(|c| c == &raw.committer)
-				.unwrap();
+			// Find the author and committer for that commit
+			let raw = commits.find(&hash).ok_or_else(|| {
+				log::error!("failed to find commit");
+				Error::UnspecifiedQueryState
+			})?;
 
-			CommitContributor {
-				commit_id,
-				author_id,
-				committer_id,
-			}
+			Ok(CommitContributorView {
+				commit: to_commit(raw),
+				author: raw.author.clone(),
+				committer: raw.committer.clone(),
+			})
 		})
-		.collect();
-
-	Ok(commit_contributors)
+		.collect()
 }
 
 #[derive(Clone, Debug)]
@@ -493,18 +432,147 @@ impl Plugin for GitPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
-	PluginServer::register(GitPlugin {}).listen(args.port).await
+	PluginServer::register(GitPlugin {})
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
+		.await
 }
 
 #[cfg(test)]
 mod test {
+	use super::*;
+	use std::process::Command;
+	use tempfile::TempDir;
+
+	/// Make a repo of three commits, each adding one file, and return it with
+	/// the hash of the first commit.
+	fn three_commit_repo() -> (TempDir, String) {
+		let dir = TempDir::with_prefix("hipcheck").unwrap();
+		let git = |args: &[&str]| {
+			let output = Command::new("git")
+				.args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+				.args(args)
+				.current_dir(dir.path())
+				.output()
+				.unwrap();
+			assert!(output.status.success());
+			String::from_utf8(output.stdout).unwrap()
+		};
+
+		git(&["init", "--quiet"]);
+		for file in ["a.rs", "b.rs", "c.rs"] {
+			std::fs::write(dir.path().join(file), "fn main() {}\n").unwrap();
+			git(&["add", file]);
+			git(&["commit", "--quiet", "-m", file]);
+		}
+		let first = git(&["rev-list", "--max-parents=0", "HEAD"]);
+		(dir, first.trim().to_owned())
+	}
+
+	#[tokio::test]
+	async fn test_base_ref_limits_commits_to_range() {
+		let (dir, first) = three_commit_repo();
+		let mut engine = PluginEngine::mock(MockResponses::new());
+		let mut repo = LocalGitRepo {
+			path: dir.path().to_str().unwrap().to_owned(),
+			git_ref: "HEAD".to_owned(),
+			base_ref: None,
+		};
+
+		let all = commit_diffs(&mut engine, repo.clone()).await.unwrap();
+		assert_eq!(all.len(), 3);
+
+		repo.base_ref = Some(first.clone());
+		let range = commit_diffs(&mut engine, repo.clone()).await.unwrap();
+		let files = range
+			.iter()
+			.flat_map(|cd| cd.diff.file_diffs.iter().map(|fd| fd.file_name.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(files, ["c.rs", "b.rs"]);
+		assert!(range.iter().all(|cd| cd.commit.hash != first));
+
+		let contributors = contributors(&mut engine, repo.clone()).await.unwrap();
+		assert_eq!(contributors.len(), 1);
+
+		let hashes = commit_hashes_in_range(&mut engine, repo.clone())
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(hashes.len(), 2);
+		assert!(!hashes.contains(&first));
+		repo.base_ref = None;
+		assert!(commit_hashes_in_range(&mut engine, repo)
+			.await
+			.unwrap()
+			.is_none());
+	}
+
+	#[tokio::test]
+	async fn test_commit_lookups_answer_whole_batch() {
+		let (dir, first) = three_commit_repo();
+		let mut engine = PluginEngine::mock(MockResponses::new());
+		let repo = LocalGitRepo {
+			path: dir.path().to_str().unwrap().to_owned(),
+			git_ref: "HEAD".to_owned(),
+			base_ref: Some(first.clone()),
+		};
+		let hashes = commits(&mut engine, repo.clone())
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|commit| commit.hash)
+			.collect::<Vec<_>>();
+		assert_eq!(hashes.len(), 2);
+
+		let keys = hashes
+			.iter()
+			.map(|hash| {
+				to_value(DetailedGitRepo {
+					local: repo.clone(),
+					details: Some(hash.clone()),
+				})
+				.unwrap()
+			})
+			.collect();
+		let views = ContributorsForCommit {}
+			.run_batch(&mut engine, keys)
+			.await
+			.unwrap();
+		assert_eq!(views.len(), 2);
+		for (view, hash) in views.iter().zip(&hashes) {
+			assert_eq!(view["commit"]["hash"], hash.as_str());
+			assert_eq!(view["author"]["email"], "test@example.com");
+		}
+
+		// Commits before `base_ref` are out of range.
+		let outside = DetailedGitRepo {
+			local: repo.clone(),
+			details: Some(first),
+		};
+		assert!(contributors_for_commit(&mut engine, vec![outside])
+			.await
+			.is_err());
+
+		let contributor = DetailedGitRepo {
+			local: repo,
+			details: Some("test@example.com".to_owned()),
+		};
+		let views = commits_for_contributor(&mut engine, vec![contributor.clone(), contributor])
+			.await
+			.unwrap();
+		assert_eq!(views.len(), 2);
+		assert!(views.iter().all(|view| view.commits.len() == 2));
+	}
+
 	#[test]
 	fn test_no_newline_before_end_of_chunk() {
 		let input = "diff --git a/plugins/review/plugin.kdl b/plugins/review/plugin.kdl\nindex 83f0355..9fa8e47 100644\n--- a/plugins/review/plugin.kdl\n+++ b/plugins/review/plugin.kdl\n@@ -6,4 +6,4 @@ entrypoint {\n-  on arch=\"aarch64-apple-darwin\" \"./hc-mitre-review\"\n-  on arch=\"x86_64-apple-darwin\" \"./hc-mitre-review\"\n-  on arch=\"x86_64-unknown-linux-gnu\" \"./hc-mitre-review\"\n-  on arch=\"x86_64-pc-windows-msvc\" \"./hc-mitre-review\"\n+  on arch=\"aarch64-apple-darwin\" \"./target/debug/review_sdk\"\n+  on arch=\"x86_64-apple-darwin\" \"./target/debug/review_sdk\"\n+  on arch=\"x86_64-unknown-linux-gnu\" \"./target/debug/review_sdk\"\n+  on arch=\"x86_64-pc-windows-msvc\" \"./target/debug/review_sdk\"\n@@ -14 +14 @@ dependencies {\n-}\n\\ No newline at end of file\n+}\n";
@@ -6,7 +6,8 @@ use crate::util::command::log_git_args;
 
 use anyhow::{anyhow, Context as _, Result};
 use std::{
-	convert::AsRef, ffi::OsStr, iter::IntoIterator, ops::Not as _, path::Path, process::Command,
+	collections::HashSet, convert::AsRef, ffi::OsStr, iter::IntoIterator, ops::Not as _,
+	path::Path, process::Command,
 };
 
 #[derive(Debug)]
@@ -69,6 +70,17 @@ impl GitCommand {
 	}
 }
 
+/// Get the id of the commit checked out in the repo.
+pub fn get_head_commit(repo: &str) -> Result<String> {
+	let path = Path::new(repo);
+	let output = GitCommand::for_repo(path, ["rev-parse", "HEAD"])?
+		.output()
+		.context("git rev-parse command failed")?;
+
+	Ok(output.trim().to_owned())
+}
+
+/// Get the commits in the repo.
 pub fn get_commits(repo: &str) -> Result<Vec<RawCommit>> {
 	let path = Path::new(repo);
 	let raw_output = GitCommand::for_repo(
@@ -87,6 +99,18 @@ pub fn get_commits(repo: &str) -> Result<Vec<RawCommit>> {
 	git_log(&raw_output)
 }
 
+/// Get the hashes of the commits in the repo after `base_ref`, up to the
+/// checked-out commit.
+pub fn get_commit_hashes_after(repo: &str, base_ref: &str) -> Result<HashSet<String>> {
+	let path = Path::new(repo);
+	let range = format!("{}..HEAD", base_ref);
+	let output = GitCommand::for_repo(path, ["rev-list", "--no-merges", &range])?
+		.output()
+		.context("git rev-list command failed")?;
+
+	Ok(output.lines().map(str::to_owned).collect())
+}
+
 pub fn get_commits_from_date(repo: &str, date: &str) -> Result<Vec<RawCommit>> {
 	let path = Path::new(repo);
 	let since_date = format!("--since='{} month ago'", date);
@@ -109,6 +133,7 @@ pub fn get_commits_from_date(repo: &str, date: &str) -> Result<Vec<RawCommit>> {
 	git_log(&raw_output)
 }
 
+/// Get the diff of each commit in the repo, in the same order as `get_commits`.
 pub fn get_diffs(repo: &str) -> Result<Vec<Diff>> {
 	let path = Path::new(repo);
 	let output = GitCommand::for_repo(
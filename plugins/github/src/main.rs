@@ -80,6 +80,10 @@ fn get_github_agent<'a>(owner: &'a str, repo: &'a str) -> Result<GitHub<'a>> {
 async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<PullRequest>> {
 	let (owner, repo) = match &key {
 		KnownRemote::GitHub { owner, repo } => (owner, repo),
+		_ => {
+			log::error!("target repository is not a GitHub repository");
+			return Err(Error::UnexpectedPluginQueryInputFormat);
+		}
 	};
 	let results = get_github_agent(owner, repo)?
 		.get_reviews_for_pr()
@@ -101,7 +105,7 @@ async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<
 async fn has_fuzz(_engine: &mut PluginEngine, key: RemoteGitRepo) -> Result<bool> {
 	let (owner, repo) = match &key.known_remote {
 		Some(KnownRemote::GitHub { owner, repo }) => (owner.as_str(), repo.as_str()),
-		None => ("", ""),
+		_ => ("", ""),
 	};
 	let url = Rc::new(key.url.to_string());
 	get_github_agent(owner, repo)?.fuzz_check(url).map_err(|e| {
@@ -112,8 +116,11 @@ async fn has_fuzz(_engine: &mut PluginEngine, key: RemoteGitRepo) -> Result<bool
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[derive(Clone, Debug)]
@@ -149,6 +156,6 @@ impl Plugin for GithubAPIPlugin {
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(GithubAPIPlugin {})
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
@@ -2,7 +2,7 @@
 
 //! Defines an authenticated [`Agent`] type that adds token auth to all requests.
 
-use crate::util::{agent, redacted::Redacted};
+use hipcheck_sdk::{agent, redacted::Redacted};
 use ureq::{Agent, Request};
 
 /// An [`Agent`] which authenticates requests with token auth.
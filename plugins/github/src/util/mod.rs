@@ -1,5 +1,3 @@
 // SPDX-License-Identifier: Apache-2.0
 
-pub mod agent;
 pub mod authenticated_agent;
-pub mod redacted;
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Queries against the GraphQL API of a GitLab instance.
+
+use anyhow::{anyhow, Context as _, Result};
+use hipcheck_sdk::{agent, redacted::Redacted};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Get every merged merge request of a project, with who approved it.
+const MERGE_REQUESTS_QUERY: &str = r#"
+query($fullPath: ID!, $cursor: String) {
+  project(fullPath: $fullPath) {
+    mergeRequests(state: merged, first: 100, after: $cursor) {
+      pageInfo {
+        hasNextPage
+        endCursor
+      }
+      nodes {
+        iid
+        approvedBy {
+          nodes {
+            id
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// Get the contents of a project's CI configuration.
+const CI_CONFIG_QUERY: &str = r#"
+query($fullPath: ID!) {
+  project(fullPath: $fullPath) {
+    ciConfigPathOrDefault
+    repository {
+      blobs(paths: [".gitlab-ci.yml"]) {
+        nodes {
+          path
+          rawBlob
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// A project on a GitLab instance.
+pub struct GitLab<'a> {
+	/// The URL of the instance's GraphQL API.
+	api: String,
+
+	/// The full path of the project, like `gitlab-org/gitlab`.
+	full_path: String,
+
+	/// The token to authenticate with, if any. Public projects don't need one.
+	token: Option<Redacted<&'a str>>,
+}
+
+/// A merged merge request, and how many users approved it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GitLabMergeRequest {
+	pub iid: u64,
+	pub approvals: u64,
+}
+
+impl<'a> GitLab<'a> {
+	pub fn new(host: &str, namespace: &str, project: &str, token: Option<&'a str>) -> GitLab<'a> {
+		GitLab {
+			api: format!("https://{}/api/graphql", host),
+			full_path: format!("{}/{}", namespace, project),
+			token: token.map(Redacted::new),
+		}
+	}
+
+	/// Get every merged merge request of the project with its number of approvals.
+	pub fn get_approvals_for_mr(&self) -> Result<Vec<GitLabMergeRequest>> {
+		let mut data = Vec::new();
+		let mut cursor: Option<String> = None;
+
+		// Keep making requests so long as there are more pages.
+		loop {
+			let response = self.request(
+				MERGE_REQUESTS_QUERY,
+				json!({ "fullPath": self.full_path, "cursor": cursor }),
+			)?;
+			let page = parse_merge_requests(response)?;
+			data.extend(page.merge_requests);
+
+			match page.cursor {
+				Some(next) => cursor = Some(next),
+				None => break,
+			}
+		}
+
+		Ok(data)
+	}
+
+	/// Check whether the project's CI runs GitLab's coverage-guided fuzz testing.
+	pub fn fuzz_check(&self) -> Result<bool> {
+		let response = self.request(CI_CONFIG_QUERY, json!({ "fullPath": self.full_path }))?;
+		let config = parse_ci_config(response)?;
+		Ok(config.as_deref().is_some_and(uses_coverage_fuzzing))
+	}
+
+	/// Make a GraphQL request, returning its `data`.
+	fn request(&self, query: &str, variables: Value) -> Result<Value> {
+		let mut request = agent::agent().post(&self.api);
+		if let Some(token) = &self.token {
+			request = request.set("Authorization", &format!("Bearer {}", token.as_ref()));
+		}
+
+		let response: GraphQlResponse = request
+			.send_json(json!({ "query": query, "variables": variables }))
+			.with_context(|| format!("request to GitLab API at '{}' failed", self.api))?
+			.into_json()
+			.context("GitLab API response isn't valid JSON")?;
+
+		if let Some(error) = response.errors.first() {
+			return Err(anyhow!("GitLab API returned an error: {}", error.message));
+		}
+
+		response
+			.data
+			.ok_or_else(|| anyhow!("GitLab API returned no data"))
+	}
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse {
+	data: Option<Value>,
+	#[serde(default)]
+	errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+	message: String,
+}
+
+/// One page of merge requests, with the cursor of the next page if there is one.
+struct MergeRequestPage {
+	merge_requests: Vec<GitLabMergeRequest>,
+	cursor: Option<String>,
+}
+
+fn parse_merge_requests(data: Value) -> Result<MergeRequestPage> {
+	let connection = project(&data)?
+		.get("mergeRequests")
+		.ok_or_else(|| anyhow!("missing merge requests in GitLab API response"))?;
+
+	let nodes = connection
+		.pointer("/nodes")
+		.and_then(Value::as_array)
+		.ok_or_else(|| anyhow!("missing merge request nodes in GitLab API response"))?;
+
+	let merge_requests = nodes
+		.iter()
+		.map(|node| {
+			// GitLab gives internal IDs as strings
+			let iid = node
+				.get("iid")
+				.and_then(Value::as_str)
+				.and_then(|iid| iid.parse().ok())
+				.ok_or_else(|| anyhow!("missing or invalid merge request iid"))?;
+			let approvals = node
+				.pointer("/approvedBy/nodes")
+				.and_then(Value::as_array)
+				.map_or(0, |approvers| approvers.len() as u64);
+			Ok(GitLabMergeRequest { iid, approvals })
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	let has_next_page = connection
+		.pointer("/pageInfo/hasNextPage")
+		.and_then(Value::as_bool)
+		.unwrap_or(false);
+	let cursor = connection
+		.pointer("/pageInfo/endCursor")
+		.and_then(Value::as_str)
+		.filter(|_| has_next_page)
+		.map(ToOwned::to_owned);
+
+	Ok(MergeRequestPage {
+		merge_requests,
+		cursor,
+	})
+}
+
+/// Get the contents of the project's CI configuration, if it's kept at the
+/// default path and the project has one.
+fn parse_ci_config(data: Value) -> Result<Option<String>> {
+	let project = project(&data)?;
+
+	if project.get("ciConfigPathOrDefault").and_then(Value::as_str) != Some(".gitlab-ci.yml") {
+		log::warn!("project keeps its CI configuration outside the repository root");
+		return Ok(None);
+	}
+
+	let config = project
+		.pointer("/repository/blobs/nodes")
+		.and_then(Value::as_array)
+		.and_then(|blobs| blobs.first())
+		.and_then(|blob| blob.get("rawBlob"))
+		.and_then(Value::as_str)
+		.map(ToOwned::to_owned);
+
+	Ok(config)
+}
+
+fn project(data: &Value) -> Result<&Value> {
+	data.get("project")
+		.filter(|project| !project.is_null())
+		.ok_or_else(|| anyhow!("project not found, or not visible with the configured token"))
+}
+
+/// Check whether a CI configuration runs coverage-guided fuzz testing, either
+/// from GitLab's template or by calling `gitlab-cov-fuzz` directly.
+fn uses_coverage_fuzzing(config: &str) -> bool {
+	config.contains("Coverage-Fuzzing.gitlab-ci.yml")
+		|| config.contains("extends: .fuzz_base")
+		|| config.contains("gitlab-cov-fuzz")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_merge_requests() {
+		let data = json!({
+			"project": {
+				"mergeRequests": {
+					"pageInfo": { "hasNextPage": true, "endCursor": "abc" },
+					"nodes": [
+						{ "iid": "12", "approvedBy": { "nodes": [{ "id": "gid://gitlab/User/1" }] } },
+						{ "iid": "13", "approvedBy": { "nodes": [] } }
+					]
+				}
+			}
+		});
+
+		let page = parse_merge_requests(data).unwrap();
+		assert_eq!(
+			page.merge_requests,
+			vec![
+				GitLabMergeRequest {
+					iid: 12,
+					approvals: 1
+				},
+				GitLabMergeRequest {
+					iid: 13,
+					approvals: 0
+				},
+			]
+		);
+		assert_eq!(page.cursor.as_deref(), Some("abc"));
+	}
+
+	#[test]
+	fn test_parse_merge_requests_last_page() {
+		let data = json!({
+			"project": {
+				"mergeRequests": {
+					"pageInfo": { "hasNextPage": false, "endCursor": "abc" },
+					"nodes": []
+				}
+			}
+		});
+
+		let page = parse_merge_requests(data).unwrap();
+		assert!(page.merge_requests.is_empty());
+		assert!(page.cursor.is_none());
+	}
+
+	#[test]
+	fn test_missing_project() {
+		assert!(parse_merge_requests(json!({ "project": null })).is_err());
+	}
+
+	#[test]
+	fn test_uses_coverage_fuzzing() {
+		let config = parse_ci_config(json!({
+			"project": {
+				"ciConfigPathOrDefault": ".gitlab-ci.yml",
+				"repository": {
+					"blobs": {
+						"nodes": [{
+							"path": ".gitlab-ci.yml",
+							"rawBlob": "include:\n  - template: Coverage-Fuzzing.gitlab-ci.yml\n"
+						}]
+					}
+				}
+			}
+		}))
+		.unwrap()
+		.unwrap();
+
+		assert!(uses_coverage_fuzzing(&config));
+		assert!(!uses_coverage_fuzzing("test:\n  script: cargo test\n"));
+	}
+}
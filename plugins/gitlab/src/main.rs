@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod api;
+
+use crate::api::GitLab;
+use clap::Parser;
+use hipcheck_sdk::{
+	prelude::*,
+	types::{KnownRemote, RemoteGitRepo},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::result::Result as StdResult;
+use std::sync::OnceLock;
+
+struct Config {
+	pub api_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(rename = "api-token-var")]
+	api_token_var: Option<String>,
+}
+
+impl TryFrom<RawConfig> for Config {
+	type Error = ConfigError;
+	fn try_from(value: RawConfig) -> StdResult<Config, ConfigError> {
+		// Public projects can be queried without a token, so one is only
+		// required if a variable to read it from was given.
+		let Some(atv) = value.api_token_var else {
+			return Ok(Config { api_token: None });
+		};
+		let api_token =
+			std::env::var(atv.as_str()).map_err(|_e| ConfigError::InvalidConfigValue {
+				field_name: "api-token-var".to_owned(),
+				value: atv,
+				reason: "could not find an env var with that name".to_owned(),
+			})?;
+		Ok(Config {
+			api_token: Some(api_token),
+		})
+	}
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// A merge request, with the number of users who approved it as its reviews.
+#[derive(Debug, Serialize, JsonSchema, PartialEq, Eq)]
+pub struct PullRequest {
+	pub id: u64,
+	pub reviews: u64,
+}
+
+fn get_gitlab_agent<'a>(known_remote: Option<&'a KnownRemote>) -> Result<GitLab<'a>> {
+	let Some(KnownRemote::GitLab {
+		host,
+		namespace,
+		project,
+	}) = known_remote
+	else {
+		log::error!("target repository is not a GitLab repository");
+		return Err(Error::UnexpectedPluginQueryInputFormat);
+	};
+
+	let config = CONFIG.get().ok_or_else(|| {
+		log::error!("tried to access config before set by Hipcheck core!");
+		Error::UnspecifiedQueryState
+	})?;
+
+	Ok(GitLab::new(
+		host,
+		namespace,
+		project,
+		config.api_token.as_deref(),
+	))
+}
+
+#[query]
+async fn pr_reviews(_engine: &mut PluginEngine, key: KnownRemote) -> Result<Vec<PullRequest>> {
+	let results = get_gitlab_agent(Some(&key))?
+		.get_approvals_for_mr()
+		.map_err(|e| {
+			log::error!("{:#}", e);
+			Error::UnspecifiedQueryState
+		})?
+		.into_iter()
+		.map(|mr| PullRequest {
+			id: mr.iid,
+			reviews: mr.approvals,
+		})
+		.collect();
+
+	Ok(results)
+}
+
+#[query(default)]
+async fn has_fuzz(_engine: &mut PluginEngine, key: RemoteGitRepo) -> Result<bool> {
+	get_gitlab_agent(key.known_remote.as_ref())?
+		.fuzz_check()
+		.map_err(|e| {
+			log::error!("{:#}", e);
+			Error::UnspecifiedQueryState
+		})
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+struct GitlabAPIPlugin {}
+
+impl Plugin for GitlabAPIPlugin {
+	const PUBLISHER: &'static str = "mitre";
+	const NAME: &'static str = "gitlab";
+
+	fn set_config(&self, config: Value) -> StdResult<(), ConfigError> {
+		let conf: Config = serde_json::from_value::<RawConfig>(config)
+			.map_err(|e| ConfigError::Unspecified {
+				message: e.to_string(),
+			})?
+			.try_into()?;
+		CONFIG.set(conf).map_err(|_e| ConfigError::Unspecified {
+			message: "config was already set".to_owned(),
+		})
+	}
+
+	fn default_policy_expr(&self) -> Result<String> {
+		Ok("".to_owned())
+	}
+
+	fn explain_default_query(&self) -> Result<Option<String>> {
+		Ok(None)
+	}
+
+	queries! {}
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+	let args = Args::try_parse().unwrap();
+	PluginServer::register(GitlabAPIPlugin {})
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
+		.await
+}
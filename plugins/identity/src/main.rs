@@ -146,15 +146,18 @@ impl Plugin for IdentityPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(IdentityPlugin::default())
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
 
@@ -164,6 +167,7 @@ mod test {
 
 	fn target() -> Target {
 		let local = LocalGitRepo {
+			base_ref: None,
 			git_ref: "HEAD".to_owned(),
 			path: ".".to_owned(),
 		};
@@ -4,7 +4,10 @@
 
 use anyhow::Context as _;
 use clap::Parser;
-use hipcheck_sdk::{prelude::*, types::Target};
+use hipcheck_sdk::{
+	prelude::*,
+	types::{KnownRemote, Target},
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{result::Result as StdResult, sync::OnceLock};
@@ -34,22 +37,28 @@ pub struct PullReview {
 async fn review(engine: &mut PluginEngine, value: Target) -> Result<Vec<bool>> {
 	log::debug!("running review metric");
 
-	// Confirm that the target is a GitHub repo
+	// Confirm that the target is a GitHub or GitLab repo
 	let Some(remote) = value.remote else {
 		log::error!("target repository does not have a remote repository URL");
 		return Err(Error::UnexpectedPluginQueryInputFormat);
 	};
 
 	let Some(known_remote) = remote.known_remote else {
-		log::error!("target repository is not a GitHub repository or else is missing GitHub repo information");
+		log::error!("target repository is not a GitHub or GitLab repository or else is missing repo information");
 		return Err(Error::UnexpectedPluginQueryInputFormat);
 	};
 
+	// GitLab merge requests are reviewed by approving them
+	let (query, forge) = match &known_remote {
+		KnownRemote::GitHub { .. } => ("mitre/github/pr_reviews", "GitHub"),
+		KnownRemote::GitLab { .. } => ("mitre/gitlab/pr_reviews", "GitLab"),
+	};
+
 	// Get a list of all pull requests to the repo, with their corresponding number of reviews
 	let value = engine
-		.query("mitre/github/pr_reviews", known_remote)
+		.query(query, known_remote)
 		.await
-		.context("failed to get pull request reviews from GitHub")?;
+		.with_context(|| format!("failed to get pull request reviews from {}", forge))?;
 
 	let pull_requests: Vec<PullRequest> =
 		serde_json::from_value(value).map_err(Error::InvalidJsonInQueryOutput)?;
@@ -109,15 +118,18 @@ impl Plugin for ReviewPlugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
 	PluginServer::register(ReviewPlugin {})
-		.listen(args.port)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
 		.await
 }
 
@@ -125,7 +137,7 @@ async fn main() -> Result<()> {
 mod test {
 	use super::*;
 
-	use hipcheck_sdk::types::{KnownRemote, LocalGitRepo, RemoteGitRepo};
+	use hipcheck_sdk::types::{LocalGitRepo, RemoteGitRepo};
 	use std::result::Result as StdResult;
 	use url::Url;
 
@@ -157,6 +169,7 @@ mod test {
 			specifier: "express".to_string(),
 			local: LocalGitRepo {
 				path: "/home/users/me/.cache/hipcheck/clones/github/expressjs/express/".to_string(),
+				base_ref: None,
 				git_ref: "main".to_string(),
 			},
 			remote: Some(RemoteGitRepo {
@@ -173,4 +186,40 @@ mod test {
 
 		assert_eq!(result, expected);
 	}
+
+	#[tokio::test]
+	async fn test_review_gitlab() {
+		let known_remote = KnownRemote::GitLab {
+			host: "gitlab.com".to_string(),
+			namespace: "gitlab-org".to_string(),
+			project: "gitlab-runner".to_string(),
+		};
+		let prs = vec![
+			PullRequest { id: 1, reviews: 0 },
+			PullRequest { id: 2, reviews: 2 },
+		];
+		let mut mock_responses = MockResponses::new();
+		mock_responses
+			.insert("mitre/gitlab/pr_reviews", known_remote.clone(), Ok(prs))
+			.unwrap();
+
+		let target = Target {
+			specifier: "gitlab-runner".to_string(),
+			local: LocalGitRepo {
+				path: "/home/users/me/.cache/hipcheck/clones/gitlab/gitlab.com/gitlab-org/gitlab-runner/".to_string(),
+				base_ref: None,
+				git_ref: "main".to_string(),
+			},
+			remote: Some(RemoteGitRepo {
+				url: Url::parse("https://gitlab.com/gitlab-org/gitlab-runner.git").unwrap(),
+				known_remote: Some(known_remote),
+			}),
+			package: None,
+		};
+
+		let mut engine = PluginEngine::mock(mock_responses);
+		let result = review(&mut engine, target).await.unwrap();
+
+		assert_eq!(result, vec![false, true]);
+	}
 }
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Globally defined agent containing system TLS certs, for plugins that make
+//! HTTP(S) requests. Requires the `http` feature.
+
+use crate::offline::is_offline;
+use rustls::{ClientConfig, RootCertStore};
+use std::{
+	io,
+	sync::{Arc, OnceLock},
+};
+use ureq::{Agent, AgentBuilder, MiddlewareNext, Request, Response};
+
+/// Global static holding the agent with the appropriate TLS certs.
+static AGENT: OnceLock<Agent> = OnceLock::new();
+
+/// Refuse any request made while Hipcheck is offline.
+fn refuse_if_offline(request: Request, next: MiddlewareNext) -> Result<Response, ureq::Error> {
+	if is_offline() {
+		return Err(io::Error::other(format!(
+			"network access is turned off by Hipcheck's --offline, but a request was made to {}",
+			request.url()
+		))
+		.into());
+	}
+	next.handle(request)
+}
+
+/// Get or initialize the global static agent used in making http(s) requests from plugins.
+/// Requests made through it fail immediately while Hipcheck is offline.
+///
+/// # Panics
+/// - If native certs cannot be loaded the first time this function is called.
+pub fn agent() -> &'static Agent {
+	AGENT.get_or_init(|| {
+		// Retrieve system certs
+		let mut roots = RootCertStore::empty();
+		let native_certs =
+			rustls_native_certs::load_native_certs().expect("should load native certs");
+		roots.add_parsable_certificates(native_certs);
+
+		// Add certs to connection configuration
+		let tls_config = ClientConfig::builder()
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+
+		// Construct agent
+		AgentBuilder::new()
+			.tls_config(Arc::new(tls_config))
+			.middleware(refuse_if_offline)
+			.build()
+	})
+}
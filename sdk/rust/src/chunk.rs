@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Splitting query messages too large for gRPC into chunks, and putting them back
+//! together. Both plugins and Hipcheck reply to queries, so both use these.
+
+use crate::{
+	error::{Error, Result},
+	proto::{Query as PluginQuery, QueryState},
+};
+use anyhow::anyhow;
+use std::ops::Not;
+
+/// The largest message gRPC will send.
+pub const GRPC_MAX_SIZE: usize = 1024 * 1024 * 4; // 4MB
+/// The largest the parts of a query message which are chunked can be, leaving
+/// room for the rest of the message.
+pub const GRPC_EFFECTIVE_MAX_SIZE: usize = GRPC_MAX_SIZE - 1024; // Minus one KB
+
+/// Try to drain `max` bytes from `buf`, or the full string, whichever is shortest.
+/// If `max` bytes is somewhere within `buf` but lands within a char boundary,
+/// walk backwards to the start of the previous char. Returns the substring
+/// drained from `buf`.
+fn drain_at_most_n_bytes(buf: &mut String, max: usize) -> Result<String> {
+	let mut to_drain = std::cmp::min(buf.len(), max);
+	while to_drain > 0 && buf.is_char_boundary(to_drain).not() {
+		to_drain -= 1;
+	}
+	if to_drain == 0 {
+		return Err(anyhow!("Could not drain any whole char from string").into());
+	}
+	Ok(buf.drain(0..to_drain).collect::<String>())
+}
+
+/// Estimate the size of a query message from the parts of it which are chunked.
+pub fn estimate_size(msg: &PluginQuery) -> usize {
+	msg.key.iter().map(|x| x.len()).sum::<usize>()
+		+ msg.output.iter().map(|x| x.len()).sum::<usize>()
+		+ msg.concern.iter().map(|x| x.len()).sum::<usize>()
+}
+
+/// Move whole entries from the front of `from` to `to` while they fit in
+/// `remaining` bytes. If the next entry does not fit, move as much of it as
+/// will fit and return `true` to indicate that the entry was split.
+fn drain_entries(from: &mut Vec<String>, to: &mut Vec<String>, remaining: &mut usize) -> bool {
+	while let Some(first) = from.first_mut() {
+		let len = first.len();
+		if len <= *remaining {
+			*remaining -= len;
+			to.push(from.remove(0));
+			continue;
+		}
+		// The entry may be too short to split on a char boundary, in which case
+		// it waits for the next chunk
+		if let Ok(part) = drain_at_most_n_bytes(first, *remaining) {
+			*remaining -= part.len();
+			to.push(part);
+			return true;
+		}
+		break;
+	}
+	false
+}
+
+/// Split a reply into chunks whose estimated size is at most `max_est_size`. Requests are
+/// never chunked.
+pub fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<PluginQuery>> {
+	// Chunking only does something on response objects, mostly because
+	// we don't have a state to represent "SubmitInProgress"
+	if msg.state == QueryState::Submit as i32 {
+		return Ok(vec![msg]);
+	}
+
+	let mut out: Vec<PluginQuery> = vec![];
+	let mut base: PluginQuery = msg;
+
+	// Track whether we did anything on each iteration to avoid infinite loop
+	let mut made_progress = true;
+	while estimate_size(&base) > max_est_size {
+		log::trace!("Estimated size is too large, chunking");
+		if !made_progress {
+			log::error!("Message could not be chunked");
+			return Err(Error::UnspecifiedQueryState);
+		}
+
+		// For this loop, we want to take at most MAX_SIZE bytes because that's
+		// all that can fit in a PluginQuery
+		let mut remaining = max_est_size;
+		let mut query = PluginQuery {
+			id: base.id,
+			state: QueryState::ReplyInProgress as i32,
+			publisher_name: base.publisher_name.clone(),
+			plugin_name: base.plugin_name.clone(),
+			query_name: base.query_name.clone(),
+			key: vec![],
+			output: vec![],
+			concern: vec![],
+			split: false,
+		};
+
+		// Steal from key, then from output. A field is only started once the
+		// one before it is empty, and a split entry always ends the chunk, so
+		// the receiver can tell which field a split applies to.
+		query.split = drain_entries(&mut base.key, &mut query.key, &mut remaining);
+		if !query.split && base.key.is_empty() {
+			query.split = drain_entries(&mut base.output, &mut query.output, &mut remaining);
+		}
+
+		let mut l = base.concern.len();
+		// While we still want to steal more bytes and we have more elements of
+		// `concern` to possibly steal
+		while !query.split && remaining > 0 && l > 0 {
+			let i = l - 1;
+
+			let c_bytes = base.concern.get(i).unwrap().len();
+
+			if c_bytes > max_est_size {
+				return Err(anyhow!("Query cannot be chunked, there is a concern that is larger than max chunk size").into());
+			} else if c_bytes <= remaining {
+				// steal this concern
+				let concern = base.concern.swap_remove(i);
+				query.concern.push(concern);
+				remaining -= c_bytes;
+			}
+			// since we use `swap_remove`, whether or not we stole a concern we know the element
+			// currently at `i` is too big for `remainder` (since if we removed, the element at `i`
+			// now is one we already passed on)
+			l -= 1;
+		}
+
+		made_progress =
+			!(query.key.is_empty() && query.output.is_empty() && query.concern.is_empty());
+		out.push(query);
+	}
+	out.push(base);
+	Ok(out)
+}
+
+/// Append the entries of the next chunk of a field to those received so far. If
+/// the previous chunk split an entry of this field, the first new entry is the
+/// rest of it.
+fn extend_chunked(acc: &mut Vec<String>, next: Vec<String>, split: bool) {
+	let mut next = next.into_iter();
+	if split {
+		if let Some(last) = acc.last_mut() {
+			last.push_str(&next.next().unwrap_or_default());
+		}
+	}
+	acc.extend(next);
+}
+
+/// Combine the next chunk of a reply into the message reassembled so far.
+pub fn combine_chunk(acc: &mut PluginQuery, next: PluginQuery) {
+	// All key entries are sent before any output entries, so a split applies
+	// to `output` once any of it has been received
+	let split_output = acc.split && !acc.output.is_empty();
+	let split_key = acc.split && !split_output;
+	extend_chunked(&mut acc.key, next.key, split_key);
+	extend_chunked(&mut acc.output, next.output, split_output);
+	acc.concern.extend(next.concern);
+	acc.split = next.split;
+}
+
+/// Split a reply into chunks small enough to send over gRPC.
+pub fn chunk(msg: PluginQuery) -> Result<Vec<PluginQuery>> {
+	chunk_with_size(msg, GRPC_EFFECTIVE_MAX_SIZE)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_bounded_char_draining() {
+		let orig_key = "aこれは実験です".to_owned();
+
+		let mut key = orig_key.clone();
+		let res = drain_at_most_n_bytes(&mut key, 10).unwrap();
+		let num_bytes = res.len();
+
+		assert!(num_bytes > 0 && num_bytes <= 10);
+
+		// Make sure the drained str + retained str combine to re-create original
+		let mut reassembled = res.clone();
+		reassembled.push_str(&key);
+
+		assert_eq!(orig_key, reassembled);
+	}
+
+	#[test]
+	fn test_chunking() {
+		let query = PluginQuery {
+			id: 0,
+			state: QueryState::ReplyComplete as i32,
+			publisher_name: "".to_owned(),
+			plugin_name: "".to_owned(),
+			query_name: "".to_owned(),
+			// This key will cause the chunk not to occur on a char boundary
+			key: vec!["aこれは実験です".to_owned()],
+			output: vec![],
+			concern: vec!["< 10".to_owned(), "0123456789".to_owned()],
+			split: false,
+		};
+		let res = chunk_with_size(query, 10).unwrap();
+		assert_eq!(res.len(), 4);
+	}
+
+	#[test]
+	fn test_chunked_batch_reassembly() {
+		let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+		let query = PluginQuery {
+			id: 0,
+			state: QueryState::ReplyComplete as i32,
+			publisher_name: "".to_owned(),
+			plugin_name: "".to_owned(),
+			query_name: "".to_owned(),
+			key: strings(&["\"a\"", "\"aこれは実験です\""]),
+			output: strings(&["[1,2,3,4,5,6,7,8,9]", "null"]),
+			concern: vec![],
+			split: false,
+		};
+		let mut chunks = chunk_with_size(query.clone(), 10).unwrap().into_iter();
+		let mut reassembled = chunks.next().unwrap();
+		for next in chunks {
+			combine_chunk(&mut reassembled, next);
+		}
+		assert_eq!(reassembled.key, query.key);
+		assert_eq!(reassembled.output, query.output);
+	}
+}
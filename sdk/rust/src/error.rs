@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::proto::{ConfigurationStatus, InitiateQueryProtocolResponse, SetConfigurationResponse};
-use std::{convert::Infallible, error::Error as StdError, ops::Not, result::Result as StdResult};
+use std::{
+	convert::Infallible, error::Error as StdError, ops::Not, path::PathBuf,
+	result::Result as StdResult,
+};
 use tokio::sync::mpsc::error::SendError as TokioMpscSendError;
 use tonic::Status as TonicStatus;
 
@@ -38,6 +41,10 @@ pub enum Error {
 	#[error("plugin sent QuerySubmit when server was expecting a reply chunk")]
 	ReceivedSubmitWhenExpectingReplyChunk,
 
+	/// The `PluginEngine` received a query message with a different number of keys and outputs
+	#[error("query has {keys} keys but {outputs} outputs")]
+	MismatchedBatchSize { keys: usize, outputs: usize },
+
 	/// The `PluginEngine` received additional messages when it did not expect any
 	#[error("received additional message for ID '{id}' after query completion")]
 	MoreAfterQueryComplete { id: usize },
@@ -45,6 +52,21 @@ pub enum Error {
 	#[error("failed to start server")]
 	FailedToStartServer(#[source] tonic::transport::Error),
 
+	#[error("failed to bind socket '{path}'")]
+	FailedToBindSocket {
+		path: PathBuf,
+		#[source]
+		source: std::io::Error,
+	},
+
+	/// The plugin was asked to listen on a Unix domain socket on a platform without them
+	#[error("Unix domain sockets are not supported on this platform")]
+	UnsupportedSocket,
+
+	/// The plugin was given neither a port nor a Unix domain socket to listen on
+	#[error("no port or socket to listen on")]
+	NoListenAddr,
+
 	/// The `Query::run` function implementation received an incorrectly-typed JSON Value key
 	#[error("unexpected JSON value from plugin")]
 	UnexpectedPluginQueryInputFormat,
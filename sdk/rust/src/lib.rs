@@ -12,14 +12,20 @@ use std::str::FromStr;
 #[cfg(feature = "macros")]
 extern crate hipcheck_sdk_macros;
 
-mod proto {
+/// The messages and services of the gRPC protocol between Hipcheck and plugins.
+pub mod proto {
 	include!(concat!(env!("OUT_DIR"), "/hipcheck.v1.rs"));
 }
 
+#[cfg(feature = "http")]
+pub mod agent;
+pub mod chunk;
 pub mod error;
 mod mock;
+pub mod offline;
 pub mod plugin_engine;
 pub mod plugin_server;
+pub mod redacted;
 pub mod types;
 
 /// A utility module, users can simply write `use hipcheck_sdk::prelude::*` to import everything
@@ -28,7 +34,7 @@ pub mod prelude {
 	pub use crate::deps::*;
 	pub use crate::error::{ConfigError, Error, Result};
 	pub use crate::plugin_engine::PluginEngine;
-	pub use crate::plugin_server::{PluginServer, QueryResult};
+	pub use crate::plugin_server::{ListenAddr, PluginServer, QueryResult};
 	pub use crate::{DynQuery, NamedQuery, Plugin, Query, QuerySchema, QueryTarget};
 	// Re-export macros
 	#[cfg(feature = "macros")]
@@ -124,7 +130,7 @@ impl NamedQuery {
 
 /// Defines a single query endpoint for the plugin.
 #[tonic::async_trait]
-pub trait Query: Send {
+pub trait Query: Send + Sync {
 	/// Get the input schema for the query as a `schemars::schema::SchemaObject`.
 	fn input_schema(&self) -> JsonSchema;
 
@@ -135,6 +141,21 @@ pub trait Query: Send {
 	/// The `PluginEngine` reference allows the endpoint to query other Hipcheck plugins by
 	/// calling `engine::query()`.
 	async fn run(&self, engine: &mut PluginEngine, input: JsonValue) -> Result<JsonValue>;
+
+	/// Run the query endpoint logic on each of `inputs`, a batch of keys sent together, returning
+	/// one output per key in the same order. By default each key is run in turn; queries which
+	/// can share work between the keys of a batch may answer them all at once instead.
+	async fn run_batch(
+		&self,
+		engine: &mut PluginEngine,
+		inputs: Vec<JsonValue>,
+	) -> Result<Vec<JsonValue>> {
+		let mut outputs = Vec::with_capacity(inputs.len());
+		for input in inputs {
+			outputs.push(self.run(engine, input).await?);
+		}
+		Ok(outputs)
+	}
 }
 
 /// The core trait that a plugin author must implement to write a plugin with the Hipcheck SDK.
@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Whether Hipcheck was started with `--offline`. Hipcheck tells the plugins it starts through the
+//! environment, and the SDK's HTTP agent refuses to make requests while it's set.
+
+/// The environment variable Hipcheck sets for the plugins it starts when it's offline.
+pub const OFFLINE_ENV_VAR: &str = "HC_OFFLINE";
+
+/// Whether the plugin was started by Hipcheck while it's offline, and so shouldn't use the
+/// network.
+pub fn is_offline() -> bool {
+	std::env::var_os(OFFLINE_ENV_VAR).is_some_and(|value| !value.is_empty())
+}
@@ -2,6 +2,7 @@
 
 use crate::proto::QueryState;
 use crate::{
+	chunk::{chunk, combine_chunk, GRPC_EFFECTIVE_MAX_SIZE},
 	error::{Error, Result},
 	proto::{
 		self, InitiateQueryProtocolRequest, InitiateQueryProtocolResponse, Query as PluginQuery,
@@ -9,7 +10,6 @@ use crate::{
 	QueryTarget,
 };
 use crate::{mock::MockResponses, JsonValue, Plugin};
-use anyhow::anyhow;
 use futures::Stream;
 use serde::Serialize;
 use serde_json::{json, Value};
@@ -24,109 +24,29 @@ use std::{
 use tokio::sync::mpsc::{self, error::TrySendError};
 use tonic::Status;
 
-const GRPC_MAX_SIZE: usize = 1024 * 1024 * 4; // 4MB
-const GRPC_EFFECTIVE_MAX_SIZE: usize = GRPC_MAX_SIZE - 1024; // Minus one KB
-
-/// Try to drain `max` bytes from `buf`, or the full string, whichever is shortest.
-/// If `max` bytes is somewhere within `buf` but lands within a char boundary,
-/// walk backwards to the start of the previous char. Returns the substring
-/// drained from `buf`.
-fn drain_at_most_n_bytes(buf: &mut String, max: usize) -> Result<String> {
-	let mut to_drain = std::cmp::min(buf.bytes().len(), max);
-	while to_drain > 0 && buf.is_char_boundary(to_drain).not() {
-		to_drain -= 1;
-	}
-	if to_drain == 0 {
-		return Err(anyhow!("Could not drain any whole char from string").into());
-	}
-	Ok(buf.drain(0..to_drain).collect::<String>())
-}
-
-fn estimate_size(msg: &PluginQuery) -> usize {
-	msg.key.bytes().len()
-		+ msg.output.bytes().len()
-		+ msg.concern.iter().map(|x| x.bytes().len()).sum::<usize>()
-}
-
-fn chunk_with_size(msg: PluginQuery, max_est_size: usize) -> Result<Vec<PluginQuery>> {
-	// Chunking only does something on response objects, mostly because
-	// we don't have a state to represent "SubmitInProgress"
-	if msg.state == QueryState::Submit as i32 {
-		return Ok(vec![msg]);
-	}
-
-	let mut out: Vec<PluginQuery> = vec![];
-	let mut base: PluginQuery = msg;
-
-	// Track whether we did anything on each iteration to avoid infinite loop
-	let mut made_progress = true;
-	while estimate_size(&base) > max_est_size {
-		log::trace!("Estimated size is too large, chunking");
-		if !made_progress {
-			log::error!("Message could not be chunked");
-			return Err(Error::UnspecifiedQueryState);
-		}
-
-		made_progress = false;
-		// For this loop, we want to take at most MAX_SIZE bytes because that's
-		// all that can fit in a PluginQuery
-		let mut remaining = max_est_size;
-		let mut query = proto::Query {
-			id: base.id,
-			state: QueryState::ReplyInProgress as i32,
-			publisher_name: base.publisher_name.clone(),
-			plugin_name: base.plugin_name.clone(),
-			query_name: base.query_name.clone(),
-			key: String::new(),
-			output: String::new(),
-			concern: vec![],
-		};
-
-		if remaining > 0 && base.key.bytes().len() > 0 {
-			// steal from key
-			query.key = drain_at_most_n_bytes(&mut base.key, remaining)?;
-			remaining -= query.key.bytes().len();
-			made_progress = true;
-		}
-
-		if remaining > 0 && base.output.bytes().len() > 0 {
-			// steal from output
-			query.output = drain_at_most_n_bytes(&mut base.output, remaining)?;
-			remaining -= query.output.bytes().len();
-			made_progress = true;
+/// Split `keys` into groups whose requests each fit in a single message, since
+/// requests cannot be chunked. A key too large to share a message is sent alone.
+fn group_keys(keys: Vec<Value>, max_est_size: usize) -> Result<Vec<Vec<Value>>> {
+	let mut groups: Vec<Vec<Value>> = vec![];
+	let mut curr: Vec<Value> = vec![];
+	let mut remaining = max_est_size;
+	for key in keys {
+		let key_bytes = serde_json::to_string(&key)
+			.map_err(Error::InvalidJsonInQueryKey)?
+			.len();
+		// Each key is sent with a `null` output
+		let size = key_bytes + "null".len();
+		if size > remaining && curr.is_empty().not() {
+			groups.push(std::mem::take(&mut curr));
+			remaining = max_est_size;
 		}
-
-		let mut l = base.concern.len();
-		// While we still want to steal more bytes and we have more elements of
-		// `concern` to possibly steal
-		while remaining > 0 && l > 0 {
-			let i = l - 1;
-
-			let c_bytes = base.concern.get(i).unwrap().bytes().len();
-
-			if c_bytes > max_est_size {
-				return Err(anyhow!("Query cannot be chunked, there is a concern that is larger than max chunk size").into());
-			} else if c_bytes <= remaining {
-				// steal this concern
-				let concern = base.concern.swap_remove(i);
-				query.concern.push(concern);
-				remaining -= c_bytes;
-				made_progress = true;
-			}
-			// since we use `swap_remove`, whether or not we stole a concern we know the element
-			// currently at `i` is too big for `remainder` (since if we removed, the element at `i`
-			// now is one we already passed on)
-			l -= 1;
-		}
-
-		out.push(query);
+		remaining = remaining.saturating_sub(size);
+		curr.push(key);
 	}
-	out.push(base);
-	Ok(out)
-}
-
-fn chunk(msg: PluginQuery) -> Result<Vec<PluginQuery>> {
-	chunk_with_size(msg, GRPC_EFFECTIVE_MAX_SIZE)
+	if curr.is_empty().not() {
+		groups.push(curr);
+	}
+	Ok(groups)
 }
 
 impl From<Status> for Error {
@@ -142,8 +62,10 @@ struct Query {
 	publisher: String,
 	plugin: String,
 	query: String,
-	key: Value,
-	output: Value,
+	// More than one key makes this a batch query. `key` and `output` always
+	// have the same length.
+	key: Vec<Value>,
+	output: Vec<Value>,
 	concerns: Vec<String>,
 }
 
@@ -179,14 +101,33 @@ impl TryFrom<PluginQuery> for Query {
 	type Error = Error;
 
 	fn try_from(value: PluginQuery) -> Result<Query> {
+		let key = value
+			.key
+			.iter()
+			.map(|k| serde_json::from_str(k))
+			.collect::<StdResult<Vec<Value>, _>>()
+			.map_err(Error::InvalidJsonInQueryKey)?;
+		let output = value
+			.output
+			.iter()
+			.map(|o| serde_json::from_str(o))
+			.collect::<StdResult<Vec<Value>, _>>()
+			.map_err(Error::InvalidJsonInQueryOutput)?;
+
+		if key.len() != output.len() {
+			return Err(Error::MismatchedBatchSize {
+				keys: key.len(),
+				outputs: output.len(),
+			});
+		}
+
 		Ok(Query {
 			direction: QueryDirection::try_from(value.state())?,
 			publisher: value.publisher_name,
 			plugin: value.plugin_name,
 			query: value.query_name,
-			key: serde_json::from_str(value.key.as_str()).map_err(Error::InvalidJsonInQueryKey)?,
-			output: serde_json::from_str(value.output.as_str())
-				.map_err(Error::InvalidJsonInQueryOutput)?,
+			key,
+			output,
 			concerns: value.concern,
 		})
 	}
@@ -219,51 +160,84 @@ impl PluginEngine {
 	/// default query endpoint is desired. `input` must of a type implementing `Into<JsonValue>`,
 	/// which can be done by deriving or implementing `serde::Serialize`.
 	pub async fn query<T, V>(&mut self, target: T, input: V) -> Result<JsonValue>
+	where
+		T: TryInto<QueryTarget, Error: Into<Error>>,
+		V: Serialize,
+	{
+		let mut outputs = self.batch_query(target, vec![input]).await?;
+		// `batch_query` returns exactly one output per key
+		Ok(outputs.remove(0))
+	}
+
+	/// Query another Hipcheck plugin `target` once for each key in `keys`, sending as few
+	/// messages as possible. On success, the JSONified results are returned in the same order as
+	/// `keys`. Hipcheck caches the result for each key separately, so a key answered here is not
+	/// recomputed by a later `query()` with the same key, or vice versa. `target` has the same
+	/// format as for `query()`.
+	pub async fn batch_query<T, V>(&mut self, target: T, keys: Vec<V>) -> Result<Vec<JsonValue>>
 	where
 		T: TryInto<QueryTarget, Error: Into<Error>>,
 		V: Serialize,
 	{
 		let query_target: QueryTarget = target.try_into().map_err(|e| e.into())?;
-		let input: JsonValue = serde_json::to_value(input).map_err(Error::InvalidJsonInQueryKey)?;
+		let mut json_keys: Vec<JsonValue> = Vec::with_capacity(keys.len());
+		for key in keys {
+			json_keys.push(serde_json::to_value(key).map_err(Error::InvalidJsonInQueryKey)?);
+		}
 
-		async fn query_inner(
+		async fn batch_query_inner(
 			engine: &mut PluginEngine,
 			target: QueryTarget,
-			input: JsonValue,
-		) -> Result<JsonValue> {
-			// If doing a mock engine, look to the `mock_responses` field for the query answer
+			keys: Vec<JsonValue>,
+		) -> Result<Vec<JsonValue>> {
+			// If doing a mock engine, look to the `mock_responses` field for the query answers
 			if cfg!(feature = "mock_engine") {
-				match engine.mock_responses.0.get(&(target, input)) {
-					Some(res) => {
-						match res {
-							Ok(val) => Ok(val.clone()),
-							// TODO: since Error is not Clone, is there a better way to deal with this
-							Err(_) => Err(Error::UnexpectedPluginQueryInputFormat),
+				let mut outputs = Vec::with_capacity(keys.len());
+				for key in keys {
+					let output = match engine.mock_responses.0.get(&(target.clone(), key)) {
+						Some(res) => {
+							match res {
+								Ok(val) => val.clone(),
+								// TODO: since Error is not Clone, is there a better way to deal with this
+								Err(_) => return Err(Error::UnexpectedPluginQueryInputFormat),
+							}
 						}
-					}
-					None => Err(Error::UnknownPluginQuery),
+						None => return Err(Error::UnknownPluginQuery),
+					};
+					outputs.push(output);
 				}
+				Ok(outputs)
 			}
 			// Normal execution, send messages to hipcheck core to query other plugin
 			else {
-				let query = Query {
-					direction: QueryDirection::Request,
-					publisher: target.publisher,
-					plugin: target.plugin,
-					query: target.query.unwrap_or_else(|| "".to_owned()),
-					key: input,
-					output: json!(Value::Null),
-					concerns: vec![],
-				};
-				engine.send(query).await?;
-				let response = engine.recv().await?;
-				match response {
-					Some(response) => Ok(response.output),
-					None => Err(Error::SessionChannelClosed),
+				let mut outputs = Vec::with_capacity(keys.len());
+				for keys in group_keys(keys, GRPC_EFFECTIVE_MAX_SIZE)? {
+					let num_keys = keys.len();
+					let query = Query {
+						direction: QueryDirection::Request,
+						publisher: target.publisher.clone(),
+						plugin: target.plugin.clone(),
+						query: target.query.clone().unwrap_or_else(|| "".to_owned()),
+						key: keys,
+						output: vec![json!(Value::Null); num_keys],
+						concerns: vec![],
+					};
+					engine.send(query).await?;
+					let Some(response) = engine.recv().await? else {
+						return Err(Error::SessionChannelClosed);
+					};
+					if response.output.len() != num_keys {
+						return Err(Error::MismatchedBatchSize {
+							keys: num_keys,
+							outputs: response.output.len(),
+						});
+					}
+					outputs.extend(response.output);
 				}
+				Ok(outputs)
 			}
 		}
-		query_inner(self, query_target, input).await
+		batch_query_inner(self, query_target, json_keys).await
 	}
 
 	fn id(&self) -> usize {
@@ -274,9 +248,18 @@ impl PluginEngine {
 	// comes from the QuerySession
 	fn convert(&self, value: Query) -> Result<PluginQuery> {
 		let state: QueryState = value.direction.into();
-		let key = serde_json::to_string(&value.key).map_err(Error::InvalidJsonInQueryKey)?;
-		let output =
-			serde_json::to_string(&value.output).map_err(Error::InvalidJsonInQueryOutput)?;
+		let key = value
+			.key
+			.iter()
+			.map(serde_json::to_string)
+			.collect::<StdResult<Vec<String>, _>>()
+			.map_err(Error::InvalidJsonInQueryKey)?;
+		let output = value
+			.output
+			.iter()
+			.map(serde_json::to_string)
+			.collect::<StdResult<Vec<String>, _>>()
+			.map_err(Error::InvalidJsonInQueryOutput)?;
 
 		Ok(PluginQuery {
 			id: self.id() as i32,
@@ -287,6 +270,7 @@ impl PluginEngine {
 			key,
 			output,
 			concern: value.concerns,
+			split: false,
 		})
 	}
 
@@ -346,9 +330,10 @@ impl PluginEngine {
 			publisher_name: P::PUBLISHER.to_owned(),
 			plugin_name: P::NAME.to_owned(),
 			query_name: "".to_owned(),
-			key: json!(Value::Null).to_string(),
-			output: json!(Value::Null).to_string(),
+			key: vec![],
+			output: vec![],
 			concern: self.take_concerns(),
+			split: false,
 		};
 		self.tx
 			.send(Ok(InitiateQueryProtocolResponse { query: Some(query) }))
@@ -401,9 +386,7 @@ impl PluginEngine {
 						if state == QueryState::ReplyComplete {
 							raw.state = QueryState::ReplyComplete.into();
 						}
-						raw.key.push_str(next.key.as_str());
-						raw.output.push_str(next.output.as_str());
-						raw.concern.extend_from_slice(next.concern.as_slice());
+						combine_chunk(&mut raw, next);
 					}
 				};
 			}
@@ -430,7 +413,7 @@ impl PluginEngine {
 		}
 
 		let name = query.query;
-		let key = query.key;
+		let keys = query.key;
 
 		// if we find the plugin by name, run it
 		// if not, check if there is a default plugin and run that one
@@ -442,15 +425,21 @@ impl PluginEngine {
 			.or_else(|| plugin.default_query())
 			.ok_or_else(|| Error::UnknownPluginQuery)?;
 
-		let value = query.run(self, key).await?;
+		let output = query.run_batch(self, keys.clone()).await?;
+		if output.len() != keys.len() {
+			return Err(Error::MismatchedBatchSize {
+				keys: keys.len(),
+				outputs: output.len(),
+			});
+		}
 
 		let query = Query {
 			direction: QueryDirection::Response,
 			publisher: P::PUBLISHER.to_owned(),
 			plugin: P::NAME.to_owned(),
 			query: name.to_owned(),
-			key: json!(Value::Null),
-			output: value,
+			key: keys,
+			output,
 			concerns: self.take_concerns(),
 		};
 
@@ -691,43 +680,13 @@ mod test {
 	use super::*;
 
 	#[test]
-	fn test_bounded_char_draining() {
-		let orig_key = "aこれは実験です".to_owned();
-
-		let mut key = orig_key.clone();
-		let res = drain_at_most_n_bytes(&mut key, 10).unwrap();
-		let num_bytes = res.bytes().len();
-
-		assert!(num_bytes > 0 && num_bytes <= 10);
-
-		// Make sure the drained str + retained str combine to re-create original
-		let mut reassembled = res.clone();
-		reassembled.push_str(&key);
-
-		assert_eq!(orig_key, reassembled);
-	}
-
-	#[test]
-	fn test_chunking() {
-		let query = PluginQuery {
-			id: 0,
-			state: QueryState::ReplyComplete as i32,
-			publisher_name: "".to_owned(),
-			plugin_name: "".to_owned(),
-			query_name: "".to_owned(),
-			// This key will cause the chunk not to occur on a char boundary
-			key: "aこれは実験です".to_owned(),
-			output: "".to_owned(),
-			concern: vec!["< 10".to_owned(), "0123456789".to_owned()],
-		};
-		let res = match chunk_with_size(query, 10) {
-			Ok(r) => r,
-			Err(e) => {
-				println!("{e}");
-				assert!(false);
-				return;
-			}
-		};
-		assert_eq!(res.len(), 4);
+	fn test_key_grouping() {
+		let keys: Vec<Value> = (0..5).map(|_| json!("12")).collect();
+		// Each key is 4 bytes of JSON plus a 4 byte `null` output
+		let groups = group_keys(keys, 16).unwrap();
+		assert_eq!(
+			groups.iter().map(Vec::len).collect::<Vec<_>>(),
+			vec![2, 2, 1]
+		);
 	}
 }
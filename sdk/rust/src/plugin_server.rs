@@ -10,7 +10,8 @@ use crate::{
 		GetDefaultPolicyExpressionRequest as GetDefaultPolicyExpressionReq,
 		GetDefaultPolicyExpressionResponse as GetDefaultPolicyExpressionResp,
 		GetQuerySchemasRequest as GetQuerySchemasReq,
-		GetQuerySchemasResponse as GetQuerySchemasResp,
+		GetQuerySchemasResponse as GetQuerySchemasResp, HealthCheckRequest as HealthCheckReq,
+		HealthCheckResponse as HealthCheckResp, HealthStatus,
 		InitiateQueryProtocolRequest as InitiateQueryProtocolReq,
 		InitiateQueryProtocolResponse as InitiateQueryProtocolResp,
 		SetConfigurationRequest as SetConfigurationReq,
@@ -18,9 +19,11 @@ use crate::{
 	},
 	Plugin, QuerySchema,
 };
-use std::{result::Result as StdResult, sync::Arc};
+use std::{path::PathBuf, result::Result as StdResult, sync::Arc};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream as RecvStream;
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
 use tonic::{transport::Server, Code, Request as Req, Response as Resp, Status, Streaming};
 
 /// Runs the Hipcheck plugin protocol based on the user's implementation of the `Plugin` trait.
@@ -38,18 +41,65 @@ impl<P: Plugin> PluginServer<P> {
 		}
 	}
 
-	/// Run the plugin server on the provided port.
-	pub async fn listen(self, port: u16) -> Result<()> {
+	/// Run the plugin server on the provided port or Unix domain socket.
+	pub async fn listen(self, addr: impl Into<ListenAddr>) -> Result<()> {
 		let service = PluginServiceServer::new(self);
-		let host = format!("127.0.0.1:{}", port).parse().unwrap();
+		let router = Server::builder().add_service(service);
 
-		Server::builder()
-			.add_service(service)
-			.serve(host)
-			.await
-			.map_err(Error::FailedToStartServer)?;
+		let result = match addr.into() {
+			ListenAddr::Port(port) => {
+				let host = format!("127.0.0.1:{}", port).parse().unwrap();
+				router.serve(host).await
+			}
+			#[cfg(unix)]
+			ListenAddr::Socket(path) => {
+				let listener = tokio::net::UnixListener::bind(&path)
+					.map_err(|source| Error::FailedToBindSocket { path, source })?;
+				router
+					.serve_with_incoming(UnixListenerStream::new(listener))
+					.await
+			}
+			#[cfg(not(unix))]
+			ListenAddr::Socket(_) => return Err(Error::UnsupportedSocket),
+		};
+
+		result.map_err(Error::FailedToStartServer)
+	}
+}
 
-		Ok(())
+/// Where a plugin server listens for Hipcheck to connect.
+///
+/// Hipcheck passes either a `--port <PORT>` or a `--socket <PATH>` flag to a
+/// plugin, which should be passed on to `PluginServer::listen()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+	/// A TCP port on the loopback interface.
+	Port(u16),
+	/// The path of a Unix domain socket to create.
+	Socket(PathBuf),
+}
+
+impl ListenAddr {
+	/// Pick the address from a plugin's `--port` and `--socket` flags,
+	/// preferring the socket if both are given.
+	pub fn from_args(port: Option<u16>, socket: Option<PathBuf>) -> Result<ListenAddr> {
+		match (port, socket) {
+			(_, Some(path)) => Ok(ListenAddr::Socket(path)),
+			(Some(port), None) => Ok(ListenAddr::Port(port)),
+			(None, None) => Err(Error::NoListenAddr),
+		}
+	}
+}
+
+impl From<u16> for ListenAddr {
+	fn from(port: u16) -> ListenAddr {
+		ListenAddr::Port(port)
+	}
+}
+
+impl From<PathBuf> for ListenAddr {
+	fn from(path: PathBuf) -> ListenAddr {
+		ListenAddr::Socket(path)
 	}
 }
 
@@ -181,4 +231,104 @@ impl<P: Plugin> PluginService for PluginServer<P> {
 		});
 		Ok(Resp::new(RecvStream::new(out_rx)))
 	}
+
+	async fn health_check(&self, _req: Req<HealthCheckReq>) -> QueryResult<Resp<HealthCheckResp>> {
+		// If we can answer at all, the server is up and able to take queries.
+		Ok(Resp::new(HealthCheckResp {
+			status: HealthStatus::Serving as i32,
+			message: "".to_owned(),
+		}))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		error::ConfigError, proto::plugin_service_client::PluginServiceClient, NamedQuery,
+	};
+	use serde_json::Value;
+
+	struct TestPlugin;
+
+	impl Plugin for TestPlugin {
+		const PUBLISHER: &'static str = "test";
+
+		const NAME: &'static str = "test";
+
+		fn set_config(&self, _config: Value) -> StdResult<(), ConfigError> {
+			Ok(())
+		}
+
+		fn default_policy_expr(&self) -> Result<String> {
+			Ok("(eq 0 $)".to_owned())
+		}
+
+		fn explain_default_query(&self) -> Result<Option<String>> {
+			Ok(None)
+		}
+
+		fn queries(&self) -> impl Iterator<Item = NamedQuery> {
+			std::iter::empty()
+		}
+	}
+
+	#[test]
+	fn test_listen_addr_from_args() {
+		let socket = PathBuf::from("plugin.sock");
+		assert_eq!(
+			ListenAddr::from_args(Some(8888), Some(socket.clone())).unwrap(),
+			ListenAddr::Socket(socket)
+		);
+		assert_eq!(
+			ListenAddr::from_args(Some(8888), None).unwrap(),
+			ListenAddr::Port(8888)
+		);
+		assert!(matches!(
+			ListenAddr::from_args(None, None),
+			Err(Error::NoListenAddr)
+		));
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_socket_round_trip() {
+		use hyper_util::rt::TokioIo;
+		use tokio::net::UnixStream;
+		use tonic::transport::{Endpoint, Uri};
+
+		let dir = tempfile::TempDir::new().unwrap();
+		let path = dir.path().join("plugin.sock");
+		let server = tokio::spawn(PluginServer::register(TestPlugin).listen(path.clone()));
+		// The socket is bound as soon as the server starts running
+		while !path.exists() {
+			tokio::task::yield_now().await;
+		}
+
+		// Tonic requires a URI, but the connector ignores it
+		let channel = Endpoint::from_static("http://[::]:50051")
+			.connect_with_connector(tower::service_fn(move |_: Uri| {
+				let path = path.clone();
+				async move { Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(path).await?)) }
+			}))
+			.await
+			.unwrap();
+		let mut client = PluginServiceClient::new(channel);
+
+		let health = client
+			.health_check(HealthCheckReq { empty: None })
+			.await
+			.unwrap()
+			.into_inner();
+		assert_eq!(health.status, HealthStatus::Serving as i32);
+
+		let policy_expr = client
+			.get_default_policy_expression(GetDefaultPolicyExpressionReq { empty: None })
+			.await
+			.unwrap()
+			.into_inner();
+		assert_eq!(policy_expr.policy_expression, "(eq 0 $)");
+
+		server.abort();
+	}
 }
@@ -37,12 +37,17 @@ impl Plugin for Sha256Plugin {
 
 #[derive(Parser, Debug)]
 struct Args {
-	#[arg(long)]
-	port: u16,
+	#[arg(long, required_unless_present = "socket")]
+	port: Option<u16>,
+
+	#[arg(long, conflicts_with = "port")]
+	socket: Option<std::path::PathBuf>,
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
 	let args = Args::try_parse().unwrap();
-	PluginServer::register(Sha256Plugin).listen(args.port).await
+	PluginServer::register(Sha256Plugin)
+		.listen(ListenAddr::from_args(args.port, args.socket)?)
+		.await
 }